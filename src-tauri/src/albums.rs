@@ -0,0 +1,217 @@
+use crate::catalog::Catalog;
+use crate::hash_cache::HashCache;
+use crate::scanner::PhotoFile;
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+/// User-defined albums stored alongside the catalog
+/// Members are keyed by content hash so they survive moves and renames;
+/// the last known path is kept for lookups and refreshed after each scan
+pub struct Albums {
+    conn: Connection,
+}
+
+/// Album header returned to the frontend (members are fetched via `search_photos`)
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Album {
+    pub id: i64,
+    pub name: String,
+    pub created_at: i64,
+    pub photo_count: usize,
+    /// Most recently added member, for a thumbnail
+    pub cover_path: Option<String>,
+}
+
+/// Create the album tables; called when the catalog is opened so searches
+/// can filter by album even before one has been created
+pub fn create_tables(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS albums (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL,
+            created_at INTEGER NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS album_photos (
+            album_id INTEGER NOT NULL REFERENCES albums(id) ON DELETE CASCADE,
+            hash TEXT NOT NULL,
+            path TEXT NOT NULL,
+            size INTEGER NOT NULL,
+            added_at INTEGER NOT NULL,
+            PRIMARY KEY (album_id, hash)
+        );
+        CREATE INDEX IF NOT EXISTS idx_album_photos_path ON album_photos(path);
+        ",
+    )
+}
+
+impl Albums {
+    /// Open the album tables in the catalog database
+    pub fn open() -> Result<Self, String> {
+        let conn = Catalog::connection()?;
+        conn.execute_batch("PRAGMA foreign_keys = ON;")
+            .map_err(|e| e.to_string())?;
+        Ok(Self { conn })
+    }
+
+    /// Create an empty album
+    pub fn create(&self, name: &str) -> Result<Album, String> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err("Album name cannot be empty".to_string());
+        }
+        let created_at = chrono::Utc::now().timestamp_millis();
+        self.conn
+            .execute(
+                "INSERT INTO albums (name, created_at) VALUES (?1, ?2)",
+                params![name, created_at],
+            )
+            .map_err(|e| e.to_string())?;
+
+        Ok(Album {
+            id: self.conn.last_insert_rowid(),
+            name: name.to_string(),
+            created_at,
+            photo_count: 0,
+            cover_path: None,
+        })
+    }
+
+    /// Add files to an album, hashing any that aren't cached yet
+    /// Returns the number of files added (files already in the album are updated)
+    pub fn add(&self, album_id: i64, paths: &[String], cache: &HashCache) -> Result<usize, String> {
+        self.ensure_exists(album_id)?;
+        let added_at = chrono::Utc::now().timestamp_millis();
+
+        let mut added = 0;
+        for path in paths {
+            let size = fs::metadata(path)
+                .map_err(|e| format!("{}: {}", path, e))?
+                .len();
+            let hash = cache
+                .full_hash(path, size)
+                .ok_or_else(|| format!("Could not hash {}", path))?;
+            added += self
+                .conn
+                .execute(
+                    "INSERT OR REPLACE INTO album_photos (album_id, hash, path, size, added_at)
+                    VALUES (?1, ?2, ?3, ?4, ?5)",
+                    params![album_id, hash, path, size as i64, added_at],
+                )
+                .map_err(|e| e.to_string())?;
+        }
+        Ok(added)
+    }
+
+    /// Remove files from an album by path
+    pub fn remove(&self, album_id: i64, paths: &[String]) -> Result<usize, String> {
+        self.ensure_exists(album_id)?;
+        let mut removed = 0;
+        for path in paths {
+            removed += self
+                .conn
+                .execute(
+                    "DELETE FROM album_photos WHERE album_id = ?1 AND path = ?2",
+                    params![album_id, path],
+                )
+                .map_err(|e| e.to_string())?;
+        }
+        Ok(removed)
+    }
+
+    /// All albums with member counts, alphabetically
+    pub fn list(&self) -> Result<Vec<Album>, String> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT a.id, a.name, a.created_at,
+                    (SELECT COUNT(*) FROM album_photos m WHERE m.album_id = a.id),
+                    (SELECT m.path FROM album_photos m WHERE m.album_id = a.id
+                        ORDER BY m.added_at DESC LIMIT 1)
+                FROM albums a
+                ORDER BY a.name COLLATE NOCASE",
+            )
+            .map_err(|e| e.to_string())?;
+
+        let albums = stmt
+            .query_map([], |row| {
+                Ok(Album {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                    created_at: row.get(2)?,
+                    photo_count: row.get::<_, i64>(3)? as usize,
+                    cover_path: row.get(4)?,
+                })
+            })
+            .map_err(|e| e.to_string())?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+        Ok(albums)
+    }
+
+    /// Point members whose file has gone at the scanned photo with the same content
+    /// Only same-size photos are hashed, so this stays cheap after a normal scan
+    /// Returns the number of members relinked
+    pub fn relink(&self, photos: &[PhotoFile], cache: &HashCache) -> Result<usize, String> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT DISTINCT hash, path, size FROM album_photos")
+            .map_err(|e| e.to_string())?;
+        let members: Vec<(String, String, u64)> = stmt
+            .query_map([], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get::<_, i64>(2)? as u64))
+            })
+            .map_err(|e| e.to_string())?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+
+        let member_paths: HashSet<&str> = members.iter().map(|(_, p, _)| p.as_str()).collect();
+        let missing: Vec<&(String, String, u64)> = members
+            .iter()
+            .filter(|(_, path, _)| !Path::new(path).exists())
+            .collect();
+        if missing.is_empty() {
+            return Ok(0);
+        }
+
+        // Candidates: scanned photos not already in an album, bucketed by size
+        let mut by_size: HashMap<u64, Vec<&PhotoFile>> = HashMap::new();
+        for photo in photos {
+            if !photo.is_cloud_placeholder && !member_paths.contains(photo.path.as_str()) {
+                by_size.entry(photo.size).or_default().push(photo);
+            }
+        }
+
+        let mut relinked = 0;
+        for (hash, old_path, size) in missing {
+            let candidate = by_size.get(size).and_then(|candidates| {
+                candidates
+                    .iter()
+                    .find(|p| cache.full_hash(&p.path, p.size).as_deref() == Some(hash.as_str()))
+            });
+            if let Some(photo) = candidate {
+                relinked += self
+                    .conn
+                    .execute(
+                        "UPDATE album_photos SET path = ?1 WHERE hash = ?2 AND path = ?3",
+                        params![photo.path, hash, old_path],
+                    )
+                    .map_err(|e| e.to_string())?;
+            }
+        }
+        Ok(relinked)
+    }
+
+    fn ensure_exists(&self, album_id: i64) -> Result<(), String> {
+        self.conn
+            .query_row(
+                "SELECT 1 FROM albums WHERE id = ?1",
+                params![album_id],
+                |_| Ok(()),
+            )
+            .map_err(|_| "Album not found".to_string())
+    }
+}
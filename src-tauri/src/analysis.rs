@@ -0,0 +1,46 @@
+use rusqlite::{params, Connection};
+use std::collections::{HashMap, HashSet};
+
+/// Catalog tables of per-photo results from an analyzer (faces, machine tags,
+/// quality), which remember each photo's modification time when it was
+/// analyzed so unchanged photos can be skipped the next time
+pub trait AnalysisStore {
+    /// Table with the path and `modified_at` of every analyzed photo
+    const SCANS: &'static str;
+    /// Tables with rows by path, cleared in this order when a photo is forgotten
+    const TABLES: &'static [&'static str];
+
+    fn connection(&self) -> &Connection;
+
+    /// Modification time (ms since epoch) of each photo when it was last
+    /// analyzed, by path
+    fn scanned(&self) -> Result<HashMap<String, i64>, String> {
+        let mut stmt = self
+            .connection()
+            .prepare(&format!("SELECT path, modified_at FROM {}", Self::SCANS))
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .map_err(|e| e.to_string())?;
+        rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+    }
+
+    /// Forget photos that are no longer in the library
+    fn retain(&self, paths: &HashSet<&str>) -> Result<(), String> {
+        let scanned = self.scanned()?;
+        let tx = self
+            .connection()
+            .unchecked_transaction()
+            .map_err(|e| e.to_string())?;
+        for path in scanned.keys().filter(|p| !paths.contains(p.as_str())) {
+            for table in Self::TABLES {
+                tx.execute(
+                    &format!("DELETE FROM {} WHERE path = ?1", table),
+                    params![path],
+                )
+                .map_err(|e| e.to_string())?;
+            }
+        }
+        tx.commit().map_err(|e| e.to_string())
+    }
+}
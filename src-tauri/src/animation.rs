@@ -0,0 +1,190 @@
+use image::codecs::gif::GifDecoder;
+use image::codecs::webp::WebPDecoder;
+use image::{AnimationDecoder, DynamicImage, Frames};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+
+/// Longest side of generated thumbnails in pixels
+const THUMBNAIL_SIZE: u32 = 512;
+
+/// Browsers play GIF frames with a delay of 0 or 1 centiseconds at this delay instead
+const DEFAULT_GIF_DELAY_CS: u64 = 10;
+
+/// Frame count and play time of an animated GIF or WebP
+/// Animations are not stills: pixel-based similarity matching should skip them so
+/// they are not matched to their first frame exported as a JPEG
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AnimationInfo {
+    pub frame_count: u32,
+    /// Length of one loop in milliseconds
+    pub duration_ms: u64,
+}
+
+/// Whether files with this (lowercase) extension can be animated
+pub fn may_be_animated(extension: &str) -> bool {
+    matches!(extension, "gif" | "webp")
+}
+
+/// Animation details of a GIF or WebP; None for stills and unreadable files
+/// Only the container structure is read, frames are not decoded
+pub fn probe_animation(path: &Path) -> Option<AnimationInfo> {
+    let data = fs::read(path).ok()?;
+    let info = if data.starts_with(b"GIF8") {
+        gif_animation(&data)?
+    } else if data.starts_with(b"RIFF") && data.get(8..12) == Some(b"WEBP") {
+        webp_animation(&data)?
+    } else {
+        return None;
+    };
+    (info.frame_count > 1).then_some(info)
+}
+
+/// Walk the GIF block structure counting image descriptors and summing
+/// the delays of their graphic control extensions
+fn gif_animation(data: &[u8]) -> Option<AnimationInfo> {
+    let packed = *data.get(10)?;
+    let mut pos = 13;
+    if packed & 0x80 != 0 {
+        pos += 3 << ((packed & 0x07) + 1);
+    }
+
+    let mut frame_count = 0;
+    let mut duration_cs = 0;
+    loop {
+        match *data.get(pos)? {
+            // Extension
+            0x21 => {
+                if *data.get(pos + 1)? == 0xF9 {
+                    let delay = u16::from_le_bytes([*data.get(pos + 4)?, *data.get(pos + 5)?]);
+                    duration_cs += match delay {
+                        0 | 1 => DEFAULT_GIF_DELAY_CS,
+                        delay => delay as u64,
+                    };
+                }
+                pos = skip_sub_blocks(data, pos + 2)?;
+            }
+            // Image descriptor
+            0x2C => {
+                frame_count += 1;
+                let packed = *data.get(pos + 9)?;
+                pos += 10;
+                if packed & 0x80 != 0 {
+                    pos += 3 << ((packed & 0x07) + 1);
+                }
+                // LZW minimum code size, then the image data
+                pos = skip_sub_blocks(data, pos + 1)?;
+            }
+            // Trailer
+            0x3B => break,
+            _ => return None,
+        }
+    }
+
+    Some(AnimationInfo {
+        frame_count,
+        duration_ms: duration_cs * 10,
+    })
+}
+
+/// Skip a chain of length-prefixed sub-blocks, returning the position after the terminator
+fn skip_sub_blocks(data: &[u8], mut pos: usize) -> Option<usize> {
+    loop {
+        let len = *data.get(pos)? as usize;
+        pos += 1;
+        if len == 0 {
+            return Some(pos);
+        }
+        pos += len;
+    }
+}
+
+/// Count the ANMF chunks of an animated WebP and sum their frame durations
+fn webp_animation(data: &[u8]) -> Option<AnimationInfo> {
+    let mut pos = 12;
+    let mut animated = false;
+    let mut frame_count = 0;
+    let mut duration_ms = 0;
+
+    while pos + 8 <= data.len() {
+        let fourcc = &data[pos..pos + 4];
+        let size = u32::from_le_bytes(data[pos + 4..pos + 8].try_into().ok()?) as usize;
+        let payload = data.get(pos + 8..(pos + 8 + size).min(data.len()))?;
+        match fourcc {
+            b"VP8X" => animated = payload.first().is_some_and(|flags| flags & 0x02 != 0),
+            b"ANMF" => {
+                frame_count += 1;
+                if let Some(d) = payload.get(12..15) {
+                    duration_ms += u32::from_le_bytes([d[0], d[1], d[2], 0]) as u64;
+                }
+            }
+            _ => {}
+        }
+        // Chunks are padded to an even size
+        pos += 8 + size + (size & 1);
+    }
+
+    animated.then_some(AnimationInfo {
+        frame_count,
+        duration_ms,
+    })
+}
+
+/// Folder for generated thumbnails and extracted previews
+pub fn thumbnail_dir() -> PathBuf {
+    dirs::config_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("photo-manager")
+        .join("thumbnails")
+}
+
+/// Remove everything cached for `path` (thumbnails, previews, renditions and
+/// downscaled copies), for when its pixels changed but its dates didn't
+pub fn remove_cached_thumbnails(path: &Path) {
+    let name = blake3::hash(path.to_string_lossy().as_bytes()).to_hex();
+    let prefix = format!("{}.", &name[..32]);
+    let Ok(entries) = fs::read_dir(thumbnail_dir()) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        if entry.file_name().to_string_lossy().starts_with(&prefix) {
+            let _ = fs::remove_file(entry.path());
+        }
+    }
+}
+
+/// JPEG thumbnail of the middle frame of an animation (first frames are often
+/// blank or a title card); reused while it is newer than the source
+pub fn representative_thumbnail(path: &Path, info: &AnimationInfo) -> Option<PathBuf> {
+    let name = blake3::hash(path.to_string_lossy().as_bytes()).to_hex();
+    let target = thumbnail_dir().join(format!("{}.jpg", &name[..32]));
+
+    let modified = |p: &Path| fs::metadata(p).and_then(|m| m.modified()).ok();
+    if let (Some(thumb), Some(source)) = (modified(&target), modified(path)) {
+        if thumb >= source {
+            return Some(target);
+        }
+    }
+
+    let reader = BufReader::new(File::open(path).ok()?);
+    let mut frames: Frames = match path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase())
+        .as_deref()
+    {
+        Some("gif") => GifDecoder::new(reader).ok()?.into_frames(),
+        Some("webp") => WebPDecoder::new(reader).ok()?.into_frames(),
+        _ => return None,
+    };
+    let frame = frames.nth((info.frame_count / 2) as usize)?.ok()?;
+
+    let image = DynamicImage::ImageRgba8(frame.into_buffer())
+        .thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE)
+        .to_rgb8();
+    fs::create_dir_all(thumbnail_dir()).ok()?;
+    image.save(&target).ok()?;
+    Some(target)
+}
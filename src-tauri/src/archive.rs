@@ -0,0 +1,143 @@
+use crate::catalog::Catalog;
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::path::Path;
+
+/// Files and folders marked as dealt with, stored in the catalog database
+/// Archived photos stay cataloged and searchable but are left out of the
+/// default listings and duplicate views; archiving a folder covers everything
+/// under it
+pub struct Archive {
+    conn: Connection,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchivedEntry {
+    pub path: String,
+    pub archived_at: i64,
+    pub is_folder: bool,
+}
+
+/// SQL condition (on `photos.path`) true for photos that are archived
+/// themselves or lie under an archived folder
+pub const ARCHIVED_CONDITION: &str = "EXISTS (SELECT 1 FROM archived a WHERE photos.path = a.path
+    OR substr(photos.path, 1, length(a.path) + 1) IN (a.path || '/', a.path || '\\'))";
+
+/// Create the archive table; called when the catalog is opened
+pub fn create_tables(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS archived (
+            path TEXT PRIMARY KEY,
+            archived_at INTEGER NOT NULL
+        );",
+    )
+}
+
+impl Archive {
+    /// Open the archive table in the catalog database
+    pub fn open() -> Result<Self, String> {
+        let conn = Catalog::connection()?;
+        Ok(Self { conn })
+    }
+
+    /// Archive or unarchive files and folders
+    /// Unarchiving only clears the paths given, so a file stays archived while
+    /// a folder above it is. Returns the number of entries changed
+    pub fn set(&self, paths: &[String], archived: bool) -> Result<usize, String> {
+        let archived_at = chrono::Utc::now().timestamp_millis();
+        let mut changed = 0;
+        for path in paths {
+            let path = normalize(path);
+            if path.is_empty() {
+                continue;
+            }
+            changed += if archived {
+                self.conn.execute(
+                    "INSERT OR IGNORE INTO archived (path, archived_at) VALUES (?1, ?2)",
+                    params![path, archived_at],
+                )
+            } else {
+                self.conn
+                    .execute("DELETE FROM archived WHERE path = ?1", params![path])
+            }
+            .map_err(|e| e.to_string())?;
+        }
+        Ok(changed)
+    }
+
+    /// Every archived file and folder, most recently archived first
+    pub fn list(&self) -> Result<Vec<ArchivedEntry>, String> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT path, archived_at FROM archived ORDER BY archived_at DESC, path")
+            .map_err(|e| e.to_string())?;
+        let entries = stmt
+            .query_map([], |row| {
+                let path: String = row.get(0)?;
+                Ok(ArchivedEntry {
+                    is_folder: Path::new(&path).is_dir(),
+                    path,
+                    archived_at: row.get(1)?,
+                })
+            })
+            .map_err(|e| e.to_string())?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+        Ok(entries)
+    }
+
+    /// The archived paths, for filtering photos in memory
+    pub fn paths(&self) -> Result<ArchivedPaths, String> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT path FROM archived")
+            .map_err(|e| e.to_string())?;
+        let paths = stmt
+            .query_map([], |row| row.get(0))
+            .map_err(|e| e.to_string())?
+            .collect::<Result<HashSet<String>, _>>()
+            .map_err(|e| e.to_string())?;
+        Ok(ArchivedPaths { paths })
+    }
+}
+
+/// Archived files and folders, matched like `ARCHIVED_CONDITION`
+#[derive(Debug, Default)]
+pub struct ArchivedPaths {
+    paths: HashSet<String>,
+}
+
+impl ArchivedPaths {
+    pub fn is_empty(&self) -> bool {
+        self.paths.is_empty()
+    }
+
+    /// Whether a file is archived itself or through one of its folders
+    pub fn contains(&self, path: &str) -> bool {
+        !self.paths.is_empty()
+            && Path::new(path)
+                .ancestors()
+                .any(|p| self.paths.contains(p.to_string_lossy().as_ref()))
+    }
+}
+
+/// Follow an archived file or folder the app moved or renamed
+pub fn relocate(conn: &Connection, from: &str, to: &str) -> rusqlite::Result<()> {
+    conn.execute(
+        "UPDATE OR IGNORE archived SET path = ?2 WHERE path = ?1",
+        params![from, to],
+    )?;
+    Ok(())
+}
+
+/// Folder paths are stored without a trailing separator
+fn normalize(path: &str) -> &str {
+    let trimmed = path.trim_end_matches(['/', '\\']);
+    if trimmed.is_empty() {
+        path
+    } else {
+        trimmed
+    }
+}
@@ -0,0 +1,332 @@
+use crate::exclusions::Exclusions;
+use crate::hash_cache::HashCache;
+use crate::jobs::JobMonitor;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::UNIX_EPOCH;
+use tauri::Window;
+use walkdir::WalkDir;
+
+/// How `compare_trees` decides two files at the same path are the same
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct CompareOptions {
+    /// Hash files at the same path even when size and modification time
+    /// agree, like `rsync --checksum`; slower, but catches silent corruption
+    pub checksum: bool,
+}
+
+/// `compare-progress` payload
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompareProgress {
+    /// "listing" | "hashing" | "complete"
+    pub stage: String,
+    pub current: usize,
+    pub total: usize,
+    pub current_file: String,
+}
+
+/// A file on one side only, by its path relative to its root
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TreeFile {
+    pub path: String,
+    pub size: u64,
+}
+
+/// The same relative path on both sides with different content
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TreeDifference {
+    pub path: String,
+    pub library_size: u64,
+    pub backup_size: u64,
+}
+
+/// Content the backup holds under another relative path
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TreeMove {
+    pub library_path: String,
+    pub backup_path: String,
+    pub size: u64,
+}
+
+/// What it would take to bring the backup in line with the library
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TreeComparison {
+    pub library_root: String,
+    pub backup_root: String,
+    /// Files present at the same path with the same content
+    pub identical_count: usize,
+    /// Library files whose content is nowhere on the backup
+    pub missing: Vec<TreeFile>,
+    /// Backup files whose content is nowhere in the library
+    pub extra: Vec<TreeFile>,
+    pub differing: Vec<TreeDifference>,
+    /// Library files backed up under another path (renamed or reorganized)
+    pub moved: Vec<TreeMove>,
+    /// Bytes to copy to bring the backup up to date (missing and differing files)
+    pub bytes_to_copy: u64,
+    /// Files that could not be hashed, by absolute path; they are not counted
+    /// as missing or extra
+    pub unreadable: Vec<String>,
+}
+
+pub fn emit_progress(window: &Window, stage: &str, current: usize, total: usize, path: &str) {
+    let _ = window.emit(
+        "compare-progress",
+        CompareProgress {
+            stage: stage.to_string(),
+            current,
+            total,
+            current_file: Path::new(path)
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default(),
+        },
+    );
+}
+
+/// A file found under a root
+struct Listed {
+    relative: String,
+    path: PathBuf,
+    size: u64,
+    modified: Option<u64>,
+}
+
+/// Compare a library folder with its backup, matching files by path and, for
+/// files that don't line up, by content hash; nothing is copied or deleted
+/// Files at the same path with the same size and modification time count as
+/// identical without hashing unless `checksum` is set
+pub fn compare(
+    window: &Window,
+    library_root: &Path,
+    backup_root: &Path,
+    options: &CompareOptions,
+) -> Result<TreeComparison, String> {
+    for root in [library_root, backup_root] {
+        if !root.is_dir() {
+            return Err(format!("{} is not a folder", root.display()));
+        }
+    }
+    if library_root.starts_with(backup_root) || backup_root.starts_with(library_root) {
+        return Err("The library and the backup can't be inside each other".to_string());
+    }
+    let cache = HashCache::open()?;
+    let monitor = JobMonitor::start(window, "compare");
+    let cancelled = || "Comparison cancelled".to_string();
+
+    emit_progress(window, "listing", 0, 0, "");
+    let library = list_files(library_root, &monitor);
+    let backup = list_files(backup_root, &monitor);
+    if monitor.is_cancelled() {
+        return Err(cancelled());
+    }
+    let backup_by_path: HashMap<&str, &Listed> =
+        backup.iter().map(|f| (f.relative.as_str(), f)).collect();
+    let library_paths: HashSet<&str> = library.iter().map(|f| f.relative.as_str()).collect();
+
+    // Pairs at the same path that need a hash to tell, and files that didn't line up
+    let mut pairs: Vec<(&Listed, &Listed)> = Vec::new();
+    let mut identical_count = 0;
+    let mut differing = Vec::new();
+    let mut library_only: Vec<&Listed> = Vec::new();
+    for file in &library {
+        match backup_by_path.get(file.relative.as_str()) {
+            Some(copy) if copy.size != file.size => differing.push(TreeDifference {
+                path: file.relative.clone(),
+                library_size: file.size,
+                backup_size: copy.size,
+            }),
+            Some(copy) if !options.checksum && same_time(file, copy) => identical_count += 1,
+            Some(copy) => pairs.push((file, copy)),
+            None => library_only.push(file),
+        }
+    }
+    let backup_only: Vec<&Listed> = backup
+        .iter()
+        .filter(|f| !library_paths.contains(f.relative.as_str()))
+        .collect();
+
+    // A file on one side only can still have a copy anywhere on the other,
+    // but only among files of the same size
+    let sizes = |files: &[&Listed]| files.iter().map(|f| f.size).collect::<HashSet<u64>>();
+    let (library_only_sizes, backup_only_sizes) = (sizes(&library_only), sizes(&backup_only));
+    let library_sizes: HashSet<u64> = library.iter().map(|f| f.size).collect();
+    let backup_sizes: HashSet<u64> = backup.iter().map(|f| f.size).collect();
+    let mut to_hash: Vec<&Listed> = pairs.iter().flat_map(|(a, b)| [*a, *b]).collect();
+    to_hash.extend(
+        library_only
+            .iter()
+            .filter(|f| backup_sizes.contains(&f.size)),
+    );
+    to_hash.extend(
+        backup
+            .iter()
+            .filter(|f| library_only_sizes.contains(&f.size)),
+    );
+    to_hash.extend(
+        backup_only
+            .iter()
+            .filter(|f| library_sizes.contains(&f.size)),
+    );
+    to_hash.extend(
+        library
+            .iter()
+            .filter(|f| backup_only_sizes.contains(&f.size)),
+    );
+    let mut seen = HashSet::new();
+    to_hash.retain(|f| seen.insert(f.path.as_path()));
+
+    let hashed_count = AtomicUsize::new(0);
+    let hashes: HashMap<&Path, String> = to_hash
+        .par_iter()
+        .filter_map(|file| {
+            if monitor.is_cancelled() {
+                return None;
+            }
+            let path_str = file.path.to_string_lossy();
+            let _work = monitor.working_on(&path_str);
+            let hash = cache.full_hash(&path_str, file.size);
+            let current = hashed_count.fetch_add(1, Ordering::Relaxed) + 1;
+            emit_progress(window, "hashing", current, to_hash.len(), &path_str);
+            hash.filter(|_| !monitor.is_skipped(&path_str))
+                .map(|hash| (file.path.as_path(), hash))
+        })
+        .collect();
+    if monitor.is_cancelled() {
+        return Err(cancelled());
+    }
+    let hash_of = |file: &Listed| hashes.get(file.path.as_path());
+    let mut unreadable: Vec<String> = to_hash
+        .iter()
+        .filter(|f| hash_of(f).is_none())
+        .map(|f| f.path.to_string_lossy().to_string())
+        .collect();
+    unreadable.sort();
+
+    for (file, copy) in pairs {
+        match (hash_of(file), hash_of(copy)) {
+            (Some(a), Some(b)) if a == b => identical_count += 1,
+            (Some(_), Some(_)) => differing.push(TreeDifference {
+                path: file.relative.clone(),
+                library_size: file.size,
+                backup_size: copy.size,
+            }),
+            _ => {}
+        }
+    }
+
+    let library_hashes: HashSet<&str> = library
+        .iter()
+        .filter_map(hash_of)
+        .map(String::as_str)
+        .collect();
+    let mut backup_hashes: HashMap<&str, &Listed> = HashMap::new();
+    for file in &backup {
+        if let Some(hash) = hash_of(file) {
+            backup_hashes.entry(hash.as_str()).or_insert(file);
+        }
+    }
+
+    let mut missing = Vec::new();
+    let mut moved = Vec::new();
+    for file in library_only {
+        match hash_of(file) {
+            Some(hash) => match backup_hashes.get(hash.as_str()) {
+                Some(copy) => moved.push(TreeMove {
+                    library_path: file.relative.clone(),
+                    backup_path: copy.relative.clone(),
+                    size: file.size,
+                }),
+                None => missing.push(tree_file(file)),
+            },
+            // Not hashed because no backup file has its size
+            None if !backup_sizes.contains(&file.size) => missing.push(tree_file(file)),
+            None => {}
+        }
+    }
+    let extra: Vec<TreeFile> = backup_only
+        .into_iter()
+        .filter(|file| match hash_of(file) {
+            Some(hash) => !library_hashes.contains(hash.as_str()),
+            None => !library_sizes.contains(&file.size),
+        })
+        .map(tree_file)
+        .collect();
+
+    let bytes_to_copy = missing.iter().map(|f| f.size).sum::<u64>()
+        + differing.iter().map(|d| d.library_size).sum::<u64>();
+    drop(monitor);
+    emit_progress(window, "complete", to_hash.len(), to_hash.len(), "");
+
+    Ok(TreeComparison {
+        library_root: library_root.to_string_lossy().to_string(),
+        backup_root: backup_root.to_string_lossy().to_string(),
+        identical_count,
+        missing,
+        extra,
+        differing,
+        moved,
+        bytes_to_copy,
+        unreadable,
+    })
+}
+
+/// Every file under a root, sorted by relative path, skipping what scans skip
+/// by default (hidden files, other apps' library bundles, NAS thumbnails)
+fn list_files(root: &Path, monitor: &JobMonitor) -> Vec<Listed> {
+    let exclusions = Exclusions::default();
+    let mut files = Vec::new();
+    for entry in WalkDir::new(root)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|e| !exclusions.excludes(root, e))
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+    {
+        if monitor.is_cancelled() {
+            break;
+        }
+        monitor.progress(entry.path().to_str());
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        let Ok(relative) = entry.path().strip_prefix(root) else {
+            continue;
+        };
+        files.push(Listed {
+            relative: relative.to_string_lossy().to_string(),
+            path: entry.path().to_path_buf(),
+            size: metadata.len(),
+            modified: metadata
+                .modified()
+                .ok()
+                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                .map(|d| d.as_secs()),
+        });
+    }
+    files
+}
+
+/// Modification times within a second of each other, since FAT and exFAT
+/// drives store them in two-second steps
+fn same_time(a: &Listed, b: &Listed) -> bool {
+    a.modified
+        .zip(b.modified)
+        .is_some_and(|(a, b)| a.abs_diff(b) <= 1)
+}
+
+fn tree_file(file: &Listed) -> TreeFile {
+    TreeFile {
+        path: file.relative.clone(),
+        size: file.size,
+    }
+}
@@ -0,0 +1,91 @@
+use crate::library::camera_name;
+use crate::media_info::is_video_extension;
+use crate::scanner::PhotoFile;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Longest pause between two frames of a burst or bracket, in milliseconds
+const MAX_FRAME_INTERVAL_MS: i64 = 1000;
+
+/// Fewer frames than this are just two quick shots
+const MIN_BURST_SIZE: usize = 3;
+
+/// A photo that may be part of a burst
+struct Shot<'a> {
+    captured: i64,
+    prefix: &'a str,
+    number: u64,
+    path: &'a str,
+}
+
+/// Runs of photos shot as a burst or exposure bracket: same folder, same
+/// camera, consecutive file numbers and at most a second between frames
+/// Each run lists its paths in shooting order
+pub fn detect(photos: &[PhotoFile]) -> Vec<Vec<String>> {
+    let mut candidates: HashMap<(&str, String), Vec<Shot>> = HashMap::new();
+    for photo in photos {
+        if is_video_extension(&photo.extension) {
+            continue;
+        }
+        let (Some(captured), Some(camera)) = (photo.capture_date, camera_name(photo)) else {
+            continue;
+        };
+        let Some((prefix, number)) = file_number(&photo.name) else {
+            continue;
+        };
+        let folder = Path::new(&photo.path)
+            .parent()
+            .and_then(|p| p.to_str())
+            .unwrap_or("");
+        candidates.entry((folder, camera)).or_default().push(Shot {
+            captured,
+            prefix,
+            number,
+            path: &photo.path,
+        });
+    }
+
+    let mut bursts = Vec::new();
+    for mut shots in candidates.into_values() {
+        shots.sort_by(|a, b| {
+            a.captured
+                .cmp(&b.captured)
+                .then_with(|| a.number.cmp(&b.number))
+        });
+        let mut run: Vec<&str> = Vec::new();
+        for (i, shot) in shots.iter().enumerate() {
+            let continues = i > 0 && {
+                let previous = &shots[i - 1];
+                shot.captured - previous.captured <= MAX_FRAME_INTERVAL_MS
+                    && shot.prefix.eq_ignore_ascii_case(previous.prefix)
+                    && shot.number == previous.number + 1
+            };
+            if !continues {
+                finish_run(&mut run, &mut bursts);
+            }
+            run.push(shot.path);
+        }
+        finish_run(&mut run, &mut bursts);
+    }
+    bursts.sort();
+    bursts
+}
+
+fn finish_run(run: &mut Vec<&str>, bursts: &mut Vec<Vec<String>>) {
+    if run.len() >= MIN_BURST_SIZE {
+        bursts.push(run.iter().map(|p| p.to_string()).collect());
+    }
+    run.clear();
+}
+
+/// Prefix and counter of a camera file name, e.g. ("IMG_", 1234) for
+/// IMG_1234.JPG or ("DSC", 42) for DSC00042.ARW
+fn file_number(name: &str) -> Option<(&str, u64)> {
+    let stem = Path::new(name).file_stem()?.to_str()?;
+    let prefix = stem.trim_end_matches(|c: char| c.is_ascii_digit());
+    let digits = &stem[prefix.len()..];
+    if digits.is_empty() || digits.len() > 9 {
+        return None;
+    }
+    Some((prefix, digits.parse().ok()?))
+}
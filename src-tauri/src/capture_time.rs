@@ -0,0 +1,148 @@
+use crate::metadata::{read_capture_date, update_exif, writable_exif_type};
+use crate::xmp;
+use chrono::{Duration, Local, NaiveDateTime, TimeZone};
+use little_exif::exif_tag::ExifTag;
+use little_exif::filetype::FileExtension;
+use little_exif::metadata::Metadata;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// How EXIF writes its dates
+const EXIF_DATE_FORMAT: &str = "%Y:%m:%d %H:%M:%S";
+
+/// Where a shifted capture time was written
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ShiftTarget {
+    /// The file's own EXIF dates
+    Exif,
+    /// `exif:DateTimeOriginal` in the XMP sidecar
+    Sidecar,
+}
+
+/// A photo whose capture time was shifted
+pub struct Shifted {
+    pub target: ShiftTarget,
+    /// The new capture time, ms since epoch
+    pub capture_date: i64,
+}
+
+/// Move a photo's capture time by `offset_seconds`, for a camera whose clock
+/// was wrong, keeping the file's dates
+/// JPEG, HEIC, PNG, WebP and TIFF files have their EXIF capture, digitized
+/// and modify dates shifted in place. RAW and video files get the shifted
+/// time in their XMP sidecar, starting from a time already there, else from
+/// EXIF, else from `cataloged` (ms since epoch, e.g. a video's creation
+/// time); None when there is no capture time to shift
+/// A time in the sidecar overrides the file's own when scanning, so it is
+/// shifted along with in-place dates too. A RAW and a JPEG of the same name
+/// share a sidecar: `shifted_sidecars` keeps it from being shifted twice
+pub fn shift(
+    path: &Path,
+    offset_seconds: i64,
+    cataloged: Option<i64>,
+    shifted_sidecars: &mut HashSet<PathBuf>,
+) -> Result<Option<Shifted>, String> {
+    if !path.is_file() {
+        return Err("The file is gone".to_string());
+    }
+    let offset = Duration::seconds(offset_seconds);
+    let sidecar = xmp::sidecar_path(path);
+    let in_sidecar = xmp::parse_sidecar(&sidecar)
+        .and_then(|s| s.capture_date)
+        .and_then(|date| xmp::parse_date(&date));
+    let already_shifted = shifted_sidecars.contains(&sidecar);
+
+    if let Some(file_type) = exif_type(path) {
+        let Some(original) = read_capture_date(path) else {
+            return Ok(None);
+        };
+        update_exif(path, file_type, |metadata| {
+            shift_exif_dates(metadata, offset)
+        })?;
+        let capture_date = match in_sidecar {
+            Some(date) if already_shifted => date,
+            Some(date) => {
+                let shifted = date + offset.num_milliseconds();
+                write_sidecar_date(path, shifted)?;
+                shifted_sidecars.insert(sidecar);
+                shifted
+            }
+            None => local_millis(&(original + offset))?,
+        };
+        return Ok(Some(Shifted {
+            target: ShiftTarget::Exif,
+            capture_date,
+        }));
+    }
+
+    if already_shifted {
+        return Ok(in_sidecar.map(|capture_date| Shifted {
+            target: ShiftTarget::Sidecar,
+            capture_date,
+        }));
+    }
+    let in_exif = || read_capture_date(path).and_then(|date| local_millis(&date).ok());
+    let Some(original) = in_sidecar.or_else(in_exif).or(cataloged) else {
+        return Ok(None);
+    };
+    let shifted = original + offset.num_milliseconds();
+    write_sidecar_date(path, shifted)?;
+    shifted_sidecars.insert(sidecar);
+    Ok(Some(Shifted {
+        target: ShiftTarget::Sidecar,
+        capture_date: shifted,
+    }))
+}
+
+/// Write a capture time (ms since epoch) to the sidecar in the local time zone
+fn write_sidecar_date(path: &Path, millis: i64) -> Result<(), String> {
+    let date = Local
+        .timestamp_millis_opt(millis)
+        .single()
+        .ok_or("Shifted capture time is out of range")?;
+    xmp::write_capture_date(path, &date.fixed_offset())
+}
+
+/// The formats whose EXIF is rewritten; HEIC too, since iPhones, the usual
+/// source of HEIC, keep no sidecars
+fn exif_type(path: &Path) -> Option<FileExtension> {
+    writable_exif_type(path).or_else(|| {
+        let extension = path.extension()?.to_str()?.to_lowercase();
+        matches!(extension.as_str(), "heic" | "heif").then_some(FileExtension::HEIF)
+    })
+}
+
+/// Shift every EXIF date tag the file has; sub-second and time zone tags are
+/// left as they are
+fn shift_exif_dates(metadata: &mut Metadata, offset: Duration) {
+    let tags: [fn(String) -> ExifTag; 3] = [
+        ExifTag::DateTimeOriginal,
+        ExifTag::CreateDate,
+        ExifTag::ModifyDate,
+    ];
+    for tag in tags {
+        let Some(
+            ExifTag::DateTimeOriginal(value)
+            | ExifTag::CreateDate(value)
+            | ExifTag::ModifyDate(value),
+        ) = metadata.get_tag(&tag(String::new())).next().cloned()
+        else {
+            continue;
+        };
+        let value = value.trim_end_matches('\0').trim();
+        if let Ok(date) = NaiveDateTime::parse_from_str(value, EXIF_DATE_FORMAT) {
+            metadata.set_tag(tag((date + offset).format(EXIF_DATE_FORMAT).to_string()));
+        }
+    }
+}
+
+/// A local capture time, as scans read it from EXIF, as ms since epoch
+fn local_millis(date: &NaiveDateTime) -> Result<i64, String> {
+    Local
+        .from_local_datetime(date)
+        .earliest()
+        .map(|d| d.timestamp_millis())
+        .ok_or_else(|| format!("{} doesn't exist in the local time zone", date))
+}
@@ -0,0 +1,1200 @@
+use crate::albums;
+use crate::archive::{self, ARCHIVED_CONDITION};
+use crate::events;
+use crate::faces;
+use crate::folder_review;
+use crate::geocode;
+use crate::ids::{self, PhotoId};
+use crate::machine_tags;
+use crate::media_info::VIDEO_EXTENSIONS;
+use crate::operation_log;
+use crate::pairings;
+use crate::quality;
+use crate::ratings::{self, Flag};
+use crate::scanner::{DuplicateStatus, PhotoFile, RelatedFile, RAW_EXTENSIONS};
+use crate::smart_albums;
+use crate::source_kind::{self, SourceKind};
+use crate::stacks;
+use crate::tags;
+use crate::transform::Transformed;
+use crate::transliterate::search_key;
+use crate::trash_history;
+use rusqlite::{params, params_from_iter, Connection, Row, ToSql};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Set once the live catalog's schema is up to date, so later opens in the
+/// same run just connect
+static SCHEMA_READY: AtomicBool = AtomicBool::new(false);
+
+/// Persistent library catalog stored in SQLite
+/// Holds every photo from the most recent scan (with EXIF and duplicate info)
+/// so the app can start instantly without rescanning
+pub struct Catalog {
+    conn: Connection,
+}
+
+/// A page of photos from the catalog
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PhotoPage {
+    pub total: usize,
+    pub offset: usize,
+    pub photos: Vec<PhotoFile>,
+    /// Member count of each stack whose cover is on this page, by cover path
+    pub stack_sizes: HashMap<String, usize>,
+}
+
+/// Filters, sort and pagination for `search_photos`
+/// All filters are optional and combined with AND
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SearchQuery {
+    /// Lowercase extensions without the dot, e.g. ["arw", "jpg"]
+    pub extensions: Vec<String>,
+    pub min_size: Option<u64>,
+    pub max_size: Option<u64>,
+    /// Inclusive range in ms since epoch, on capture date (falling back to file date)
+    pub date_from: Option<i64>,
+    pub date_to: Option<i64>,
+    /// Only photos taken in this year, local time (modified, without a
+    /// capture date)
+    pub year: Option<i32>,
+    /// Only photos under this folder (recursively)
+    pub directory: Option<String>,
+    /// true = only duplicates, false = only originals
+    pub is_duplicate: Option<bool>,
+    /// Case-insensitive filename substring, also matched across scripts
+    /// ("beijing" finds 北京.jpg, "moskva" finds Москва.jpg)
+    pub name_contains: Option<String>,
+    /// true = only videos, false = only stills
+    pub is_video: Option<bool>,
+    /// Only camera photos, screenshots or saved images
+    pub source_kind: Option<SourceKind>,
+    /// Video duration range in seconds (stills never match)
+    pub min_duration: Option<f64>,
+    pub max_duration: Option<f64>,
+    /// Only members of this album
+    pub album_id: Option<i64>,
+    /// Only photos in which this person's face was found
+    pub person_id: Option<i64>,
+    /// Only photos carrying every one of these tags
+    pub tags: Vec<String>,
+    /// Only photos labeled with every one of these by `detect_machine_tags`;
+    /// kept apart from the user's own tags
+    pub machine_tags: Vec<String>,
+    /// true = only photos with a GPS position, false = only photos without
+    pub has_location: Option<bool>,
+    /// Case-insensitive substring of the place name, e.g. "paris" or ", JP"
+    pub place: Option<String>,
+    /// Case-insensitive substring of the camera make or model, e.g. "iphone"
+    pub camera: Option<String>,
+    /// Only photos inside this map area
+    pub bounds: Option<GeoBounds>,
+    /// Resolution range in megapixels (photos of unknown size never match)
+    pub min_megapixels: Option<f64>,
+    pub max_megapixels: Option<f64>,
+    /// Range of width / height as displayed: below 1 is portrait, above 1
+    /// landscape, e.g. 1.7 to 1.8 for 16:9
+    pub min_aspect_ratio: Option<f64>,
+    pub max_aspect_ratio: Option<f64>,
+    /// Only photos with at least this many stars
+    pub min_rating: Option<u8>,
+    /// Only photos with at most this many stars; 0 finds unrated photos
+    pub max_rating: Option<u8>,
+    pub flag: Option<Flag>,
+    /// Color label, e.g. "Red"
+    pub label: Option<String>,
+    /// Also list archived photos, which are hidden by default
+    pub include_archived: bool,
+    /// Show every member of a stack instead of just its cover
+    pub expand_stacks: bool,
+    /// Only members of this stack (all of them, cover included)
+    pub stack_id: Option<i64>,
+    /// "name" | "date" | "size" | "path" (default "date")
+    pub sort_field: Option<String>,
+    /// "asc" | "desc" (default "desc")
+    pub sort_order: Option<String>,
+    pub offset: usize,
+    /// Page size (default 500)
+    pub limit: Option<usize>,
+}
+
+/// A map area in decimal degrees
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct GeoBounds {
+    pub south: f64,
+    pub west: f64,
+    pub north: f64,
+    pub east: f64,
+}
+
+/// Default page size for searches
+const DEFAULT_SEARCH_LIMIT: usize = 500;
+
+impl SearchQuery {
+    /// SQL WHERE clause (without the keyword) and its parameters
+    fn where_clause(&self) -> (String, Vec<Box<dyn ToSql>>) {
+        let mut clauses: Vec<String> = Vec::new();
+        let mut values: Vec<Box<dyn ToSql>> = Vec::new();
+
+        if !self.extensions.is_empty() {
+            let placeholders = vec!["?"; self.extensions.len()].join(", ");
+            clauses.push(format!("extension IN ({})", placeholders));
+            for ext in &self.extensions {
+                values.push(Box::new(ext.trim_start_matches('.').to_lowercase()));
+            }
+        }
+        if let Some(min) = self.min_size {
+            clauses.push("size >= ?".to_string());
+            values.push(Box::new(min as i64));
+        }
+        if let Some(max) = self.max_size {
+            clauses.push("size <= ?".to_string());
+            values.push(Box::new(max as i64));
+        }
+        if let Some(from) = self.date_from {
+            clauses.push("COALESCE(capture_date, modified_at) >= ?".to_string());
+            values.push(Box::new(from));
+        }
+        if let Some(to) = self.date_to {
+            clauses.push("COALESCE(capture_date, modified_at) <= ?".to_string());
+            values.push(Box::new(to));
+        }
+        if let Some(year) = self.year {
+            let date = "COALESCE(capture_date, modified_at) / 1000, 'unixepoch', 'localtime'";
+            clauses.push(format!("strftime('%Y', {}) = ?", date));
+            values.push(Box::new(format!("{:04}", year)));
+        }
+        if let Some(dir) = self.directory.as_deref().filter(|d| !d.is_empty()) {
+            let prefix = format!("{}/", dir.trim_end_matches('/'));
+            clauses.push("path LIKE ? ESCAPE '\\'".to_string());
+            values.push(Box::new(format!("{}%", escape_like(&prefix))));
+        }
+        if let Some(is_duplicate) = self.is_duplicate {
+            clauses.push("is_duplicate = ?".to_string());
+            values.push(Box::new(is_duplicate));
+        }
+        if let Some(text) = self.name_contains.as_deref().filter(|t| !t.is_empty()) {
+            // LIKE is case-insensitive for ASCII in SQLite
+            let key = search_key(text);
+            if key.is_empty() {
+                clauses.push("name LIKE ? ESCAPE '\\'".to_string());
+            } else {
+                clauses.push("(name LIKE ? ESCAPE '\\' OR name_key LIKE ?)".to_string());
+            }
+            values.push(Box::new(format!("%{}%", escape_like(text))));
+            if !key.is_empty() {
+                // Keys are only ASCII letters and digits, so there is nothing to escape
+                values.push(Box::new(format!("%{}%", key)));
+            }
+        }
+        if let Some(is_video) = self.is_video {
+            let list = VIDEO_EXTENSIONS
+                .iter()
+                .map(|e| format!("'{}'", e))
+                .collect::<Vec<_>>()
+                .join(", ");
+            let op = if is_video { "IN" } else { "NOT IN" };
+            clauses.push(format!("extension {} ({})", op, list));
+        }
+        if let Some(kind) = self.source_kind {
+            clauses.push("source_kind = ?".to_string());
+            values.push(Box::new(kind.as_str()));
+        }
+        if let Some(min) = self.min_duration {
+            clauses.push("json_extract(media_info, '$.durationSecs') >= ?".to_string());
+            values.push(Box::new(min));
+        }
+        if let Some(max) = self.max_duration {
+            clauses.push("json_extract(media_info, '$.durationSecs') <= ?".to_string());
+            values.push(Box::new(max));
+        }
+        if let Some(has_location) = self.has_location {
+            clauses.push(if has_location {
+                "latitude IS NOT NULL".to_string()
+            } else {
+                "latitude IS NULL".to_string()
+            });
+        }
+        if let Some(place) = self
+            .place
+            .as_deref()
+            .map(str::trim)
+            .filter(|p| !p.is_empty())
+        {
+            clauses.push("place_name LIKE ? ESCAPE '\\'".to_string());
+            values.push(Box::new(format!("%{}%", escape_like(place))));
+        }
+        if let Some(camera) = self
+            .camera
+            .as_deref()
+            .map(str::trim)
+            .filter(|c| !c.is_empty())
+        {
+            clauses.push(
+                "(camera_make LIKE ? ESCAPE '\\' OR camera_model LIKE ? ESCAPE '\\')".to_string(),
+            );
+            let pattern = format!("%{}%", escape_like(camera));
+            values.push(Box::new(pattern.clone()));
+            values.push(Box::new(pattern));
+        }
+        if let Some(bounds) = &self.bounds {
+            clauses.push("latitude BETWEEN ? AND ?".to_string());
+            values.push(Box::new(bounds.south));
+            values.push(Box::new(bounds.north));
+            // An area across the antimeridian has its west edge east of its east edge
+            clauses.push(if bounds.west <= bounds.east {
+                "longitude BETWEEN ? AND ?".to_string()
+            } else {
+                "(longitude >= ? OR longitude <= ?)".to_string()
+            });
+            values.push(Box::new(bounds.west));
+            values.push(Box::new(bounds.east));
+        }
+        if let Some(min) = self.min_megapixels {
+            clauses.push("width * height >= ?".to_string());
+            values.push(Box::new(min * 1_000_000.0));
+        }
+        if let Some(max) = self.max_megapixels {
+            clauses.push("width * height <= ?".to_string());
+            values.push(Box::new(max * 1_000_000.0));
+        }
+        if let Some(min) = self.min_aspect_ratio {
+            clauses.push("CAST(width AS REAL) / height >= ?".to_string());
+            values.push(Box::new(min));
+        }
+        if let Some(max) = self.max_aspect_ratio {
+            clauses.push("CAST(width AS REAL) / height <= ?".to_string());
+            values.push(Box::new(max));
+        }
+        if let Some(album_id) = self.album_id {
+            clauses.push("path IN (SELECT path FROM album_photos WHERE album_id = ?)".to_string());
+            values.push(Box::new(album_id));
+        }
+        if let Some(person_id) = self.person_id {
+            clauses.push("path IN (SELECT path FROM faces WHERE person_id = ?)".to_string());
+            values.push(Box::new(person_id));
+        }
+        for (table, tags) in [
+            ("photo_tags", &self.tags),
+            ("machine_tags", &self.machine_tags),
+        ] {
+            // Tags compare case-insensitively, so dedupe the same way to keep the count right
+            let mut seen = HashSet::new();
+            let tags: Vec<&str> = tags
+                .iter()
+                .map(|t| t.trim())
+                .filter(|t| !t.is_empty() && seen.insert(t.to_lowercase()))
+                .collect();
+            if !tags.is_empty() {
+                let placeholders = vec!["?"; tags.len()].join(", ");
+                clauses.push(format!(
+                    "path IN (SELECT path FROM {} WHERE tag IN ({}) \
+                    GROUP BY path HAVING COUNT(*) = {})",
+                    table,
+                    placeholders,
+                    tags.len()
+                ));
+                for tag in tags {
+                    values.push(Box::new(tag.to_string()));
+                }
+            }
+        }
+        if let Some(min_rating) = self.min_rating.filter(|r| *r > 0) {
+            clauses.push("path IN (SELECT path FROM photo_ratings WHERE rating >= ?)".to_string());
+            values.push(Box::new(min_rating));
+        }
+        if let Some(max_rating) = self.max_rating {
+            // Unrated photos have no row or a rating of 0
+            clauses
+                .push("path NOT IN (SELECT path FROM photo_ratings WHERE rating > ?)".to_string());
+            values.push(Box::new(max_rating));
+        }
+        if let Some(flag) = self.flag {
+            clauses.push("path IN (SELECT path FROM photo_ratings WHERE flag = ?)".to_string());
+            values.push(Box::new(flag.as_str()));
+        }
+        if let Some(label) = self.label.as_deref().filter(|l| !l.is_empty()) {
+            clauses.push(
+                "path IN (SELECT path FROM photo_ratings WHERE label = ? COLLATE NOCASE)"
+                    .to_string(),
+            );
+            values.push(Box::new(label.to_string()));
+        }
+        if !self.include_archived {
+            clauses.push(format!("NOT {}", ARCHIVED_CONDITION));
+        }
+        if let Some(stack_id) = self.stack_id {
+            clauses.push("path IN (SELECT path FROM stack_members WHERE stack_id = ?)".to_string());
+            values.push(Box::new(stack_id));
+        } else if !self.expand_stacks {
+            // Members stand in for a cover that is no longer cataloged
+            clauses.push(
+                "path NOT IN (SELECT m.path FROM stack_members m JOIN stacks s ON s.id = m.stack_id
+                WHERE m.path != s.cover_path AND s.cover_path IN (SELECT path FROM photos))"
+                    .to_string(),
+            );
+        }
+
+        let clause = if clauses.is_empty() {
+            "1".to_string()
+        } else {
+            clauses.join(" AND ")
+        };
+        (clause, values)
+    }
+
+    /// SQL ORDER BY expression (without the keyword)
+    fn order_by(&self) -> String {
+        let column = match self.sort_field.as_deref() {
+            Some("name") => "name COLLATE NOCASE",
+            Some("size") => "size",
+            Some("path") => "path",
+            _ => "COALESCE(capture_date, modified_at)",
+        };
+        let direction = match self.sort_order.as_deref() {
+            Some("asc") => "ASC",
+            _ => "DESC",
+        };
+        format!("{} {}, path", column, direction)
+    }
+}
+
+/// Escape LIKE wildcards so user text matches literally
+fn escape_like(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+/// Columns selected for a full `PhotoFile`, in `photo_from_row` order
+const PHOTO_COLUMNS: &str = "id, path, name, directory, extension, size, disk_size, modified_at,
+    hash, thumbnail_path, related_files, is_duplicate, duplicate_of, is_cloud_placeholder,
+    capture_date, camera_make, camera_model, sidecar_metadata, photo_id, root_priority,
+    previously_trashed_at, media_info, animation, duplicate_status, latitude, longitude,
+    place_name, width, height, orientation, pixel_hash, hardlink_of, source_kind";
+
+impl Catalog {
+    /// Open or create the catalog database
+    pub fn open() -> Result<Self, String> {
+        Ok(Self {
+            conn: Self::connection()?,
+        })
+    }
+
+    /// A connection to the live catalog database, for the modules that keep
+    /// their tables in it
+    /// The first call of a run creates the database and brings every table up
+    /// to date; later calls only connect
+    pub fn connection() -> Result<Connection, String> {
+        let db_path = Self::db_path();
+        if !SCHEMA_READY.load(Ordering::Acquire) || !db_path.exists() {
+            let catalog = Self::open_path(&db_path)?;
+            SCHEMA_READY.store(true, Ordering::Release);
+            return Ok(catalog.conn);
+        }
+        Connection::open(db_path).map_err(|e| e.to_string())
+    }
+
+    /// Open a catalog database at any path (the live one or a snapshot),
+    /// bringing its schema up to date
+    pub fn open_path(db_path: &Path) -> Result<Self, String> {
+        // Ensure parent directory exists
+        if let Some(parent) = db_path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+
+        let conn = Connection::open(db_path).map_err(|e| e.to_string())?;
+
+        conn.execute_batch(
+            "PRAGMA journal_mode = WAL;
+            CREATE TABLE IF NOT EXISTS photos (
+                id TEXT PRIMARY KEY,
+                path TEXT NOT NULL,
+                name TEXT NOT NULL,
+                directory TEXT NOT NULL,
+                extension TEXT NOT NULL,
+                size INTEGER NOT NULL,
+                disk_size INTEGER NOT NULL,
+                modified_at INTEGER NOT NULL,
+                hash TEXT,
+                thumbnail_path TEXT,
+                related_files TEXT NOT NULL DEFAULT '[]',
+                is_duplicate INTEGER NOT NULL DEFAULT 0,
+                duplicate_of TEXT,
+                is_cloud_placeholder INTEGER NOT NULL DEFAULT 0,
+                capture_date INTEGER,
+                camera_make TEXT,
+                camera_model TEXT,
+                sidecar_metadata TEXT,
+                photo_id INTEGER NOT NULL DEFAULT 0,
+                root_priority INTEGER NOT NULL DEFAULT 0,
+                previously_trashed_at INTEGER,
+                media_info TEXT,
+                animation TEXT,
+                name_key TEXT,
+                duplicate_status TEXT NOT NULL DEFAULT 'checked',
+                latitude REAL,
+                longitude REAL,
+                place_name TEXT,
+                width INTEGER,
+                height INTEGER,
+                orientation INTEGER,
+                pixel_hash TEXT,
+                hardlink_of TEXT,
+                source_kind TEXT
+            );
+            CREATE INDEX IF NOT EXISTS idx_photos_hash ON photos(hash);
+            CREATE INDEX IF NOT EXISTS idx_photos_date ON photos(COALESCE(capture_date, modified_at));
+            ",
+        )
+        .map_err(|e| e.to_string())?;
+
+        // Migrate catalogs created before these columns existed
+        add_column_if_missing(&conn, "photos", "sidecar_metadata", "TEXT")?;
+        add_column_if_missing(&conn, "photos", "photo_id", "INTEGER NOT NULL DEFAULT 0")?;
+        add_column_if_missing(
+            &conn,
+            "photos",
+            "root_priority",
+            "INTEGER NOT NULL DEFAULT 0",
+        )?;
+        add_column_if_missing(&conn, "photos", "previously_trashed_at", "INTEGER")?;
+        add_column_if_missing(&conn, "photos", "media_info", "TEXT")?;
+        add_column_if_missing(&conn, "photos", "animation", "TEXT")?;
+        add_column_if_missing(&conn, "photos", "name_key", "TEXT")?;
+        add_column_if_missing(
+            &conn,
+            "photos",
+            "duplicate_status",
+            "TEXT NOT NULL DEFAULT 'checked'",
+        )?;
+        add_column_if_missing(&conn, "photos", "latitude", "REAL")?;
+        add_column_if_missing(&conn, "photos", "longitude", "REAL")?;
+        add_column_if_missing(&conn, "photos", "place_name", "TEXT")?;
+        add_column_if_missing(&conn, "photos", "width", "INTEGER")?;
+        add_column_if_missing(&conn, "photos", "height", "INTEGER")?;
+        add_column_if_missing(&conn, "photos", "orientation", "INTEGER")?;
+        add_column_if_missing(&conn, "photos", "pixel_hash", "TEXT")?;
+        add_column_if_missing(&conn, "photos", "hardlink_of", "TEXT")?;
+        add_column_if_missing(&conn, "photos", "source_kind", "TEXT")?;
+        fill_name_keys(&conn)?;
+        fill_source_kinds(&conn)?;
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_photos_photo_id ON photos(photo_id)",
+            [],
+        )
+        .map_err(|e| e.to_string())?;
+
+        ids::create_tables(&conn).map_err(|e| e.to_string())?;
+        albums::create_tables(&conn).map_err(|e| e.to_string())?;
+        folder_review::create_tables(&conn).map_err(|e| e.to_string())?;
+        tags::create_tables(&conn).map_err(|e| e.to_string())?;
+        ratings::create_tables(&conn).map_err(|e| e.to_string())?;
+        trash_history::create_tables(&conn).map_err(|e| e.to_string())?;
+        stacks::create_tables(&conn).map_err(|e| e.to_string())?;
+        add_column_if_missing(&conn, "stacks", "kind", "TEXT NOT NULL DEFAULT 'manual'")?;
+        archive::create_tables(&conn).map_err(|e| e.to_string())?;
+        events::create_tables(&conn).map_err(|e| e.to_string())?;
+        pairings::create_tables(&conn).map_err(|e| e.to_string())?;
+        operation_log::create_tables(&conn).map_err(|e| e.to_string())?;
+        add_column_if_missing(&conn, "operation_files", "verified_hash", "TEXT")?;
+        faces::create_tables(&conn).map_err(|e| e.to_string())?;
+        machine_tags::create_tables(&conn).map_err(|e| e.to_string())?;
+        quality::create_tables(&conn).map_err(|e| e.to_string())?;
+        smart_albums::create_tables(&conn).map_err(|e| e.to_string())?;
+
+        Ok(Self { conn })
+    }
+
+    pub fn db_path() -> PathBuf {
+        dirs::config_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("photo-manager")
+            .join("catalog.db")
+    }
+
+    /// Replace the catalog contents with the result of a scan
+    pub fn replace_all(&mut self, photos: &[PhotoFile]) -> Result<(), String> {
+        let tx = self.conn.transaction().map_err(|e| e.to_string())?;
+        tx.execute("DELETE FROM photos", [])
+            .map_err(|e| e.to_string())?;
+        {
+            let mut stmt = tx
+                .prepare(&format!(
+                    "INSERT INTO photos ({}, name_key) VALUES
+                    (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21, ?22, ?23, ?24, ?25, ?26, ?27, ?28, ?29, ?30, ?31, ?32, ?33, ?34)",
+                    PHOTO_COLUMNS
+                ))
+                .map_err(|e| e.to_string())?;
+
+            for photo in photos {
+                let related =
+                    serde_json::to_string(&photo.related_files).map_err(|e| e.to_string())?;
+                let sidecar = photo
+                    .sidecar_metadata
+                    .as_ref()
+                    .map(serde_json::to_string)
+                    .transpose()
+                    .map_err(|e| e.to_string())?;
+                let media_info = photo
+                    .media_info
+                    .as_ref()
+                    .map(serde_json::to_string)
+                    .transpose()
+                    .map_err(|e| e.to_string())?;
+                let animation = photo
+                    .animation
+                    .as_ref()
+                    .map(serde_json::to_string)
+                    .transpose()
+                    .map_err(|e| e.to_string())?;
+                stmt.execute(params![
+                    photo.id,
+                    photo.path,
+                    photo.name,
+                    photo.directory,
+                    photo.extension,
+                    photo.size as i64,
+                    photo.disk_size as i64,
+                    photo.modified_at,
+                    photo.hash,
+                    photo.thumbnail_path,
+                    related,
+                    photo.is_duplicate,
+                    photo.duplicate_of,
+                    photo.is_cloud_placeholder,
+                    photo.capture_date,
+                    photo.camera_make,
+                    photo.camera_model,
+                    sidecar,
+                    photo.photo_id.0,
+                    photo.root_priority,
+                    photo.previously_trashed_at,
+                    media_info,
+                    animation,
+                    photo.duplicate_status.as_str(),
+                    photo.latitude,
+                    photo.longitude,
+                    photo.place_name,
+                    photo.width,
+                    photo.height,
+                    photo.orientation,
+                    photo.pixel_hash,
+                    photo.hardlink_of,
+                    photo.source_kind.as_str(),
+                    search_key(&photo.name),
+                ])
+                .map_err(|e| e.to_string())?;
+            }
+        }
+        tx.commit().map_err(|e| e.to_string())
+    }
+
+    /// Follow a file the app moved or renamed, as a photo or as another
+    /// photo's related file, so the catalog doesn't wait for a rescan to find it
+    pub fn relocate(&mut self, from: &str, to: &str) -> Result<(), String> {
+        let target = Path::new(to);
+        let name = target
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let directory = target
+            .parent()
+            .map(|p| p.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        let tx = self.conn.transaction().map_err(|e| e.to_string())?;
+        // A row still at the new path is a file that used to be there
+        tx.execute(
+            "DELETE FROM photos WHERE path = ?2
+                AND EXISTS (SELECT 1 FROM photos WHERE path = ?1)",
+            params![from, to],
+        )
+        .map_err(|e| e.to_string())?;
+        tx.execute(
+            "UPDATE photos SET id = ?2, path = ?2, name = ?3, directory = ?4, name_key = ?5
+            WHERE path = ?1",
+            params![from, to, name, directory, search_key(&name)],
+        )
+        .map_err(|e| e.to_string())?;
+        tx.execute(
+            "UPDATE photos SET duplicate_of = ?2 WHERE duplicate_of = ?1",
+            params![from, to],
+        )
+        .map_err(|e| e.to_string())?;
+        tx.execute(
+            "UPDATE photos SET hardlink_of = ?2 WHERE hardlink_of = ?1",
+            params![from, to],
+        )
+        .map_err(|e| e.to_string())?;
+
+        let owners: Vec<(String, String)> = {
+            let mut stmt = tx
+                .prepare(
+                    "SELECT id, related_files FROM photos WHERE EXISTS
+                    (SELECT 1 FROM json_each(related_files) WHERE json_extract(value, '$.path') = ?1)",
+                )
+                .map_err(|e| e.to_string())?;
+            let rows = stmt
+                .query_map(params![from], |row| Ok((row.get(0)?, row.get(1)?)))
+                .map_err(|e| e.to_string())?;
+            rows.collect::<Result<_, _>>().map_err(|e| e.to_string())?
+        };
+        for (id, related) in owners {
+            let mut related: Vec<RelatedFile> = serde_json::from_str(&related).unwrap_or_default();
+            for file in related.iter_mut().filter(|f| f.path == from) {
+                file.path = to.to_string();
+                file.name = name.clone();
+            }
+            let related = serde_json::to_string(&related).map_err(|e| e.to_string())?;
+            tx.execute(
+                "UPDATE photos SET related_files = ?2 WHERE id = ?1",
+                params![id, related],
+            )
+            .map_err(|e| e.to_string())?;
+        }
+        stacks::relocate(&tx, from, to).map_err(|e| e.to_string())?;
+        archive::relocate(&tx, from, to).map_err(|e| e.to_string())?;
+        pairings::relocate(&tx, from, to).map_err(|e| e.to_string())?;
+        tags::relocate(&tx, from, to).map_err(|e| e.to_string())?;
+        ratings::relocate(&tx, from, to).map_err(|e| e.to_string())?;
+        faces::relocate(&tx, from, to).map_err(|e| e.to_string())?;
+        machine_tags::relocate(&tx, from, to).map_err(|e| e.to_string())?;
+        quality::relocate(&tx, from, to).map_err(|e| e.to_string())?;
+
+        tx.commit().map_err(|e| e.to_string())
+    }
+
+    /// Record that files were downloaded from or evicted to the cloud
+    pub fn set_cloud_placeholder(
+        &mut self,
+        paths: &[String],
+        placeholder: bool,
+    ) -> Result<(), String> {
+        let tx = self.conn.transaction().map_err(|e| e.to_string())?;
+        {
+            let mut stmt = tx
+                .prepare("UPDATE photos SET is_cloud_placeholder = ?2 WHERE path = ?1")
+                .map_err(|e| e.to_string())?;
+            for path in paths {
+                stmt.execute(params![path, placeholder])
+                    .map_err(|e| e.to_string())?;
+            }
+        }
+        tx.commit().map_err(|e| e.to_string())
+    }
+
+    /// Record positions written to files, named after the nearest town, so
+    /// location searches see them before the next scan
+    pub fn set_locations(
+        &mut self,
+        locations: &[(String, Option<(f64, f64)>)],
+    ) -> Result<(), String> {
+        let tx = self.conn.transaction().map_err(|e| e.to_string())?;
+        {
+            let mut stmt = tx
+                .prepare(
+                    "UPDATE photos SET latitude = ?2, longitude = ?3, place_name = ?4
+                    WHERE path = ?1",
+                )
+                .map_err(|e| e.to_string())?;
+            for (path, position) in locations {
+                let place = position.and_then(|(lat, lon)| geocode::place_name(lat, lon));
+                stmt.execute(params![
+                    path,
+                    position.map(|(lat, _)| lat),
+                    position.map(|(_, lon)| lon),
+                    place
+                ])
+                .map_err(|e| e.to_string())?;
+            }
+        }
+        tx.commit().map_err(|e| e.to_string())
+    }
+
+    /// Record capture times written by `shift_capture_time`, as (path, ms since epoch)
+    pub fn set_capture_dates(&mut self, dates: &[(String, i64)]) -> Result<(), String> {
+        let tx = self.conn.transaction().map_err(|e| e.to_string())?;
+        {
+            let mut stmt = tx
+                .prepare("UPDATE photos SET capture_date = ?2 WHERE path = ?1")
+                .map_err(|e| e.to_string())?;
+            for (path, date) in dates {
+                stmt.execute(params![path, date])
+                    .map_err(|e| e.to_string())?;
+            }
+        }
+        tx.commit().map_err(|e| e.to_string())
+    }
+
+    /// Record the new hashes of files rewritten in place; photos the scan
+    /// never hashed are left without one
+    pub fn set_hashes(&mut self, hashes: &[(String, String)]) -> Result<(), String> {
+        let tx = self.conn.transaction().map_err(|e| e.to_string())?;
+        {
+            let mut stmt = tx
+                .prepare("UPDATE photos SET hash = ?2 WHERE path = ?1 AND hash IS NOT NULL")
+                .map_err(|e| e.to_string())?;
+            for (path, hash) in hashes {
+                stmt.execute(params![path, hash])
+                    .map_err(|e| e.to_string())?;
+            }
+        }
+        tx.commit().map_err(|e| e.to_string())
+    }
+
+    /// Add files an import copied into the library, as (path, size, hash), so
+    /// they count as in the library before the next scan reads their metadata
+    /// Paths already in the catalog are left as they are
+    pub fn add_imported(&mut self, files: &[(String, u64, String)]) -> Result<(), String> {
+        let tx = self.conn.transaction().map_err(|e| e.to_string())?;
+        {
+            let mut stmt = tx
+                .prepare(
+                    "INSERT OR IGNORE INTO photos
+                        (id, path, name, directory, extension, size, disk_size, modified_at,
+                         hash, thumbnail_path, name_key)
+                     VALUES (?1, ?1, ?2, ?3, ?4, ?5, ?5, ?6, ?7, ?8, ?9)",
+                )
+                .map_err(|e| e.to_string())?;
+            for (path, size, hash) in files {
+                let file = Path::new(path);
+                let name = file
+                    .file_name()
+                    .and_then(|n| n.to_str())
+                    .unwrap_or_default();
+                let directory = file
+                    .parent()
+                    .and_then(|p| p.file_name())
+                    .and_then(|n| n.to_str())
+                    .unwrap_or_default();
+                let extension = file
+                    .extension()
+                    .and_then(|e| e.to_str())
+                    .unwrap_or_default()
+                    .to_lowercase();
+                // The copy keeps the card file's dates
+                let modified_at = std::fs::metadata(file)
+                    .and_then(|m| m.created().or_else(|_| m.modified()))
+                    .ok()
+                    .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+                    .map(|d| d.as_millis() as i64)
+                    .unwrap_or(0);
+                // RAW files get their JPEG as thumbnail once a scan pairs them
+                let thumbnail = (!RAW_EXTENSIONS.contains(&extension.as_str())
+                    && !VIDEO_EXTENSIONS.contains(&extension.as_str()))
+                .then_some(path);
+                stmt.execute(params![
+                    path,
+                    name,
+                    directory,
+                    extension,
+                    *size as i64,
+                    modified_at,
+                    hash,
+                    thumbnail,
+                    search_key(name),
+                ])
+                .map_err(|e| e.to_string())?;
+            }
+        }
+        tx.commit().map_err(|e| e.to_string())
+    }
+
+    /// Record new EXIF orientations and displayed sizes after photos were turned
+    pub fn set_orientations(
+        &mut self,
+        transformed: &[(String, Transformed)],
+    ) -> Result<(), String> {
+        let tx = self.conn.transaction().map_err(|e| e.to_string())?;
+        {
+            let mut stmt = tx
+                .prepare(
+                    "UPDATE photos SET orientation = ?2,
+                        width = COALESCE(?3, width), height = COALESCE(?4, height)
+                    WHERE path = ?1",
+                )
+                .map_err(|e| e.to_string())?;
+            for (path, photo) in transformed {
+                stmt.execute(params![
+                    path,
+                    photo.orientation,
+                    photo.dimensions.map(|(w, _)| w),
+                    photo.dimensions.map(|(_, h)| h)
+                ])
+                .map_err(|e| e.to_string())?;
+            }
+        }
+        tx.commit().map_err(|e| e.to_string())
+    }
+
+    /// Write a compacted, consistent copy of the whole database to `path`
+    pub fn backup_to(&self, path: &Path) -> Result<(), String> {
+        self.conn
+            .execute("VACUUM INTO ?1", params![path.to_string_lossy()])
+            .map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    /// Every photo in the catalog
+    pub fn all_photos(&self) -> Result<Vec<PhotoFile>, String> {
+        let mut stmt = self
+            .conn
+            .prepare(&format!("SELECT {} FROM photos", PHOTO_COLUMNS))
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map([], photo_from_row)
+            .map_err(|e| e.to_string())?;
+        let mut photos = rows
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+        self.fill_stack_ids(&mut photos)?;
+        Ok(photos)
+    }
+
+    /// Stacks are kept in their own tables, so every loaded photo is looked up
+    fn fill_stack_ids(&self, photos: &mut [PhotoFile]) -> Result<(), String> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT path, stack_id FROM stack_members")
+            .map_err(|e| e.to_string())?;
+        let memberships = stmt
+            .query_map([], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?))
+            })
+            .map_err(|e| e.to_string())?
+            .collect::<Result<HashMap<_, _>, _>>()
+            .map_err(|e| e.to_string())?;
+        for photo in photos {
+            photo.stack_id = memberships.get(&photo.path).copied();
+        }
+        Ok(())
+    }
+
+    /// Duplicate status of every cataloged photo (id → is_duplicate)
+    pub fn duplicate_state(&self) -> Result<HashMap<String, bool>, String> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT id, is_duplicate FROM photos")
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .map_err(|e| e.to_string())?;
+        rows.collect::<Result<HashMap<_, _>, _>>()
+            .map_err(|e| e.to_string())
+    }
+
+    /// Photo count and total size of each folder holding cataloged photos,
+    /// not counting its subfolders
+    pub fn folder_totals(&self) -> Result<HashMap<String, (usize, u64)>, String> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT path, size FROM photos")
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)? as u64))
+            })
+            .map_err(|e| e.to_string())?;
+        // `directory` only holds the folder's name, so folders go by the path's parent
+        let mut totals: HashMap<String, (usize, u64)> = HashMap::new();
+        for row in rows {
+            let (path, size) = row.map_err(|e| e.to_string())?;
+            let Some(folder) = Path::new(&path).parent() else {
+                continue;
+            };
+            let total = totals
+                .entry(folder.to_string_lossy().to_string())
+                .or_default();
+            total.0 += 1;
+            total.1 += size;
+        }
+        Ok(totals)
+    }
+
+    /// Run several reads against one consistent version of the catalog
+    /// In WAL mode a read transaction keeps seeing the snapshot taken at its first
+    /// read, so a scan committing `replace_all` meanwhile can't produce a count from
+    /// one scan and rows from the next; the writer is never blocked
+    pub fn read_snapshot<T>(&self, read: impl FnOnce() -> Result<T, String>) -> Result<T, String> {
+        let tx = self
+            .conn
+            .unchecked_transaction()
+            .map_err(|e| e.to_string())?;
+        let result = read();
+        // Read-only, so ending the transaction either way just releases the snapshot
+        tx.finish().map_err(|e| e.to_string())?;
+        result
+    }
+
+    /// A page of photos that aren't archived, newest first
+    pub fn query(&self, offset: usize, limit: usize) -> Result<PhotoPage, String> {
+        self.read_snapshot(|| {
+            let total: i64 = self
+                .conn
+                .query_row(
+                    &format!(
+                        "SELECT COUNT(*) FROM photos WHERE NOT {}",
+                        ARCHIVED_CONDITION
+                    ),
+                    [],
+                    |row| row.get(0),
+                )
+                .map_err(|e| e.to_string())?;
+
+            let mut stmt = self
+                .conn
+                .prepare(&format!(
+                    "SELECT {} FROM photos WHERE NOT {}
+                     ORDER BY COALESCE(capture_date, modified_at) DESC, path
+                     LIMIT ?1 OFFSET ?2",
+                    PHOTO_COLUMNS, ARCHIVED_CONDITION
+                ))
+                .map_err(|e| e.to_string())?;
+            let mut photos = stmt
+                .query_map(params![limit as i64, offset as i64], photo_from_row)
+                .map_err(|e| e.to_string())?
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| e.to_string())?;
+            self.fill_stack_ids(&mut photos)?;
+
+            Ok(PhotoPage {
+                total: total as usize,
+                offset,
+                photos,
+                stack_sizes: HashMap::new(),
+            })
+        })
+    }
+
+    /// Filtered, sorted page of photos
+    pub fn search(&self, query: &SearchQuery) -> Result<PhotoPage, String> {
+        let (clause, values) = query.where_clause();
+        let limit = query.limit.unwrap_or(DEFAULT_SEARCH_LIMIT);
+
+        self.read_snapshot(|| self.search_page(query, &clause, &values, limit))
+    }
+
+    fn search_page(
+        &self,
+        query: &SearchQuery,
+        clause: &str,
+        values: &[Box<dyn ToSql>],
+        limit: usize,
+    ) -> Result<PhotoPage, String> {
+        let total: i64 = self
+            .conn
+            .query_row(
+                &format!("SELECT COUNT(*) FROM photos WHERE {}", clause),
+                params_from_iter(values.iter()),
+                |row| row.get(0),
+            )
+            .map_err(|e| e.to_string())?;
+
+        let mut stmt = self
+            .conn
+            .prepare(&format!(
+                "SELECT {} FROM photos WHERE {} ORDER BY {} LIMIT {} OFFSET {}",
+                PHOTO_COLUMNS,
+                clause,
+                query.order_by(),
+                limit,
+                query.offset
+            ))
+            .map_err(|e| e.to_string())?;
+        let mut photos = stmt
+            .query_map(params_from_iter(values.iter()), photo_from_row)
+            .map_err(|e| e.to_string())?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+        self.fill_stack_ids(&mut photos)?;
+
+        let stack_sizes = self.stack_sizes(&photos)?;
+
+        Ok(PhotoPage {
+            total: total as usize,
+            offset: query.offset,
+            photos,
+            stack_sizes,
+        })
+    }
+
+    /// Member counts of the stacks covered by `photos`
+    fn stack_sizes(&self, photos: &[PhotoFile]) -> Result<HashMap<String, usize>, String> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT COUNT(*) FROM stacks s JOIN stack_members m ON m.stack_id = s.id
+                WHERE s.cover_path = ?1",
+            )
+            .map_err(|e| e.to_string())?;
+        let mut sizes = HashMap::new();
+        for photo in photos {
+            let count: i64 = stmt
+                .query_row(params![photo.path], |row| row.get(0))
+                .map_err(|e| e.to_string())?;
+            if count > 0 {
+                sizes.insert(photo.path.clone(), count as usize);
+            }
+        }
+        Ok(sizes)
+    }
+
+    /// A single photo by id
+    pub fn get(&self, id: &str) -> Option<PhotoFile> {
+        let mut photo = self
+            .conn
+            .query_row(
+                &format!("SELECT {} FROM photos WHERE id = ?1", PHOTO_COLUMNS),
+                params![id],
+                photo_from_row,
+            )
+            .ok()?;
+        photo.stack_id = self
+            .conn
+            .query_row(
+                "SELECT stack_id FROM stack_members WHERE path = ?1",
+                params![photo.path],
+                |row| row.get(0),
+            )
+            .ok();
+        Some(photo)
+    }
+}
+
+/// Build a `PhotoFile` from a row selected with `PHOTO_COLUMNS`
+fn photo_from_row(row: &Row) -> rusqlite::Result<PhotoFile> {
+    let related: String = row.get(10)?;
+    let related_files: Vec<RelatedFile> = serde_json::from_str(&related).unwrap_or_default();
+
+    Ok(PhotoFile {
+        id: row.get(0)?,
+        path: row.get(1)?,
+        name: row.get(2)?,
+        directory: row.get(3)?,
+        extension: row.get(4)?,
+        size: row.get::<_, i64>(5)? as u64,
+        disk_size: row.get::<_, i64>(6)? as u64,
+        modified_at: row.get(7)?,
+        hash: row.get(8)?,
+        thumbnail_path: row.get(9)?,
+        related_files,
+        is_duplicate: row.get(11)?,
+        duplicate_of: row.get(12)?,
+        is_cloud_placeholder: row.get(13)?,
+        capture_date: row.get(14)?,
+        camera_make: row.get(15)?,
+        camera_model: row.get(16)?,
+        sidecar_metadata: row
+            .get::<_, Option<String>>(17)?
+            .and_then(|json| serde_json::from_str(&json).ok()),
+        photo_id: PhotoId(row.get(18)?),
+        root_priority: row.get(19)?,
+        previously_trashed_at: row.get(20)?,
+        media_info: row
+            .get::<_, Option<String>>(21)?
+            .and_then(|json| serde_json::from_str(&json).ok()),
+        animation: row
+            .get::<_, Option<String>>(22)?
+            .and_then(|json| serde_json::from_str(&json).ok()),
+        duplicate_status: DuplicateStatus::from_name(&row.get::<_, String>(23)?),
+        latitude: row.get(24)?,
+        longitude: row.get(25)?,
+        place_name: row.get(26)?,
+        width: row.get(27)?,
+        height: row.get(28)?,
+        orientation: row.get(29)?,
+        pixel_hash: row.get(30)?,
+        hardlink_of: row.get(31)?,
+        stack_id: None,
+        source_kind: row
+            .get::<_, Option<String>>(32)?
+            .map_or(SourceKind::Unknown, |kind| SourceKind::from_name(&kind)),
+    })
+}
+
+/// Compute transliterated search keys for rows cataloged before they existed
+fn fill_name_keys(conn: &Connection) -> Result<(), String> {
+    let names: Vec<(String, String)> = {
+        let mut stmt = conn
+            .prepare("SELECT id, name FROM photos WHERE name_key IS NULL")
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .map_err(|e| e.to_string())?;
+        rows.collect::<Result<_, _>>().map_err(|e| e.to_string())?
+    };
+    if names.is_empty() {
+        return Ok(());
+    }
+
+    let tx = conn.unchecked_transaction().map_err(|e| e.to_string())?;
+    {
+        let mut stmt = tx
+            .prepare("UPDATE photos SET name_key = ?2 WHERE id = ?1")
+            .map_err(|e| e.to_string())?;
+        for (id, name) in names {
+            stmt.execute(params![id, search_key(&name)])
+                .map_err(|e| e.to_string())?;
+        }
+    }
+    tx.commit().map_err(|e| e.to_string())
+}
+
+/// Guess where photos came from for rows cataloged before that was recorded
+fn fill_source_kinds(conn: &Connection) -> Result<(), String> {
+    let kinds: Vec<(String, SourceKind)> = {
+        let mut stmt = conn
+            .prepare(&format!(
+                "SELECT {} FROM photos WHERE source_kind IS NULL",
+                PHOTO_COLUMNS
+            ))
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map([], |row| {
+                let photo = photo_from_row(row)?;
+                Ok((photo.id.clone(), source_kind::classify(&photo)))
+            })
+            .map_err(|e| e.to_string())?;
+        rows.collect::<Result<_, _>>().map_err(|e| e.to_string())?
+    };
+    if kinds.is_empty() {
+        return Ok(());
+    }
+
+    let tx = conn.unchecked_transaction().map_err(|e| e.to_string())?;
+    {
+        let mut stmt = tx
+            .prepare("UPDATE photos SET source_kind = ?2 WHERE id = ?1")
+            .map_err(|e| e.to_string())?;
+        for (id, kind) in kinds {
+            stmt.execute(params![id, kind.as_str()])
+                .map_err(|e| e.to_string())?;
+        }
+    }
+    tx.commit().map_err(|e| e.to_string())
+}
+
+/// Add a column to an existing table unless it is already there
+fn add_column_if_missing(
+    conn: &Connection,
+    table: &str,
+    column: &str,
+    definition: &str,
+) -> Result<(), String> {
+    if conn
+        .prepare(&format!("SELECT {} FROM {} LIMIT 0", column, table))
+        .is_err()
+    {
+        conn.execute(
+            &format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition),
+            [],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
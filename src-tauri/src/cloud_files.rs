@@ -0,0 +1,112 @@
+use crate::scanner::is_cloud_placeholder;
+use std::path::Path;
+#[cfg(target_os = "macos")]
+use std::process::Command;
+#[cfg(target_os = "macos")]
+use std::thread;
+#[cfg(target_os = "macos")]
+use std::time::Duration;
+
+/// Run the first of several equivalent tools that succeeds
+/// `brctl` only knows iCloud Drive; `fileproviderctl` covers every File
+/// Provider (Dropbox, OneDrive, Google Drive) on macOS 12 and later
+#[cfg(target_os = "macos")]
+fn run_first(commands: &[(&str, &str)], path: &Path) -> Result<(), String> {
+    let mut last_error = String::new();
+    for (program, verb) in commands {
+        match Command::new(program).arg(verb).arg(path).output() {
+            Ok(output) if output.status.success() => return Ok(()),
+            Ok(output) => last_error = String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            Err(e) => last_error = format!("{}: {}", program, e),
+        }
+    }
+    Err(last_error)
+}
+
+/// Download a placeholder's content so it can be hashed or edited locally
+/// The download is requested from the provider and then waited for; `give_up`
+/// is polled meanwhile so a cancelled or skipped job doesn't wait forever
+#[cfg(target_os = "macos")]
+pub fn download(path: &Path, give_up: impl Fn() -> bool) -> Result<(), String> {
+    let path_str = path.to_string_lossy();
+    if !is_cloud_placeholder(&path_str) {
+        return Ok(());
+    }
+    run_first(
+        &[("brctl", "download"), ("fileproviderctl", "materialize")],
+        path,
+    )?;
+    while is_cloud_placeholder(&path_str) {
+        if give_up() {
+            return Err("Download abandoned".to_string());
+        }
+        thread::sleep(Duration::from_millis(500));
+    }
+    Ok(())
+}
+
+/// Drop a file's local content, keeping it in the cloud as a placeholder
+#[cfg(target_os = "macos")]
+pub fn evict(path: &Path) -> Result<(), String> {
+    if is_cloud_placeholder(&path.to_string_lossy()) {
+        return Ok(());
+    }
+    run_first(&[("brctl", "evict"), ("fileproviderctl", "evict")], path)
+}
+
+/// Reading a placeholder makes the Cloud Files API recall its content, so
+/// downloading is reading the file through; the read is chunked so `give_up`
+/// can interrupt a slow download
+#[cfg(windows)]
+pub fn download(path: &Path, give_up: impl Fn() -> bool) -> Result<(), String> {
+    use std::io::Read;
+
+    if !is_cloud_placeholder(&path.to_string_lossy()) {
+        return Ok(());
+    }
+    let mut file = std::fs::File::open(path).map_err(|e| e.to_string())?;
+    let mut buffer = vec![0u8; 1024 * 1024];
+    loop {
+        if give_up() {
+            return Err("Download abandoned".to_string());
+        }
+        match file.read(&mut buffer) {
+            Ok(0) => return Ok(()),
+            Ok(_) => {}
+            Err(e) => return Err(e.to_string()),
+        }
+    }
+}
+
+/// Unpinning a file (`attrib +U -P`) tells OneDrive and other Cloud Files
+/// providers to dehydrate it
+#[cfg(windows)]
+pub fn evict(path: &Path) -> Result<(), String> {
+    if is_cloud_placeholder(&path.to_string_lossy()) {
+        return Ok(());
+    }
+    let output = std::process::Command::new("attrib")
+        .args(["+U", "-P"])
+        .arg(path)
+        .output()
+        .map_err(|e| e.to_string())?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+}
+
+/// Linux sync clients keep full local copies (see `is_cloud_placeholder`)
+#[cfg(not(any(target_os = "macos", windows)))]
+pub fn download(path: &Path, _give_up: impl Fn() -> bool) -> Result<(), String> {
+    if is_cloud_placeholder(&path.to_string_lossy()) {
+        return Err("Cloud placeholders are not supported on this platform".to_string());
+    }
+    Ok(())
+}
+
+#[cfg(not(any(target_os = "macos", windows)))]
+pub fn evict(_path: &Path) -> Result<(), String> {
+    Err("Evicting cloud files is not supported on this platform".to_string())
+}
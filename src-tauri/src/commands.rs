@@ -1,242 +1,3907 @@
-use crate::config::AppConfig;
-use crate::scanner::{scan_directories_with_progress, PhotoFile};
+use crate::albums::{Album, Albums};
+use crate::analysis::AnalysisStore;
+use crate::animation::remove_cached_thumbnails;
+use crate::archive::{Archive, ArchivedEntry};
+use crate::backup_compare::{self, CompareOptions, TreeComparison};
+use crate::capture_time::{self, ShiftTarget};
+use crate::catalog::{Catalog, PhotoPage, SearchQuery};
+use crate::cloud_files;
+use crate::compare::{self, PhotoComparison};
+use crate::config::{default_organize_template, AppConfig, ReadOnlyRoots};
+use crate::consolidation::{
+    self, ConsolidationPlan, ConsolidationRules, PlanStep, StepAction, StepStatus,
+};
+use crate::convert::{self, convert_image, ConvertFormat};
+use crate::date_mismatch::{self, DateMismatch, MismatchOptions};
+use crate::delete_confirmation;
+use crate::directory_tree::{self, FolderNode};
+use crate::duplicates::{
+    duplicate_groups, rank_duplicate_groups, DuplicateGroup, DuplicateRanking, MatchConfidence,
+    ResolvePolicy,
+};
+use crate::editors::{self, AvailableEditor};
+use crate::error::CommandError;
+use crate::events::{self, Event, EventOptions, Events};
+use crate::export::{self, ExportOptions, ExportedFile, FolderStructure, ZipExport};
+use crate::faces::{FaceModels, Faces, Person, SAME_PERSON_SIMILARITY};
+use crate::file_ops::{
+    self, allocated_size, find_unique_name_with, hardlink_id, move_all, move_path,
+    remove_emptied_dirs, remove_empty_dirs, replace_with_clone, replace_with_hardlink, restem,
+    reveal, roll_back, same_volume, DiskUsage, MovePlanner,
+};
+use crate::folder_review::{FolderReviews, UnreviewedFolder};
+use crate::format_check::{check_volume, FormatCheck};
+use crate::geotag::{self, Coordinates, GeotagTarget};
+use crate::gpx::{self, GpxOptions, Track};
+use crate::hash_cache::{volume_mounted, CacheStats, FixityOptions, HashAlgorithm, HashCache};
+use crate::ids::{PhotoId, PhotoIds};
+use crate::import_report::{ImportReport, ImportedFile};
+use crate::ingest::{self, Device, IngestOptions, IngestResult};
+use crate::jobs::{JobMonitor, JobProgress, Jobs};
+use crate::library::{GroupBy, GroupMembers, Library, PhotoGroup};
+use crate::library_report::{self, ReportFormat};
+use crate::lightroom::{self, LightroomImport};
+use crate::machine_tags::{Classifier, MachineTags};
+use crate::media_info::is_video_extension;
+use crate::metadata::{capture_date_or_file_date, expand_date_template, read_capture_date};
+use crate::notifications;
+use crate::operation_log::{LoggedFile, LoggedOperation, OperationLog};
+use crate::pairings::Pairings;
+use crate::quality::{self, PhotoQuality, Quality};
+use crate::ratings::{Flag, PhotoRating, Ratings};
+use crate::report::ScanReport;
+use crate::rules::{self, RuleMove};
+use crate::scan_checkpoint::{self, InterruptedScan, ScanCheckpoint};
+use crate::scan_options::{builtin_presets, CloudPolicy, ScanOptions, ScanPreset};
+use crate::scanner::{
+    compute_full_hash, is_cloud_placeholder, is_media_extension, resume_scan_with_progress,
+    scan_directories_with_progress, DuplicateStatus, PhotoFile, ScanSummary,
+};
+use crate::session::{self, Session};
+use crate::shutdown;
+use crate::smart_albums::{self, SmartAlbum, SmartAlbums};
+use crate::snapshots::{self, SnapshotInfo};
+use crate::stacks::{Stack, StackKind, Stacks};
+use crate::stats::{FolderStats, LibraryStats, Stats};
+use crate::tags::{TagCount, Tags};
+use crate::takeout::{self, TakeoutOptions, TakeoutResult};
+use crate::transcode::{transcode_video, TranscodeOptions};
+use crate::transform::{self, FlipDirection, Transform, TransformMethod};
+use crate::trash_history::TrashHistory;
+use crate::volumes::{self, Volume};
+use crate::windows::{self, ComparedPhotos};
+use chrono::{Local, TimeZone};
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::fs;
-use std::path::Path;
-use std::process::Command;
-use tauri::Window;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tauri::{AppHandle, State, Window};
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MoveOperation {
     pub from: String,
     pub to: String,
+    /// Full hash confirmed on the destination when the move crossed volumes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub verified_hash: Option<String>,
 }
 
 /// Scan directories for photos with progress reporting
+/// Photos are streamed via `scan-batch` events (with `scan-delta` updates once
+/// hashing finishes); only a summary is returned
+/// `options` (or a preset's options) default to a full scan
 #[tauri::command]
 pub async fn scan_directories(
     window: Window,
+    library: State<'_, Library>,
     directories: Vec<String>,
-) -> Result<Vec<PhotoFile>, String> {
+    options: Option<ScanOptions>,
+) -> Result<ScanSummary, CommandError> {
+    let options = options.unwrap_or_else(configured_scan_options);
+    scan_into_library(window, &library, directories, options, None).await
+}
+
+/// Rescan all enabled directories from the config and refresh the catalog
+#[tauri::command]
+pub async fn refresh_catalog(
+    window: Window,
+    library: State<'_, Library>,
+) -> Result<ScanSummary, CommandError> {
+    scan_into_library(
+        window,
+        &library,
+        enabled_directories(),
+        configured_scan_options(),
+        None,
+    )
+    .await
+}
+
+/// Hash the cloud placeholders earlier scans skipped (`duplicateStatus:
+/// "unknown"`), downloading them, by rescanning the enabled directories
+/// incrementally; everything else comes out of the hash cache
+#[tauri::command]
+pub async fn hash_pending_files(
+    window: Window,
+    library: State<'_, Library>,
+) -> Result<ScanSummary, CommandError> {
+    let photos = library.snapshot();
+    if !photos
+        .iter()
+        .any(|p| p.duplicate_status == DuplicateStatus::Unknown)
+    {
+        return Ok(ScanSummary::from_photos(&photos));
+    }
+    let options = ScanOptions {
+        incremental: true,
+        cloud_policy: CloudPolicy::Download,
+        ..Default::default()
+    };
+    scan_into_library(window, &library, enabled_directories(), options, None).await
+}
+
+fn enabled_directories() -> Vec<String> {
+    AppConfig::load()
+        .directories
+        .into_iter()
+        .filter(|d| d.enabled)
+        .map(|d| d.path)
+        .collect()
+}
+
+/// Options for scans started without any: the defaults plus the configured cloud policy
+fn configured_scan_options() -> ScanOptions {
+    ScanOptions {
+        cloud_policy: AppConfig::load().cloud_policy,
+        ..Default::default()
+    }
+}
+
+/// Built-in scan presets followed by the ones saved in the config
+#[tauri::command]
+pub async fn list_scan_presets() -> Result<Vec<ScanPreset>, CommandError> {
+    let mut presets = builtin_presets();
+    presets.extend(AppConfig::load().scan_presets);
+    Ok(presets)
+}
+
+/// Continue the scan that was interrupted by a crash or by closing the app,
+/// from the last phase it completed (see `scan_checkpoint`)
+#[tauri::command]
+pub async fn resume_scan(
+    window: Window,
+    library: State<'_, Library>,
+) -> Result<ScanSummary, CommandError> {
+    let checkpoint = scan_checkpoint::load().ok_or("There is no interrupted scan to resume")?;
+    let directories = checkpoint.directories.clone();
+    let options = checkpoint.options.clone();
+    scan_into_library(window, &library, directories, options, Some(checkpoint)).await
+}
+
+/// The scan `resume_scan` would continue, if one was interrupted
+#[tauri::command]
+pub async fn get_interrupted_scan() -> Result<Option<InterruptedScan>, CommandError> {
+    Ok(scan_checkpoint::interrupted())
+}
+
+/// Run a scan, persist it to the catalog and keep it in the in-memory library
+/// `resume` continues an interrupted scan of the same directories and options
+async fn scan_into_library(
+    window: Window,
+    library: &Library,
+    directories: Vec<String>,
+    options: ScanOptions,
+    resume: Option<ScanCheckpoint>,
+) -> Result<ScanSummary, CommandError> {
     // Use Tauri's async runtime to run blocking code without blocking event processing
+    let report_dir = AppConfig::load().scan_report_dir;
+    let notify_window = window.clone();
     let result = tauri::async_runtime::spawn_blocking(move || {
-        scan_directories_with_progress(&directories, window)
+        let photos = match resume {
+            Some(checkpoint) => resume_scan_with_progress(checkpoint, window)?,
+            None => scan_directories_with_progress(&directories, &options, window)?,
+        };
+        let catalog = Catalog::open();
+
+        // Compare against the previous scan before it is overwritten
+        if let Some(dir) = report_dir.filter(|d| !d.trim().is_empty()) {
+            let previous = catalog
+                .as_ref()
+                .ok()
+                .and_then(|c| c.duplicate_state().ok())
+                .unwrap_or_default();
+            let report = ScanReport::build(&directories, &previous, &photos);
+            if let Err(e) = report.write_to(Path::new(&dir)) {
+                eprintln!("Failed to write scan report: {}", e);
+            }
+        }
+
+        // Content hashes help recognise files across moves
+        if let Err(e) = PhotoIds::open().and_then(|mut ids| ids.record_hashes(&photos)) {
+            eprintln!("Failed to record photo hashes: {}", e);
+        }
+
+        // Keep the previous state browsable before the scan replaces it
+        if let Err(e) = snapshots::take_if_due() {
+            eprintln!("Failed to snapshot catalog: {}", e);
+        }
+
+        // A scan filtered by type or size replaces only what it could have seen
+        let photos = match &catalog {
+            Ok(catalog) if options.is_filtered() => {
+                merge_filtered_scan(photos, catalog, &directories, &options)?
+            }
+            _ => photos,
+        };
+
+        // Persist so the next launch can show the library without rescanning
+        if let Err(e) = catalog.and_then(|mut c| c.replace_all(&photos)) {
+            eprintln!("Failed to update catalog: {}", e);
+        }
+        scan_checkpoint::clear();
+
+        // Follow album members that were moved or renamed outside the app
+        let relinked =
+            Albums::open().and_then(|albums| albums.relink(&photos, &HashCache::open()?));
+        if let Err(e) = relinked {
+            eprintln!("Failed to relink album members: {}", e);
+        }
+
+        // Ratings and labels set in Lightroom/Bridge show up here too
+        if let Err(e) = Ratings::open().and_then(|r| r.import_sidecars(&photos)) {
+            eprintln!("Failed to import sidecar ratings: {}", e);
+        }
+
+        Ok::<_, String>(photos)
+    })
+    .await
+    .map_err(|e| e.to_string())
+    .and_then(|scanned| scanned);
+    notifications::job_finished(&notify_window, "Scan", "library", &result, |photos| {
+        let summary = ScanSummary::from_photos(photos);
+        format!(
+            "{} photos, {} duplicates",
+            summary.photo_count, summary.duplicate_count
+        )
+    });
+    let result = result?;
+
+    let summary = ScanSummary::from_photos(&result);
+
+    // Keep the result around for lazy group queries
+    library.replace(result);
+
+    Ok(summary)
+}
+
+/// The photos of a filtered scan plus the cataloged ones it couldn't have
+/// seen: files outside its folders, types or sizes
+fn merge_filtered_scan(
+    scanned: Vec<PhotoFile>,
+    catalog: &Catalog,
+    directories: &[String],
+    options: &ScanOptions,
+) -> Result<Vec<PhotoFile>, String> {
+    let ids: HashSet<&str> = scanned.iter().map(|p| p.id.as_str()).collect();
+    let kept: Vec<PhotoFile> = catalog
+        .all_photos()?
+        .into_iter()
+        .filter(|p| {
+            !ids.contains(p.id.as_str())
+                && !options.covers(&p.path, &p.extension, p.size, directories)
+        })
+        .collect();
+    let mut merged = scanned;
+    merged.extend(kept);
+    Ok(merged)
+}
+
+/// Page through the persisted catalog, newest first
+#[tauri::command]
+pub async fn query_photos(offset: usize, limit: usize) -> Result<PhotoPage, CommandError> {
+    Ok(Catalog::open()?.query(offset, limit)?)
+}
+
+/// Filter, sort and page the catalog without loading the full list into the frontend
+#[tauri::command]
+pub async fn search_photos(query: SearchQuery) -> Result<PhotoPage, CommandError> {
+    Ok(Catalog::open()?.search(&query)?)
+}
+
+/// Look up a single photo in the catalog
+#[tauri::command]
+pub async fn get_photo(id: String) -> Result<PhotoFile, CommandError> {
+    Ok(Catalog::open()?
+        .get(&id)
+        .ok_or_else(|| "Photo not found".to_string())?)
+}
+
+/// Stored catalog snapshots, newest first
+#[tauri::command]
+pub async fn list_snapshots() -> Result<Vec<SnapshotInfo>, CommandError> {
+    Ok(snapshots::list())
+}
+
+/// Snapshot the catalog now, e.g. before a large cleanup
+#[tauri::command]
+pub async fn take_snapshot() -> Result<SnapshotInfo, CommandError> {
+    Ok(snapshots::take()?)
+}
+
+/// Search the library as it was when a snapshot was taken
+#[tauri::command]
+pub async fn browse_snapshot(
+    timestamp: i64,
+    query: SearchQuery,
+) -> Result<PhotoPage, CommandError> {
+    Ok(snapshots::open(timestamp)?.search(&query)?)
+}
+
+/// Bring back tags and ratings files had in a snapshot; returns how many files
+/// got something back
+#[tauri::command]
+pub async fn restore_snapshot_metadata(
+    timestamp: i64,
+    paths: Vec<String>,
+) -> Result<usize, CommandError> {
+    Ok(snapshots::restore_metadata(timestamp, &paths)?)
+}
+
+/// Create an empty album
+#[tauri::command]
+pub async fn create_album(name: String) -> Result<Album, CommandError> {
+    Ok(Albums::open()?.create(&name)?)
+}
+
+/// Add files to an album (hashing them so membership survives moves)
+#[tauri::command]
+pub async fn add_to_album(album_id: i64, paths: Vec<String>) -> Result<usize, CommandError> {
+    Ok(tauri::async_runtime::spawn_blocking(move || {
+        Albums::open()?.add(album_id, &paths, &HashCache::open()?)
+    })
+    .await
+    .map_err(|e| e.to_string())??)
+}
+
+/// Remove files from an album; the files themselves are untouched
+#[tauri::command]
+pub async fn remove_from_album(album_id: i64, paths: Vec<String>) -> Result<usize, CommandError> {
+    Ok(Albums::open()?.remove(album_id, &paths)?)
+}
+
+/// List all albums with member counts
+#[tauri::command]
+pub async fn list_albums() -> Result<Vec<Album>, CommandError> {
+    Ok(Albums::open()?.list()?)
+}
+
+/// Save a search under a name as a smart album, whose photos are found anew
+/// each time it is opened
+#[tauri::command]
+pub async fn create_smart_album(
+    name: String,
+    query: SearchQuery,
+) -> Result<SmartAlbum, CommandError> {
+    Ok(SmartAlbums::open()?.create(&name, &query)?)
+}
+
+/// A page of the photos a smart album's search finds in the catalog now
+#[tauri::command]
+pub async fn evaluate_smart_album(
+    album_id: i64,
+    offset: Option<usize>,
+    limit: Option<usize>,
+) -> Result<PhotoPage, CommandError> {
+    let album = SmartAlbums::open()?.get(album_id)?;
+    Ok(smart_albums::evaluate(&album, offset.unwrap_or(0), limit)?)
+}
+
+/// List smart albums with their saved searches
+#[tauri::command]
+pub async fn list_smart_albums() -> Result<Vec<SmartAlbum>, CommandError> {
+    Ok(SmartAlbums::open()?.list()?)
+}
+
+#[tauri::command]
+pub async fn delete_smart_album(album_id: i64) -> Result<(), CommandError> {
+    Ok(SmartAlbums::open()?.delete(album_id)?)
+}
+
+/// Archive or unarchive files and folders: archived photos stay cataloged and
+/// searchable (`includeArchived`) but drop out of listings and duplicate views
+#[tauri::command]
+pub async fn set_archived(paths: Vec<String>, archived: bool) -> Result<usize, CommandError> {
+    Ok(Archive::open()?.set(&paths, archived)?)
+}
+
+/// Archived files and folders, most recent first
+#[tauri::command]
+pub async fn list_archived() -> Result<Vec<ArchivedEntry>, CommandError> {
+    Ok(Archive::open()?.list()?)
+}
+
+/// Stack photos behind a cover so searches show them as one; `cover` defaults
+/// to the first path
+#[tauri::command]
+pub async fn create_stack(
+    paths: Vec<String>,
+    cover: Option<String>,
+) -> Result<Stack, CommandError> {
+    Ok(Stacks::open()?.create(&paths, cover.as_deref())?)
+}
+
+/// Choose the photo a stack is shown as
+#[tauri::command]
+pub async fn set_stack_cover(stack_id: i64, path: String) -> Result<(), CommandError> {
+    Ok(Stacks::open()?.set_cover(stack_id, &path)?)
+}
+
+/// Take photos out of their stacks; returns how many were stacked
+#[tauri::command]
+pub async fn unstack_photos(paths: Vec<String>) -> Result<usize, CommandError> {
+    Ok(Stacks::open()?.unstack(&paths)?)
+}
+
+/// Dissolve a stack; its photos show up individually again
+#[tauri::command]
+pub async fn dissolve_stack(stack_id: i64) -> Result<(), CommandError> {
+    Ok(Stacks::open()?.dissolve(stack_id)?)
+}
+
+/// Group files under a primary photo the way scans pair a RAW with its JPEG,
+/// for files whose names don't match; takes effect with the next scan
+/// Returns the number of files attached
+#[tauri::command]
+pub async fn stack_files(primary: String, paths: Vec<String>) -> Result<usize, CommandError> {
+    let extension = Path::new(&primary)
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase())
+        .unwrap_or_default();
+    if !is_media_extension(&extension) {
+        return Err("The primary must be a photo or video".into());
+    }
+    Ok(Pairings::open()?.attach(&primary, &paths)?)
+}
+
+/// Make files their own photos again, e.g. a JPEG a RAW of the same name
+/// claimed although they are different shots; takes effect with the next scan
+#[tauri::command]
+pub async fn unstack_files(paths: Vec<String>) -> Result<usize, CommandError> {
+    Ok(Pairings::open()?.detach(&paths)?)
+}
+
+/// Stacks of one kind (bursts and brackets found by scans, or stacks made by
+/// hand), or all of them, newest first
+#[tauri::command]
+pub async fn get_stacks(kind: Option<StackKind>) -> Result<Vec<Stack>, CommandError> {
+    Ok(Stacks::open()?.list(kind)?)
+}
+
+/// All stacks, or just the stack of one file when `path` is given
+#[tauri::command]
+pub async fn list_stacks(path: Option<String>) -> Result<Vec<Stack>, CommandError> {
+    let stacks = Stacks::open()?;
+    match path {
+        Some(path) => Ok(stacks.for_path(&path)?.into_iter().collect()),
+        None => Ok(stacks.list(None)?),
+    }
+}
+
+/// Tag files, optionally mirroring their keywords into XMP sidecars for Lightroom
+#[tauri::command]
+pub async fn add_tags(
+    paths: Vec<String>,
+    tags: Vec<String>,
+    write_xmp: bool,
+) -> Result<(), CommandError> {
+    if write_xmp {
+        ReadOnlyRoots::load().check_all(&paths)?;
+    }
+    let store = Tags::open()?;
+    store.add(&paths, &tags)?;
+    if write_xmp {
+        store.write_sidecars(&paths)?;
+    }
+    Ok(())
+}
+
+/// Untag files, optionally updating their XMP sidecars
+#[tauri::command]
+pub async fn remove_tags(
+    paths: Vec<String>,
+    tags: Vec<String>,
+    write_xmp: bool,
+) -> Result<(), CommandError> {
+    if write_xmp {
+        ReadOnlyRoots::load().check_all(&paths)?;
+    }
+    let store = Tags::open()?;
+    store.remove(&paths, &tags)?;
+    if write_xmp {
+        store.write_sidecars(&paths)?;
+    }
+    Ok(())
+}
+
+/// All tags with photo counts, or just the tags of one file when `path` is given
+#[tauri::command]
+pub async fn list_tags(path: Option<String>) -> Result<Vec<TagCount>, CommandError> {
+    let store = Tags::open()?;
+    match path {
+        Some(path) => Ok(store
+            .for_path(&path)?
+            .into_iter()
+            .map(|name| TagCount { name, count: 1 })
+            .collect()),
+        None => Ok(store.list()?),
+    }
+}
+
+/// Set a 0–5 star rating, optionally writing `xmp:Rating` to the sidecars
+#[tauri::command]
+pub async fn set_rating(
+    paths: Vec<String>,
+    rating: u8,
+    write_xmp: bool,
+) -> Result<(), CommandError> {
+    if write_xmp {
+        ReadOnlyRoots::load().check_all(&paths)?;
+    }
+    let ratings = Ratings::open()?;
+    for path in &paths {
+        ratings.set_rating(path, rating)?;
+        if write_xmp {
+            ratings.write_sidecar(path)?;
+        }
+    }
+    Ok(())
+}
+
+/// Set or clear the pick/reject flag (rejects are written to XMP as rating -1)
+#[tauri::command]
+pub async fn set_flag(
+    paths: Vec<String>,
+    flag: Option<Flag>,
+    write_xmp: bool,
+) -> Result<(), CommandError> {
+    if write_xmp {
+        ReadOnlyRoots::load().check_all(&paths)?;
+    }
+    let ratings = Ratings::open()?;
+    for path in &paths {
+        ratings.set_flag(path, flag)?;
+        if write_xmp {
+            ratings.write_sidecar(path)?;
+        }
+    }
+    Ok(())
+}
+
+/// Set or clear the color label, optionally writing `xmp:Label` to the sidecars
+#[tauri::command]
+pub async fn set_label(
+    paths: Vec<String>,
+    label: Option<String>,
+    write_xmp: bool,
+) -> Result<(), CommandError> {
+    if write_xmp {
+        ReadOnlyRoots::load().check_all(&paths)?;
+    }
+    let ratings = Ratings::open()?;
+    for path in &paths {
+        ratings.set_label(path, label.as_deref())?;
+        if write_xmp {
+            ratings.write_sidecar(path)?;
+        }
+    }
+    Ok(())
+}
+
+/// Rating, flag and label of each file
+#[tauri::command]
+pub async fn get_ratings(paths: Vec<String>) -> Result<Vec<PhotoRating>, CommandError> {
+    let ratings = Ratings::open()?;
+    Ok(paths
+        .iter()
+        .map(|path| ratings.get(path))
+        .collect::<Result<_, _>>()?)
+}
+
+/// Outcome of `set_gps_coordinates`
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GeotagResult {
+    /// Files whose own EXIF was updated
+    pub exif_count: usize,
+    /// Files whose XMP sidecar was updated (RAW, HEIC and video)
+    pub sidecar_count: usize,
+    pub failed: Vec<FailedFile>,
+}
+
+/// Write a GPS position to files, into EXIF for JPEG, PNG, WebP and TIFF and
+/// into the XMP sidecar for everything else; `None` removes the position
+#[tauri::command]
+pub async fn set_gps_coordinates(
+    paths: Vec<String>,
+    coordinates: Option<Coordinates>,
+) -> Result<GeotagResult, CommandError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let _busy = shutdown::begin("geotag");
+        ReadOnlyRoots::load().check_all(&paths)?;
+        let mut result = GeotagResult::default();
+        let mut written = Vec::new();
+        let mut rewritten = Vec::new();
+        for path in paths {
+            match geotag::write_position(Path::new(&path), coordinates) {
+                Ok(GeotagTarget::Exif) => {
+                    result.exif_count += 1;
+                    rewritten.push(path.clone());
+                }
+                Ok(GeotagTarget::Sidecar) => result.sidecar_count += 1,
+                Err(error) => {
+                    result.failed.push(FailedFile { path, error });
+                    continue;
+                }
+            }
+            written.push((path, coordinates.map(|c| (c.latitude, c.longitude))));
+        }
+        Catalog::open()?.set_locations(&written)?;
+        refresh_hashes(&rewritten)?;
+        Ok(result)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Outcome of `correlate_gpx`
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GpxCorrelation {
+    /// Timed points read from the GPX file
+    pub track_points: usize,
+    /// Files given a position in their EXIF
+    pub exif_count: usize,
+    /// Files given a position through their XMP sidecar
+    pub sidecar_count: usize,
+    /// Photos taken when the track has no position for them
+    pub outside_track: Vec<String>,
+    /// Photos left alone because they already have a position
+    pub already_tagged: Vec<String>,
+    pub failed: Vec<FailedFile>,
+}
+
+/// Geotag photos from a GPX track: each capture time, shifted by the time
+/// offset, is looked up on the track and the position written as
+/// `set_gps_coordinates` does
+#[tauri::command]
+pub async fn correlate_gpx(
+    library: State<'_, Library>,
+    gpx_path: String,
+    paths: Vec<String>,
+    options: GpxOptions,
+) -> Result<GpxCorrelation, CommandError> {
+    let photos = library.snapshot();
+    tauri::async_runtime::spawn_blocking(move || {
+        let _busy = shutdown::begin("geotag");
+        ReadOnlyRoots::load().check_all(&paths)?;
+        let track = Track::load(Path::new(&gpx_path))?;
+        let max_gap = options.max_gap.unwrap_or(gpx::DEFAULT_MAX_GAP_SECONDS);
+        let by_path: HashMap<&str, &PhotoFile> =
+            photos.iter().map(|p| (p.path.as_str(), p)).collect();
+        let mut result = GpxCorrelation {
+            track_points: track.points.len(),
+            ..Default::default()
+        };
+        let mut written = Vec::new();
+        let mut rewritten = Vec::new();
+
+        for path in paths {
+            let photo = by_path.get(path.as_str());
+            if !options.overwrite && photo.is_some_and(|p| p.latitude.is_some()) {
+                result.already_tagged.push(path);
+                continue;
+            }
+            // Capture times are read as local time, as scans do
+            let captured = match photo {
+                Some(photo) => photo.capture_date.map(|ms| ms.div_euclid(1000)),
+                None => read_capture_date(Path::new(&path)).and_then(|date| {
+                    Local
+                        .from_local_datetime(&date)
+                        .earliest()
+                        .map(|d| d.timestamp())
+                }),
+            };
+            let Some(captured) = captured else {
+                result.failed.push(FailedFile {
+                    path,
+                    error: "No capture time".to_string(),
+                });
+                continue;
+            };
+            let Some(position) = track.position_at(captured + options.time_offset, max_gap) else {
+                result.outside_track.push(path);
+                continue;
+            };
+            match geotag::write_position(Path::new(&path), Some(position)) {
+                Ok(GeotagTarget::Exif) => {
+                    result.exif_count += 1;
+                    rewritten.push(path.clone());
+                }
+                Ok(GeotagTarget::Sidecar) => result.sidecar_count += 1,
+                Err(error) => {
+                    result.failed.push(FailedFile { path, error });
+                    continue;
+                }
+            }
+            written.push((path, Some((position.latitude, position.longitude))));
+        }
+        Catalog::open()?.set_locations(&written)?;
+        refresh_hashes(&rewritten)?;
+        Ok(result)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Outcome of `shift_capture_time`
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CaptureTimeShift {
+    /// Files whose own EXIF dates were shifted
+    pub exif_count: usize,
+    /// Files shifted through their XMP sidecar (RAW and video)
+    pub sidecar_count: usize,
+    /// Files without a capture time to shift
+    pub no_capture_time: Vec<String>,
+    pub failed: Vec<FailedFile>,
+}
+
+/// Move the capture time of files by `offset_seconds` (negative moves it
+/// earlier), for a camera whose clock was set wrong
+#[tauri::command]
+pub async fn shift_capture_time(
+    library: State<'_, Library>,
+    paths: Vec<String>,
+    offset_seconds: i64,
+) -> Result<CaptureTimeShift, CommandError> {
+    let photos = library.snapshot();
+    tauri::async_runtime::spawn_blocking(move || {
+        let _busy = shutdown::begin("shift_capture_time");
+        ReadOnlyRoots::load().check_all(&paths)?;
+        let cataloged: HashMap<&str, i64> = photos
+            .iter()
+            .filter_map(|p| Some((p.path.as_str(), p.capture_date?)))
+            .collect();
+        let mut result = CaptureTimeShift::default();
+        let mut shifted_sidecars = HashSet::new();
+        let mut dates = Vec::new();
+        let mut rewritten = Vec::new();
+        for path in paths {
+            let shifted = capture_time::shift(
+                Path::new(&path),
+                offset_seconds,
+                cataloged.get(path.as_str()).copied(),
+                &mut shifted_sidecars,
+            );
+            match shifted {
+                Ok(Some(shifted)) => {
+                    match shifted.target {
+                        ShiftTarget::Exif => {
+                            result.exif_count += 1;
+                            rewritten.push(path.clone());
+                        }
+                        ShiftTarget::Sidecar => result.sidecar_count += 1,
+                    }
+                    dates.push((path, shifted.capture_date));
+                }
+                Ok(None) => result.no_capture_time.push(path),
+                Err(error) => result.failed.push(FailedFile { path, error }),
+            }
+        }
+        Catalog::open()?.set_capture_dates(&dates)?;
+        refresh_hashes(&rewritten)?;
+        Ok(result)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Outcome of `rotate_image` and `flip_image`
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransformResult {
+    /// JPEGs whose pixels were turned losslessly
+    pub pixels_count: usize,
+    /// Files whose EXIF orientation was changed instead
+    pub orientation_count: usize,
+    pub failed: Vec<FailedFile>,
+}
+
+/// Turn photos clockwise by `degrees`, a multiple of 90 (negative turns
+/// counterclockwise)
+#[tauri::command]
+pub async fn rotate_image(
+    paths: Vec<String>,
+    degrees: i32,
+) -> Result<TransformResult, CommandError> {
+    if degrees % 90 != 0 {
+        return Err(CommandError::Other(format!(
+            "Can't rotate by {} degrees, only by quarter turns",
+            degrees
+        )));
+    }
+    match degrees.rem_euclid(360) / 90 {
+        0 => Ok(TransformResult::default()),
+        turns => transform_files(paths, Transform::Rotate(turns as u8)).await,
+    }
+}
+
+/// Mirror photos horizontally or vertically
+#[tauri::command]
+pub async fn flip_image(
+    paths: Vec<String>,
+    direction: FlipDirection,
+) -> Result<TransformResult, CommandError> {
+    transform_files(paths, direction.into()).await
+}
+
+/// Apply a transform to each file, drop its cached thumbnails and record its
+/// new orientation and size in the catalog
+async fn transform_files(
+    paths: Vec<String>,
+    transform: Transform,
+) -> Result<TransformResult, CommandError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let _busy = shutdown::begin("transform");
+        ReadOnlyRoots::load().check_all(&paths)?;
+        let mut result = TransformResult::default();
+        let mut transformed = Vec::new();
+        for path in paths {
+            match transform::apply(Path::new(&path), transform) {
+                Ok(done) => {
+                    match done.method {
+                        TransformMethod::Pixels => result.pixels_count += 1,
+                        TransformMethod::Orientation => result.orientation_count += 1,
+                    }
+                    // File dates are kept, so the caches can't tell they are stale
+                    remove_cached_thumbnails(Path::new(&path));
+                    transformed.push((path, done));
+                }
+                Err(error) => result.failed.push(FailedFile { path, error }),
+            }
+        }
+        Catalog::open()?.set_orientations(&transformed)?;
+        let rewritten: Vec<String> = transformed.iter().map(|(path, _)| path.clone()).collect();
+        refresh_hashes(&rewritten)?;
+        Ok(result)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Outcome of `cluster_events`
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EventClustering {
+    pub events: Vec<Event>,
+    /// Photos with no capture date, which belong to no event
+    pub undated: usize,
+}
+
+/// Group the library into events by capture-time gaps and distance between
+/// photos, replacing the event assignments stored in the catalog
+#[tauri::command]
+pub async fn cluster_events(
+    library: State<'_, Library>,
+    options: EventOptions,
+) -> Result<EventClustering, CommandError> {
+    let photos = unarchived_photos(&library)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut events = events::cluster(&photos, &options);
+        Events::open()?.replace(&mut events)?;
+        Ok(EventClustering {
+            undated: photos.iter().filter(|p| p.capture_date.is_none()).count(),
+            events,
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Events from the last `cluster_events`, oldest first
+#[tauri::command]
+pub async fn get_events() -> Result<Vec<Event>, CommandError> {
+    Ok(Events::open()?.list()?)
+}
+
+/// Run an analyzer over the library's photos, or only `paths`, in `phase` of
+/// a job; results for photos gone from the library are dropped first, and
+/// videos, cloud placeholders and photos unchanged since they were last
+/// analyzed are skipped. `analyze` reads a photo and `record` stores what it
+/// found. Returns how many photos were analyzed and the ones that failed
+fn run_analysis<S: AnalysisStore, T>(
+    monitor: &JobMonitor,
+    phase: &str,
+    photos: &[PhotoFile],
+    paths: Option<Vec<String>>,
+    store: &mut S,
+    analyze: impl Fn(&Path) -> Result<T, String>,
+    mut record: impl FnMut(&mut S, &PhotoFile, T) -> Result<(), String>,
+) -> Result<(usize, Vec<FailedFile>), String> {
+    if paths.is_none() && !photos.is_empty() {
+        store.retain(&photos.iter().map(|p| p.path.as_str()).collect())?;
+    }
+    let done = store.scanned()?;
+    let wanted: Option<HashSet<String>> = paths.map(|paths| paths.into_iter().collect());
+    let pending: Vec<&PhotoFile> = photos
+        .iter()
+        .filter(|p| wanted.as_ref().is_none_or(|w| w.contains(&p.path)))
+        .filter(|p| !is_video_extension(&p.extension) && !p.is_cloud_placeholder)
+        .filter(|p| done.get(&p.path) != Some(&p.modified_at))
+        .collect();
+
+    monitor.set_phase(phase, Some(pending.len()));
+    let mut analyzed = 0;
+    let mut failed = Vec::new();
+    for photo in pending {
+        if monitor.is_cancelled() {
+            break;
+        }
+        let _work = monitor.working_on(&photo.path);
+        match analyze(Path::new(&photo.path)) {
+            _ if monitor.is_skipped(&photo.path) => failed.push(skipped_file(&photo.path)),
+            Ok(found) => {
+                record(store, photo, found)?;
+                analyzed += 1;
+            }
+            Err(error) => failed.push(FailedFile {
+                path: photo.path.clone(),
+                error,
+            }),
+        }
+    }
+    Ok((analyzed, failed))
+}
+
+/// Outcome of `detect_faces`
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FaceDetection {
+    /// Photos faces were looked for in
+    pub scanned: usize,
+    /// Faces found in them
+    pub faces: usize,
+    /// Everyone found so far, after grouping the new faces
+    pub people: Vec<Person>,
+    pub failed: Vec<FailedFile>,
+}
+
+/// Find faces in the library's photos, or only in `paths`, and group them into
+/// people, as a `faces` job; photos unchanged since faces were last looked for
+/// in them are skipped. Needs a build with the `faces` feature
+#[tauri::command]
+pub async fn detect_faces(
+    window: Window,
+    library: State<'_, Library>,
+    paths: Option<Vec<String>>,
+) -> Result<FaceDetection, CommandError> {
+    let photos = library.snapshot();
+    tauri::async_runtime::spawn_blocking(move || {
+        let models = FaceModels::load()?;
+        let mut faces = Faces::open()?;
+        let monitor = JobMonitor::start(&window, "faces");
+        let mut found = 0;
+        let (scanned, failed) = run_analysis(
+            &monitor,
+            "detecting",
+            &photos,
+            paths,
+            &mut faces,
+            |path| models.detect(path),
+            |faces, photo, detected| {
+                found += detected.len();
+                faces.replace(&photo.path, photo.modified_at, &detected)
+            },
+        )?;
+
+        monitor.set_phase("grouping", None);
+        faces.cluster(SAME_PERSON_SIMILARITY)?;
+        Ok(FaceDetection {
+            scanned,
+            faces: found,
+            people: faces.people()?,
+            failed,
+        })
     })
     .await
-    .map_err(|e| e.to_string())?;
+    .map_err(|e| e.to_string())?
+}
+
+/// People found by `detect_faces`, named ones first
+#[tauri::command]
+pub async fn list_people() -> Result<Vec<Person>, CommandError> {
+    Ok(Faces::open()?.people()?)
+}
+
+/// Name a person, or clear their name with null; the name of someone else
+/// merges the two. Returns the id of the person who now has the faces
+#[tauri::command]
+pub async fn name_person(person_id: i64, name: Option<String>) -> Result<i64, CommandError> {
+    Ok(Faces::open()?.name_person(person_id, name.as_deref())?)
+}
 
-    Ok(result)
+/// Outcome of `detect_machine_tags`
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MachineTagging {
+    /// Photos that were labeled
+    pub scanned: usize,
+    /// Those given at least one tag
+    pub tagged: usize,
+    pub failed: Vec<FailedFile>,
+}
+
+/// Label the library's photos, or only `paths`, with what an image classifier
+/// sees in them ("dog", "beach"), as a `machine-tags` job; photos unchanged
+/// since they were last labeled are skipped. The labels are stored apart from
+/// the user's tags. Needs a build with the `machine-tags` feature
+#[tauri::command]
+pub async fn detect_machine_tags(
+    window: Window,
+    library: State<'_, Library>,
+    paths: Option<Vec<String>>,
+) -> Result<MachineTagging, CommandError> {
+    let photos = library.snapshot();
+    tauri::async_runtime::spawn_blocking(move || {
+        let classifier = Classifier::load()?;
+        let mut store = MachineTags::open()?;
+        let monitor = JobMonitor::start(&window, "machine-tags");
+        let mut tagged = 0;
+        let (scanned, failed) = run_analysis(
+            &monitor,
+            "labeling",
+            &photos,
+            paths,
+            &mut store,
+            |path| classifier.classify(path),
+            |store, photo, labels| {
+                tagged += !labels.is_empty() as usize;
+                store.replace(&photo.path, photo.modified_at, &labels)
+            },
+        )?;
+        Ok(MachineTagging {
+            scanned,
+            tagged,
+            failed,
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Labels from `detect_machine_tags` with photo counts, or just the labels of
+/// one file when `path` is given
+#[tauri::command]
+pub async fn list_machine_tags(path: Option<String>) -> Result<Vec<TagCount>, CommandError> {
+    let store = MachineTags::open()?;
+    match path {
+        Some(path) => Ok(store
+            .for_path(&path)?
+            .into_iter()
+            .map(|name| TagCount { name, count: 1 })
+            .collect()),
+        None => Ok(store.list()?),
+    }
+}
+
+/// Outcome of `score_quality`
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QualityScoring {
+    /// Photos that were measured
+    pub scored: usize,
+    /// Those found blurry
+    pub blurry: usize,
+    pub failed: Vec<FailedFile>,
+}
+
+/// Measure sharpness and exposure of the library's photos, or only `paths`,
+/// on downscaled previews, as a `quality` job; photos unchanged since they
+/// were last measured are skipped
+#[tauri::command]
+pub async fn score_quality(
+    window: Window,
+    library: State<'_, Library>,
+    paths: Option<Vec<String>>,
+) -> Result<QualityScoring, CommandError> {
+    let photos = library.snapshot();
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut store = Quality::open()?;
+        let monitor = JobMonitor::start(&window, "quality");
+        let mut blurry = 0;
+        let (scored, failed) = run_analysis(
+            &monitor,
+            "measuring",
+            &photos,
+            paths,
+            &mut store,
+            quality::measure,
+            |store, photo, measured| {
+                blurry += measured.blurry as usize;
+                store.record(&measured, photo.modified_at)
+            },
+        )?;
+        Ok(QualityScoring {
+            scored,
+            blurry,
+            failed,
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Photos `score_quality` found blurry, blurriest first, for deleting
+/// accidental shots; `max_sharpness` raises or lowers the bar
+#[tauri::command]
+pub async fn find_blurry_photos(
+    max_sharpness: Option<f64>,
+) -> Result<Vec<PhotoQuality>, CommandError> {
+    Ok(Quality::open()?.blurry(max_sharpness)?)
+}
+
+/// The member of a stack with the best quality score, to suggest as its
+/// cover; None when no member has been scored
+#[tauri::command]
+pub async fn suggest_best_in_stack(stack_id: i64) -> Result<Option<PhotoQuality>, CommandError> {
+    Ok(Quality::open()?.best_in_stack(stack_id)?)
+}
+
+/// The last scan without archived photos, which duplicate views leave alone
+fn unarchived_photos(library: &Library) -> Result<Arc<Vec<PhotoFile>>, String> {
+    let photos = library.snapshot();
+    let archived = Archive::open()?.paths()?;
+    if archived.is_empty() {
+        return Ok(photos);
+    }
+    Ok(Arc::new(
+        photos
+            .iter()
+            .filter(|p| !archived.contains(&p.path))
+            .cloned()
+            .collect(),
+    ))
+}
+
+/// Duplicate sets from the last scan with all copies and a suggested keeper
+#[tauri::command]
+pub async fn get_duplicate_groups(
+    library: State<'_, Library>,
+) -> Result<Vec<DuplicateGroup>, CommandError> {
+    Ok(duplicate_groups(&unarchived_photos(&library)?))
+}
+
+/// Duplicate groups ranked by the space they free (discounted for quick-hash
+/// matches), with the running share of the total for "top N frees X%"
+#[tauri::command]
+pub async fn rank_duplicates(
+    library: State<'_, Library>,
+    limit: Option<usize>,
+) -> Result<DuplicateRanking, CommandError> {
+    Ok(rank_duplicate_groups(&unarchived_photos(&library)?, limit))
+}
+
+/// Import ratings, flags, labels, keywords and collections from a Lightroom
+/// Classic catalog (.lrcat) for the photos it shares with the library;
+/// collections become albums
+#[tauri::command]
+pub async fn import_lightroom_catalog(
+    library: State<'_, Library>,
+    catalog_path: String,
+) -> Result<LightroomImport, CommandError> {
+    let photos = library.snapshot();
+    Ok(tauri::async_runtime::spawn_blocking(move || {
+        lightroom::import_catalog(Path::new(&catalog_path), &photos, &HashCache::open()?)
+    })
+    .await
+    .map_err(|e| e.to_string())??)
+}
+
+/// Write the library from the last scan to a CSV or JSON file (path, size,
+/// hash, capture date, duplicate set, camera) for spreadsheets and other tools
+/// Returns the number of rows written
+#[tauri::command]
+pub async fn export_report(
+    library: State<'_, Library>,
+    destination: String,
+    format: ReportFormat,
+) -> Result<usize, CommandError> {
+    if destination.trim().is_empty() {
+        return Err("Choose where to save the report".into());
+    }
+    ReadOnlyRoots::load().check(Path::new(&destination))?;
+    let photos = library.snapshot();
+    tauri::async_runtime::spawn_blocking(move || {
+        let rows = library_report::rows(&photos);
+        library_report::write(Path::new(&destination), &rows, format)?;
+        Ok(rows.len())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Outcome of `resolve_top_groups`
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResolveResult {
+    pub groups_resolved: usize,
+    /// Files trashed or moved away (extra copies and their related files)
+    pub removed: Vec<String>,
+    /// Bytes freed on disk in the library; at most, see `DiskUsage`
+    pub reclaimed_bytes: u64,
+    /// Groups left alone, by keeper path
+    pub failed: Vec<FailedFile>,
+}
+
+/// Keep the suggested keeper of the `n` highest-ranked duplicate groups and
+/// trash (or move away) every other copy with its related files
+/// A group is skipped if its keeper is gone or a copy changed since the scan
+#[tauri::command]
+pub async fn resolve_top_groups(
+    window: Window,
+    library: State<'_, Library>,
+    n: usize,
+    policy: ResolvePolicy,
+) -> Result<ResolveResult, CommandError> {
+    let groups: Vec<DuplicateGroup> = rank_duplicate_groups(&unarchived_photos(&library)?, None)
+        .groups
+        .into_iter()
+        .map(|ranked| ranked.group)
+        .filter(|g| policy.include_likely || g.confidence == MatchConfidence::Exact)
+        .take(n)
+        .collect();
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let _busy = shutdown::begin("delete");
+        let monitor = JobMonitor::start(&window, "resolve");
+        let cache = HashCache::open().ok();
+        let recorder = trash_recorder();
+        let destination = policy.move_to.map(PathBuf::from);
+        let read_only = ReadOnlyRoots::load();
+        let mut result = ResolveResult::default();
+        let mut logged = Vec::new();
+
+        for group in groups {
+            if monitor.is_cancelled() {
+                break;
+            }
+            let keeper = &group.members[0];
+            let _work = monitor.working_on(&keeper.path);
+            if let Err(error) = check_resolvable(&group, cache.as_ref()) {
+                result.failed.push(FailedFile {
+                    path: keeper.path.clone(),
+                    error,
+                });
+                continue;
+            }
+
+            let files: Vec<PathBuf> = group.members[1..].iter().flat_map(group_paths).collect();
+            if let Err(error) = read_only
+                .check_all(&files)
+                .and_then(|()| read_only.check_all(&destination))
+            {
+                result.failed.push(FailedFile {
+                    path: keeper.path.clone(),
+                    error: error.to_string(),
+                });
+                continue;
+            }
+            let mut disk_usage = DiskUsage::default();
+            for metadata in files.iter().filter_map(|f| fs::metadata(f).ok()) {
+                disk_usage.add(&metadata);
+            }
+            let outcome = match &destination {
+                Some(folder) => move_copies(&files, folder, cache.as_ref()).map(|moved| {
+                    logged.extend(logged_moves(&moved));
+                }),
+                None => trash::delete_all(&files)
+                    .map_err(|e| e.to_string())
+                    .map(|()| {
+                        logged.extend(
+                            files
+                                .iter()
+                                .map(|f| LoggedFile::removed(&f.to_string_lossy())),
+                        );
+                    }),
+            };
+            match outcome {
+                _ if monitor.is_skipped(&keeper.path) => {
+                    result.failed.push(skipped_file(&keeper.path))
+                }
+                Ok(()) => {
+                    if let (None, Some(recorder), MatchConfidence::Exact) =
+                        (&destination, &recorder, group.confidence)
+                    {
+                        for copy in &group.members[1..] {
+                            remember_trashed(recorder, &group.hash, copy.size, &copy.path);
+                        }
+                    }
+                    record_folder_activity(files.iter().filter_map(|f| f.to_str()));
+                    result.groups_resolved += 1;
+                    result.reclaimed_bytes += disk_usage.bytes();
+                    result
+                        .removed
+                        .extend(files.iter().map(|f| f.to_string_lossy().to_string()));
+                }
+                Err(error) => result.failed.push(FailedFile {
+                    path: keeper.path.clone(),
+                    error,
+                }),
+            }
+        }
+        log_operation("resolve", logged);
+        result
+    })
+    .await
+    .map_err(|e| CommandError::Other(e.to_string()))
+}
+
+/// Refuse to resolve a group whose keeper disappeared or whose copies no
+/// longer match it (checked through the hash cache, so unchanged files aren't reread)
+fn check_resolvable(group: &DuplicateGroup, cache: Option<&HashCache>) -> Result<(), String> {
+    if !Path::new(&group.members[0].path).exists() {
+        return Err("The copy to keep no longer exists".to_string());
+    }
+    let Some(cache) = cache else {
+        return Ok(());
+    };
+    for member in &group.members {
+        let current = match group.confidence {
+            MatchConfidence::Exact => {
+                let size = fs::metadata(&member.path).map(|m| m.len()).unwrap_or(0);
+                cache.full_hash(&member.path, size)
+            }
+            MatchConfidence::Pixels => cache
+                .pixel_hash(&member.path)
+                .map(|hash| format!("pixels:{}", hash)),
+            MatchConfidence::Likely => return Ok(()),
+        };
+        if current.as_deref() != Some(group.hash.as_str()) {
+            return Err(format!("{} changed since the scan", member.name));
+        }
+    }
+    Ok(())
+}
+
+/// Outcome of `deduplicate_by_hardlink` and `deduplicate_by_clonefile`
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LinkResult {
+    pub groups_linked: usize,
+    /// Copies that now share their data with the copy kept
+    pub linked: Vec<String>,
+    /// Bytes freed on disk in the library
+    pub reclaimed_bytes: u64,
+    /// Copies left alone, by path
+    pub failed: Vec<FailedFile>,
+}
+
+/// Replace the extra copies of the `n` highest-ranked confirmed duplicate
+/// groups with hard links to the suggested keeper: every path stays where it
+/// is, but the data is stored once
+/// Only exact matches are linked, and only copies on the keeper's volume;
+/// related files such as sidecars are left as they are
+#[tauri::command]
+pub async fn deduplicate_by_hardlink(
+    window: Window,
+    library: State<'_, Library>,
+    n: usize,
+) -> Result<LinkResult, CommandError> {
+    share_top_groups(window, &library, n, "link", replace_with_hardlink).await
+}
+
+/// Replace the extra copies of the `n` highest-ranked confirmed duplicate
+/// groups with APFS clones of the suggested keeper (macOS only): both paths
+/// stay independent files, but unchanged data is stored once, and editing
+/// either one later copies only the blocks that change
+/// Only exact matches are cloned, and only copies on the keeper's volume; each
+/// copy keeps its own timestamps, and related files are left as they are
+#[tauri::command]
+pub async fn deduplicate_by_clonefile(
+    window: Window,
+    library: State<'_, Library>,
+    n: usize,
+) -> Result<LinkResult, CommandError> {
+    share_top_groups(window, &library, n, "clone", replace_with_clone).await
+}
+
+/// Have every extra copy of the top `n` exact groups share its data with the
+/// keeper through `replace`, which swaps a copy for a link or clone in place;
+/// `kind` names the operation in the operation log
+async fn share_top_groups(
+    window: Window,
+    library: &Library,
+    n: usize,
+    kind: &'static str,
+    replace: fn(&Path, &Path) -> Result<(), String>,
+) -> Result<LinkResult, CommandError> {
+    let groups: Vec<DuplicateGroup> = rank_duplicate_groups(&unarchived_photos(library)?, None)
+        .groups
+        .into_iter()
+        .map(|ranked| ranked.group)
+        .filter(|g| g.confidence == MatchConfidence::Exact)
+        .take(n)
+        .collect();
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let _busy = shutdown::begin("link");
+        let monitor = JobMonitor::start(&window, "link");
+        let cache = HashCache::open().ok();
+        let read_only = ReadOnlyRoots::load();
+        let mut result = LinkResult::default();
+
+        for group in groups {
+            if monitor.is_cancelled() {
+                break;
+            }
+            let keeper = Path::new(&group.members[0].path);
+            let _work = monitor.working_on(&group.members[0].path);
+            if let Err(error) = check_resolvable(&group, cache.as_ref()) {
+                result.failed.push(FailedFile {
+                    path: group.members[0].path.clone(),
+                    error,
+                });
+                continue;
+            }
+            let keeper_id = fs::metadata(keeper).ok().and_then(|m| hardlink_id(&m));
+
+            let mut linked_any = false;
+            for copy in &group.members[1..] {
+                let Ok(metadata) = fs::metadata(&copy.path) else {
+                    continue;
+                };
+                // Already the same file
+                if keeper_id.is_some() && hardlink_id(&metadata) == keeper_id {
+                    continue;
+                }
+                let outcome = read_only
+                    .check(Path::new(&copy.path))
+                    .map_err(String::from)
+                    .and_then(|()| replace(keeper, Path::new(&copy.path)));
+                match outcome {
+                    _ if monitor.is_skipped(&copy.path) => {
+                        result.failed.push(skipped_file(&copy.path))
+                    }
+                    Ok(()) => {
+                        // Other links to the copy still hold on to its data
+                        if hardlink_id(&metadata).is_none() {
+                            result.reclaimed_bytes += allocated_size(&metadata);
+                        }
+                        if let Some(c) = cache.as_ref() {
+                            c.invalidate(&copy.path);
+                        }
+                        result.linked.push(copy.path.clone());
+                        linked_any = true;
+                    }
+                    Err(error) => result.failed.push(FailedFile {
+                        path: copy.path.clone(),
+                        error,
+                    }),
+                }
+            }
+            if linked_any {
+                result.groups_linked += 1;
+            }
+        }
+        log_operation(
+            kind,
+            result
+                .linked
+                .iter()
+                .map(|path| LoggedFile::removed(path))
+                .collect(),
+        );
+        result
+    })
+    .await
+    .map_err(|e| CommandError::Other(e.to_string()))
+}
+
+/// Move files into `folder` under unique names, all or nothing
+fn move_copies(
+    files: &[PathBuf],
+    folder: &Path,
+    cache: Option<&HashCache>,
+) -> Result<Vec<MoveOperation>, String> {
+    fs::create_dir_all(folder).map_err(|e| e.to_string())?;
+    let mut planned: HashSet<PathBuf> = HashSet::new();
+    let mut moves = Vec::with_capacity(files.len());
+    for file in files {
+        let target = folder.join(file.file_name().ok_or("Invalid file name")?);
+        let target = if target.exists() || planned.contains(&target) {
+            find_unique_name_with(&target, |p| p.exists() || planned.contains(p))?
+        } else {
+            target
+        };
+        planned.insert(target.clone());
+        moves.push((file.clone(), target));
+    }
+    let hashes = move_all(&moves, cache)?;
+    let operations = into_operations(moves, hashes);
+    relocate_records(&operations);
+    Ok(operations)
+}
+
+/// Plan consolidating folders into one dated library: every move, every
+/// duplicate to trash, every name conflict and the resulting folders and size
+/// Nothing is touched; the plan is saved for `execute_consolidation`
+#[tauri::command]
+pub async fn plan_consolidation(
+    window: Window,
+    sources: Vec<String>,
+    target_root: String,
+    rules: ConsolidationRules,
+) -> Result<ConsolidationPlan, CommandError> {
+    Ok(tauri::async_runtime::spawn_blocking(move || {
+        consolidation::plan(&window, &sources, &target_root, &rules)
+    })
+    .await
+    .map_err(|e| e.to_string())??)
+}
+
+/// A saved consolidation plan with the status of each step
+#[tauri::command]
+pub async fn get_consolidation_plan(plan_id: String) -> Result<ConsolidationPlan, CommandError> {
+    Ok(ConsolidationPlan::load(&plan_id)?)
+}
+
+/// Steps run between checkpoints of a consolidation plan
+const CONSOLIDATION_CHECKPOINT: usize = 100;
+
+/// Outcome of `execute_consolidation`
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConsolidationRun {
+    pub completed: usize,
+    pub failed: Vec<FailedFile>,
+    /// Steps still pending; run again to continue
+    pub remaining: usize,
+    /// Moves made, for undo
+    pub operations: Vec<MoveOperation>,
+    pub trashed: Vec<String>,
+}
+
+/// Run the pending steps of a saved consolidation plan in order, at most
+/// `max_steps` of them; progress is checkpointed into the plan, so a run that
+/// was cancelled or interrupted continues where it stopped
+#[tauri::command]
+pub async fn execute_consolidation(
+    window: Window,
+    plan_id: String,
+    max_steps: Option<usize>,
+) -> Result<ConsolidationRun, CommandError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut plan = ConsolidationPlan::load(&plan_id)?;
+        let monitor = JobMonitor::start(&window, "consolidate");
+        // Taken once the job's turn in the queue has come: a job still waiting
+        // for it has nothing to finish before the app quits
+        let _busy = shutdown::begin("consolidate");
+        let cache = HashCache::open().ok();
+        let read_only = ReadOnlyRoots::load();
+        let recorder = trash_recorder();
+        let pending: Vec<usize> = (0..plan.steps.len())
+            .filter(|&i| plan.steps[i].status == StepStatus::Pending)
+            .take(max_steps.unwrap_or(usize::MAX))
+            .collect();
+        let mut run = ConsolidationRun::default();
+        let mut recorded = 0;
+        let mut trashed_recorded = 0;
+
+        for (n, &i) in pending.iter().enumerate() {
+            if monitor.is_cancelled() {
+                break;
+            }
+            let from = plan.steps[i].from.clone();
+            let _work = monitor.working_on(&from);
+            consolidation::emit_progress(&window, "moving", n + 1, pending.len(), &from);
+
+            let outcome = run_consolidation_step(&plan.steps[i], cache.as_ref(), &read_only);
+            let step = &mut plan.steps[i];
+            match outcome {
+                _ if monitor.is_skipped(&from) => {
+                    step.status = StepStatus::Failed;
+                    step.error = Some("Skipped after stalling".to_string());
+                    run.failed.push(skipped_file(&from));
+                }
+                Ok(operations) => {
+                    step.status = StepStatus::Done;
+                    run.completed += 1;
+                    if step.action == StepAction::Trash {
+                        if let (Some(recorder), Some(hash)) = (&recorder, &step.hash) {
+                            remember_trashed(recorder, hash, step.size, &from);
+                        }
+                        run.trashed.push(from);
+                    }
+                    run.operations.extend(operations);
+                }
+                Err(error) => {
+                    step.status = StepStatus::Failed;
+                    step.error = Some(error.clone());
+                    run.failed.push(FailedFile { path: from, error });
+                }
+            }
+
+            if (n + 1) % CONSOLIDATION_CHECKPOINT == 0 {
+                plan.save()?;
+                relocate_records(&run.operations[recorded..]);
+                log_consolidation(
+                    &run.operations[recorded..],
+                    &run.trashed[trashed_recorded..],
+                );
+                recorded = run.operations.len();
+                trashed_recorded = run.trashed.len();
+            }
+        }
+
+        plan.save()?;
+        relocate_records(&run.operations[recorded..]);
+        log_consolidation(
+            &run.operations[recorded..],
+            &run.trashed[trashed_recorded..],
+        );
+        record_folder_activity(
+            run.operations
+                .iter()
+                .map(|op| op.from.as_str())
+                .chain(run.trashed.iter().map(String::as_str)),
+        );
+        consolidation::emit_progress(&window, "complete", run.completed, pending.len(), "");
+        run.remaining = plan.pending_count();
+        Ok(run)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Log the steps run since the last checkpoint as one operation
+fn log_consolidation(operations: &[MoveOperation], trashed: &[String]) {
+    let mut files = logged_moves(operations);
+    files.extend(trashed.iter().map(|path| LoggedFile::removed(path)));
+    log_operation("consolidate", files);
+}
+
+/// Carry out one plan step, re-checking that the files are as planned
+/// A step whose source is already gone (run before an interrupted checkpoint)
+/// counts as done
+fn run_consolidation_step(
+    step: &PlanStep,
+    cache: Option<&HashCache>,
+    read_only: &ReadOnlyRoots,
+) -> Result<Vec<MoveOperation>, String> {
+    let source = Path::new(&step.from);
+    read_only.check(source)?;
+    read_only.check_all(&step.to)?;
+    match step.action {
+        StepAction::Move => {
+            let target = Path::new(step.to.as_deref().ok_or("Move without a destination")?);
+            if !source.exists() {
+                return if target.exists() {
+                    Ok(Vec::new())
+                } else {
+                    Err("The file is gone".to_string())
+                };
+            }
+            if target.exists() {
+                return Err(format!("{} already exists", target.display()));
+            }
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+            }
+            let mut moves = vec![(source.to_path_buf(), target.to_path_buf())];
+            moves.extend(
+                step.companions
+                    .iter()
+                    .filter(|(from, _)| Path::new(from).exists())
+                    .map(|(from, to)| (PathBuf::from(from), PathBuf::from(to))),
+            );
+            let hashes = move_all(&moves, cache)?;
+            Ok(into_operations(moves, hashes))
+        }
+        StepAction::Trash => {
+            if !source.exists() {
+                return Ok(Vec::new());
+            }
+            let original = step
+                .duplicate_of
+                .as_deref()
+                .ok_or("Trash without a copy to keep")?;
+            let original_size = fs::metadata(original)
+                .map_err(|_| "The copy to keep is gone".to_string())?
+                .len();
+            if let Some(cache) = cache {
+                if cache.full_hash(original, original_size) != step.hash
+                    || cache.full_hash(&step.from, step.size) != step.hash
+                {
+                    return Err("The file or the copy to keep changed since planning".to_string());
+                }
+            }
+            trash::delete(source).map_err(|e| e.to_string())?;
+            Ok(Vec::new())
+        }
+    }
+}
+
+/// List virtual groups (by month, folder or camera) with counts from the last scan
+#[tauri::command]
+pub async fn list_photo_groups(
+    library: State<'_, Library>,
+    group_by: GroupBy,
+) -> Result<Vec<PhotoGroup>, CommandError> {
+    Ok(library.groups(group_by, &Archive::open()?.paths()?))
+}
+
+/// List a page of photos belonging to a group returned by `list_photo_groups`
+#[tauri::command]
+pub async fn list_group_members(
+    library: State<'_, Library>,
+    group_id: String,
+    offset: usize,
+    limit: usize,
+) -> Result<GroupMembers, CommandError> {
+    Ok(library.group_members(&group_id, offset, limit, &Archive::open()?.paths()?)?)
+}
+
+/// Load app configuration
+#[tauri::command]
+pub async fn load_config() -> Result<AppConfig, CommandError> {
+    Ok(AppConfig::load())
+}
+
+/// Save app configuration
+#[tauri::command]
+pub async fn save_config(config: AppConfig) -> Result<(), CommandError> {
+    Ok(config.save()?)
+}
+
+/// Save where the user is in the app (selection, folder, scroll position and
+/// filter) so `load_session` can resume there
+#[tauri::command]
+pub async fn save_session(session: Session) -> Result<(), CommandError> {
+    Ok(session::save(session)?)
+}
+
+/// The last saved session, None when nothing was saved yet
+#[tauri::command]
+pub async fn load_session() -> Result<Option<Session>, CommandError> {
+    Ok(session::load())
+}
+
+/// `move-progress` payload
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MoveProgress {
+    /// "moving" (a rename on the same volume) | "copying" (to another volume,
+    /// verified before the original is removed) | "complete"
+    pub phase: String,
+    pub current: usize,
+    pub total: usize,
+    pub bytes_moved: u64,
+    pub total_bytes: u64,
+    pub current_file: String,
+}
+
+/// Why `move_files` failed; the files it had already moved were moved back
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MoveFailure {
+    /// The file that could not be moved and where it was going, when the
+    /// failure was about one file
+    pub path: Option<String>,
+    pub destination: Option<String>,
+    pub error: CommandError,
+    /// Files moved back to where they were
+    pub rolled_back: usize,
+    /// Files that could not be moved back, by the path they were left at
+    pub stranded: Vec<FailedFile>,
+}
+
+impl From<CommandError> for MoveFailure {
+    fn from(error: CommandError) -> Self {
+        Self {
+            path: None,
+            destination: None,
+            error,
+            rolled_back: 0,
+            stranded: Vec::new(),
+        }
+    }
+}
+
+impl From<String> for MoveFailure {
+    fn from(error: String) -> Self {
+        CommandError::from(error).into()
+    }
+}
+
+/// Move files to a destination folder, all or nothing: if one file fails, the
+/// files already moved are moved back and the failure says which file and why
+/// Moves to another volume (an external drive, a NAS) copy each file, verify
+/// the copy's hash and only then remove the original, emitting `move-progress`
+/// as they go; cancelling moves back the files already moved and fails with
+/// `cancelled`
+/// With `dry_run` the planned moves, including renames that avoid name
+/// conflicts, are returned without touching the filesystem
+#[tauri::command]
+#[allow(clippy::result_large_err)] // only built once, when the move fails
+pub async fn move_files(
+    window: Window,
+    files: Vec<String>,
+    destination: String,
+    dry_run: bool,
+    remove_empty_folders: bool,
+) -> Result<Vec<MoveOperation>, MoveFailure> {
+    let dest_path = Path::new(&destination);
+    let read_only = ReadOnlyRoots::load();
+    read_only.check_all(&files)?;
+    read_only.check(dest_path)?;
+
+    // Plan every move first so conflicts between files in the same batch are resolved too
+    let mut planner = MovePlanner::new(files.iter().map(PathBuf::from));
+    // Each photo's moves followed by its companions'
+    let mut groups: Vec<Vec<MoveOperation>> = Vec::new();
+
+    for file in files {
+        let source = Path::new(&file);
+        if !source.exists() {
+            continue;
+        }
+        let file_name = source.file_name().ok_or("Invalid file name".to_string())?;
+        let moves = planner.plan_group(source, dest_path.join(file_name), &[])?;
+        groups.push(planned_operations(moves));
+    }
+
+    if dry_run {
+        return Ok(groups.concat());
+    }
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let _busy = shutdown::begin("move");
+        let dest_path = Path::new(&destination);
+        if !dest_path.exists() {
+            fs::create_dir_all(dest_path).map_err(|e| e.to_string())?;
+        }
+        let monitor = JobMonitor::start(&window, "move");
+        let cache = HashCache::open().ok();
+        let total = groups.iter().map(Vec::len).sum();
+        let size = |op: &MoveOperation| fs::metadata(&op.from).map(|m| m.len()).unwrap_or(0);
+        let total_bytes: u64 = groups.iter().flatten().map(size).sum();
+        let mut bytes_moved = 0;
+        let mut operations = Vec::with_capacity(total);
+
+        for group in groups {
+            if monitor.is_cancelled() {
+                let _ = window.emit(
+                    "move-progress",
+                    MoveProgress {
+                        phase: "rolling_back".to_string(),
+                        current: operations.len(),
+                        total,
+                        bytes_moved,
+                        total_bytes,
+                        current_file: String::new(),
+                    },
+                );
+                return Err(roll_back_moves(
+                    &operations,
+                    None,
+                    CommandError::Cancelled,
+                    "move",
+                    cache.as_ref(),
+                ));
+            }
+            for mut op in group {
+                let _work = monitor.working_on(&op.from);
+                let source = Path::new(&op.from);
+                let phase = if same_volume(source, dest_path) {
+                    "moving"
+                } else {
+                    "copying"
+                };
+                let progress = MoveProgress {
+                    phase: phase.to_string(),
+                    current: operations.len() + 1,
+                    total,
+                    bytes_moved,
+                    total_bytes,
+                    current_file: op.from.clone(),
+                };
+                let _ = window.emit("move-progress", progress);
+
+                bytes_moved += size(&op);
+                match move_path(source, Path::new(&op.to), cache.as_ref()) {
+                    Ok(verified_hash) => op.verified_hash = verified_hash,
+                    Err(error) => {
+                        let progress = MoveProgress {
+                            phase: "rolling_back".to_string(),
+                            current: operations.len(),
+                            total,
+                            bytes_moved,
+                            total_bytes,
+                            current_file: op.from.clone(),
+                        };
+                        let _ = window.emit("move-progress", progress);
+                        return Err(roll_back_moves(
+                            &operations,
+                            Some(op),
+                            error,
+                            "move",
+                            cache.as_ref(),
+                        ));
+                    }
+                }
+                operations.push(op);
+            }
+        }
+
+        let _ = window.emit(
+            "move-progress",
+            MoveProgress {
+                phase: "complete".to_string(),
+                current: operations.len(),
+                total,
+                bytes_moved,
+                total_bytes,
+                current_file: String::new(),
+            },
+        );
+        relocate_records(&operations);
+        record_folder_activity(operations.iter().map(|op| op.from.as_str()));
+        log_operation("move", logged_moves(&operations));
+        if remove_empty_folders {
+            remove_emptied_source_folders(&operations);
+        }
+        Ok(operations)
+    })
+    .await
+    .map_err(|e| MoveFailure::from(e.to_string()))?
+}
+
+/// Move back the files a batch moved before `failed` couldn't be moved, or
+/// before it was cancelled; the ones that can't be moved back are logged as an
+/// operation of `kind`
+fn roll_back_moves(
+    operations: &[MoveOperation],
+    failed: Option<MoveOperation>,
+    error: CommandError,
+    kind: &'static str,
+    cache: Option<&HashCache>,
+) -> MoveFailure {
+    let moves: Vec<(PathBuf, PathBuf)> = operations
+        .iter()
+        .map(|op| (PathBuf::from(&op.from), PathBuf::from(&op.to)))
+        .collect();
+    let stranded = roll_back(&moves, cache);
+
+    // Files left at their new path must still be found there
+    let stranded_paths: HashSet<&Path> = stranded.iter().map(|(path, _)| path.as_path()).collect();
+    let kept: Vec<MoveOperation> = operations
+        .iter()
+        .filter(|op| stranded_paths.contains(Path::new(&op.to)))
+        .cloned()
+        .collect();
+    relocate_records(&kept);
+    log_operation(kind, logged_moves(&kept));
+
+    let (path, destination) = failed.map(|op| (op.from, op.to)).unzip();
+    MoveFailure {
+        path,
+        destination,
+        error,
+        rolled_back: operations.len() - stranded.len(),
+        stranded: stranded
+            .into_iter()
+            .map(|(path, error)| FailedFile {
+                path: path.to_string_lossy().to_string(),
+                error,
+            })
+            .collect(),
+    }
+}
+
+/// Move files in batch (for undo operations)
+#[tauri::command]
+pub async fn move_files_batch(operations: Vec<MoveOperation>) -> Result<(), CommandError> {
+    let _busy = shutdown::begin("undo");
+    let read_only = ReadOnlyRoots::load();
+    for op in &operations {
+        read_only.check(Path::new(&op.from))?;
+        read_only.check(Path::new(&op.to))?;
+    }
+    let cache = HashCache::open().ok();
+    let mut moved = Vec::new();
+
+    for op in operations {
+        let source = Path::new(&op.from);
+        let target = Path::new(&op.to);
+
+        // Ensure parent directory exists
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+
+        if source.exists() {
+            move_path(source, target, cache.as_ref())?;
+            moved.push(op);
+        }
+    }
+
+    relocate_records(&moved);
+    log_operation("undo", logged_moves(&moved));
+    Ok(())
+}
+
+/// Delete progress event payload
+#[derive(Debug, Clone, Serialize)]
+pub struct DeleteProgress {
+    pub current: usize,
+    pub total: usize,
+    pub deleted_bytes: u64,
+    pub current_file: String,
+    pub phase: String,
+}
+
+/// Delete completion result
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DeleteResult {
+    pub deleted_count: usize,
+    pub failed_count: usize,
+    pub total_bytes: u64,
+    /// Bytes freed on disk (can be far less than total_bytes for compression and
+    /// hard links); at most, since blocks shared with APFS clones stay in use
+    pub total_disk_bytes: u64,
+    /// Files moved to the trash or deleted, or that would be for a dry run
+    pub files: Vec<String>,
+    /// From a `delete_permanently` dry run: pass it back to delete the same files
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub confirmation_token: Option<String>,
+}
+
+/// Move files to system trash with progress reporting
+/// With `dry_run` nothing is trashed; the result counts the files that would be
+/// and the space it would free, and files that are already gone as failed
+#[tauri::command]
+pub async fn trash_files(
+    window: Window,
+    files: Vec<String>,
+    dry_run: bool,
+) -> Result<DeleteResult, CommandError> {
+    ReadOnlyRoots::load().check_all(&files)?;
+    if dry_run {
+        let mut result = DeleteResult::default();
+        let mut disk_usage = DiskUsage::default();
+        for file in files {
+            match fs::metadata(&file) {
+                Ok(metadata) => {
+                    result.deleted_count += 1;
+                    result.total_bytes += metadata.len();
+                    disk_usage.add(&metadata);
+                    result.files.push(file);
+                }
+                Err(_) => result.failed_count += 1,
+            }
+        }
+        result.total_disk_bytes = disk_usage.bytes();
+        return Ok(result);
+    }
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let _busy = shutdown::begin("delete");
+        let total = files.len();
+        let mut deleted_count = 0;
+        let mut failed_count = 0;
+        let mut total_bytes: u64 = 0;
+        let mut disk_usage = DiskUsage::default();
+        let mut trashed: Vec<&str> = Vec::new();
+        let recorder = trash_recorder();
+        let monitor = JobMonitor::start(&window, "delete");
+
+        for (i, file) in files.iter().enumerate() {
+            if monitor.is_cancelled() {
+                break;
+            }
+            let _work = monitor.working_on(file);
+            let path = Path::new(&file);
+
+            // Get file size before deletion
+            let metadata = fs::metadata(path).ok();
+            let file_size = metadata.as_ref().map_or(0, |m| m.len());
+            let file_name = path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .unwrap_or("unknown")
+                .to_string();
+
+            // Emit progress event
+            let _ = window.emit(
+                "delete-progress",
+                DeleteProgress {
+                    current: i + 1,
+                    total,
+                    deleted_bytes: total_bytes,
+                    current_file: file_name.clone(),
+                    phase: "deleting".to_string(),
+                },
+            );
+
+            // Hash known before the file is gone, so a re-import can be recognised
+            let hash = recorder
+                .as_ref()
+                .and_then(|(_, cache)| cache.cached_full_hash(file, file_size));
+            if monitor.is_skipped(file) {
+                failed_count += 1;
+                continue;
+            }
+
+            // Attempt deletion
+            match trash::delete(file) {
+                Ok(_) => {
+                    if let (Some(recorder), Some(hash)) = (&recorder, &hash) {
+                        remember_trashed(recorder, hash, file_size, file);
+                    }
+                    trashed.push(file);
+                    deleted_count += 1;
+                    total_bytes += file_size;
+                    if let Some(metadata) = &metadata {
+                        disk_usage.add(metadata);
+                    }
+                }
+                Err(e) => {
+                    eprintln!("Failed to delete {}: {}", file, e);
+                    failed_count += 1;
+                }
+            }
+        }
+
+        // Emit completion event
+        let _ = window.emit(
+            "delete-progress",
+            DeleteProgress {
+                current: total,
+                total,
+                deleted_bytes: total_bytes,
+                current_file: String::new(),
+                phase: "complete".to_string(),
+            },
+        );
+
+        log_operation(
+            "trash",
+            trashed
+                .iter()
+                .map(|path| LoggedFile::removed(path))
+                .collect(),
+        );
+        record_folder_activity(trashed.iter().copied());
+
+        Ok(DeleteResult {
+            deleted_count,
+            failed_count,
+            total_bytes,
+            total_disk_bytes: disk_usage.bytes(),
+            files: trashed.into_iter().map(str::to_string).collect(),
+            ..Default::default()
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Delete files for good, for when the trash isn't available (network volumes,
+/// files too big for it); reports progress and reclaimed space like `trash_files`
+/// Only a dry run does anything without a token: it counts the files and the
+/// space they would free and returns a `confirmation_token`. Passing that token
+/// back with the same files, within ten minutes, deletes them; a token works once
+#[tauri::command]
+pub async fn delete_permanently(
+    window: Window,
+    files: Vec<String>,
+    dry_run: bool,
+    confirmation_token: Option<String>,
+) -> Result<DeleteResult, CommandError> {
+    ReadOnlyRoots::load().check_all(&files)?;
+    if dry_run {
+        let mut result = trash_files(window, files.clone(), true).await?;
+        result.confirmation_token = Some(delete_confirmation::issue(&files));
+        return Ok(result);
+    }
+    delete_confirmation::redeem(confirmation_token.as_deref().unwrap_or_default(), &files)?;
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let _busy = shutdown::begin("delete");
+        let total = files.len();
+        let mut result = DeleteResult::default();
+        let mut disk_usage = DiskUsage::default();
+        let recorder = trash_recorder();
+        let monitor = JobMonitor::start(&window, "delete");
+
+        for (i, file) in files.iter().enumerate() {
+            if monitor.is_cancelled() {
+                break;
+            }
+            let _work = monitor.working_on(file);
+            let path = Path::new(&file);
+            let metadata = fs::metadata(path).ok();
+            let file_size = metadata.as_ref().map_or(0, |m| m.len());
+            let _ = window.emit(
+                "delete-progress",
+                DeleteProgress {
+                    current: i + 1,
+                    total,
+                    deleted_bytes: result.total_bytes,
+                    current_file: path
+                        .file_name()
+                        .and_then(|n| n.to_str())
+                        .unwrap_or("unknown")
+                        .to_string(),
+                    phase: "deleting".to_string(),
+                },
+            );
+
+            let hash = recorder
+                .as_ref()
+                .and_then(|(_, cache)| cache.cached_full_hash(file, file_size));
+            if monitor.is_skipped(file) {
+                result.failed_count += 1;
+                continue;
+            }
+
+            match fs::remove_file(path) {
+                Ok(()) => {
+                    if let (Some(recorder), Some(hash)) = (&recorder, &hash) {
+                        remember_trashed(recorder, hash, file_size, file);
+                    }
+                    result.deleted_count += 1;
+                    result.total_bytes += file_size;
+                    if let Some(metadata) = &metadata {
+                        disk_usage.add(metadata);
+                    }
+                    result.files.push(file.clone());
+                }
+                Err(e) => {
+                    eprintln!("Failed to delete {}: {}", file, e);
+                    result.failed_count += 1;
+                }
+            }
+        }
+
+        let _ = window.emit(
+            "delete-progress",
+            DeleteProgress {
+                current: total,
+                total,
+                deleted_bytes: result.total_bytes,
+                current_file: String::new(),
+                phase: "complete".to_string(),
+            },
+        );
+
+        log_operation(
+            "delete",
+            result
+                .files
+                .iter()
+                .map(|path| LoggedFile::removed(path))
+                .collect(),
+        );
+        record_folder_activity(result.files.iter().map(String::as_str));
+        result.total_disk_bytes = disk_usage.bytes();
+        Ok(result)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Organize files into date-based folders (e.g. `YYYY/YYYY-MM/`) under a root
+/// Uses the EXIF capture date, falling back to the file date; companions and
+/// sidecars go with their photo. With `dry_run` the planned moves are returned
+/// without touching the filesystem.
+#[tauri::command]
+#[allow(clippy::result_large_err)] // only built once, when a move fails
+pub async fn organize_by_date(
+    files: Vec<String>,
+    destination: String,
+    template: Option<String>,
+    dry_run: bool,
+    remove_empty_folders: bool,
+) -> Result<Vec<MoveOperation>, MoveFailure> {
+    let template = template
+        .filter(|t| !t.trim().is_empty())
+        .or_else(|| Some(AppConfig::load().organize_template).filter(|t| !t.trim().is_empty()))
+        .unwrap_or_else(default_organize_template);
+    let root = Path::new(&destination);
+
+    // Plan every move first so conflicts between files in the same batch are resolved too
+    let mut planner = MovePlanner::new(files.iter().map(PathBuf::from));
+    let mut operations = Vec::new();
+
+    for file in files {
+        let source = Path::new(&file);
+        if !source.exists() {
+            continue;
+        }
+
+        let Some(date) = capture_date_or_file_date(source) else {
+            continue;
+        };
+
+        let file_name = source.file_name().ok_or("Invalid file name".to_string())?;
+        let target = root.join(expand_date_template(&template, &date)).join(file_name);
+
+        // Already in the right place
+        if target == source {
+            continue;
+        }
+        operations.extend(planned_operations(planner.plan_group(
+            source,
+            target,
+            &[],
+        )?));
+    }
+
+    if dry_run {
+        return Ok(operations);
+    }
+    organize_files(&mut operations, "organize", remove_empty_folders)?;
+    Ok(operations)
+}
+
+/// Carry out planned moves into their folders, creating the folders, and log
+/// them as one operation of `kind`
+/// All or nothing, like `move_files`: if one move fails, the ones already made
+/// are moved back
+#[allow(clippy::result_large_err)] // only built once, when a move fails
+fn organize_files(
+    operations: &mut [MoveOperation],
+    kind: &'static str,
+    remove_empty_folders: bool,
+) -> Result<(), MoveFailure> {
+    let read_only = ReadOnlyRoots::load();
+    for op in operations.iter() {
+        read_only.check(Path::new(&op.from))?;
+        read_only.check(Path::new(&op.to))?;
+    }
+    let _busy = shutdown::begin(kind);
+    let cache = HashCache::open().ok();
+    for i in 0..operations.len() {
+        let op = &operations[i];
+        let target = Path::new(&op.to);
+        let moved = target
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .map_err(|e| CommandError::io(target, &e))
+            .and_then(|()| move_path(Path::new(&op.from), target, cache.as_ref()));
+        match moved {
+            Ok(verified_hash) => operations[i].verified_hash = verified_hash,
+            Err(error) => {
+                let failed = Some(op.clone());
+                return Err(roll_back_moves(
+                    &operations[..i],
+                    failed,
+                    error,
+                    kind,
+                    cache.as_ref(),
+                ));
+            }
+        }
+    }
+
+    relocate_records(operations);
+    record_folder_activity(operations.iter().map(|op| op.from.as_str()));
+    log_operation(kind, logged_moves(operations));
+    if remove_empty_folders {
+        remove_emptied_source_folders(operations);
+    }
+    Ok(())
+}
+
+/// The moves the organize rules in the config call for, without touching
+/// anything; `paths` limits them to those photos (the whole catalog when unset)
+#[tauri::command]
+pub async fn preview_rules(paths: Option<Vec<String>>) -> Result<Vec<RuleMove>, CommandError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let paths: Option<HashSet<String>> = paths.map(|p| p.into_iter().collect());
+        Ok(rules::plan(
+            &AppConfig::load().organize_rules,
+            paths.as_ref(),
+        )?)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// File photos by the organize rules in the config, moving each into the
+/// folder of the first rule it matches; returns the moves made, for undo
+#[tauri::command]
+#[allow(clippy::result_large_err)] // only built once, when a move fails
+pub async fn apply_rules(
+    paths: Option<Vec<String>>,
+    remove_empty_folders: bool,
+) -> Result<Vec<MoveOperation>, MoveFailure> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let paths: Option<HashSet<String>> = paths.map(|p| p.into_iter().collect());
+        let mut operations: Vec<MoveOperation> =
+            rules::plan(&AppConfig::load().organize_rules, paths.as_ref())?
+                .into_iter()
+                .map(|m| MoveOperation {
+                    from: m.from,
+                    to: m.to,
+                    verified_hash: None,
+                })
+                .collect();
+        organize_files(&mut operations, "rules", remove_empty_folders)?;
+        Ok(operations)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Whether empty-folder cleanup has to leave a folder alone: only folders
+/// inside a configured library folder are ever removed, never the library
+/// folders themselves, the home folder or anything read-only
+fn protected_folder() -> impl Fn(&Path) -> bool {
+    let roots: Vec<PathBuf> = AppConfig::load()
+        .directories
+        .into_iter()
+        .map(|d| PathBuf::from(d.path))
+        .collect();
+    let home = dirs::home_dir();
+    let read_only = ReadOnlyRoots::load();
+    move |dir| {
+        !roots
+            .iter()
+            .any(|root| dir.starts_with(root) && dir != root)
+            || home.as_deref() == Some(dir)
+            || read_only.check(dir).is_err()
+    }
+}
+
+/// Remove the folders `operations` moved the last files out of
+fn remove_emptied_source_folders(operations: &[MoveOperation]) {
+    let protected = protected_folder();
+    let sources: BTreeSet<&Path> = operations
+        .iter()
+        .filter_map(|op| Path::new(&op.from).parent())
+        .collect();
+    // Deepest first, so a parent is only looked at once its subfolders are gone
+    let removed: Vec<PathBuf> = sources
+        .into_iter()
+        .rev()
+        .flat_map(|folder| remove_emptied_dirs(folder, &protected))
+        .collect();
+    log_removed_folders(&removed);
+}
+
+fn log_removed_folders(removed: &[PathBuf]) -> Vec<String> {
+    let removed: Vec<String> = removed
+        .iter()
+        .map(|f| f.to_string_lossy().to_string())
+        .collect();
+    log_operation(
+        "remove_folders",
+        removed.iter().map(|f| LoggedFile::removed(f)).collect(),
+    );
+    removed
+}
+
+/// Remove the folders under `directories` that hold no files (or only OS junk
+/// like `.DS_Store`); the directories themselves and their parents are kept
+/// Only folders inside configured library folders are removed, never
+/// read-only ones
+/// Returns the folders removed
+#[tauri::command]
+pub async fn remove_empty_directories(
+    directories: Vec<String>,
+) -> Result<Vec<String>, CommandError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let _busy = shutdown::begin("cleanup");
+        let protected = protected_folder();
+        let removed: Vec<PathBuf> = directories
+            .iter()
+            .flat_map(|dir| remove_empty_dirs(Path::new(dir), &protected))
+            .collect();
+        log_removed_folders(&removed)
+    })
+    .await
+    .map_err(|e| CommandError::Other(e.to_string()))
+}
+
+/// Trash history plus the hash cache to look up files about to be trashed in,
+/// when `rememberTrashed` is enabled
+/// Only hashes already cached (from a duplicate scan) are recorded: hashing a
+/// large file just before deleting it would make trashing slow
+fn trash_recorder() -> Option<(TrashHistory, HashCache)> {
+    if !AppConfig::load().remember_trashed {
+        return None;
+    }
+    Some((TrashHistory::open().ok()?, HashCache::open().ok()?))
+}
+
+fn remember_trashed(recorder: &(TrashHistory, HashCache), hash: &str, size: u64, path: &str) {
+    let (history, cache) = recorder;
+    if let Err(e) = history.record(hash, cache.algorithm(), size, path) {
+        eprintln!("Failed to remember trashed file {}: {}", path, e);
+    }
+}
+
+/// Append a completed operation to the operation log; an operation that
+/// touched nothing isn't logged
+fn log_operation(kind: &str, files: Vec<LoggedFile>) {
+    if files.is_empty() {
+        return;
+    }
+    if let Err(e) = OperationLog::open().and_then(|mut log| log.record(kind, &files)) {
+        eprintln!("Failed to log {} of {} files: {}", kind, files.len(), e);
+    }
+}
+
+fn logged_moves(operations: &[MoveOperation]) -> Vec<LoggedFile> {
+    operations
+        .iter()
+        .map(|op| LoggedFile::verified(&op.from, &op.to, op.verified_hash.as_deref()))
+        .collect()
+}
+
+/// Count moving or trashing files as reviewing the folders they came from
+fn record_folder_activity<'a>(files: impl IntoIterator<Item = &'a str>) {
+    if let Ok(reviews) = FolderReviews::open() {
+        reviews.record_activity(files);
+    }
+}
+
+/// Mark a folder as reviewed/organized (or clear the mark)
+#[tauri::command]
+pub async fn mark_folder_reviewed(folder: String, reviewed: bool) -> Result<(), CommandError> {
+    Ok(FolderReviews::open()?.mark(&folder, reviewed)?)
+}
+
+/// Folders from the catalog that haven't been reviewed, largest and oldest first
+#[tauri::command]
+pub async fn get_unreviewed_folders(
+    limit: Option<usize>,
+) -> Result<Vec<UnreviewedFolder>, CommandError> {
+    Ok(FolderReviews::open()?.unreviewed(limit.unwrap_or(50))?)
+}
+
+/// Folder tree of each enabled configured directory, with the photos and bytes
+/// the catalog holds in every folder and below it
+#[tauri::command]
+pub async fn list_directory_tree() -> Result<Vec<FolderNode>, CommandError> {
+    Ok(tauri::async_runtime::spawn_blocking(directory_tree::list)
+        .await
+        .map_err(|e| e.to_string())??)
+}
+
+/// Photo count, size, RAW/JPEG split, duplicates and date range of every
+/// folder holding cataloged photos, or only of `folder` and its subfolders
+#[tauri::command]
+pub async fn folder_stats(folder: Option<String>) -> Result<Vec<FolderStats>, CommandError> {
+    Ok(Stats::open()?.folders(folder.as_deref().map(Path::new))?)
+}
+
+/// Counts by extension, camera and year, a size histogram and the space
+/// duplicates take up; computed from the catalog, so it returns right away
+#[tauri::command]
+pub async fn library_stats() -> Result<LibraryStats, CommandError> {
+    Ok(Stats::open()?.library()?)
+}
+
+/// Photos whose name carries a date that disagrees with their EXIF capture
+/// date or file creation time, e.g. files renamed or re-dated by a migration
+#[tauri::command]
+pub async fn find_date_mismatches(
+    library: State<'_, Library>,
+    options: Option<MismatchOptions>,
+) -> Result<Vec<DateMismatch>, CommandError> {
+    let photos = library.snapshot();
+    tauri::async_runtime::spawn_blocking(move || {
+        Ok(date_mismatch::find(&photos, &options.unwrap_or_default()))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Convert progress event payload
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConvertProgress {
+    pub current: usize,
+    pub total: usize,
+    pub current_file: String,
+    pub phase: String,
+}
+
+/// A file that could not be processed, with the reason
+#[derive(Debug, Clone, Serialize)]
+pub struct FailedFile {
+    pub path: String,
+    pub error: String,
+}
+
+/// Convert completion result
+#[derive(Debug, Clone, Serialize)]
+pub struct ConvertResult {
+    /// Source → written file
+    pub converted: Vec<MoveOperation>,
+    pub failed: Vec<FailedFile>,
+}
+
+/// Convert images (HEIC/TIFF/PNG/...) to JPEG or WebP, keeping EXIF/ICC metadata
+/// Output goes to `destination` if given, otherwise next to each source file
+#[tauri::command]
+pub async fn convert_images(
+    window: Window,
+    paths: Vec<String>,
+    format: ConvertFormat,
+    quality: Option<u8>,
+    destination: Option<String>,
+) -> Result<ConvertResult, CommandError> {
+    // Converted files are written to the destination or next to their sources
+    let read_only = ReadOnlyRoots::load();
+    match &destination {
+        Some(destination) => read_only.check(Path::new(destination))?,
+        None => read_only.check_all(&paths)?,
+    }
+    tauri::async_runtime::spawn_blocking(move || {
+        let total = paths.len();
+        let destination = destination.map(PathBuf::from);
+        let mut converted = Vec::new();
+        let mut failed = Vec::new();
+        let monitor = JobMonitor::start(&window, "convert");
+
+        for (i, path) in paths.into_iter().enumerate() {
+            if monitor.is_cancelled() {
+                break;
+            }
+            let _work = monitor.working_on(&path);
+            let _ = window.emit(
+                "convert-progress",
+                ConvertProgress {
+                    current: i + 1,
+                    total,
+                    current_file: Path::new(&path)
+                        .file_name()
+                        .and_then(|n| n.to_str())
+                        .unwrap_or("unknown")
+                        .to_string(),
+                    phase: "converting".to_string(),
+                },
+            );
+
+            match convert_image(Path::new(&path), format, quality, destination.as_deref()) {
+                _ if monitor.is_skipped(&path) => failed.push(skipped_file(&path)),
+                Ok(target) => converted.push(MoveOperation {
+                    from: path,
+                    to: target.to_string_lossy().to_string(),
+                    verified_hash: None,
+                }),
+                Err(error) => failed.push(FailedFile { path, error }),
+            }
+        }
+
+        let _ = window.emit(
+            "convert-progress",
+            ConvertProgress {
+                current: total,
+                total,
+                current_file: String::new(),
+                phase: "complete".to_string(),
+            },
+        );
+
+        ConvertResult { converted, failed }
+    })
+    .await
+    .map_err(|e| CommandError::Other(e.to_string()))
+}
+
+/// Export progress event payload
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportProgress {
+    pub current: usize,
+    pub total: usize,
+    pub current_file: String,
+}
+
+/// Export completion result
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportResult {
+    pub exported: Vec<ExportedFile>,
+    pub failed: Vec<FailedFile>,
+    /// The manifest written to the target folder, when anything was exported
+    pub manifest_path: Option<String>,
+}
+
+/// Copy a selection into a folder, keeping the folders below the deepest one
+/// they share or flattening them, optionally converting RAW to JPEG and
+/// resizing; records a manifest of what was written
+#[tauri::command]
+pub async fn export_files(
+    window: Window,
+    paths: Vec<String>,
+    options: ExportOptions,
+) -> Result<ExportResult, CommandError> {
+    if options.destination.trim().is_empty() {
+        return Err("Choose a folder to export to".into());
+    }
+    ReadOnlyRoots::load().check(Path::new(&options.destination))?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let total = paths.len();
+        let targets = export::relative_targets(&paths, options.folder_structure);
+        let mut planned = HashSet::new();
+        let mut exported = Vec::new();
+        let mut failed = Vec::new();
+        let monitor = JobMonitor::start(&window, "export");
+
+        for (i, (path, relative)) in paths.into_iter().zip(targets).enumerate() {
+            if monitor.is_cancelled() {
+                break;
+            }
+            let _work = monitor.working_on(&path);
+            let _ = window.emit(
+                "export-progress",
+                ExportProgress {
+                    current: i + 1,
+                    total,
+                    current_file: Path::new(&path)
+                        .file_name()
+                        .and_then(|n| n.to_str())
+                        .unwrap_or("unknown")
+                        .to_string(),
+                },
+            );
+
+            match export::export_file(Path::new(&path), &relative, &options, &mut planned) {
+                _ if monitor.is_skipped(&path) => failed.push(skipped_file(&path)),
+                Ok(file) => exported.push(file),
+                Err(error) => failed.push(FailedFile { path, error }),
+            }
+        }
+
+        let manifest_path = if exported.is_empty() {
+            None
+        } else {
+            let path = export::write_manifest(&options.destination, &exported)?;
+            Some(path.to_string_lossy().to_string())
+        };
+        Ok(ExportResult {
+            exported,
+            failed,
+            manifest_path,
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Zip export completion result
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ZipExportResult {
+    /// Where the archive was written; a numbered name if the chosen one was taken
+    pub zip_path: Option<String>,
+    /// Files added, sidecars included
+    pub added: usize,
+    pub size: u64,
+    pub failed: Vec<FailedFile>,
+}
+
+/// Stream a selection into a .zip for sharing, keeping the folders below the
+/// deepest one they share; with `include_sidecars`, XMP sidecars and
+/// companions go in next to their photos
+/// Nothing is written when every file fails or the export is cancelled
+#[tauri::command]
+pub async fn export_zip(
+    window: Window,
+    paths: Vec<String>,
+    destination: String,
+    include_sidecars: bool,
+) -> Result<ZipExportResult, CommandError> {
+    if destination.trim().is_empty() {
+        return Err("Choose where to save the zip".into());
+    }
+    ReadOnlyRoots::load().check(Path::new(&destination))?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let total = paths.len();
+        let names = export::relative_targets(&paths, FolderStructure::Preserve);
+        let mut zip = ZipExport::create(Path::new(&destination))?;
+        let mut added_sources: HashSet<PathBuf> = HashSet::new();
+        let mut failed = Vec::new();
+        let monitor = JobMonitor::start(&window, "export");
+
+        for (i, (path, name)) in paths.into_iter().zip(names).enumerate() {
+            if monitor.is_cancelled() {
+                break;
+            }
+            let _work = monitor.working_on(&path);
+            let _ = window.emit(
+                "export-zip-progress",
+                ExportProgress {
+                    current: i + 1,
+                    total,
+                    current_file: Path::new(&path)
+                        .file_name()
+                        .and_then(|n| n.to_str())
+                        .unwrap_or("unknown")
+                        .to_string(),
+                },
+            );
+
+            let source = PathBuf::from(&path);
+            if !added_sources.insert(source.clone()) {
+                continue;
+            }
+            let entry = match zip.add(&source, &name, false) {
+                _ if monitor.is_skipped(&path) => {
+                    failed.push(skipped_file(&path));
+                    continue;
+                }
+                Ok(entry) => entry,
+                Err(error) => {
+                    failed.push(FailedFile { path, error });
+                    continue;
+                }
+            };
+            if !include_sidecars {
+                continue;
+            }
+
+            // Sidecars follow their photo's entry, renamed with it if it was numbered
+            let old_stem = source.file_stem().and_then(|s| s.to_str()).unwrap_or("");
+            let new_stem = entry.file_stem().and_then(|s| s.to_str()).unwrap_or("");
+            for sidecar in export::sidecars(&source) {
+                if !added_sources.insert(sidecar.clone()) {
+                    continue;
+                }
+                let file_name = sidecar
+                    .file_name()
+                    .and_then(|n| n.to_str())
+                    .unwrap_or_default();
+                let renamed = restem(file_name, old_stem, new_stem);
+                if let Err(error) = zip.add(&sidecar, &entry.with_file_name(renamed), true) {
+                    failed.push(FailedFile {
+                        path: sidecar.to_string_lossy().to_string(),
+                        error,
+                    });
+                }
+            }
+        }
+
+        let added = added_sources.len() - failed.len();
+        if monitor.is_cancelled() || added == 0 {
+            zip.abort();
+            return Ok(ZipExportResult {
+                zip_path: None,
+                added: 0,
+                size: 0,
+                failed,
+            });
+        }
+        let zip_path = zip.finish()?;
+        Ok(ZipExportResult {
+            size: fs::metadata(&zip_path).map(|m| m.len()).unwrap_or(0),
+            zip_path: Some(zip_path.to_string_lossy().to_string()),
+            added,
+            failed,
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Result entry for a file the user skipped after it stalled
+fn skipped_file(path: &str) -> FailedFile {
+    FailedFile {
+        path: path.to_string(),
+        error: "Skipped after stalling".to_string(),
+    }
+}
+
+/// Transcode progress event payload
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TranscodeProgress {
+    pub current: usize,
+    pub total: usize,
+    pub current_file: String,
+    /// Progress within the current file, 0–100
+    pub file_percent: u32,
+    pub phase: String,
+}
+
+/// Transcode videos with ffmpeg (e.g. HEVC → H.264, capped resolution/bitrate) for sharing
+#[tauri::command]
+pub async fn transcode_videos(
+    window: Window,
+    paths: Vec<String>,
+    options: TranscodeOptions,
+) -> Result<ConvertResult, CommandError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let total = paths.len();
+        let mut converted = Vec::new();
+        let mut failed = Vec::new();
+        let monitor = JobMonitor::start(&window, "transcode");
+
+        for (i, path) in paths.into_iter().enumerate() {
+            if monitor.is_cancelled() {
+                break;
+            }
+            let _work = monitor.working_on(&path);
+            let current_file = Path::new(&path)
+                .file_name()
+                .and_then(|n| n.to_str())
+                .unwrap_or("unknown")
+                .to_string();
+
+            let mut last_percent = None;
+            let result = transcode_video(Path::new(&path), &options, |fraction| {
+                // ffmpeg reports several times a second; only emit when the percent changes
+                monitor.progress(None);
+                let percent = (fraction * 100.0) as u32;
+                if last_percent != Some(percent) {
+                    last_percent = Some(percent);
+                    let _ = window.emit(
+                        "transcode-progress",
+                        TranscodeProgress {
+                            current: i + 1,
+                            total,
+                            current_file: current_file.clone(),
+                            file_percent: percent,
+                            phase: "transcoding".to_string(),
+                        },
+                    );
+                }
+            });
+
+            match result {
+                _ if monitor.is_skipped(&path) => failed.push(skipped_file(&path)),
+                Ok(target) => converted.push(MoveOperation {
+                    from: path,
+                    to: target.to_string_lossy().to_string(),
+                    verified_hash: None,
+                }),
+                Err(error) => failed.push(FailedFile { path, error }),
+            }
+        }
+
+        let _ = window.emit(
+            "transcode-progress",
+            TranscodeProgress {
+                current: total,
+                total,
+                current_file: String::new(),
+                file_percent: 100,
+                phase: "complete".to_string(),
+            },
+        );
+
+        ConvertResult { converted, failed }
+    })
+    .await
+    .map_err(|e| CommandError::Other(e.to_string()))
 }
 
-/// Load app configuration
+/// Rename a file; with `dry_run` the new path is returned, and a name that is
+/// taken reported, without renaming anything
 #[tauri::command]
-pub async fn load_config() -> Result<AppConfig, String> {
-    Ok(AppConfig::load())
+pub async fn rename_file(
+    path: String,
+    new_name: String,
+    dry_run: Option<bool>,
+) -> Result<String, CommandError> {
+    let _busy = shutdown::begin("rename");
+    let source = Path::new(&path);
+    if !source.exists() {
+        return Err(CommandError::NotFound { path });
+    }
+    ReadOnlyRoots::load().check(source)?;
+
+    let parent = source.parent().ok_or("Invalid path")?;
+    let target = parent.join(&new_name);
+
+    if target.exists() && target != source {
+        return Err(CommandError::Conflict {
+            path: target.to_string_lossy().to_string(),
+        });
+    }
+    if dry_run.unwrap_or(false) {
+        return Ok(target.to_string_lossy().to_string());
+    }
+
+    fs::rename(source, &target).map_err(|e| CommandError::io(source, &e))?;
+    let target = target.to_string_lossy().to_string();
+    log_operation("rename", vec![LoggedFile::moved(&path, &target)]);
+
+    Ok(target)
 }
 
-/// Save app configuration
-#[tauri::command]
-pub async fn save_config(config: AppConfig) -> Result<(), String> {
-    config.save()
+/// One file of `rename_files_batch`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RenameRequest {
+    pub path: String,
+    pub new_name: String,
 }
 
-/// Move files to a destination folder
+/// Rename several files at once, all or nothing; a name that is taken, on disk
+/// or by an earlier file of the batch, gets a number
+/// With `dry_run` the renames, numbered names included, are returned without
+/// renaming anything
 #[tauri::command]
-pub async fn move_files(
-    files: Vec<String>,
-    destination: String,
-) -> Result<Vec<MoveOperation>, String> {
-    let dest_path = Path::new(&destination);
+pub async fn rename_files_batch(
+    renames: Vec<RenameRequest>,
+    dry_run: bool,
+) -> Result<Vec<MoveOperation>, CommandError> {
+    let read_only = ReadOnlyRoots::load();
+    let mut planner = MovePlanner::new([]);
+    let mut moves = Vec::new();
+    for rename in renames {
+        let source = PathBuf::from(&rename.path);
+        if !source.exists() {
+            return Err(CommandError::NotFound { path: rename.path });
+        }
+        read_only.check(&source)?;
+        let new_name = rename.new_name.trim();
+        if new_name.is_empty() || new_name.contains(['/', '\\']) {
+            return Err("Invalid name".into());
+        }
+        let target = source.parent().ok_or("Invalid path")?.join(new_name);
+        if target != source {
+            let target = planner.unique_target(target)?;
+            moves.push((source, target));
+        }
+    }
+    if dry_run {
+        return Ok(planned_operations(moves));
+    }
+
+    let _busy = shutdown::begin("rename");
+    let hashes = move_all(&moves, HashCache::open().ok().as_ref())?;
+    let operations = into_operations(moves, hashes);
+    relocate_records(&operations);
+    record_folder_activity(operations.iter().map(|op| op.from.as_str()));
+    log_operation("rename", logged_moves(&operations));
+    Ok(operations)
+}
+
+/// Primary file followed by its related files (RAW/JPEG pair, sidecars) that still exist
+fn group_paths(photo: &PhotoFile) -> Vec<PathBuf> {
+    std::iter::once(photo.path.as_str())
+        .chain(photo.related_files.iter().map(|r| r.path.as_str()))
+        .map(PathBuf::from)
+        .filter(|p| p.exists())
+        .collect()
+}
 
-    if !dest_path.exists() {
-        fs::create_dir_all(dest_path).map_err(|e| e.to_string())?;
+/// The files of a group still on disk; NotFound when none of them is
+fn existing_group_paths(photo: &PhotoFile) -> Result<Vec<PathBuf>, CommandError> {
+    let files = group_paths(photo);
+    if files.is_empty() {
+        return Err(CommandError::NotFound {
+            path: photo.path.clone(),
+        });
     }
+    Ok(files)
+}
 
-    let mut operations = Vec::new();
+/// Plan where each file of a group goes when moved to `folder` under `stem`
+fn plan_group(
+    files: &[PathBuf],
+    old_stem: &str,
+    folder: &Path,
+    stem: &str,
+) -> Vec<(PathBuf, PathBuf)> {
+    files
+        .iter()
+        .map(|file| {
+            let name = file
+                .file_name()
+                .and_then(|n| n.to_str())
+                .unwrap_or_default();
+            (file.clone(), folder.join(restem(name, old_stem, stem)))
+        })
+        .collect()
+}
 
-    for file in files {
-        let source = Path::new(&file);
-        if !source.exists() {
-            continue;
+/// Hash files again after they were rewritten in place: their dates are kept
+/// and their size can be too, so the hash cache can't tell they changed
+fn refresh_hashes(paths: &[String]) -> Result<(), String> {
+    let cache = HashCache::open()?;
+    let mut hashes = Vec::new();
+    for path in paths {
+        cache.invalidate(path);
+        let size = fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+        if let Some(hash) = cache.full_hash(path, size) {
+            hashes.push((path.clone(), hash));
+        }
+    }
+    Catalog::open()?.set_hashes(&hashes)
+}
+
+/// Keep stable ids and catalog rows pointing at files the app just moved or renamed
+fn relocate_records(operations: &[MoveOperation]) {
+    if let Ok(mut ids) = PhotoIds::open() {
+        for op in operations {
+            if let Err(e) = ids.relocate(&op.from, &op.to) {
+                eprintln!("Failed to update photo id for {}: {}", op.to, e);
+            }
+        }
+    }
+    if let Ok(mut catalog) = Catalog::open() {
+        for op in operations {
+            if let Err(e) = catalog.relocate(&op.from, &op.to) {
+                eprintln!("Failed to update catalog for {}: {}", op.to, e);
+            }
         }
+    }
+}
+
+fn into_operations(
+    moves: Vec<(PathBuf, PathBuf)>,
+    hashes: Vec<Option<String>>,
+) -> Vec<MoveOperation> {
+    moves
+        .into_iter()
+        .zip(hashes)
+        .map(|((from, to), verified_hash)| MoveOperation {
+            from: from.to_string_lossy().to_string(),
+            to: to.to_string_lossy().to_string(),
+            verified_hash,
+        })
+        .collect()
+}
+
+/// Moves that haven't been made yet
+fn planned_operations(moves: Vec<(PathBuf, PathBuf)>) -> Vec<MoveOperation> {
+    let hashes = vec![None; moves.len()];
+    into_operations(moves, hashes)
+}
+
+fn photo_stem(photo: &PhotoFile) -> Result<String, String> {
+    Path::new(&photo.path)
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .map(str::to_string)
+        .ok_or_else(|| "Invalid file name".to_string())
+}
 
-        let file_name = source.file_name().ok_or("Invalid file name")?;
-        let target = dest_path.join(file_name);
+/// Move a photo together with its related files; on a name conflict the whole
+/// group gets the same numbered stem so the files stay paired
+#[tauri::command]
+pub async fn move_group(
+    library: State<'_, Library>,
+    photo_id: PhotoId,
+    destination: String,
+) -> Result<Vec<MoveOperation>, CommandError> {
+    let photo = library.get(photo_id).ok_or("Photo not found")?;
+    let files = existing_group_paths(&photo)?;
+    let old_stem = photo_stem(&photo)?;
+    let folder = PathBuf::from(&destination);
+    let read_only = ReadOnlyRoots::load();
+    read_only.check_all(&files)?;
+    read_only.check(&folder)?;
 
-        // Handle name conflicts
-        let final_target = if target.exists() {
-            find_unique_name(&target)?
+    let mut counter = 0;
+    let moves = loop {
+        let stem = if counter == 0 {
+            old_stem.clone()
         } else {
-            target
+            format!("{} ({})", old_stem, counter)
         };
+        let moves = plan_group(&files, &old_stem, &folder, &stem);
+        if moves.iter().all(|(from, to)| from == to || !to.exists()) {
+            break moves;
+        }
+        counter += 1;
+        if counter > 1000 {
+            return Err("Could not find unique name".into());
+        }
+    };
+    let moves: Vec<_> = moves.into_iter().filter(|(from, to)| from != to).collect();
+
+    let operations = tauri::async_runtime::spawn_blocking(move || {
+        let _busy = shutdown::begin("move");
+        fs::create_dir_all(&folder).map_err(|e| e.to_string())?;
+        let hashes = move_all(&moves, HashCache::open().ok().as_ref())?;
+        let operations = into_operations(moves, hashes);
+        relocate_records(&operations);
+        record_folder_activity(operations.iter().map(|op| op.from.as_str()));
+        log_operation("move", logged_moves(&operations));
+        Ok::<_, CommandError>(operations)
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+    library.relocate(
+        operations
+            .iter()
+            .map(|op| (op.from.as_str(), op.to.as_str())),
+    );
+    Ok(operations)
+}
+
+/// Rename a photo and its related files to a new stem, keeping each extension
+#[tauri::command]
+pub async fn rename_group(
+    library: State<'_, Library>,
+    photo_id: PhotoId,
+    stem: String,
+) -> Result<Vec<MoveOperation>, CommandError> {
+    let stem = stem.trim();
+    if stem.is_empty() || stem.contains(['/', '\\']) {
+        return Err("Invalid name".into());
+    }
 
-        fs::rename(&source, &final_target).map_err(|e| e.to_string())?;
+    let photo = library.get(photo_id).ok_or("Photo not found")?;
+    let files = existing_group_paths(&photo)?;
+    ReadOnlyRoots::load().check_all(&files)?;
+    let old_stem = photo_stem(&photo)?;
+    let folder = Path::new(&photo.path).parent().ok_or("Invalid path")?;
 
-        operations.push(MoveOperation {
-            from: file,
-            to: final_target.to_string_lossy().to_string(),
+    let moves: Vec<_> = plan_group(&files, &old_stem, folder, stem)
+        .into_iter()
+        .filter(|(from, to)| from != to)
+        .collect();
+    if let Some((_, taken)) = moves.iter().find(|(_, to)| to.exists()) {
+        return Err(CommandError::Conflict {
+            path: taken.to_string_lossy().to_string(),
         });
     }
 
+    let operations = tauri::async_runtime::spawn_blocking(move || {
+        let _busy = shutdown::begin("rename");
+        let hashes = move_all(&moves, HashCache::open().ok().as_ref())?;
+        let operations = into_operations(moves, hashes);
+        relocate_records(&operations);
+        record_folder_activity(operations.iter().map(|op| op.from.as_str()));
+        log_operation("rename", logged_moves(&operations));
+        Ok::<_, CommandError>(operations)
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+    library.relocate(
+        operations
+            .iter()
+            .map(|op| (op.from.as_str(), op.to.as_str())),
+    );
     Ok(operations)
 }
 
-/// Move files in batch (for undo operations)
+/// Move a photo and all its related files to the trash in one operation
 #[tauri::command]
-pub async fn move_files_batch(operations: Vec<MoveOperation>) -> Result<(), String> {
-    for op in operations {
-        let source = Path::new(&op.from);
-        let target = Path::new(&op.to);
+pub async fn trash_group(
+    library: State<'_, Library>,
+    photo_id: PhotoId,
+) -> Result<DeleteResult, CommandError> {
+    let _busy = shutdown::begin("delete");
+    let photo = library.get(photo_id).ok_or("Photo not found")?;
+    let files = group_paths(&photo);
+    ReadOnlyRoots::load().check_all(&files)?;
 
-        // Ensure parent directory exists
-        if let Some(parent) = target.parent() {
-            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
-        }
+    let mut total_bytes = 0;
+    let mut disk_usage = DiskUsage::default();
+    for metadata in files.iter().filter_map(|f| fs::metadata(f).ok()) {
+        total_bytes += metadata.len();
+        disk_usage.add(&metadata);
+    }
 
-        if source.exists() {
-            fs::rename(source, target).map_err(|e| e.to_string())?;
+    // Hashes known before the files are gone, so a re-import can be recognised
+    let recorder = trash_recorder();
+    let hashes: Vec<(String, u64, String)> = match &recorder {
+        Some((_, cache)) => files
+            .iter()
+            .filter_map(|f| {
+                let path = f.to_str()?;
+                let size = fs::metadata(f).ok()?.len();
+                Some((path.to_string(), size, cache.cached_full_hash(path, size)?))
+            })
+            .collect(),
+        None => Vec::new(),
+    };
+
+    trash::delete_all(&files).map_err(|e| e.to_string())?;
+    record_folder_activity(files.iter().filter_map(|f| f.to_str()));
+    log_operation(
+        "trash",
+        files
+            .iter()
+            .map(|f| LoggedFile::removed(&f.to_string_lossy()))
+            .collect(),
+    );
+    if let Some(recorder) = &recorder {
+        for (path, size, hash) in &hashes {
+            remember_trashed(recorder, hash, *size, path);
         }
     }
 
-    Ok(())
+    Ok(DeleteResult {
+        deleted_count: files.len(),
+        failed_count: 0,
+        total_bytes,
+        total_disk_bytes: disk_usage.bytes(),
+        files: files
+            .iter()
+            .map(|f| f.to_string_lossy().to_string())
+            .collect(),
+        confirmation_token: None,
+    })
 }
 
-/// Delete progress event payload
+/// Moves, renames, trashing and duplicate resolution the app performed, newest
+/// first, each with the full list of files; `since` and `until` are ms since
+/// epoch and `kind` limits the result to one kind of operation
+#[tauri::command]
+pub async fn get_operation_history(
+    since: Option<i64>,
+    until: Option<i64>,
+    kind: Option<String>,
+    limit: Option<usize>,
+) -> Result<Vec<LoggedOperation>, CommandError> {
+    Ok(OperationLog::open()?.history(since, until, kind.as_deref(), limit.unwrap_or(100))?)
+}
+
+/// Entry counts and size of the hash cache
+#[tauri::command]
+pub async fn cache_stats() -> Result<CacheStats, CommandError> {
+    Ok(HashCache::open()?.stats()?)
+}
+
+/// Drop hash cache entries for files that no longer exist and compact the database
+/// Returns the number of entries removed
+#[tauri::command]
+pub async fn prune_cache() -> Result<usize, CommandError> {
+    Ok(
+        tauri::async_runtime::spawn_blocking(|| HashCache::open()?.prune())
+            .await
+            .map_err(|e| e.to_string())??,
+    )
+}
+
+/// Empty the hash cache; the next scan re-hashes everything
+#[tauri::command]
+pub async fn clear_cache() -> Result<(), CommandError> {
+    Ok(HashCache::open()?.clear()?)
+}
+
+/// Fixity progress event payload
 #[derive(Debug, Clone, Serialize)]
-pub struct DeleteProgress {
+#[serde(rename_all = "camelCase")]
+pub struct FixityProgress {
     pub current: usize,
     pub total: usize,
-    pub deleted_bytes: u64,
     pub current_file: String,
+    /// "verifying" | "complete"
     pub phase: String,
 }
 
-/// Delete completion result
+/// A file whose content no longer matches its stored hash
 #[derive(Debug, Clone, Serialize)]
-pub struct DeleteResult {
-    pub deleted_count: usize,
-    pub failed_count: usize,
-    pub total_bytes: u64,
+#[serde(rename_all = "camelCase")]
+pub struct FixityMismatch {
+    pub path: String,
+    pub expected_hash: String,
+    pub actual_hash: String,
+    /// The size or modification time changed too, so the file was most likely
+    /// edited; otherwise the content changed underneath the file system
+    pub modified: bool,
 }
 
-/// Move files to system trash with progress reporting
+/// Outcome of a `verify_fixity` run
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FixityReport {
+    /// Files re-hashed
+    pub checked_count: usize,
+    /// Files whose content still matched
+    pub verified_count: usize,
+    pub changed: Vec<FixityMismatch>,
+    /// Files gone from a volume that is mounted
+    pub missing: Vec<String>,
+    /// Files on unmounted volumes and cloud placeholders, left for later
+    pub unavailable_count: usize,
+    pub failed: Vec<FailedFile>,
+}
+
+/// Re-hash files from the hash cache and compare them with their stored full
+/// hash, emitting `fixity-progress`; matching files get a last-verified time,
+/// so sampled runs work through the library oldest-verified first
+/// Changed files keep their stored hash and are reported again until a scan
+/// re-hashes them
 #[tauri::command]
-pub async fn trash_files(window: Window, files: Vec<String>) -> Result<DeleteResult, String> {
-    let total = files.len();
-    let mut deleted_count = 0;
-    let mut failed_count = 0;
-    let mut total_bytes: u64 = 0;
-
-    for (i, file) in files.iter().enumerate() {
-        let path = Path::new(&file);
-        
-        // Get file size before deletion
-        let file_size = fs::metadata(path).map(|m| m.len()).unwrap_or(0);
-        let file_name = path.file_name()
-            .and_then(|n| n.to_str())
-            .unwrap_or("unknown")
-            .to_string();
-
-        // Emit progress event
-        let _ = window.emit("delete-progress", DeleteProgress {
-            current: i + 1,
-            total,
-            deleted_bytes: total_bytes,
-            current_file: file_name.clone(),
-            phase: "deleting".to_string(),
+pub async fn verify_fixity(
+    window: Window,
+    options: FixityOptions,
+) -> Result<FixityReport, CommandError> {
+    Ok(tauri::async_runtime::spawn_blocking(move || {
+        let result = fixity_job(&window, &options);
+        notifications::job_finished(&window, "Fixity check", "fixity", &result, |report| {
+            format!(
+                "{} verified, {} changed, {} missing",
+                report.verified_count,
+                report.changed.len(),
+                report.missing.len()
+            )
         });
+        result
+    })
+    .await
+    .map_err(|e| e.to_string())??)
+}
 
-        // Attempt deletion
-        match trash::delete(&file) {
-            Ok(_) => {
-                deleted_count += 1;
-                total_bytes += file_size;
-            }
-            Err(e) => {
-                eprintln!("Failed to delete {}: {}", file, e);
-                failed_count += 1;
+fn fixity_job(window: &Window, options: &FixityOptions) -> Result<FixityReport, String> {
+    let cache = HashCache::open()?;
+    let due = cache.fixity_due(options)?;
+    let total = due.len();
+    let mut report = FixityReport::default();
+    let monitor = JobMonitor::start(window, "fixity");
+
+    for (i, entry) in due.into_iter().enumerate() {
+        if monitor.is_cancelled() {
+            break;
+        }
+        let _work = monitor.working_on(&entry.path);
+        let _ = window.emit(
+            "fixity-progress",
+            FixityProgress {
+                current: i + 1,
+                total,
+                current_file: Path::new(&entry.path)
+                    .file_name()
+                    .and_then(|n| n.to_str())
+                    .unwrap_or("unknown")
+                    .to_string(),
+                phase: "verifying".to_string(),
+            },
+        );
+
+        let path = Path::new(&entry.path);
+        let Ok(metadata) = fs::metadata(path) else {
+            if volume_mounted(path) {
+                report.missing.push(entry.path);
+            } else {
+                report.unavailable_count += 1;
             }
+            continue;
+        };
+        // Reading a placeholder would download it
+        if is_cloud_placeholder(&entry.path) {
+            report.unavailable_count += 1;
+            continue;
+        }
+
+        let actual = compute_full_hash(&entry.path, cache.algorithm());
+        if monitor.is_skipped(&entry.path) {
+            report.failed.push(skipped_file(&entry.path));
+            continue;
+        }
+        let Some(actual) = actual else {
+            report.failed.push(FailedFile {
+                path: entry.path,
+                error: "Could not read the file".to_string(),
+            });
+            continue;
+        };
+
+        report.checked_count += 1;
+        if actual == entry.full_hash {
+            cache.set_verified(&entry.path, chrono::Utc::now().timestamp_millis());
+            report.verified_count += 1;
+        } else {
+            report.changed.push(FixityMismatch {
+                modified: !entry.matches_metadata(&metadata),
+                path: entry.path,
+                expected_hash: entry.full_hash,
+                actual_hash: actual,
+            });
         }
     }
+    drop(monitor);
 
-    // Emit completion event
-    let _ = window.emit("delete-progress", DeleteProgress {
-        current: total,
-        total,
-        deleted_bytes: total_bytes,
-        current_file: String::new(),
-        phase: "complete".to_string(),
-    });
+    let _ = window.emit(
+        "fixity-progress",
+        FixityProgress {
+            current: total,
+            total,
+            current_file: String::new(),
+            phase: "complete".to_string(),
+        },
+    );
+    Ok(report)
+}
 
-    Ok(DeleteResult {
-        deleted_count,
-        failed_count,
-        total_bytes,
+/// Hash each imported file on the device and in the library, then store a
+/// signed verification report (see `get_import_reports`)
+#[tauri::command]
+pub async fn verify_import(
+    window: Window,
+    source: String,
+    destination: String,
+    files: Vec<ImportedFile>,
+) -> Result<ImportReport, CommandError> {
+    Ok(tauri::async_runtime::spawn_blocking(move || {
+        let report =
+            ImportReport::verify(&source, &destination, &files, HashAlgorithm::configured());
+        let result = report.save().map(|_| report);
+        notifications::job_finished(
+            &window,
+            "Import verification",
+            "import-reports",
+            &result,
+            |report| {
+                format!(
+                    "{} verified, {} failed",
+                    report.verified_count, report.failed_count
+                )
+            },
+        );
+        result
     })
+    .await
+    .map_err(|e| e.to_string())??)
 }
 
-/// Rename a file
+/// Stored import verification reports, newest first
 #[tauri::command]
-pub async fn rename_file(path: String, new_name: String) -> Result<String, String> {
-    let source = Path::new(&path);
-    if !source.exists() {
-        return Err("File not found".to_string());
-    }
+pub async fn get_import_reports() -> Result<Vec<ImportReport>, CommandError> {
+    Ok(ImportReport::load_all()?)
+}
 
-    let parent = source.parent().ok_or("Invalid path")?;
-    let target = parent.join(&new_name);
+/// Mounted removable and external volumes with capacity and free space;
+/// `volume-mounted`/`volume-unmounted` events report changes
+#[tauri::command]
+pub async fn list_volumes() -> Result<Vec<Volume>, CommandError> {
+    Ok(volumes::list())
+}
 
-    if target.exists() && target != source {
-        return Err("A file with that name already exists".to_string());
-    }
+/// Mounted camera cards (volumes with a DCIM folder)
+#[tauri::command]
+pub async fn list_import_devices() -> Result<Vec<Device>, CommandError> {
+    Ok(ingest::camera_volumes())
+}
 
-    fs::rename(source, &target).map_err(|e| e.to_string())?;
+/// Import the new photos and videos from a camera card into the library, with
+/// `ingest-progress` events per stage; without `volume` the only inserted card is used
+#[tauri::command]
+pub async fn import_from_device(
+    window: Window,
+    volume: Option<String>,
+    options: IngestOptions,
+) -> Result<IngestResult, CommandError> {
+    ReadOnlyRoots::load().check(Path::new(&options.destination))?;
+    Ok(tauri::async_runtime::spawn_blocking(move || {
+        let volume = match volume {
+            Some(volume) => PathBuf::from(volume),
+            None => ingest::only_camera_volume()?,
+        };
+        let result = ingest::import_from_device(&window, &volume, &options);
+        notifications::job_finished(&window, "Import", "import", &result, |result| {
+            format!(
+                "{} imported, {} already in the library, {} failed",
+                result.imported_count,
+                result.already_imported_count,
+                result.failed.len()
+            )
+        });
+        result
+    })
+    .await
+    .map_err(|e| e.to_string())??)
+}
 
-    Ok(target.to_string_lossy().to_string())
+/// Import a Google Photos Takeout folder into the library: new content only,
+/// with Google's capture time, caption and location written to XMP sidecars
+/// and edited copies stacked on their originals; emits `takeout-progress`
+#[tauri::command]
+pub async fn import_takeout(
+    window: Window,
+    takeout_path: String,
+    options: TakeoutOptions,
+) -> Result<TakeoutResult, CommandError> {
+    ReadOnlyRoots::load().check(Path::new(&options.destination))?;
+    Ok(tauri::async_runtime::spawn_blocking(move || {
+        let result = takeout::import_takeout(&window, Path::new(&takeout_path), &options);
+        notifications::job_finished(&window, "Takeout import", "import", &result, |result| {
+            format!(
+                "{} imported, {} already in the library, {} failed",
+                result.imported_count,
+                result.already_imported_count,
+                result.failed.len()
+            )
+        });
+        result
+    })
+    .await
+    .map_err(|e| e.to_string())??)
 }
 
-/// Create a new folder
+/// Check that every photo and video on a memory card has a verified copy in
+/// the library; `safe` is only true when nothing is left behind
 #[tauri::command]
-pub async fn create_folder(path: String) -> Result<(), String> {
-    fs::create_dir_all(&path).map_err(|e| e.to_string())
+pub async fn safe_to_format(volume: String) -> Result<FormatCheck, CommandError> {
+    Ok(tauri::async_runtime::spawn_blocking(move || {
+        check_volume(Path::new(&volume), &HashCache::open()?)
+    })
+    .await
+    .map_err(|e| e.to_string())??)
 }
 
-/// Reveal a file in Finder (macOS)
+/// Compare a library folder with its backup, like an rsync dry run: files
+/// missing from the backup, extra on it, differing at the same path, or backed
+/// up under another path; emits `compare-progress`
 #[tauri::command]
-pub async fn reveal_in_finder(path: String) -> Result<(), String> {
-    Command::new("open")
-        .arg("-R")  // Reveal in Finder
-        .arg(&path)
-        .spawn()
-        .map_err(|e| e.to_string())?;
-    Ok(())
+pub async fn compare_trees(
+    window: Window,
+    library_root: String,
+    backup_root: String,
+    options: CompareOptions,
+) -> Result<TreeComparison, CommandError> {
+    Ok(tauri::async_runtime::spawn_blocking(move || {
+        let result = backup_compare::compare(
+            &window,
+            Path::new(&library_root),
+            Path::new(&backup_root),
+            &options,
+        );
+        notifications::job_finished(&window, "Backup comparison", "backup", &result, |c| {
+            format!(
+                "{} missing from the backup, {} differing, {} extra",
+                c.missing.len(),
+                c.differing.len(),
+                c.extra.len()
+            )
+        });
+        result
+    })
+    .await
+    .map_err(|e| e.to_string())??)
 }
 
-/// Find a unique name for a file by appending a number
-fn find_unique_name(path: &Path) -> Result<std::path::PathBuf, String> {
-    let stem = path
-        .file_stem()
-        .and_then(|s| s.to_str())
-        .ok_or("Invalid file name")?;
-    let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("");
-    let parent = path.parent().ok_or("Invalid path")?;
-
-    let mut counter = 1;
-    loop {
-        let new_name = if ext.is_empty() {
-            format!("{} ({})", stem, counter)
-        } else {
-            format!("{} ({}).{}", stem, counter, ext)
-        };
+/// Download or evict progress event payload
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CloudFilesProgress {
+    pub current: usize,
+    pub total: usize,
+    pub current_file: String,
+    /// "downloading" | "evicting" | "complete"
+    pub phase: String,
+}
+
+/// Download/evict completion result
+#[derive(Debug, Clone, Serialize)]
+pub struct CloudFilesResult {
+    pub completed: Vec<String>,
+    pub failed: Vec<FailedFile>,
+}
+
+/// Materialize cloud placeholders (iCloud Drive, Dropbox, OneDrive, ...) so
+/// they can be hashed or edited; the catalog stops treating them as placeholders
+#[tauri::command]
+pub async fn download_cloud_files(
+    window: Window,
+    paths: Vec<String>,
+) -> Result<CloudFilesResult, CommandError> {
+    Ok(tauri::async_runtime::spawn_blocking(move || {
+        cloud_files_job(&window, paths, "downloading", false, |path, monitor| {
+            cloud_files::download(Path::new(path), || {
+                monitor.is_cancelled() || monitor.is_skipped(path)
+            })
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())??)
+}
+
+/// Free local space by turning downloaded cloud files back into placeholders
+#[tauri::command]
+pub async fn evict_cloud_files(
+    window: Window,
+    paths: Vec<String>,
+) -> Result<CloudFilesResult, CommandError> {
+    Ok(tauri::async_runtime::spawn_blocking(move || {
+        cloud_files_job(&window, paths, "evicting", true, |path, _| {
+            cloud_files::evict(Path::new(path))
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())??)
+}
+
+/// Run a download or evict over `paths` as a monitored job, emitting
+/// `cloud-files-progress`, and record the new placeholder state in the catalog
+fn cloud_files_job(
+    window: &Window,
+    paths: Vec<String>,
+    phase: &str,
+    placeholder: bool,
+    action: impl Fn(&str, &JobMonitor) -> Result<(), String>,
+) -> Result<CloudFilesResult, String> {
+    let total = paths.len();
+    let mut completed = Vec::new();
+    let mut failed = Vec::new();
+    let monitor = JobMonitor::start(window, phase);
 
-        let new_path = parent.join(&new_name);
-        if !new_path.exists() {
-            return Ok(new_path);
+    for (i, path) in paths.into_iter().enumerate() {
+        if monitor.is_cancelled() {
+            break;
         }
-        counter += 1;
+        let _work = monitor.working_on(&path);
+        let _ = window.emit(
+            "cloud-files-progress",
+            CloudFilesProgress {
+                current: i + 1,
+                total,
+                current_file: Path::new(&path)
+                    .file_name()
+                    .and_then(|n| n.to_str())
+                    .unwrap_or("unknown")
+                    .to_string(),
+                phase: phase.to_string(),
+            },
+        );
 
-        if counter > 1000 {
-            return Err("Could not find unique name".to_string());
+        match action(&path, &monitor) {
+            _ if monitor.is_skipped(&path) => failed.push(skipped_file(&path)),
+            Ok(()) => completed.push(path),
+            Err(error) => failed.push(FailedFile { path, error }),
         }
     }
+    drop(monitor);
+
+    Catalog::open()?.set_cloud_placeholder(&completed, placeholder)?;
+    let _ = window.emit(
+        "cloud-files-progress",
+        CloudFilesProgress {
+            current: total,
+            total,
+            current_file: String::new(),
+            phase: "complete".to_string(),
+        },
+    );
+    Ok(CloudFilesResult { completed, failed })
+}
+
+/// Running, paused and queued jobs, oldest first
+#[tauri::command]
+pub async fn list_jobs(jobs: State<'_, Jobs>) -> Result<Vec<JobProgress>, CommandError> {
+    Ok(jobs.list())
+}
+
+/// Stop a running job (ids come from `job-progress` events or `list_jobs`);
+/// it finishes the file it is on first, and a queued job never starts
+#[tauri::command]
+pub async fn cancel_job(jobs: State<'_, Jobs>, job_id: u64) -> Result<(), CommandError> {
+    Ok(jobs.cancel(job_id)?)
+}
+
+/// Hold a job after the file it is on until `resume_job`
+#[tauri::command]
+pub async fn pause_job(jobs: State<'_, Jobs>, job_id: u64) -> Result<(), CommandError> {
+    Ok(jobs.pause(job_id)?)
+}
+
+#[tauri::command]
+pub async fn resume_job(jobs: State<'_, Jobs>, job_id: u64) -> Result<(), CommandError> {
+    Ok(jobs.resume(job_id)?)
+}
+
+/// Give up on a file a job is stalled on and continue with the rest
+#[tauri::command]
+pub async fn skip_stalled_file(
+    jobs: State<'_, Jobs>,
+    job_id: u64,
+    path: String,
+) -> Result<(), CommandError> {
+    Ok(jobs.skip(job_id, &path)?)
+}
+
+/// Create a new folder
+#[tauri::command]
+pub async fn create_folder(path: String) -> Result<(), CommandError> {
+    Ok(fs::create_dir_all(&path).map_err(|e| e.to_string())?)
+}
+
+/// Reveal a file in Finder (macOS), Explorer (Windows) or the desktop's file manager (Linux)
+#[tauri::command]
+pub async fn reveal_in_finder(path: String) -> Result<(), CommandError> {
+    Ok(reveal(Path::new(&path)).map_err(|e| e.to_string())?)
+}
+
+/// Preview a file in Quick Look at full resolution, RAW formats included (macOS)
+#[tauri::command]
+pub async fn quick_look(path: String) -> Result<(), CommandError> {
+    let path = Path::new(&path);
+    if !path.exists() {
+        return Err(CommandError::NotFound {
+            path: path.to_string_lossy().to_string(),
+        });
+    }
+    file_ops::quick_look(path).map_err(|e| CommandError::Other(e.to_string()))
+}
+
+/// Path of a JPEG rendition of a HEIC/HEIF photo for the webview to show,
+/// converted on first use and cached
+#[tauri::command]
+pub async fn heif_preview(path: String) -> Result<String, CommandError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let source = Path::new(&path);
+        if !source.exists() {
+            return Err(CommandError::NotFound { path });
+        }
+        if is_cloud_placeholder(&path) {
+            return Err(CommandError::CloudNotDownloaded { path });
+        }
+        Ok(convert::heif_preview(source)?.to_string_lossy().to_string())
+    })
+    .await
+    .map_err(|e| CommandError::Other(e.to_string()))?
+}
+
+/// Open a photo in an external app, given by macOS bundle id or by path
+#[tauri::command]
+pub async fn open_with(path: String, app: String) -> Result<(), CommandError> {
+    editors::open_with(Path::new(&path), &app)
+}
+
+/// Configured editors, then the apps the system says can open `path`
+#[tauri::command]
+pub async fn list_available_editors(
+    path: Option<String>,
+) -> Result<Vec<AvailableEditor>, CommandError> {
+    Ok(editors::available(path.as_deref().map(Path::new)))
+}
+
+/// Show two photos side by side in the compare window, opening it or, when it
+/// is already open, sending it the photos with `compare-photos`
+#[tauri::command]
+pub async fn open_compare_window(
+    app: AppHandle,
+    left: String,
+    right: String,
+) -> Result<(), CommandError> {
+    Ok(windows::open_compare(&app, vec![left, right])?)
+}
+
+/// The photos the compare window shows, from the library (or the catalog for
+/// photos outside it); files that are gone are left out
+#[tauri::command]
+pub async fn compared_photos(
+    library: State<'_, Library>,
+    compared: State<'_, ComparedPhotos>,
+) -> Result<Vec<PhotoFile>, CommandError> {
+    let snapshot = library.snapshot();
+    let catalog = Catalog::open().ok();
+    Ok(compared
+        .paths()
+        .into_iter()
+        .filter_map(|path| {
+            snapshot
+                .iter()
+                .find(|photo| photo.path == path)
+                .cloned()
+                .or_else(|| catalog.as_ref()?.get(&path))
+        })
+        .collect())
+}
+
+/// Line up 2 to 4 files for a duplicate review: dimensions, EXIF, sizes and
+/// hashes side by side, which of them differ, and whether the bytes or the
+/// decoded pixels are exactly the same
+#[tauri::command]
+pub async fn compare_photos(paths: Vec<String>) -> Result<PhotoComparison, CommandError> {
+    tauri::async_runtime::spawn_blocking(move || Ok(compare::compare(&paths)?))
+        .await
+        .map_err(|e| e.to_string())?
 }
@@ -0,0 +1,105 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Stem suffixes of depth maps, portrait mattes and other auxiliary images
+/// written next to a photo by phones and editing tools (compared lowercase)
+const AUXILIARY_SUFFIXES: &[&str] = &[
+    "_depth",
+    "-depth",
+    ".depth",
+    "_depthmap",
+    "_disparity",
+    "_portraitmatte",
+    "_matte",
+    "_aux",
+];
+
+/// Apple Photos adjustment data (edit recipe for the original)
+const ADJUSTMENT_EXTENSIONS: &[&str] = &["aae"];
+
+/// Kind of file that belongs to a primary photo without being a photo of its own
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompanionKind {
+    /// Depth map, portrait matte or other computational-photography output
+    Depth,
+    /// Edited rendition exported next to the original (`IMG_E1234.JPG`)
+    Edited,
+    /// Edit recipe (`IMG_1234.AAE`, `IMG_O1234.AAE`); losing it breaks re-editing
+    Adjustments,
+}
+
+impl CompanionKind {
+    /// Value used for `RelatedFile.file_type`
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CompanionKind::Depth => "depth",
+            CompanionKind::Edited => "edited",
+            CompanionKind::Adjustments => "adjustments",
+        }
+    }
+}
+
+/// If the file is a companion, the stem of the photo it belongs to and its kind
+pub fn companion_of(path: &Path) -> Option<(String, CompanionKind)> {
+    let stem = path.file_stem()?.to_str()?;
+    let ext = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase())
+        .unwrap_or_default();
+    let is_adjustment = ADJUSTMENT_EXTENSIONS.contains(&ext.as_str());
+
+    // Apple renditions: IMG_E1234 (edited), IMG_O1234 (original adjustments)
+    if let Some(number) = stem
+        .strip_prefix("IMG_E")
+        .or_else(|| stem.strip_prefix("IMG_O"))
+        .filter(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()))
+    {
+        let kind = if is_adjustment {
+            CompanionKind::Adjustments
+        } else {
+            CompanionKind::Edited
+        };
+        return Some((format!("IMG_{}", number), kind));
+    }
+
+    if is_adjustment {
+        return Some((stem.to_string(), CompanionKind::Adjustments));
+    }
+
+    AUXILIARY_SUFFIXES.iter().find_map(|suffix| {
+        let split = stem.len().checked_sub(suffix.len()).filter(|&i| i > 0)?;
+        let (base, tail) = (stem.get(..split)?, stem.get(split..)?);
+        tail.eq_ignore_ascii_case(suffix)
+            .then(|| (base.to_string(), CompanionKind::Depth))
+    })
+}
+
+/// Stem used to group a file with its related files: the primary's stem for
+/// companions, the file's own stem otherwise
+pub fn group_stem(path: &Path) -> Option<String> {
+    match companion_of(path) {
+        Some((stem, _)) => Some(stem),
+        None => Some(path.file_stem()?.to_str()?.to_string()),
+    }
+}
+
+/// Companion files of a photo found in its folder
+pub fn companion_paths(path: &Path) -> Vec<PathBuf> {
+    let (Some(parent), Some(stem)) = (path.parent(), path.file_stem().and_then(|s| s.to_str()))
+    else {
+        return Vec::new();
+    };
+    let Ok(entries) = fs::read_dir(parent) else {
+        return Vec::new();
+    };
+
+    entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|candidate| candidate != path && candidate.is_file())
+        .filter(|candidate| {
+            companion_of(candidate).is_some_and(|(base, _)| base.eq_ignore_ascii_case(stem))
+        })
+        .collect()
+}
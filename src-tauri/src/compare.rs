@@ -0,0 +1,162 @@
+use crate::file_ops::allocated_size;
+use crate::hash_cache::HashCache;
+use crate::metadata::{header_dimensions, oriented_dimensions, read_exif_summary};
+use crate::pixel_hash::can_hash_pixels;
+use chrono::{Local, TimeZone};
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+/// How many files the compare screen shows at once
+const MIN_FILES: usize = 2;
+const MAX_FILES: usize = 4;
+
+/// One file of a comparison, read from the file itself rather than the catalog
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ComparedFile {
+    pub path: String,
+    pub name: String,
+    pub extension: String,
+    pub size: u64,
+    pub disk_size: u64,
+    pub modified_at: i64,
+    /// Pixel size as displayed, i.e. with the EXIF orientation applied
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub orientation: Option<u16>,
+    /// EXIF capture time in ms since epoch
+    pub capture_date: Option<i64>,
+    pub camera_make: Option<String>,
+    pub camera_model: Option<String>,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    /// Hash of the file's bytes
+    pub hash: Option<String>,
+    /// Hash of the decoded pixels; None for formats that aren't decoded
+    /// (HEIF, RAW, video)
+    pub pixel_hash: Option<String>,
+}
+
+/// Files side by side, field by field, for reviewing duplicates
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PhotoComparison {
+    /// In the order they were asked for
+    pub files: Vec<ComparedFile>,
+    /// Fields (as named in `files`) whose value isn't the same for every
+    /// file; the path always differs and is left out
+    pub differing: Vec<String>,
+    /// Every file has the same bytes
+    pub identical_bytes: bool,
+    /// Every file decodes to exactly the same pixels; None when one of them
+    /// can't be decoded and the bytes differ
+    pub identical_pixels: Option<bool>,
+}
+
+/// Compare 2 to 4 files
+pub fn compare(paths: &[String]) -> Result<PhotoComparison, String> {
+    if !(MIN_FILES..=MAX_FILES).contains(&paths.len()) {
+        return Err(format!(
+            "Compare {} to {} files, not {}",
+            MIN_FILES,
+            MAX_FILES,
+            paths.len()
+        ));
+    }
+    let cache = HashCache::open()?;
+    let files = paths
+        .iter()
+        .map(|path| read_file(path, &cache))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let all_same = |value: fn(&ComparedFile) -> &Option<String>| {
+        let first = value(&files[0]);
+        first.is_some() && files.iter().all(|f| value(f) == first)
+    };
+    let identical_bytes = all_same(|f| &f.hash);
+    let identical_pixels = if identical_bytes {
+        Some(true)
+    } else if files.iter().all(|f| f.pixel_hash.is_some()) {
+        Some(all_same(|f| &f.pixel_hash))
+    } else {
+        None
+    };
+
+    Ok(PhotoComparison {
+        differing: differing_fields(&files),
+        files,
+        identical_bytes,
+        identical_pixels,
+    })
+}
+
+fn read_file(path: &str, cache: &HashCache) -> Result<ComparedFile, String> {
+    let file = Path::new(path);
+    let metadata = fs::metadata(file).map_err(|e| format!("{}: {}", path, e))?;
+    let extension = file
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default()
+        .to_lowercase();
+    let exif = read_exif_summary(file).unwrap_or_default();
+    let dimensions = header_dimensions(file)
+        .or(exif.width.zip(exif.height))
+        .map(|(w, h)| oriented_dimensions(w, h, exif.orientation));
+    let modified_at = metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or_default();
+
+    Ok(ComparedFile {
+        path: path.to_string(),
+        name: file
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or_default()
+            .to_string(),
+        size: metadata.len(),
+        disk_size: allocated_size(&metadata),
+        modified_at,
+        width: dimensions.map(|(w, _)| w),
+        height: dimensions.map(|(_, h)| h),
+        orientation: exif.orientation,
+        capture_date: exif.capture_date.and_then(|date| {
+            Local
+                .from_local_datetime(&date)
+                .earliest()
+                .map(|d| d.timestamp_millis())
+        }),
+        camera_make: exif.camera_make,
+        camera_model: exif.camera_model,
+        latitude: exif.latitude,
+        longitude: exif.longitude,
+        hash: cache.full_hash(path, metadata.len()),
+        pixel_hash: if can_hash_pixels(&extension) {
+            cache.pixel_hash(path)
+        } else {
+            None
+        },
+        extension,
+    })
+}
+
+/// Names of the fields whose values aren't all equal, path aside
+fn differing_fields(files: &[ComparedFile]) -> Vec<String> {
+    let values: Vec<serde_json::Value> = files
+        .iter()
+        .filter_map(|f| serde_json::to_value(f).ok())
+        .collect();
+    let Some(serde_json::Value::Object(first)) = values.first() else {
+        return Vec::new();
+    };
+    first
+        .iter()
+        .filter(|(field, _)| field.as_str() != "path")
+        .filter(|(field, value)| values.iter().any(|v| v.get(field.as_str()) != Some(value)))
+        .map(|(field, _)| field.clone())
+        .collect()
+}
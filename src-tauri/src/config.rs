@@ -1,12 +1,35 @@
+use crate::editors::ExternalEditor;
+use crate::error::CommandError;
+use crate::exclusions::default_exclude_patterns;
+use crate::rules::OrganizeRule;
+use crate::scan_options::{CloudPolicy, PrimaryPrecedence, ScanPreset};
 use serde::{Deserialize, Serialize};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct DirectoryConfig {
     pub path: String,
     pub enabled: bool,
     pub name: String,
+    /// When copies of a file are found under several directories, the one in
+    /// the directory with the highest priority is kept as the original
+    #[serde(default)]
+    pub priority: i32,
+    /// Globs for files and folders the scanner skips; patterns containing a `/`
+    /// match the path relative to this directory, others match any file name
+    #[serde(default = "default_exclude_patterns")]
+    pub exclude_patterns: Vec<String>,
+    /// Skip files and folders whose name starts with a dot
+    #[serde(default = "default_true")]
+    pub exclude_hidden: bool,
+    /// How many folder levels below this directory to scan (unlimited when unset)
+    #[serde(default)]
+    pub max_depth: Option<usize>,
+    /// Scanned but never written to (an archival drive): moving, renaming or
+    /// trashing anything under it is refused
+    #[serde(default)]
+    pub read_only: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
@@ -22,6 +45,40 @@ pub struct AppConfig {
     pub sort_order: String,
     #[serde(default = "default_filter_mode")]
     pub filter_mode: String,
+    /// Folder template used by organize_by_date (tokens: YYYY, MM, DD)
+    #[serde(default = "default_organize_template")]
+    pub organize_template: String,
+    /// Content hash algorithm: "sha256" or "blake3"
+    #[serde(default = "default_hash_algorithm")]
+    pub hash_algorithm: String,
+    /// Folder to write a JSON summary into after every scan (disabled when unset)
+    #[serde(default)]
+    pub scan_report_dir: Option<String>,
+    /// Whether scans without explicit options hash cloud placeholders
+    /// ("download", which fetches them) or leave them for `hash_pending_files` ("skip")
+    #[serde(default)]
+    pub cloud_policy: CloudPolicy,
+    /// Scan option sets saved by the user
+    #[serde(default)]
+    pub scan_presets: Vec<ScanPreset>,
+    /// Remember the content hash of files trashed from the app so copies that
+    /// reappear in a later scan are flagged as previously deleted; only files
+    /// hashed by an earlier scan are remembered
+    #[serde(default)]
+    pub remember_trashed: bool,
+    /// Which file of a RAW+JPEG or Live Photo pair scans show as the photo
+    #[serde(default)]
+    pub primary_precedence: PrimaryPrecedence,
+    /// Apps offered first by `list_available_editors`
+    #[serde(default)]
+    pub external_editors: Vec<ExternalEditor>,
+    /// Rules `apply_rules` files photos by, first match first
+    #[serde(default)]
+    pub organize_rules: Vec<OrganizeRule>,
+}
+
+fn default_true() -> bool {
+    true
 }
 
 fn default_view_mode() -> String {
@@ -40,6 +97,14 @@ fn default_filter_mode() -> String {
     "duplicates".to_string()
 }
 
+pub fn default_organize_template() -> String {
+    "YYYY/YYYY-MM".to_string()
+}
+
+fn default_hash_algorithm() -> String {
+    "sha256".to_string()
+}
+
 impl AppConfig {
     pub fn config_path() -> PathBuf {
         let config_dir = dirs::config_dir()
@@ -69,3 +134,63 @@ impl AppConfig {
     }
 }
 
+/// The directories marked read-only in the config, for commands that modify
+/// files to check against before touching anything
+pub struct ReadOnlyRoots {
+    /// Name and path of each root, as configured and with symlinks resolved
+    roots: Vec<(String, PathBuf, Option<PathBuf>)>,
+}
+
+impl ReadOnlyRoots {
+    pub fn load() -> Self {
+        let roots = AppConfig::load()
+            .directories
+            .into_iter()
+            .filter(|d| d.read_only)
+            .map(|d| {
+                let path = PathBuf::from(&d.path);
+                let name = if d.name.is_empty() { d.path } else { d.name };
+                let canonical = path.canonicalize().ok();
+                (name, path, canonical)
+            })
+            .collect();
+        Self { roots }
+    }
+
+    /// Refuse a change to `path`: a file being moved, renamed or trashed, or a
+    /// destination that may not exist yet
+    pub fn check(&self, path: &Path) -> Result<(), CommandError> {
+        if self.roots.is_empty() {
+            return Ok(());
+        }
+        let resolved = resolve(path);
+        for (name, root, canonical) in &self.roots {
+            let inside = path.starts_with(root)
+                || canonical
+                    .as_ref()
+                    .is_some_and(|c| resolved.as_ref().is_some_and(|r| r.starts_with(c)));
+            if inside {
+                return Err(CommandError::ReadOnly {
+                    path: path.to_string_lossy().to_string(),
+                    root: name.clone(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    pub fn check_all<P: AsRef<Path>>(
+        &self,
+        paths: impl IntoIterator<Item = P>,
+    ) -> Result<(), CommandError> {
+        paths.into_iter().try_for_each(|p| self.check(p.as_ref()))
+    }
+}
+
+/// `path` with symlinks resolved in the part of it that exists
+fn resolve(path: &Path) -> Option<PathBuf> {
+    path.ancestors().find_map(|ancestor| {
+        let canonical = ancestor.canonicalize().ok()?;
+        Some(canonical.join(path.strip_prefix(ancestor).ok()?))
+    })
+}
@@ -0,0 +1,432 @@
+use crate::config::{default_organize_template, AppConfig};
+use crate::file_ops::{companions_of, find_unique_name_with, restem};
+use crate::format_check::card_media_files;
+use crate::hash_cache::HashCache;
+use crate::jobs::JobMonitor;
+use crate::metadata::{capture_date_or_file_date, expand_date_template};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tauri::Window;
+
+/// What happens to a file whose content is already kept elsewhere
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DuplicateAction {
+    /// Move the extra copy to the trash
+    #[default]
+    Trash,
+    /// Leave the extra copy where it is
+    Leave,
+}
+
+/// What happens when the planned destination of a file is already taken
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ConflictAction {
+    /// Move it under a numbered name
+    #[default]
+    Rename,
+    /// Leave it where it is
+    Skip,
+}
+
+/// How `plan_consolidation` lays out the target
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ConsolidationRules {
+    /// Date folders under the target (YYYY, MM, DD); the organize template
+    /// from the config when unset
+    pub folder_template: Option<String>,
+    pub duplicates: DuplicateAction,
+    pub conflicts: ConflictAction,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum StepAction {
+    /// Move `from` (and `companions`) to `to`
+    Move,
+    /// Trash `from`, a copy of `duplicateOf`
+    Trash,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum StepStatus {
+    Pending,
+    Done,
+    Failed,
+}
+
+/// One move or trash of a plan
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlanStep {
+    pub action: StepAction,
+    pub from: String,
+    pub to: Option<String>,
+    /// Depth maps, edits and .AAE files moving with the photo, as (from, to)
+    pub companions: Vec<(String, String)>,
+    pub size: u64,
+    pub hash: Option<String>,
+    /// The copy that is kept, for trash steps
+    pub duplicate_of: Option<String>,
+    pub status: StepStatus,
+    pub error: Option<String>,
+}
+
+/// A file whose planned destination was already taken
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlanConflict {
+    pub path: String,
+    pub wanted: String,
+    /// Where it goes instead; `None` when it is left in place
+    pub resolved_to: Option<String>,
+}
+
+/// A folder of the target as it will look once the plan has run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectedFolder {
+    /// Relative to the target root
+    pub path: String,
+    pub file_count: usize,
+    pub size: u64,
+}
+
+/// The target as it will look once the plan has run
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Projection {
+    pub folders: Vec<ProjectedFolder>,
+    pub file_count: usize,
+    pub total_size: u64,
+    /// Bytes freed by trashing duplicates
+    pub reclaimed_bytes: u64,
+}
+
+/// Everything a consolidation will do, saved so it can be run in steps and
+/// resumed; step statuses are the checkpoints
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConsolidationPlan {
+    pub id: String,
+    pub created_at: String,
+    pub sources: Vec<String>,
+    pub target_root: String,
+    pub rules: ConsolidationRules,
+    pub steps: Vec<PlanStep>,
+    pub conflicts: Vec<PlanConflict>,
+    /// Duplicates left in place under `DuplicateAction::Leave`
+    pub duplicates_left: usize,
+    /// Files that could not be read or dated; they stay where they are
+    pub unreadable: Vec<String>,
+    pub projection: Projection,
+}
+
+/// `consolidation-progress` payload
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConsolidationProgress {
+    /// "scanning" | "hashing" | "moving" | "complete"
+    pub stage: String,
+    pub current: usize,
+    pub total: usize,
+    pub current_file: String,
+}
+
+pub fn emit_progress(window: &Window, stage: &str, current: usize, total: usize, path: &str) {
+    let _ = window.emit(
+        "consolidation-progress",
+        ConsolidationProgress {
+            stage: stage.to_string(),
+            current,
+            total,
+            current_file: Path::new(path)
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default(),
+        },
+    );
+}
+
+/// Plan moving every photo and video under `sources` into dated folders under
+/// `target_root`, keeping one copy of each content
+/// Files already in the target win over the sources, and earlier sources win
+/// over later ones; companions travel with their photo
+pub fn plan(
+    window: &Window,
+    sources: &[String],
+    target_root: &str,
+    rules: &ConsolidationRules,
+) -> Result<ConsolidationPlan, String> {
+    if sources.is_empty() {
+        return Err("Choose at least one folder to consolidate".to_string());
+    }
+    if target_root.trim().is_empty() {
+        return Err("Choose a target folder".to_string());
+    }
+    let root = Path::new(target_root);
+    let template = rules
+        .folder_template
+        .clone()
+        .filter(|t| !t.trim().is_empty())
+        .or_else(|| Some(AppConfig::load().organize_template).filter(|t| !t.trim().is_empty()))
+        .unwrap_or_else(default_organize_template);
+    let cache = HashCache::open()?;
+    let monitor = JobMonitor::start(window, "consolidate");
+    let cancelled = || "Consolidation planning cancelled".to_string();
+
+    emit_progress(window, "scanning", 0, 0, "");
+    let mut files: Vec<(PathBuf, u64)> = Vec::new();
+    let mut seen_paths = HashSet::new();
+    for source in sources {
+        for (path, size) in card_media_files(Path::new(source)).media {
+            if seen_paths.insert(path.clone()) {
+                files.push((path, size));
+            }
+        }
+    }
+    let companions: HashMap<PathBuf, Vec<PathBuf>> = files
+        .iter()
+        .map(|(path, _)| (path.clone(), companions_of(path)))
+        .filter(|(_, companions)| !companions.is_empty())
+        .collect();
+    let claimed: HashSet<&PathBuf> = companions.values().flatten().collect();
+    files.retain(|(path, _)| !claimed.contains(path));
+
+    // What the target already holds, outside the sources
+    let existing: Vec<(PathBuf, u64)> = card_media_files(root)
+        .media
+        .into_iter()
+        .filter(|(path, _)| !seen_paths.contains(path))
+        .collect();
+    let sizes: HashSet<u64> = files.iter().map(|(_, size)| *size).collect();
+
+    let to_hash: Vec<&(PathBuf, u64)> = files
+        .iter()
+        .chain(existing.iter().filter(|(_, size)| sizes.contains(size)))
+        .collect();
+    let hashed_count = AtomicUsize::new(0);
+    let hashes: HashMap<&Path, String> = to_hash
+        .par_iter()
+        .filter_map(|(path, size)| {
+            if monitor.is_cancelled() {
+                return None;
+            }
+            let path_str = path.to_string_lossy();
+            let _work = monitor.working_on(&path_str);
+            let hash = cache.full_hash(&path_str, *size);
+            let current = hashed_count.fetch_add(1, Ordering::Relaxed) + 1;
+            emit_progress(window, "hashing", current, to_hash.len(), &path_str);
+            hash.filter(|_| !monitor.is_skipped(&path_str))
+                .map(|hash| (path.as_path(), hash))
+        })
+        .collect();
+    if monitor.is_cancelled() {
+        return Err(cancelled());
+    }
+
+    let mut kept: HashMap<&str, String> = HashMap::new();
+    for (path, _) in &existing {
+        if let Some(hash) = hashes.get(path.as_path()) {
+            kept.entry(hash.as_str())
+                .or_insert_with(|| path.to_string_lossy().to_string());
+        }
+    }
+
+    let mut steps = Vec::new();
+    let mut conflicts = Vec::new();
+    let mut unreadable = Vec::new();
+    let mut duplicates_left = 0;
+    let mut planned: HashSet<PathBuf> = HashSet::new();
+    let mut projected: BTreeMap<String, (usize, u64)> = BTreeMap::new();
+    let mut reclaimed_bytes = 0;
+    let mut project = |path: &Path, size: u64| {
+        let folder = path
+            .parent()
+            .and_then(|p| p.strip_prefix(root).ok())
+            .map(|p| p.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let entry = projected.entry(folder).or_default();
+        entry.0 += 1;
+        entry.1 += size;
+    };
+    for (path, size) in &existing {
+        project(path, *size);
+    }
+
+    for (path, size) in &files {
+        let path_str = path.to_string_lossy().to_string();
+        let Some(hash) = hashes.get(path.as_path()) else {
+            unreadable.push(path_str);
+            continue;
+        };
+        if let Some(original) = kept.get(hash.as_str()) {
+            match rules.duplicates {
+                DuplicateAction::Trash => {
+                    reclaimed_bytes += size;
+                    steps.push(PlanStep {
+                        action: StepAction::Trash,
+                        from: path_str,
+                        to: None,
+                        companions: Vec::new(),
+                        size: *size,
+                        hash: Some(hash.clone()),
+                        duplicate_of: Some(original.clone()),
+                        status: StepStatus::Pending,
+                        error: None,
+                    });
+                }
+                DuplicateAction::Leave => duplicates_left += 1,
+            }
+            continue;
+        }
+
+        let Some(date) = capture_date_or_file_date(path) else {
+            unreadable.push(path_str);
+            continue;
+        };
+        let file_name = path.file_name().ok_or("Invalid file name")?;
+        let wanted = root
+            .join(expand_date_template(&template, &date))
+            .join(file_name);
+        let target = if wanted == *path {
+            wanted.clone()
+        } else if wanted.exists() || planned.contains(&wanted) {
+            let resolved = match rules.conflicts {
+                ConflictAction::Rename => Some(find_unique_name_with(&wanted, |p| {
+                    p.exists() || planned.contains(p)
+                })?),
+                ConflictAction::Skip => None,
+            };
+            conflicts.push(PlanConflict {
+                path: path_str.clone(),
+                wanted: wanted.to_string_lossy().to_string(),
+                resolved_to: resolved.as_ref().map(|p| p.to_string_lossy().to_string()),
+            });
+            match resolved {
+                Some(resolved) => resolved,
+                None => {
+                    kept.insert(hash.as_str(), path_str);
+                    continue;
+                }
+            }
+        } else {
+            wanted.clone()
+        };
+        kept.insert(hash.as_str(), target.to_string_lossy().to_string());
+        project(&target, *size);
+        planned.insert(target.clone());
+        if target == *path {
+            continue;
+        }
+
+        let old_stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("");
+        let new_stem = target.file_stem().and_then(|s| s.to_str()).unwrap_or("");
+        let mut moved_companions = Vec::new();
+        for companion in companions.get(path).into_iter().flatten() {
+            let name = companion
+                .file_name()
+                .and_then(|n| n.to_str())
+                .unwrap_or_default();
+            let companion_target = target.with_file_name(restem(name, old_stem, new_stem));
+            let companion_target =
+                if companion_target.exists() || planned.contains(&companion_target) {
+                    find_unique_name_with(&companion_target, |p| p.exists() || planned.contains(p))?
+                } else {
+                    companion_target
+                };
+            planned.insert(companion_target.clone());
+            moved_companions.push((
+                companion.to_string_lossy().to_string(),
+                companion_target.to_string_lossy().to_string(),
+            ));
+        }
+        steps.push(PlanStep {
+            action: StepAction::Move,
+            from: path_str,
+            to: Some(target.to_string_lossy().to_string()),
+            companions: moved_companions,
+            size: *size,
+            hash: Some(hash.clone()),
+            duplicate_of: None,
+            status: StepStatus::Pending,
+            error: None,
+        });
+    }
+
+    let folders: Vec<ProjectedFolder> = projected
+        .into_iter()
+        .map(|(path, (file_count, size))| ProjectedFolder {
+            path,
+            file_count,
+            size,
+        })
+        .collect();
+    let projection = Projection {
+        file_count: folders.iter().map(|f| f.file_count).sum(),
+        total_size: folders.iter().map(|f| f.size).sum(),
+        folders,
+        reclaimed_bytes,
+    };
+    emit_progress(window, "complete", 0, 0, "");
+
+    let now = chrono::Local::now();
+    let plan = ConsolidationPlan {
+        id: now.timestamp_millis().to_string(),
+        created_at: now.to_rfc3339(),
+        sources: sources.to_vec(),
+        target_root: target_root.to_string(),
+        rules: rules.clone(),
+        steps,
+        conflicts,
+        duplicates_left,
+        unreadable,
+        projection,
+    };
+    plan.save()?;
+    Ok(plan)
+}
+
+impl ConsolidationPlan {
+    /// Store the plan with its step statuses
+    pub fn save(&self) -> Result<(), String> {
+        let dir = plans_dir();
+        fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+        let json = serde_json::to_string(self).map_err(|e| e.to_string())?;
+        // Write then rename, so an interrupted save never leaves half a plan
+        let path = dir.join(format!("{}.json", self.id));
+        let temp = path.with_extension("json.tmp");
+        fs::write(&temp, json).map_err(|e| e.to_string())?;
+        fs::rename(&temp, &path).map_err(|e| e.to_string())
+    }
+
+    pub fn load(id: &str) -> Result<Self, String> {
+        if id.is_empty() || !id.chars().all(|c| c.is_ascii_digit()) {
+            return Err("Invalid plan id".to_string());
+        }
+        let json = fs::read_to_string(plans_dir().join(format!("{}.json", id)))
+            .map_err(|_| "Consolidation plan not found".to_string())?;
+        serde_json::from_str(&json).map_err(|e| e.to_string())
+    }
+
+    pub fn pending_count(&self) -> usize {
+        self.steps
+            .iter()
+            .filter(|s| s.status == StepStatus::Pending)
+            .count()
+    }
+}
+
+fn plans_dir() -> PathBuf {
+    dirs::config_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("photo-manager")
+        .join("consolidation")
+}
@@ -0,0 +1,273 @@
+use crate::animation::thumbnail_dir;
+use crate::file_ops::{copy_file_times, find_unique_name};
+use crate::scanner::RAW_EXTENSIONS;
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::webp::WebPEncoder;
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageDecoder, ImageEncoder, ImageReader};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Default JPEG quality when none is given
+const DEFAULT_QUALITY: u8 = 90;
+
+/// Output format for image conversion
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConvertFormat {
+    Jpeg,
+    /// Encoded losslessly (the bundled encoder has no lossy mode), quality is ignored
+    Webp,
+}
+
+impl ConvertFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            ConvertFormat::Jpeg => "jpg",
+            ConvertFormat::Webp => "webp",
+        }
+    }
+}
+
+/// A decoded image plus the metadata blocks to carry over to the output
+struct SourceImage {
+    image: DynamicImage,
+    exif: Option<Vec<u8>>,
+    icc_profile: Option<Vec<u8>>,
+}
+
+/// Convert a single image, returning the path of the written file
+/// Output goes to `destination` if given, otherwise next to the source
+pub fn convert_image(
+    source: &Path,
+    format: ConvertFormat,
+    quality: Option<u8>,
+    destination: Option<&Path>,
+) -> Result<PathBuf, String> {
+    let stem = source
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .ok_or("Invalid file name")?;
+    let dest_dir = match destination {
+        Some(dir) => dir.to_path_buf(),
+        None => source.parent().ok_or("Invalid path")?.to_path_buf(),
+    };
+    fs::create_dir_all(&dest_dir).map_err(|e| e.to_string())?;
+
+    let target = dest_dir.join(format!("{}.{}", stem, format.extension()));
+    let target = if target.exists() {
+        find_unique_name(&target)?
+    } else {
+        target
+    };
+
+    convert_image_to(source, &target, format, quality, None)?;
+    Ok(target)
+}
+
+/// Convert a single image to `target`, shrinking it to fit within
+/// `max_dimension` pixels on its longer side if given (never enlarging)
+pub fn convert_image_to(
+    source: &Path,
+    target: &Path,
+    format: ConvertFormat,
+    quality: Option<u8>,
+    max_dimension: Option<u32>,
+) -> Result<(), String> {
+    let mut decoded = decode_source(source)?;
+    if let Some(max) = max_dimension {
+        if decoded.image.width().max(decoded.image.height()) > max {
+            decoded.image = decoded.image.resize(max, max, FilterType::Lanczos3);
+        }
+    }
+    if let Err(e) = encode(&decoded, target, format, quality.unwrap_or(DEFAULT_QUALITY)) {
+        let _ = fs::remove_file(target);
+        return Err(e);
+    }
+
+    // Keep capture-related file dates so sorting by date still works on the output
+    let _ = copy_file_times(source, target);
+    Ok(())
+}
+
+/// Decode a source image along with its EXIF and ICC metadata
+fn decode_source(source: &Path) -> Result<SourceImage, String> {
+    let ext = source
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase())
+        .unwrap_or_default();
+
+    if ext == "heic" || ext == "heif" || RAW_EXTENSIONS.contains(&ext.as_str()) {
+        return decode_with_sips(source);
+    }
+
+    let mut decoder = ImageReader::open(source)
+        .map_err(|e| e.to_string())?
+        .with_guessed_format()
+        .map_err(|e| e.to_string())?
+        .into_decoder()
+        .map_err(|e| e.to_string())?;
+
+    let exif = decoder.exif_metadata().ok().flatten();
+    let icc_profile = decoder.icc_profile().ok().flatten();
+    let image = DynamicImage::from_decoder(decoder).map_err(|e| e.to_string())?;
+
+    Ok(SourceImage {
+        image,
+        exif,
+        icc_profile,
+    })
+}
+
+/// HEIC/HEIF and RAW aren't supported by the image crate; decode via `sips`
+/// on macOS and read the EXIF block from the original container
+#[cfg(target_os = "macos")]
+fn decode_with_sips(source: &Path) -> Result<SourceImage, String> {
+    let temp = std::env::temp_dir().join(format!(
+        "photo-manager-convert-{}-{}.png",
+        std::process::id(),
+        source
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("image")
+    ));
+
+    let status = Command::new("sips")
+        .args(["-s", "format", "png"])
+        .arg(source)
+        .arg("--out")
+        .arg(&temp)
+        .output()
+        .map_err(|e| e.to_string())?;
+    if !status.status.success() {
+        return Err(format!("sips failed to decode {}", source.display()));
+    }
+
+    let image = image::open(&temp).map_err(|e| e.to_string());
+    let _ = fs::remove_file(&temp);
+
+    Ok(SourceImage {
+        image: image?,
+        exif: read_raw_exif(source),
+        icc_profile: None,
+    })
+}
+
+#[cfg(not(target_os = "macos"))]
+fn decode_with_sips(source: &Path) -> Result<SourceImage, String> {
+    Err(format!(
+        "HEIC/HEIF and RAW decoding is only supported on macOS: {}",
+        source.display()
+    ))
+}
+
+/// JPEG rendition of a HEIC/HEIF image, which the webview can't display on
+/// every platform; written to the thumbnail cache and reused while it is newer
+/// than the source
+pub fn heif_preview(source: &Path) -> Result<PathBuf, String> {
+    let name = blake3::hash(source.to_string_lossy().as_bytes()).to_hex();
+    let target = thumbnail_dir().join(format!("{}.heif.jpg", &name[..32]));
+
+    let modified = |p: &Path| fs::metadata(p).and_then(|m| m.modified()).ok();
+    let source_modified =
+        modified(source).ok_or_else(|| format!("{} not found", source.display()))?;
+    if modified(&target).is_some_and(|preview| preview >= source_modified) {
+        return Ok(target);
+    }
+
+    fs::create_dir_all(thumbnail_dir()).map_err(|e| e.to_string())?;
+    // Written aside and renamed, so a request arriving mid-conversion never
+    // gets a half-written JPEG
+    let temporary = target.with_extension(format!("{}.tmp", std::process::id()));
+    let output = heif_to_jpeg(source, &temporary);
+    let converted = match output {
+        Ok(output) if output.status.success() && temporary.exists() => {
+            fs::rename(&temporary, &target).map_err(|e| e.to_string())
+        }
+        Ok(output) => Err(format!(
+            "Couldn't convert {}: {}",
+            source.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )),
+        Err(e) => Err(format!("Couldn't convert {}: {}", source.display(), e)),
+    };
+    if converted.is_err() {
+        let _ = fs::remove_file(&temporary);
+    }
+    converted.map(|()| target)
+}
+
+/// macOS: `sips`, which decodes HEIC natively and applies the orientation
+#[cfg(target_os = "macos")]
+fn heif_to_jpeg(source: &Path, target: &Path) -> std::io::Result<std::process::Output> {
+    Command::new("sips")
+        .args(["-s", "format", "jpeg", "-s", "formatOptions", "90"])
+        .arg(source)
+        .arg("--out")
+        .arg(target)
+        .output()
+}
+
+/// Other platforms: `heif-convert` from libheif, installed with the
+/// `libheif-examples` (Linux) or `libheif` (vcpkg, MSYS2) packages
+#[cfg(not(target_os = "macos"))]
+fn heif_to_jpeg(source: &Path, target: &Path) -> std::io::Result<std::process::Output> {
+    Command::new("heif-convert")
+        .args(["-q", "90"])
+        .arg(source)
+        .arg(target)
+        .output()
+}
+
+/// Raw TIFF-structured EXIF data from any container kamadak-exif understands
+#[cfg(target_os = "macos")]
+fn read_raw_exif(path: &Path) -> Option<Vec<u8>> {
+    let file = File::open(path).ok()?;
+    let mut reader = std::io::BufReader::new(file);
+    let exif = exif::Reader::new().read_from_container(&mut reader).ok()?;
+    Some(exif.buf().to_vec())
+}
+
+/// Encode the image to the target path, embedding EXIF and ICC data where supported
+fn encode(
+    source: &SourceImage,
+    target: &Path,
+    format: ConvertFormat,
+    quality: u8,
+) -> Result<(), String> {
+    let writer = BufWriter::new(File::create(target).map_err(|e| e.to_string())?);
+
+    // Metadata failures are not fatal - the pixels are what matter
+    match format {
+        ConvertFormat::Jpeg => {
+            let mut encoder = JpegEncoder::new_with_quality(writer, quality.clamp(1, 100));
+            if let Some(exif) = &source.exif {
+                let _ = encoder.set_exif_metadata(exif.clone());
+            }
+            if let Some(icc) = &source.icc_profile {
+                let _ = encoder.set_icc_profile(icc.clone());
+            }
+            source
+                .image
+                .write_with_encoder(encoder)
+                .map_err(|e| e.to_string())
+        }
+        ConvertFormat::Webp => {
+            let mut encoder = WebPEncoder::new_lossless(writer);
+            if let Some(exif) = &source.exif {
+                let _ = encoder.set_exif_metadata(exif.clone());
+            }
+            if let Some(icc) = &source.icc_profile {
+                let _ = encoder.set_icc_profile(icc.clone());
+            }
+            source
+                .image
+                .write_with_encoder(encoder)
+                .map_err(|e| e.to_string())
+        }
+    }
+}
@@ -0,0 +1,142 @@
+use crate::scanner::PhotoFile;
+use chrono::{Local, NaiveDate, NaiveTime, TimeZone};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::time::UNIX_EPOCH;
+
+/// Allowed difference when none is given: a day covers time zone mix-ups
+const DEFAULT_TOLERANCE_HOURS: u32 = 24;
+
+/// Years a date in a name may fall in; other eight-digit runs are counters
+const YEARS: std::ops::RangeInclusive<i32> = 1990..=2100;
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MismatchOptions {
+    /// How far a date may be from the name's date before it disagrees
+    pub tolerance_hours: Option<u32>,
+}
+
+/// A photo whose name says it was taken at another time than its EXIF or its
+/// file does
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DateMismatch {
+    pub path: String,
+    /// Date in the name, ms since epoch; the start of the day when the name
+    /// has no time
+    pub name_date: i64,
+    pub name_has_time: bool,
+    /// EXIF capture date and file creation time, ms since epoch
+    pub capture_date: Option<i64>,
+    pub created_at: Option<i64>,
+    pub capture_disagrees: bool,
+    pub created_disagrees: bool,
+}
+
+/// Photos whose name carries a date (`2021-03-04_...`, `IMG_20210304_153012`,
+/// `Screenshot 2021-03-04 at 15.30.12`) that disagrees with their capture
+/// date or creation time, sorted by path
+/// A name with only a date agrees with any time that day
+pub fn find(photos: &[PhotoFile], options: &MismatchOptions) -> Vec<DateMismatch> {
+    let tolerance =
+        options.tolerance_hours.unwrap_or(DEFAULT_TOLERANCE_HOURS) as i64 * 60 * 60 * 1000;
+    let mut mismatches: Vec<DateMismatch> = photos
+        .par_iter()
+        .filter_map(|photo| {
+            let (date, time) = name_date(&photo.name)?;
+            let start = Local
+                .from_local_datetime(&date.and_time(time.unwrap_or_default()))
+                .earliest()?
+                .timestamp_millis();
+            let end = match time {
+                Some(_) => start + 1000,
+                None => start + 24 * 60 * 60 * 1000,
+            };
+            let disagrees = |ms: i64| ms < start - tolerance || ms >= end + tolerance;
+
+            let created_at = fs::metadata(&photo.path)
+                .and_then(|m| m.created())
+                .ok()
+                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                .map(|d| d.as_millis() as i64);
+            let capture_disagrees = photo.capture_date.is_some_and(disagrees);
+            let created_disagrees = created_at.is_some_and(disagrees);
+            (capture_disagrees || created_disagrees).then(|| DateMismatch {
+                path: photo.path.clone(),
+                name_date: start,
+                name_has_time: time.is_some(),
+                capture_date: photo.capture_date,
+                created_at,
+                capture_disagrees,
+                created_disagrees,
+            })
+        })
+        .collect();
+    mismatches.sort_by(|a, b| a.path.cmp(&b.path));
+    mismatches
+}
+
+/// The first date in a file name, as `YYYYMMDD` or with `-`, `_` or `.`
+/// between its parts, and the time after it if there is one
+fn name_date(name: &str) -> Option<(NaiveDate, Option<NaiveTime>)> {
+    let bytes = name.as_bytes();
+    (0..bytes.len())
+        .filter(|&i| i == 0 || !bytes[i - 1].is_ascii_digit())
+        .find_map(|i| date_at(bytes, i))
+}
+
+fn date_at(bytes: &[u8], start: usize) -> Option<(NaiveDate, Option<NaiveTime>)> {
+    let mut pos = start;
+    let year = digits(bytes, &mut pos, 4)?;
+    let separator = bytes.get(pos).copied().filter(|b| b"-_.".contains(b));
+    pos += separator.is_some() as usize;
+    let month = digits(bytes, &mut pos, 2)?;
+    if separator.is_some() {
+        (bytes.get(pos).copied() == separator).then_some(())?;
+        pos += 1;
+    }
+    let day = digits(bytes, &mut pos, 2)?;
+    if !YEARS.contains(&(year as i32)) {
+        return None;
+    }
+    let date = NaiveDate::from_ymd_opt(year as i32, month, day)?;
+
+    let time = time_at(bytes, pos);
+    // A date running into more digits is part of a longer number
+    if time.is_none() && bytes.get(pos).is_some_and(u8::is_ascii_digit) {
+        return None;
+    }
+    Some((date, time))
+}
+
+/// `HHMMSS` or `HH-MM-SS` (also with `.`, `:` or `_`), right after the date or
+/// after one of `_`, `-`, `T`, a space or ` at `
+fn time_at(bytes: &[u8], mut pos: usize) -> Option<NaiveTime> {
+    if bytes[pos..].starts_with(b" at ") {
+        pos += 4;
+    } else if bytes.get(pos).is_some_and(|b| b"_-T ".contains(b)) {
+        pos += 1;
+    }
+    let hour = digits(bytes, &mut pos, 2)?;
+    let separator = bytes.get(pos).copied().filter(|b| b"-.:_".contains(b));
+    pos += separator.is_some() as usize;
+    let minute = digits(bytes, &mut pos, 2)?;
+    if separator.is_some() {
+        (bytes.get(pos).copied() == separator).then_some(())?;
+        pos += 1;
+    }
+    let second = digits(bytes, &mut pos, 2)?;
+    NaiveTime::from_hms_opt(hour, minute, second)
+}
+
+/// Read `count` ASCII digits at `pos` as a number, moving past them
+fn digits(bytes: &[u8], pos: &mut usize, count: usize) -> Option<u32> {
+    let run = bytes.get(*pos..*pos + count)?;
+    if !run.iter().all(u8::is_ascii_digit) {
+        return None;
+    }
+    *pos += count;
+    std::str::from_utf8(run).ok()?.parse().ok()
+}
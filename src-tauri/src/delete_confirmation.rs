@@ -0,0 +1,75 @@
+use crate::error::CommandError;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// How long a dry run's token can be used to delete its files
+const TOKEN_LIFETIME: Duration = Duration::from_secs(10 * 60);
+
+static NEXT_TOKEN_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Tokens handed out by `delete_permanently` dry runs and not used yet
+static ISSUED: Mutex<Vec<Issued>> = Mutex::new(Vec::new());
+
+struct Issued {
+    token: String,
+    /// Hash of the sorted file list the dry run covered
+    files: blake3::Hash,
+    issued_at: Instant,
+}
+
+fn issued() -> MutexGuard<'static, Vec<Issued>> {
+    ISSUED.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Identifies a set of files regardless of the order they were passed in
+fn files_hash(files: &[String]) -> blake3::Hash {
+    let mut sorted: Vec<&str> = files.iter().map(String::as_str).collect();
+    sorted.sort_unstable();
+    sorted.dedup();
+    let mut hasher = blake3::Hasher::new();
+    for file in sorted {
+        hasher.update(file.as_bytes());
+        hasher.update(&[0]);
+    }
+    hasher.finalize()
+}
+
+/// Hand out a token that lets exactly `files` be deleted once
+pub fn issue(files: &[String]) -> String {
+    let files = files_hash(files);
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default();
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(files.as_bytes());
+    hasher.update(&nanos.to_le_bytes());
+    hasher.update(&NEXT_TOKEN_ID.fetch_add(1, Ordering::Relaxed).to_le_bytes());
+    let token = hasher.finalize().to_hex()[..32].to_string();
+
+    let mut issued = issued();
+    issued.retain(|i| i.issued_at.elapsed() < TOKEN_LIFETIME);
+    issued.push(Issued {
+        token: token.clone(),
+        files,
+        issued_at: Instant::now(),
+    });
+    token
+}
+
+/// Use up `token`; fails unless a dry run issued it in the last ten minutes for
+/// the same files
+pub fn redeem(token: &str, files: &[String]) -> Result<(), CommandError> {
+    let mut issued = issued();
+    issued.retain(|i| i.issued_at.elapsed() < TOKEN_LIFETIME);
+    let index = issued
+        .iter()
+        .position(|i| i.token == token)
+        .ok_or(CommandError::Unconfirmed)?;
+    let confirmed = issued.remove(index);
+    if confirmed.files != files_hash(files) {
+        return Err(CommandError::Unconfirmed);
+    }
+    Ok(())
+}
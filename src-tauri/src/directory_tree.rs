@@ -0,0 +1,128 @@
+use crate::catalog::Catalog;
+use crate::config::{AppConfig, DirectoryConfig};
+use crate::exclusions::{Exclusions, IgnoreFiles};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// A folder under a configured directory, with what the catalog holds in it
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FolderNode {
+    pub path: String,
+    /// Folder name; the configured name for a configured directory
+    pub name: String,
+    /// Photos directly in the folder and their total size
+    pub photo_count: usize,
+    pub size: u64,
+    /// Photos in the folder and all its subfolders
+    pub total_photo_count: usize,
+    pub total_size: u64,
+    /// False for folders only known from the catalog, e.g. on a drive that
+    /// isn't connected
+    pub exists: bool,
+    pub read_only: bool,
+    /// Subfolders sorted by name
+    pub children: Vec<FolderNode>,
+}
+
+/// The folder tree of every enabled configured directory
+/// Folders come from disk, skipping what scans skip, and from the catalog, so
+/// a disconnected drive still shows the folders it had when last scanned
+pub fn list() -> Result<Vec<FolderNode>, String> {
+    let totals = Catalog::open()?.folder_totals()?;
+    Ok(AppConfig::load()
+        .directories
+        .iter()
+        .filter(|d| d.enabled)
+        .map(|config| root_tree(config, &totals))
+        .collect())
+}
+
+fn root_tree(config: &DirectoryConfig, totals: &HashMap<String, (usize, u64)>) -> FolderNode {
+    let root = PathBuf::from(&config.path);
+    let on_disk = folders_on_disk(config, &root);
+
+    // Each folder's subfolders, including folders between the root and a
+    // cataloged folder that the walk didn't reach
+    let mut children: HashMap<PathBuf, Vec<PathBuf>> = HashMap::new();
+    let mut seen: HashSet<PathBuf> = HashSet::new();
+    let cataloged = totals
+        .keys()
+        .map(PathBuf::from)
+        .filter(|folder| folder.starts_with(&root));
+    for folder in on_disk.iter().cloned().chain(cataloged) {
+        for (folder, parent) in folder.ancestors().zip(folder.ancestors().skip(1)) {
+            if folder == root || !seen.insert(folder.to_path_buf()) {
+                break;
+            }
+            children
+                .entry(parent.to_path_buf())
+                .or_default()
+                .push(folder.to_path_buf());
+        }
+    }
+
+    let mut node = build(&root, &children, &on_disk, totals, config.read_only);
+    if !config.name.is_empty() {
+        node.name = config.name.clone();
+    }
+    node
+}
+
+/// Folders under `root` a scan would walk into
+fn folders_on_disk(config: &DirectoryConfig, root: &Path) -> HashSet<PathBuf> {
+    let exclusions = Exclusions::for_directory(config);
+    let mut ignore_files = IgnoreFiles::default();
+    let mut walker = WalkDir::new(root);
+    if let Some(depth) = exclusions.max_depth {
+        walker = walker.max_depth(depth);
+    }
+    walker
+        .into_iter()
+        .filter_entry(|e| {
+            e.file_type().is_dir()
+                && !exclusions.excludes(root, e)
+                && !ignore_files.excludes(root, e)
+        })
+        .filter_map(|e| e.ok())
+        .map(|e| e.into_path())
+        .collect()
+}
+
+fn build(
+    path: &Path,
+    children: &HashMap<PathBuf, Vec<PathBuf>>,
+    on_disk: &HashSet<PathBuf>,
+    totals: &HashMap<String, (usize, u64)>,
+    read_only: bool,
+) -> FolderNode {
+    let path_str = path.to_string_lossy().to_string();
+    let (photo_count, size) = totals.get(&path_str).copied().unwrap_or_default();
+    let mut subfolders: Vec<FolderNode> = children
+        .get(path)
+        .into_iter()
+        .flatten()
+        .map(|child| build(child, children, on_disk, totals, read_only))
+        .collect();
+    subfolders.sort_by_cached_key(|f| f.name.to_lowercase());
+    FolderNode {
+        name: path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| path_str.clone()),
+        path: path_str,
+        photo_count,
+        size,
+        total_photo_count: photo_count
+            + subfolders
+                .iter()
+                .map(|f| f.total_photo_count)
+                .sum::<usize>(),
+        total_size: size + subfolders.iter().map(|f| f.total_size).sum::<u64>(),
+        exists: on_disk.contains(path),
+        read_only,
+        children: subfolders,
+    }
+}
@@ -0,0 +1,218 @@
+use crate::ids::PhotoId;
+use crate::scanner::{PhotoFile, RAW_EXTENSIONS};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+/// How sure we are that a group's files are copies of each other
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MatchConfidence {
+    /// Same hash of the whole file
+    Exact,
+    /// Same decoded pixels (pixels hash tier); the picture is the same, but
+    /// metadata such as EXIF may differ between the copies
+    Pixels,
+    /// Same size and same last 1 MB (quick hash tier); very likely, not proven
+    Likely,
+}
+
+impl MatchConfidence {
+    /// Factor applied to a group's reclaimable bytes when ranking
+    fn weight(self) -> f64 {
+        match self {
+            MatchConfidence::Exact => 1.0,
+            MatchConfidence::Pixels => 0.75,
+            MatchConfidence::Likely => 0.5,
+        }
+    }
+}
+
+/// A set of files with identical content
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DuplicateGroup {
+    /// Full content hash; `pixels:<pixel hash>` for groups found by the pixels
+    /// tier and `quick:<original path>` for groups found by the quick tier
+    pub hash: String,
+    pub confidence: MatchConfidence,
+    /// All copies, suggested keeper first
+    pub members: Vec<PhotoFile>,
+    /// Copy suggested to keep
+    pub keeper_id: PhotoId,
+    /// Why the keeper was chosen, for display
+    pub keeper_reason: String,
+    /// On-disk bytes freed by removing every copy except the keeper
+    pub reclaimable_bytes: u64,
+}
+
+/// Group the scanned photos by full content hash, largest savings first
+/// Stills compared by the pixels tier are grouped by their pixel hash instead
+/// Duplicates found by a quick-tier scan have no full hash; they are grouped
+/// with the file they were matched to and marked `Likely`
+pub fn duplicate_groups(photos: &[PhotoFile]) -> Vec<DuplicateGroup> {
+    let mut by_hash: HashMap<&str, Vec<&PhotoFile>> = HashMap::new();
+    let mut by_pixels: HashMap<&str, Vec<&PhotoFile>> = HashMap::new();
+    let mut by_original: HashMap<&str, Vec<&PhotoFile>> = HashMap::new();
+    for photo in photos {
+        if let Some(pixel_hash) = &photo.pixel_hash {
+            by_pixels.entry(pixel_hash).or_default().push(photo);
+        } else if let Some(hash) = &photo.hash {
+            by_hash.entry(hash).or_default().push(photo);
+        } else if let (true, Some(original)) = (photo.is_duplicate, &photo.duplicate_of) {
+            by_original.entry(original).or_default().push(photo);
+        }
+    }
+    let by_id: HashMap<&str, &PhotoFile> = photos.iter().map(|p| (p.id.as_str(), p)).collect();
+    for (original, members) in by_original.iter_mut() {
+        if let Some(photo) = by_id
+            .get(original)
+            .filter(|p| p.hash.is_none() && p.pixel_hash.is_none())
+        {
+            members.push(photo);
+        }
+    }
+
+    let exact = by_hash
+        .into_iter()
+        .map(|(hash, members)| (hash.to_string(), MatchConfidence::Exact, members));
+    let pixels = by_pixels
+        .into_iter()
+        .map(|(hash, members)| (format!("pixels:{}", hash), MatchConfidence::Pixels, members));
+    let likely = by_original.into_iter().map(|(original, members)| {
+        (
+            format!("quick:{}", original),
+            MatchConfidence::Likely,
+            members,
+        )
+    });
+    let mut groups: Vec<DuplicateGroup> = exact
+        .chain(pixels)
+        .chain(likely)
+        .filter(|(_, _, members)| members.len() > 1)
+        .map(|(hash, confidence, mut members)| {
+            members.sort_by(|a, b| keeper_order(a, b));
+            let keeper_reason = keeper_reason(members[0], members[1]);
+            DuplicateGroup {
+                hash,
+                confidence,
+                keeper_id: members[0].photo_id,
+                keeper_reason,
+                reclaimable_bytes: members[1..].iter().map(|p| p.disk_size).sum(),
+                members: members.into_iter().cloned().collect(),
+            }
+        })
+        .collect();
+
+    groups.sort_by(|a, b| {
+        b.reclaimable_bytes
+            .cmp(&a.reclaimable_bytes)
+            .then_with(|| a.hash.cmp(&b.hash))
+    });
+    groups
+}
+
+/// A duplicate group with its place in the cleanup order
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RankedGroup {
+    #[serde(flatten)]
+    pub group: DuplicateGroup,
+    /// Reclaimable bytes weighted by match confidence; groups are ranked by this
+    pub score: u64,
+    /// Share of all reclaimable bytes freed by resolving this group and every
+    /// group ranked above it, 0–100
+    pub cumulative_percent: f64,
+}
+
+/// Duplicate groups in the order that frees the most space soonest
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DuplicateRanking {
+    pub total_groups: usize,
+    pub total_reclaimable_bytes: u64,
+    /// The top `limit` groups (all of them without a limit)
+    pub groups: Vec<RankedGroup>,
+}
+
+/// Rank duplicate groups by reclaimable bytes, discounted for groups that are
+/// only likely duplicates, so the first few groups free most of the space
+pub fn rank_duplicate_groups(photos: &[PhotoFile], limit: Option<usize>) -> DuplicateRanking {
+    let mut groups = duplicate_groups(photos);
+    let total_reclaimable_bytes: u64 = groups.iter().map(|g| g.reclaimable_bytes).sum();
+    let score = |group: &DuplicateGroup| {
+        (group.reclaimable_bytes as f64 * group.confidence.weight()) as u64
+    };
+    groups.sort_by(|a, b| score(b).cmp(&score(a)).then_with(|| a.hash.cmp(&b.hash)));
+
+    let total_groups = groups.len();
+    let mut cumulative = 0;
+    let groups = groups
+        .into_iter()
+        .take(limit.unwrap_or(usize::MAX))
+        .map(|group| {
+            cumulative += group.reclaimable_bytes;
+            RankedGroup {
+                score: score(&group),
+                cumulative_percent: if total_reclaimable_bytes == 0 {
+                    100.0
+                } else {
+                    cumulative as f64 * 100.0 / total_reclaimable_bytes as f64
+                },
+                group,
+            }
+        })
+        .collect();
+
+    DuplicateRanking {
+        total_groups,
+        total_reclaimable_bytes,
+        groups,
+    }
+}
+
+/// How `resolve_top_groups` gets rid of the extra copies
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ResolvePolicy {
+    /// Also resolve groups matched only by the quick hash or by decoded pixels
+    pub include_likely: bool,
+    /// Move the extra copies into this folder instead of trashing them
+    pub move_to: Option<String>,
+}
+
+/// Best keeper first: the copy in the highest-priority root, RAW over JPEG, a
+/// fully downloaded file over a cloud placeholder, the larger file, the older
+/// file, then the shorter path
+fn keeper_order(a: &PhotoFile, b: &PhotoFile) -> Ordering {
+    b.root_priority
+        .cmp(&a.root_priority)
+        .then_with(|| is_raw(b).cmp(&is_raw(a)))
+        .then_with(|| a.is_cloud_placeholder.cmp(&b.is_cloud_placeholder))
+        .then_with(|| b.size.cmp(&a.size))
+        .then_with(|| a.modified_at.cmp(&b.modified_at))
+        .then_with(|| a.path.len().cmp(&b.path.len()))
+        .then_with(|| a.path.cmp(&b.path))
+}
+
+/// Name the first rule that separated the keeper from the runner-up
+fn keeper_reason(keeper: &PhotoFile, other: &PhotoFile) -> String {
+    let reason = if keeper.root_priority != other.root_priority {
+        "Higher-priority folder"
+    } else if is_raw(keeper) != is_raw(other) {
+        "RAW original"
+    } else if keeper.is_cloud_placeholder != other.is_cloud_placeholder {
+        "Downloaded locally"
+    } else if keeper.size != other.size {
+        "Largest file"
+    } else if keeper.modified_at != other.modified_at {
+        "Oldest copy"
+    } else {
+        "Shortest path"
+    };
+    reason.to_string()
+}
+
+fn is_raw(photo: &PhotoFile) -> bool {
+    RAW_EXTENSIONS.contains(&photo.extension.as_str())
+}
@@ -0,0 +1,168 @@
+use crate::config::AppConfig;
+use crate::error::CommandError;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::process::Command;
+
+/// An external app photos can be opened in, as configured by the user
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExternalEditor {
+    pub name: String,
+    /// macOS bundle id (`com.seriflabs.affinityphoto2`) or the app's path
+    pub app: String,
+}
+
+/// An app offered by `list_available_editors`
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AvailableEditor {
+    pub name: String,
+    /// What to pass to `open_with`
+    pub app: String,
+    /// Configured by the user rather than found on the system
+    pub configured: bool,
+}
+
+/// The configured editors, then the apps the system says can open `file`
+/// (LaunchServices on macOS; other platforms only list configured editors)
+pub fn available(file: Option<&Path>) -> Vec<AvailableEditor> {
+    let mut editors: Vec<AvailableEditor> = AppConfig::load()
+        .external_editors
+        .into_iter()
+        .map(|editor| AvailableEditor {
+            name: editor.name,
+            app: editor.app,
+            configured: true,
+        })
+        .collect();
+    for app in file.map(system_editors).unwrap_or_default() {
+        if editors.iter().any(|e| e.app == app) {
+            continue;
+        }
+        let name = Path::new(&app)
+            .file_stem()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| app.clone());
+        editors.push(AvailableEditor {
+            name,
+            app,
+            configured: false,
+        });
+    }
+    editors
+}
+
+/// Launch `app` (a bundle id or a path) with `file`
+pub fn open_with(file: &Path, app: &str) -> Result<(), CommandError> {
+    if !file.exists() {
+        return Err(CommandError::NotFound {
+            path: file.to_string_lossy().to_string(),
+        });
+    }
+    launch(file, app).map_err(|e| CommandError::Other(format!("Couldn't open {}: {}", app, e)))
+}
+
+/// A bundle id rather than a path: `com.example.App`
+#[cfg(target_os = "macos")]
+fn is_bundle_id(app: &str) -> bool {
+    !app.contains('/') && !app.ends_with(".app") && app.contains('.')
+}
+
+/// macOS: `open -b` for a bundle id, `open -a` for an app path
+#[cfg(target_os = "macos")]
+fn launch(file: &Path, app: &str) -> std::io::Result<()> {
+    let flag = if is_bundle_id(app) { "-b" } else { "-a" };
+    let output = Command::new("open").arg(flag).arg(app).arg(file).output()?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(std::io::Error::other(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ))
+    }
+}
+
+/// Other platforms: run the app's executable with the file
+#[cfg(not(target_os = "macos"))]
+fn launch(file: &Path, app: &str) -> std::io::Result<()> {
+    Command::new(app).arg(file).spawn().map(|_| ())
+}
+
+#[cfg(target_os = "macos")]
+mod launch_services {
+    use std::ffi::c_void;
+
+    pub type CFTypeRef = *const c_void;
+
+    /// kLSRolesViewer | kLSRolesEditor
+    pub const VIEWER_OR_EDITOR: u32 = 0x2 | 0x4;
+
+    #[link(name = "CoreServices", kind = "framework")]
+    extern "C" {
+        pub fn LSCopyApplicationURLsForURL(url: CFTypeRef, roles: u32) -> CFTypeRef;
+    }
+
+    #[link(name = "CoreFoundation", kind = "framework")]
+    extern "C" {
+        pub fn CFURLCreateFromFileSystemRepresentation(
+            allocator: CFTypeRef,
+            buffer: *const u8,
+            length: isize,
+            is_directory: u8,
+        ) -> CFTypeRef;
+        pub fn CFURLGetFileSystemRepresentation(
+            url: CFTypeRef,
+            resolve_against_base: u8,
+            buffer: *mut u8,
+            max_length: isize,
+        ) -> u8;
+        pub fn CFArrayGetCount(array: CFTypeRef) -> isize;
+        pub fn CFArrayGetValueAtIndex(array: CFTypeRef, index: isize) -> CFTypeRef;
+        pub fn CFRelease(cf: CFTypeRef);
+    }
+}
+
+/// Paths of the apps LaunchServices lists as able to view or edit `file`
+#[cfg(target_os = "macos")]
+fn system_editors(file: &Path) -> Vec<String> {
+    use launch_services::*;
+    use std::os::unix::ffi::OsStrExt;
+
+    let bytes = file.as_os_str().as_bytes();
+    let mut apps = Vec::new();
+    unsafe {
+        let url = CFURLCreateFromFileSystemRepresentation(
+            std::ptr::null(),
+            bytes.as_ptr(),
+            bytes.len() as isize,
+            0,
+        );
+        if url.is_null() {
+            return apps;
+        }
+        let list = LSCopyApplicationURLsForURL(url, VIEWER_OR_EDITOR);
+        CFRelease(url);
+        if list.is_null() {
+            return apps;
+        }
+        for i in 0..CFArrayGetCount(list) {
+            let app = CFArrayGetValueAtIndex(list, i);
+            let mut buffer = [0u8; 4096];
+            if CFURLGetFileSystemRepresentation(app, 1, buffer.as_mut_ptr(), buffer.len() as isize)
+                != 0
+            {
+                let len = buffer.iter().position(|&b| b == 0).unwrap_or(buffer.len());
+                apps.push(String::from_utf8_lossy(&buffer[..len]).to_string());
+            }
+        }
+        CFRelease(list);
+    }
+    apps.sort_by_cached_key(|app| app.to_lowercase());
+    apps
+}
+
+#[cfg(not(target_os = "macos"))]
+fn system_editors(_file: &Path) -> Vec<String> {
+    Vec::new()
+}
@@ -0,0 +1,117 @@
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+use std::io::{self, ErrorKind};
+use std::path::Path;
+
+/// Error returned by commands, sent to the frontend as
+/// `{ kind, path?, message }` so it can tell what went wrong with which file
+/// and offer the right way out (download the file, pick another name, retry)
+/// Helpers that still report plain strings convert into `Other`
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum CommandError {
+    #[error("{path} no longer exists")]
+    NotFound { path: String },
+    #[error("Not allowed to change {path}")]
+    PermissionDenied { path: String },
+    /// Something is already at a path a file was going to
+    #[error("{path} already exists")]
+    Conflict { path: String },
+    /// A rename between two volumes; the caller has to copy instead
+    #[error("{path} is on another volume")]
+    CrossDevice { path: String },
+    /// A cloud placeholder whose content would have to be downloaded first
+    #[error("{path} is only in the cloud; download it first")]
+    CloudNotDownloaded { path: String },
+    /// Under a directory marked read-only in the config
+    #[error("{path} is in \"{root}\", which is read-only")]
+    ReadOnly { path: String, root: String },
+    /// A permanent delete without a valid token from a dry run of it
+    #[error("Deleting permanently needs a confirmation from a dry run of the same files")]
+    Unconfirmed,
+    /// Stopped by the user; whatever was done has been undone
+    #[error("Cancelled")]
+    Cancelled,
+    /// Any other I/O failure on a file
+    #[error("{path}: {message}")]
+    Io { path: String, message: String },
+    #[error("{0}")]
+    Other(String),
+}
+
+impl CommandError {
+    /// Classify an I/O error on `path`
+    pub fn io(path: &Path, error: &io::Error) -> Self {
+        let path = path.to_string_lossy().to_string();
+        match error.kind() {
+            ErrorKind::NotFound => Self::NotFound { path },
+            ErrorKind::PermissionDenied | ErrorKind::ReadOnlyFilesystem => {
+                Self::PermissionDenied { path }
+            }
+            ErrorKind::AlreadyExists => Self::Conflict { path },
+            ErrorKind::CrossesDevices => Self::CrossDevice { path },
+            _ => Self::Io {
+                path,
+                message: error.to_string(),
+            },
+        }
+    }
+
+    fn kind(&self) -> &'static str {
+        match self {
+            Self::NotFound { .. } => "notFound",
+            Self::PermissionDenied { .. } => "permissionDenied",
+            Self::Conflict { .. } => "conflict",
+            Self::CrossDevice { .. } => "crossDevice",
+            Self::CloudNotDownloaded { .. } => "cloudNotDownloaded",
+            Self::ReadOnly { .. } => "readOnly",
+            Self::Unconfirmed => "unconfirmed",
+            Self::Cancelled => "cancelled",
+            Self::Io { .. } => "io",
+            Self::Other(_) => "other",
+        }
+    }
+
+    /// The file the error is about, if any
+    pub fn path(&self) -> Option<&str> {
+        match self {
+            Self::NotFound { path }
+            | Self::PermissionDenied { path }
+            | Self::Conflict { path }
+            | Self::CrossDevice { path }
+            | Self::CloudNotDownloaded { path }
+            | Self::ReadOnly { path, .. }
+            | Self::Io { path, .. } => Some(path),
+            Self::Unconfirmed | Self::Cancelled | Self::Other(_) => None,
+        }
+    }
+}
+
+impl Serialize for CommandError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut error = serializer.serialize_struct("CommandError", 3)?;
+        error.serialize_field("kind", self.kind())?;
+        error.serialize_field("path", &self.path())?;
+        error.serialize_field("message", &self.to_string())?;
+        error.end()
+    }
+}
+
+impl From<String> for CommandError {
+    fn from(message: String) -> Self {
+        Self::Other(message)
+    }
+}
+
+impl From<&str> for CommandError {
+    fn from(message: &str) -> Self {
+        Self::Other(message.to_string())
+    }
+}
+
+/// Lets helpers that return plain strings use `?` on functions that already
+/// return a `CommandError`
+impl From<CommandError> for String {
+    fn from(error: CommandError) -> Self {
+        error.to_string()
+    }
+}
@@ -0,0 +1,271 @@
+use crate::catalog::Catalog;
+use crate::geocode;
+use crate::scanner::PhotoFile;
+use chrono::TimeZone;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// A new event starts after this long without photos
+const DEFAULT_TIME_GAP_MINUTES: i64 = 180;
+
+/// ... or when the next photo was taken this far from the previous one
+const DEFAULT_MAX_DISTANCE_KM: f64 = 30.0;
+
+/// How `cluster_events` splits the timeline
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct EventOptions {
+    /// Minutes; `DEFAULT_TIME_GAP_MINUTES` when unset
+    pub time_gap_minutes: Option<i64>,
+    /// Kilometres; `DEFAULT_MAX_DISTANCE_KM` when unset
+    pub max_distance_km: Option<f64>,
+}
+
+/// Photos taken close together in time and place, like Photos' Moments
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Event {
+    pub id: i64,
+    /// Where and when, e.g. "Kyoto, Mar 12 – 14, 2024"
+    pub name: String,
+    /// Capture time of the first and last photo, in milliseconds
+    pub start_at: i64,
+    pub end_at: i64,
+    /// Most common place name among the photos
+    pub place_name: Option<String>,
+    /// Middle of the located photos
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    pub cover_path: String,
+    /// Members in capture order
+    pub paths: Vec<String>,
+}
+
+/// Create the event tables; called when the catalog is opened
+pub fn create_tables(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS events (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL,
+            start_at INTEGER NOT NULL,
+            end_at INTEGER NOT NULL,
+            place_name TEXT,
+            latitude REAL,
+            longitude REAL,
+            cover_path TEXT NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS event_photos (
+            path TEXT PRIMARY KEY,
+            event_id INTEGER NOT NULL REFERENCES events(id) ON DELETE CASCADE,
+            position INTEGER NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS idx_event_photos_event ON event_photos(event_id);
+        ",
+    )
+}
+
+/// Group dated photos into events, oldest first; photos without a capture
+/// date are left out
+/// An event ends at a gap longer than the time gap, or when consecutive
+/// located photos are farther apart than the distance
+pub fn cluster(photos: &[PhotoFile], options: &EventOptions) -> Vec<Event> {
+    let time_gap = options.time_gap_minutes.unwrap_or(DEFAULT_TIME_GAP_MINUTES) * 60_000;
+    let max_distance = options.max_distance_km.unwrap_or(DEFAULT_MAX_DISTANCE_KM);
+
+    let mut dated: Vec<(i64, &PhotoFile)> = photos
+        .iter()
+        .filter_map(|p| p.capture_date.map(|date| (date, p)))
+        .collect();
+    dated.sort_by(|a, b| a.0.cmp(&b.0).then_with(|| a.1.path.cmp(&b.1.path)));
+
+    let mut groups: Vec<Vec<&PhotoFile>> = Vec::new();
+    let mut last_time = i64::MIN;
+    let mut last_position: Option<(f64, f64)> = None;
+    for (time, photo) in dated {
+        let position = photo.latitude.zip(photo.longitude);
+        let moved = match (last_position, position) {
+            (Some(a), Some(b)) => geocode::distance_km(a, b) > max_distance,
+            _ => false,
+        };
+        if groups.is_empty() || time - last_time > time_gap || moved {
+            groups.push(Vec::new());
+            last_position = None;
+        }
+        if let Some(group) = groups.last_mut() {
+            group.push(photo);
+        }
+        last_time = time;
+        last_position = position.or(last_position);
+    }
+
+    groups.into_iter().map(|group| summarize(&group)).collect()
+}
+
+/// Name, span and place of one group of photos in capture order
+fn summarize(photos: &[&PhotoFile]) -> Event {
+    let start_at = photos[0].capture_date.unwrap_or_default();
+    let end_at = photos[photos.len() - 1].capture_date.unwrap_or_default();
+
+    let mut places: HashMap<&str, usize> = HashMap::new();
+    for name in photos.iter().filter_map(|p| p.place_name.as_deref()) {
+        *places.entry(name).or_default() += 1;
+    }
+    let place_name = places
+        .into_iter()
+        .max_by(|a, b| a.1.cmp(&b.1).then_with(|| b.0.cmp(a.0)))
+        .map(|(name, _)| name.to_string());
+
+    let located: Vec<(f64, f64)> = photos
+        .iter()
+        .filter_map(|p| p.latitude.zip(p.longitude))
+        .collect();
+    let (latitude, longitude) = if located.is_empty() {
+        (None, None)
+    } else {
+        let count = located.len() as f64;
+        (
+            Some(located.iter().map(|p| p.0).sum::<f64>() / count),
+            Some(located.iter().map(|p| p.1).sum::<f64>() / count),
+        )
+    };
+
+    let dates = date_range(start_at, end_at);
+    // "Town, Region, CC" is shortened to the town
+    let name = match place_name.as_deref().and_then(|p| p.split(", ").next()) {
+        Some(town) => format!("{}, {}", town, dates),
+        None => dates,
+    };
+
+    Event {
+        id: 0,
+        name,
+        start_at,
+        end_at,
+        place_name,
+        latitude,
+        longitude,
+        // The middle photo is more telling than the first
+        cover_path: photos[photos.len() / 2].path.clone(),
+        paths: photos.iter().map(|p| p.path.clone()).collect(),
+    }
+}
+
+/// "Mar 12, 2024", "Mar 12 – 14, 2024", "Mar 30 – Apr 2, 2024" or
+/// "Dec 30, 2023 – Jan 2, 2024"
+fn date_range(start_at: i64, end_at: i64) -> String {
+    let local = |ms: i64| chrono::Local.timestamp_millis_opt(ms).single();
+    let (Some(start), Some(end)) = (local(start_at), local(end_at)) else {
+        return "Unknown date".to_string();
+    };
+    let (start, end) = (start.date_naive(), end.date_naive());
+    if start == end {
+        start.format("%b %-d, %Y").to_string()
+    } else if start.format("%Y%m").to_string() == end.format("%Y%m").to_string() {
+        format!("{} – {}", start.format("%b %-d"), end.format("%-d, %Y"))
+    } else if start.format("%Y").to_string() == end.format("%Y").to_string() {
+        format!("{} – {}", start.format("%b %-d"), end.format("%b %-d, %Y"))
+    } else {
+        format!(
+            "{} – {}",
+            start.format("%b %-d, %Y"),
+            end.format("%b %-d, %Y")
+        )
+    }
+}
+
+/// Event assignments stored in the catalog database; each run of
+/// `cluster_events` replaces the previous one
+pub struct Events {
+    conn: Connection,
+}
+
+impl Events {
+    /// Open the event tables in the catalog database
+    pub fn open() -> Result<Self, String> {
+        let conn = Catalog::connection()?;
+        conn.execute_batch("PRAGMA foreign_keys = ON;")
+            .map_err(|e| e.to_string())?;
+        Ok(Self { conn })
+    }
+
+    /// Store a fresh clustering in place of the old one, filling in the ids
+    pub fn replace(&mut self, events: &mut [Event]) -> Result<(), String> {
+        let tx = self.conn.transaction().map_err(|e| e.to_string())?;
+        tx.execute_batch("DELETE FROM event_photos; DELETE FROM events;")
+            .map_err(|e| e.to_string())?;
+        for event in events.iter_mut() {
+            tx.execute(
+                "INSERT INTO events
+                (name, start_at, end_at, place_name, latitude, longitude, cover_path)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    event.name,
+                    event.start_at,
+                    event.end_at,
+                    event.place_name,
+                    event.latitude,
+                    event.longitude,
+                    event.cover_path
+                ],
+            )
+            .map_err(|e| e.to_string())?;
+            event.id = tx.last_insert_rowid();
+            for (position, path) in event.paths.iter().enumerate() {
+                tx.execute(
+                    "INSERT INTO event_photos (path, event_id, position) VALUES (?1, ?2, ?3)",
+                    params![path, event.id, position as i64],
+                )
+                .map_err(|e| e.to_string())?;
+            }
+        }
+        tx.commit().map_err(|e| e.to_string())
+    }
+
+    /// Stored events, oldest first
+    pub fn list(&self) -> Result<Vec<Event>, String> {
+        let mut members: HashMap<i64, Vec<String>> = HashMap::new();
+        let mut stmt = self
+            .conn
+            .prepare("SELECT event_id, path FROM event_photos ORDER BY event_id, position")
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+            })
+            .map_err(|e| e.to_string())?;
+        for row in rows {
+            let (event_id, path) = row.map_err(|e| e.to_string())?;
+            members.entry(event_id).or_default().push(path);
+        }
+
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT id, name, start_at, end_at, place_name, latitude, longitude, cover_path
+                FROM events ORDER BY start_at, id",
+            )
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok(Event {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                    start_at: row.get(2)?,
+                    end_at: row.get(3)?,
+                    place_name: row.get(4)?,
+                    latitude: row.get(5)?,
+                    longitude: row.get(6)?,
+                    cover_path: row.get(7)?,
+                    paths: Vec::new(),
+                })
+            })
+            .map_err(|e| e.to_string())?;
+        rows.map(|row| {
+            let mut event = row.map_err(|e| e.to_string())?;
+            event.paths = members.remove(&event.id).unwrap_or_default();
+            Ok(event)
+        })
+        .collect()
+    }
+}
@@ -0,0 +1,221 @@
+use crate::config::DirectoryConfig;
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::DirEntry;
+
+/// Per-folder ignore file, in gitignore syntax
+pub const IGNORE_FILE_NAME: &str = ".pmignore";
+
+/// Folders that never hold photos worth scanning: library bundles managed by
+/// other apps, Synology/QNAP thumbnail folders and dependency trees
+pub fn default_exclude_patterns() -> Vec<String> {
+    [
+        "*.photoslibrary",
+        "*.lrdata",
+        "@eaDir",
+        ".@__thumb",
+        "node_modules",
+        "$RECYCLE.BIN",
+    ]
+    .iter()
+    .map(|p| p.to_string())
+    .collect()
+}
+
+/// Which entries the walker skips under one scanned directory
+pub struct Exclusions {
+    /// Patterns without a slash, matched against the entry's file name
+    names: GlobSet,
+    /// Patterns with a slash, matched against the path relative to the root
+    paths: GlobSet,
+    hidden: bool,
+    pub max_depth: Option<usize>,
+}
+
+impl Exclusions {
+    /// Exclusions configured for a directory; invalid patterns are reported and ignored
+    pub fn for_directory(config: &DirectoryConfig) -> Self {
+        let mut names = GlobSetBuilder::new();
+        let mut paths = GlobSetBuilder::new();
+        for pattern in &config.exclude_patterns {
+            let pattern = pattern.trim().trim_end_matches('/');
+            if pattern.is_empty() {
+                continue;
+            }
+            let glob = GlobBuilder::new(pattern.trim_start_matches('/'))
+                .case_insensitive(true)
+                .literal_separator(true)
+                .build();
+            match glob {
+                Ok(glob) if pattern.contains('/') => {
+                    paths.add(glob);
+                }
+                Ok(glob) => {
+                    names.add(glob);
+                }
+                Err(e) => eprintln!("Ignoring exclude pattern {:?}: {}", pattern, e),
+            }
+        }
+
+        Self {
+            names: names.build().unwrap_or_else(|_| GlobSet::empty()),
+            paths: paths.build().unwrap_or_else(|_| GlobSet::empty()),
+            hidden: config.exclude_hidden,
+            max_depth: config.max_depth,
+        }
+    }
+
+    /// Whether the walker should skip this entry (and everything under it)
+    pub fn excludes(&self, root: &Path, entry: &DirEntry) -> bool {
+        // Never exclude the scanned directory itself
+        if entry.depth() == 0 {
+            return false;
+        }
+        let name = entry.file_name().to_string_lossy();
+        if self.hidden && name.starts_with('.') {
+            return true;
+        }
+        if self.names.is_match(name.as_ref()) {
+            return true;
+        }
+        !self.paths.is_empty()
+            && entry
+                .path()
+                .strip_prefix(root)
+                .is_ok_and(|relative| self.paths.is_match(relative))
+    }
+}
+
+impl Default for Exclusions {
+    /// Used for directories that are not in the config (e.g. a one-off scan)
+    fn default() -> Self {
+        Self::for_directory(&DirectoryConfig {
+            path: String::new(),
+            enabled: true,
+            name: String::new(),
+            priority: 0,
+            exclude_patterns: default_exclude_patterns(),
+            exclude_hidden: true,
+            max_depth: None,
+            read_only: false,
+        })
+    }
+}
+
+/// `.pmignore` files found while walking, each applying to its own folder and below
+/// A deeper file overrides a shallower one, so `!pattern` can re-include a path
+#[derive(Default)]
+pub struct IgnoreFiles {
+    /// Parsed ignore file of each folder seen so far (None when it has none)
+    loaded: HashMap<PathBuf, Option<Gitignore>>,
+}
+
+impl IgnoreFiles {
+    /// Whether an ignore file in the entry's folder or one of its parents up to
+    /// `root` excludes it
+    pub fn excludes(&mut self, root: &Path, entry: &DirEntry) -> bool {
+        if entry.depth() == 0 {
+            return false;
+        }
+        let is_dir = entry.file_type().is_dir();
+        for folder in entry.path().ancestors().skip(1) {
+            if let Some(ignore) = self.load(folder) {
+                let matched = ignore.matched(entry.path(), is_dir);
+                if !matched.is_none() {
+                    return matched.is_ignore();
+                }
+            }
+            if folder == root {
+                break;
+            }
+        }
+        false
+    }
+
+    fn load(&mut self, folder: &Path) -> Option<&Gitignore> {
+        self.loaded
+            .entry(folder.to_path_buf())
+            .or_insert_with(|| {
+                let file = folder.join(IGNORE_FILE_NAME);
+                if !file.is_file() {
+                    return None;
+                }
+                let mut builder = GitignoreBuilder::new(folder);
+                if let Some(e) = builder.add(&file) {
+                    eprintln!("Problem reading {}: {}", file.display(), e);
+                }
+                builder.build().ok()
+            })
+            .as_ref()
+    }
+}
+
+/// Identity of a file or folder regardless of the path used to reach it
+#[cfg(unix)]
+type FileId = (u64, u64);
+
+#[cfg(unix)]
+fn file_id(path: &Path) -> Option<FileId> {
+    use std::os::unix::fs::MetadataExt;
+    fs::metadata(path).ok().map(|m| (m.dev(), m.ino()))
+}
+
+#[cfg(not(unix))]
+type FileId = PathBuf;
+
+#[cfg(not(unix))]
+fn file_id(path: &Path) -> Option<FileId> {
+    fs::canonicalize(path).ok()
+}
+
+/// Keeps a scan from walking the same folder twice, whatever path leads there:
+/// symlink loops, symlinks to folders already scanned, roots listed twice and
+/// roots nested inside other roots
+pub struct WalkGuard {
+    /// Scanned directories; each is walked on its own, never as part of another
+    roots: HashSet<FileId>,
+    canonical_roots: Vec<PathBuf>,
+    visited: HashSet<FileId>,
+}
+
+impl WalkGuard {
+    pub fn new(directories: &[String]) -> Self {
+        Self {
+            roots: directories
+                .iter()
+                .filter_map(|d| file_id(Path::new(d)))
+                .collect(),
+            canonical_roots: directories
+                .iter()
+                .filter_map(|d| fs::canonicalize(d).ok())
+                .collect(),
+            visited: HashSet::new(),
+        }
+    }
+
+    /// Start walking a root; false when that folder was already walked
+    pub fn enter_root(&mut self, root: &Path) -> bool {
+        file_id(root).is_some_and(|id| self.visited.insert(id))
+    }
+
+    /// Whether the walker should skip this entry because it was or will be reached
+    /// another way
+    pub fn excludes(&mut self, entry: &DirEntry) -> bool {
+        if entry.depth() == 0 {
+            return false;
+        }
+        if entry.file_type().is_dir() {
+            let Some(id) = file_id(entry.path()) else {
+                return true;
+            };
+            return self.roots.contains(&id) || !self.visited.insert(id);
+        }
+        // A symlinked file inside a scanned directory is found there directly
+        entry.path_is_symlink()
+            && fs::canonicalize(entry.path())
+                .is_ok_and(|target| self.canonical_roots.iter().any(|r| target.starts_with(r)))
+    }
+}
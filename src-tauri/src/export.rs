@@ -0,0 +1,260 @@
+use crate::companions::companion_paths;
+use crate::convert::{convert_image_to, ConvertFormat};
+use crate::file_ops::{copy_preserving_metadata, find_unique_name_with};
+use crate::scanner::RAW_EXTENSIONS;
+use crate::xmp::sidecar_path;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{self, BufWriter};
+use std::path::{Path, PathBuf};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+/// Stills that can be resized; GIFs are left alone so animations survive
+const RESIZABLE_EXTENSIONS: &[&str] = &[
+    "jpg", "jpeg", "png", "webp", "heic", "heif", "tiff", "tif", "bmp",
+];
+
+/// Where exported files go under the target folder
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FolderStructure {
+    /// Keep the folders below the deepest folder the selection shares
+    #[default]
+    Preserve,
+    /// Put every file directly in the target folder
+    Flatten,
+}
+
+/// What `export_files` writes
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ExportOptions {
+    pub destination: String,
+    pub folder_structure: FolderStructure,
+    /// Write RAW files as JPEG instead of copying them
+    pub convert_raw: bool,
+    /// Shrink stills to fit this many pixels on the longer side; resized
+    /// files are written as JPEG
+    pub max_dimension: Option<u32>,
+    /// JPEG quality for converted and resized files
+    pub quality: Option<u8>,
+}
+
+/// One exported file, as recorded in the manifest
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportedFile {
+    pub source: String,
+    pub destination: String,
+    pub size: u64,
+    /// Re-encoded (RAW converted or resized) rather than copied byte for byte
+    pub converted: bool,
+}
+
+/// `export-manifest-<timestamp>.json`, written to the target folder
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportManifest {
+    pub created_at: String,
+    pub destination: String,
+    pub files: Vec<ExportedFile>,
+}
+
+/// Path of each source relative to the target folder, before name clashes
+/// are resolved
+pub fn relative_targets(sources: &[String], structure: FolderStructure) -> Vec<PathBuf> {
+    let base = match structure {
+        FolderStructure::Preserve => common_folder(sources),
+        FolderStructure::Flatten => None,
+    };
+    sources
+        .iter()
+        .map(|source| {
+            let path = Path::new(source);
+            match base.as_deref().and_then(|b| path.strip_prefix(b).ok()) {
+                Some(relative) => relative.to_path_buf(),
+                None => PathBuf::from(path.file_name().unwrap_or(path.as_os_str())),
+            }
+        })
+        .collect()
+}
+
+/// Deepest folder containing every source
+fn common_folder(sources: &[String]) -> Option<PathBuf> {
+    let mut common = Path::new(sources.first()?).parent()?.to_path_buf();
+    for source in &sources[1..] {
+        while !Path::new(source).starts_with(&common) {
+            common = common.parent()?.to_path_buf();
+        }
+    }
+    Some(common)
+}
+
+/// Copy or convert one file to `relative` under the target folder, never
+/// overwriting anything on disk or planned earlier in the same export
+pub fn export_file(
+    source: &Path,
+    relative: &Path,
+    options: &ExportOptions,
+    planned: &mut HashSet<PathBuf>,
+) -> Result<ExportedFile, String> {
+    let extension = source
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase())
+        .unwrap_or_default();
+    let is_raw = RAW_EXTENSIONS.contains(&extension.as_str());
+    let convert = (is_raw && options.convert_raw)
+        || options.max_dimension.is_some_and(|max| {
+            RESIZABLE_EXTENSIONS.contains(&extension.as_str())
+                && image::image_dimensions(source).map_or(true, |(w, h)| w.max(h) > max)
+        });
+
+    let mut target = Path::new(&options.destination).join(relative);
+    if convert {
+        target.set_extension(ConvertFormat::Jpeg.extension());
+    }
+    let target = if target.exists() || planned.contains(&target) {
+        find_unique_name_with(&target, |p| p.exists() || planned.contains(p))?
+    } else {
+        target
+    };
+    planned.insert(target.clone());
+
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    if convert {
+        convert_image_to(
+            source,
+            &target,
+            ConvertFormat::Jpeg,
+            options.quality,
+            options.max_dimension,
+        )?;
+    } else {
+        copy_preserving_metadata(source, &target).map_err(|e| e.to_string())?;
+    }
+
+    Ok(ExportedFile {
+        source: source.to_string_lossy().to_string(),
+        size: fs::metadata(&target).map(|m| m.len()).unwrap_or(0),
+        destination: target.to_string_lossy().to_string(),
+        converted: convert,
+    })
+}
+
+/// XMP sidecar and companions (depth maps, edits, .AAE) of a photo
+pub fn sidecars(source: &Path) -> Vec<PathBuf> {
+    let xmp = sidecar_path(source);
+    let mut sidecars = companion_paths(source);
+    if xmp != source && xmp.is_file() && !sidecars.contains(&xmp) {
+        sidecars.push(xmp);
+    }
+    sidecars
+}
+
+/// A .zip being written; it lives under a temporary name until `finish`, so a
+/// cancelled or failed export never leaves a truncated archive behind
+pub struct ZipExport {
+    writer: ZipWriter<BufWriter<File>>,
+    temp: PathBuf,
+    target: PathBuf,
+    names: HashSet<PathBuf>,
+}
+
+impl ZipExport {
+    pub fn create(target: &Path) -> Result<Self, String> {
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let temp = target.with_extension("zip.part");
+        let file = File::create(&temp).map_err(|e| e.to_string())?;
+        Ok(Self {
+            writer: ZipWriter::new(BufWriter::new(file)),
+            temp,
+            target: target.to_path_buf(),
+            names: HashSet::new(),
+        })
+    }
+
+    /// Stream a file into the archive as `name`, numbering it if the name is
+    /// taken; returns the name used
+    /// Photos and videos are already compressed, so only sidecars are deflated
+    pub fn add(&mut self, source: &Path, name: &Path, deflate: bool) -> Result<PathBuf, String> {
+        let name = if self.names.contains(name) {
+            find_unique_name_with(name, |p| self.names.contains(p))?
+        } else {
+            name.to_path_buf()
+        };
+        let mut file = File::open(source).map_err(|e| e.to_string())?;
+        let size = file.metadata().map_err(|e| e.to_string())?.len();
+        let options = SimpleFileOptions::default()
+            .compression_method(if deflate {
+                CompressionMethod::Deflated
+            } else {
+                CompressionMethod::Stored
+            })
+            .large_file(size >= u32::MAX as u64);
+        // Zip entries always use forward slashes
+        let entry = name
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        self.writer
+            .start_file(entry, options)
+            .map_err(|e| e.to_string())?;
+        io::copy(&mut file, &mut self.writer).map_err(|e| e.to_string())?;
+        self.names.insert(name.clone());
+        Ok(name)
+    }
+
+    /// Complete the archive and move it to its final name, never overwriting
+    /// an existing file
+    pub fn finish(self) -> Result<PathBuf, String> {
+        let result = self
+            .writer
+            .finish()
+            .map_err(|e| e.to_string())
+            .and_then(|writer| writer.into_inner().map_err(|e| e.error().to_string()))
+            .and_then(|file| file.sync_all().map_err(|e| e.to_string()));
+        if let Err(e) = result {
+            let _ = fs::remove_file(&self.temp);
+            return Err(e);
+        }
+        let target = if self.target.exists() {
+            find_unique_name_with(&self.target, |p| p.exists())?
+        } else {
+            self.target
+        };
+        fs::rename(&self.temp, &target).map_err(|e| e.to_string())?;
+        Ok(target)
+    }
+
+    /// Throw the partial archive away
+    pub fn abort(self) {
+        drop(self.writer);
+        let _ = fs::remove_file(&self.temp);
+    }
+}
+
+/// Record what was exported next to the exported files
+pub fn write_manifest(destination: &str, files: &[ExportedFile]) -> Result<PathBuf, String> {
+    let now = chrono::Local::now();
+    let manifest = ExportManifest {
+        created_at: now.to_rfc3339(),
+        destination: destination.to_string(),
+        files: files.to_vec(),
+    };
+    let json = serde_json::to_string_pretty(&manifest).map_err(|e| e.to_string())?;
+    let path = Path::new(destination).join(format!(
+        "export-manifest-{}.json",
+        now.format("%Y%m%d-%H%M%S")
+    ));
+    fs::create_dir_all(destination).map_err(|e| e.to_string())?;
+    fs::write(&path, json).map_err(|e| e.to_string())?;
+    Ok(path)
+}
@@ -0,0 +1,543 @@
+use crate::analysis::AnalysisStore;
+use crate::catalog::Catalog;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// Cosine similarity from which two faces are taken to be the same person
+pub const SAME_PERSON_SIMILARITY: f32 = 0.45;
+
+/// Faces a new cluster needs before it becomes a person; a face seen once
+/// waits until a later detection finds it again
+const MIN_PERSON_FACES: usize = 2;
+
+/// Faces found in photos and the people they were grouped into, stored in the
+/// catalog database
+/// Faces are detected and embedded by models read from the model folder in
+/// builds with the `faces` feature; everything else here works without them
+pub struct Faces {
+    conn: Connection,
+}
+
+/// A face found by the detector, its box as fractions of the upright photo
+pub struct DetectedFace {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    /// Detector confidence, 0 to 1
+    pub score: f32,
+    /// Unit-length embedding; faces of one person point the same way
+    pub embedding: Vec<f32>,
+}
+
+/// Where a face is: its photo and its box as fractions of the upright photo
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FaceRegion {
+    pub path: String,
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Person {
+    pub id: i64,
+    /// None until the user names them
+    pub name: Option<String>,
+    pub face_count: usize,
+    pub photo_count: usize,
+    /// The face detected with the most confidence, to show for the person
+    pub cover: FaceRegion,
+}
+
+/// Create the face tables; called when the catalog is opened so searches can
+/// filter by person before faces have been detected
+pub fn create_tables(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS people (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT
+        );
+        CREATE TABLE IF NOT EXISTS faces (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            path TEXT NOT NULL,
+            x REAL NOT NULL,
+            y REAL NOT NULL,
+            width REAL NOT NULL,
+            height REAL NOT NULL,
+            score REAL NOT NULL,
+            embedding BLOB NOT NULL,
+            person_id INTEGER REFERENCES people(id) ON DELETE SET NULL
+        );
+        CREATE INDEX IF NOT EXISTS idx_faces_path ON faces(path);
+        CREATE INDEX IF NOT EXISTS idx_faces_person ON faces(person_id);
+        CREATE TABLE IF NOT EXISTS face_scans (
+            path TEXT PRIMARY KEY,
+            modified_at INTEGER NOT NULL
+        );
+        ",
+    )
+}
+
+/// A group of faces being built up by `Faces::cluster`
+struct Cluster {
+    /// The person the cluster already is, if any
+    person_id: Option<i64>,
+    /// Sum of the members' embeddings, pointing the way of their average
+    sum: Vec<f32>,
+    /// Faces that joined during this clustering
+    added: Vec<i64>,
+}
+
+impl AnalysisStore for Faces {
+    const SCANS: &'static str = "face_scans";
+    const TABLES: &'static [&'static str] = &["faces", "face_scans"];
+
+    fn connection(&self) -> &Connection {
+        &self.conn
+    }
+}
+
+impl Faces {
+    /// Open the face tables in the catalog database
+    pub fn open() -> Result<Self, String> {
+        let conn = Catalog::connection()?;
+        conn.execute_batch("PRAGMA foreign_keys = ON;")
+            .map_err(|e| e.to_string())?;
+        Ok(Self { conn })
+    }
+
+    /// Store the faces detected in a photo in place of any found before
+    pub fn replace(
+        &mut self,
+        path: &str,
+        modified_at: i64,
+        faces: &[DetectedFace],
+    ) -> Result<(), String> {
+        let tx = self.conn.transaction().map_err(|e| e.to_string())?;
+        tx.execute("DELETE FROM faces WHERE path = ?1", params![path])
+            .map_err(|e| e.to_string())?;
+        for face in faces {
+            let embedding: Vec<u8> = face
+                .embedding
+                .iter()
+                .flat_map(|v| v.to_le_bytes())
+                .collect();
+            tx.execute(
+                "INSERT INTO faces (path, x, y, width, height, score, embedding)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    path,
+                    face.x,
+                    face.y,
+                    face.width,
+                    face.height,
+                    face.score,
+                    embedding
+                ],
+            )
+            .map_err(|e| e.to_string())?;
+        }
+        tx.execute(
+            "INSERT OR REPLACE INTO face_scans (path, modified_at) VALUES (?1, ?2)",
+            params![path, modified_at],
+        )
+        .map_err(|e| e.to_string())?;
+        tx.commit().map_err(|e| e.to_string())
+    }
+
+    /// Group faces that belong to no one yet into people
+    /// Each face joins the person or new group whose average face is most
+    /// similar to it, if at least `similarity` (cosine); groups of two or more
+    /// become unnamed people. Faces already assigned stay where they are, so
+    /// names and earlier groupings survive another detection. Unnamed people
+    /// left without faces are removed
+    pub fn cluster(&mut self, similarity: f32) -> Result<(), String> {
+        let tx = self.conn.transaction().map_err(|e| e.to_string())?;
+        let faces: Vec<(i64, Option<i64>, Vec<f32>)> = {
+            let mut stmt = tx
+                .prepare("SELECT id, person_id, embedding FROM faces ORDER BY id")
+                .map_err(|e| e.to_string())?;
+            let rows = stmt
+                .query_map([], |row| {
+                    let bytes: Vec<u8> = row.get(2)?;
+                    Ok((row.get(0)?, row.get(1)?, decode_embedding(&bytes)))
+                })
+                .map_err(|e| e.to_string())?;
+            rows.collect::<Result<_, _>>().map_err(|e| e.to_string())?
+        };
+
+        let mut clusters: Vec<Cluster> = Vec::new();
+        let mut by_person: HashMap<i64, usize> = HashMap::new();
+        for (_, person_id, embedding) in &faces {
+            let Some(person_id) = *person_id else {
+                continue;
+            };
+            let index = *by_person.entry(person_id).or_insert_with(|| {
+                clusters.push(Cluster {
+                    person_id: Some(person_id),
+                    sum: vec![0.0; embedding.len()],
+                    added: Vec::new(),
+                });
+                clusters.len() - 1
+            });
+            add_to(&mut clusters[index].sum, embedding);
+        }
+
+        for (id, _, embedding) in faces.iter().filter(|(_, p, _)| p.is_none()) {
+            let best = clusters
+                .iter()
+                .enumerate()
+                .map(|(i, c)| (i, cosine(&c.sum, embedding)))
+                .filter(|(_, s)| *s >= similarity)
+                .max_by(|a, b| a.1.total_cmp(&b.1));
+            match best {
+                Some((index, _)) => {
+                    add_to(&mut clusters[index].sum, embedding);
+                    clusters[index].added.push(*id);
+                }
+                None => clusters.push(Cluster {
+                    person_id: None,
+                    sum: embedding.clone(),
+                    added: vec![*id],
+                }),
+            }
+        }
+
+        for cluster in clusters {
+            let person_id = match cluster.person_id {
+                Some(id) => id,
+                None if cluster.added.len() >= MIN_PERSON_FACES => {
+                    tx.execute("INSERT INTO people (name) VALUES (NULL)", [])
+                        .map_err(|e| e.to_string())?;
+                    tx.last_insert_rowid()
+                }
+                None => continue,
+            };
+            for face_id in cluster.added {
+                tx.execute(
+                    "UPDATE faces SET person_id = ?1 WHERE id = ?2",
+                    params![person_id, face_id],
+                )
+                .map_err(|e| e.to_string())?;
+            }
+        }
+        tx.execute(
+            "DELETE FROM people WHERE name IS NULL
+            AND id NOT IN (SELECT person_id FROM faces WHERE person_id IS NOT NULL)",
+            [],
+        )
+        .map_err(|e| e.to_string())?;
+        tx.commit().map_err(|e| e.to_string())
+    }
+
+    /// People with at least one face, named ones first, then by how many
+    /// photos they are in
+    pub fn people(&self) -> Result<Vec<Person>, String> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT p.id, p.name, COUNT(*), COUNT(DISTINCT f.path),
+                    c.path, c.x, c.y, c.width, c.height
+                FROM people p
+                JOIN faces f ON f.person_id = p.id
+                JOIN faces c ON c.id = (SELECT id FROM faces WHERE person_id = p.id
+                    ORDER BY score DESC LIMIT 1)
+                GROUP BY p.id
+                ORDER BY p.name IS NULL, COUNT(DISTINCT f.path) DESC, p.name COLLATE NOCASE",
+            )
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok(Person {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                    face_count: row.get::<_, i64>(2)? as usize,
+                    photo_count: row.get::<_, i64>(3)? as usize,
+                    cover: FaceRegion {
+                        path: row.get(4)?,
+                        x: row.get(5)?,
+                        y: row.get(6)?,
+                        width: row.get(7)?,
+                        height: row.get(8)?,
+                    },
+                })
+            })
+            .map_err(|e| e.to_string())?;
+        rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+    }
+
+    /// Name a person, or take their name away with None or a blank name
+    /// Giving them the name of someone else merges the two into that person,
+    /// whose id is returned
+    pub fn name_person(&mut self, id: i64, name: Option<&str>) -> Result<i64, String> {
+        let name = name.map(str::trim).filter(|n| !n.is_empty());
+        let tx = self.conn.transaction().map_err(|e| e.to_string())?;
+        let exists = tx
+            .query_row(
+                "SELECT 1 FROM people WHERE id = ?1",
+                params![id],
+                |_| Ok(()),
+            )
+            .optional()
+            .map_err(|e| e.to_string())?
+            .is_some();
+        if !exists {
+            return Err(format!("No person with id {}", id));
+        }
+
+        let same_name: Option<i64> = match name {
+            Some(name) => tx
+                .query_row(
+                    "SELECT id FROM people WHERE name = ?1 COLLATE NOCASE AND id != ?2",
+                    params![name, id],
+                    |row| row.get(0),
+                )
+                .optional()
+                .map_err(|e| e.to_string())?,
+            None => None,
+        };
+        let kept = match same_name {
+            Some(other) => {
+                tx.execute(
+                    "UPDATE faces SET person_id = ?1 WHERE person_id = ?2",
+                    params![other, id],
+                )
+                .map_err(|e| e.to_string())?;
+                tx.execute("DELETE FROM people WHERE id = ?1", params![id])
+                    .map_err(|e| e.to_string())?;
+                other
+            }
+            None => id,
+        };
+        tx.execute(
+            "UPDATE people SET name = ?1 WHERE id = ?2",
+            params![name, kept],
+        )
+        .map_err(|e| e.to_string())?;
+        tx.commit().map_err(|e| e.to_string())?;
+        Ok(kept)
+    }
+}
+
+/// Follow a file the app moved or renamed, keeping its faces and the people
+/// they were named as
+pub fn relocate(conn: &Connection, from: &str, to: &str) -> rusqlite::Result<()> {
+    for table in ["faces", "face_scans"] {
+        conn.execute(
+            &format!("DELETE FROM {} WHERE path = ?1", table),
+            params![to],
+        )?;
+        conn.execute(
+            &format!("UPDATE {} SET path = ?2 WHERE path = ?1", table),
+            params![from, to],
+        )?;
+    }
+    Ok(())
+}
+
+fn decode_embedding(bytes: &[u8]) -> Vec<f32> {
+    bytes
+        .chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect()
+}
+
+fn add_to(sum: &mut [f32], embedding: &[f32]) {
+    for (s, e) in sum.iter_mut().zip(embedding) {
+        *s += e;
+    }
+}
+
+/// Cosine similarity of a cluster's sum and a unit-length embedding
+fn cosine(sum: &[f32], embedding: &[f32]) -> f32 {
+    let norm = sum.iter().map(|v| v * v).sum::<f32>().sqrt();
+    if norm == 0.0 {
+        return 0.0;
+    }
+    sum.iter().zip(embedding).map(|(s, e)| s * e).sum::<f32>() / norm
+}
+
+#[cfg(feature = "faces")]
+pub use model::FaceModels;
+
+/// Stand-in for builds without the `faces` feature, which can't detect faces
+#[cfg(not(feature = "faces"))]
+pub struct FaceModels;
+
+#[cfg(not(feature = "faces"))]
+impl FaceModels {
+    pub fn load() -> Result<Self, String> {
+        Err("This build has no face detection; it needs the `faces` feature".to_string())
+    }
+
+    pub fn detect(&self, _path: &std::path::Path) -> Result<Vec<DetectedFace>, String> {
+        Err("This build has no face detection".to_string())
+    }
+}
+
+#[cfg(feature = "faces")]
+mod model {
+    use super::DetectedFace;
+    use crate::onnx::{load_model, load_upright, tensor, Model};
+    use image::imageops::{self, FilterType};
+    use image::RgbImage;
+    use std::path::Path;
+    use tract_onnx::prelude::*;
+
+    /// UltraFace RFB-320: a 320×240 RGB image in, face scores and corner boxes
+    /// (as fractions of the image) out
+    const DETECTOR: &str = "face-detector.onnx";
+    const DETECTOR_WIDTH: u32 = 320;
+    const DETECTOR_HEIGHT: u32 = 240;
+
+    /// ArcFace-style embedder: a 112×112 RGB face in, its embedding out
+    const EMBEDDER: &str = "face-embedder.onnx";
+    const EMBEDDER_SIZE: u32 = 112;
+
+    /// Detector confidence a face needs
+    const MIN_SCORE: f32 = 0.7;
+    /// Overlap (intersection over union) from which two boxes are one face
+    const MAX_OVERLAP: f32 = 0.3;
+    /// Faces smaller than this many pixels in the photo embed too poorly to
+    /// tell people apart
+    const MIN_FACE_PIXELS: f32 = 32.0;
+    /// Room left around a face when it is cut out for the embedder, as a
+    /// fraction of its size
+    const CROP_MARGIN: f32 = 0.2;
+
+    /// The detector and embedder, loaded once per detection job
+    pub struct FaceModels {
+        detector: Model,
+        embedder: Model,
+    }
+
+    impl FaceModels {
+        pub fn load() -> Result<Self, String> {
+            Ok(Self {
+                detector: load_model(DETECTOR, [1, 3, DETECTOR_HEIGHT, DETECTOR_WIDTH])?,
+                embedder: load_model(EMBEDDER, [1, 3, EMBEDDER_SIZE, EMBEDDER_SIZE])?,
+            })
+        }
+
+        /// Faces in a photo, most confident first
+        /// The photo is turned upright first, so boxes match what is displayed
+        pub fn detect(&self, path: &Path) -> Result<Vec<DetectedFace>, String> {
+            let image = load_upright(path)?;
+            let (width, height) = (image.width() as f32, image.height() as f32);
+            // Squeezed to the detector's shape; boxes come back as fractions
+            let input = imageops::resize(
+                &image,
+                DETECTOR_WIDTH,
+                DETECTOR_HEIGHT,
+                FilterType::Triangle,
+            );
+            let outputs = self
+                .detector
+                .run(tvec!(tensor(&input, [127.0; 3], [128.0; 3]).into()))
+                .map_err(|e| e.to_string())?;
+            let scores = view3(&outputs[0])?;
+            let boxes = view3(&outputs[1])?;
+
+            let mut candidates: Vec<(f32, [f32; 4])> = (0..scores.shape()[1])
+                .filter(|&i| scores[[0, i, 1]] >= MIN_SCORE)
+                .map(|i| {
+                    let corner = |k: usize| boxes[[0, i, k]].clamp(0.0, 1.0);
+                    (
+                        scores[[0, i, 1]],
+                        [corner(0), corner(1), corner(2), corner(3)],
+                    )
+                })
+                .filter(|(_, b)| {
+                    (b[2] - b[0]) * width >= MIN_FACE_PIXELS
+                        && (b[3] - b[1]) * height >= MIN_FACE_PIXELS
+                })
+                .collect();
+            candidates.sort_by(|a, b| b.0.total_cmp(&a.0));
+            let mut kept: Vec<(f32, [f32; 4])> = Vec::new();
+            for candidate in candidates {
+                if kept
+                    .iter()
+                    .all(|k| overlap(&k.1, &candidate.1) < MAX_OVERLAP)
+                {
+                    kept.push(candidate);
+                }
+            }
+
+            kept.into_iter()
+                .map(|(score, [left, top, right, bottom])| {
+                    Ok(DetectedFace {
+                        x: left,
+                        y: top,
+                        width: right - left,
+                        height: bottom - top,
+                        score,
+                        embedding: self.embed(&image, [left, top, right, bottom])?,
+                    })
+                })
+                .collect()
+        }
+
+        /// Embedding of the face in `corners`, cut out as a square around it
+        fn embed(&self, image: &RgbImage, corners: [f32; 4]) -> Result<Vec<f32>, String> {
+            let (width, height) = (image.width() as f32, image.height() as f32);
+            let [left, top, right, bottom] = corners;
+            let center_x = (left + right) / 2.0 * width;
+            let center_y = (top + bottom) / 2.0 * height;
+            let side = ((right - left) * width).max((bottom - top) * height) * (1.0 + CROP_MARGIN);
+            let x = (center_x - side / 2.0).max(0.0);
+            let y = (center_y - side / 2.0).max(0.0);
+            let crop_width = side.min(width - x).max(1.0);
+            let crop_height = side.min(height - y).max(1.0);
+            let face = imageops::crop_imm(
+                image,
+                x as u32,
+                y as u32,
+                crop_width as u32,
+                crop_height as u32,
+            )
+            .to_image();
+            let face = imageops::resize(&face, EMBEDDER_SIZE, EMBEDDER_SIZE, FilterType::Triangle);
+
+            let outputs = self
+                .embedder
+                .run(tvec!(tensor(&face, [127.5; 3], [127.5; 3]).into()))
+                .map_err(|e| e.to_string())?;
+            let mut embedding: Vec<f32> = outputs[0]
+                .as_slice::<f32>()
+                .map_err(|e| e.to_string())?
+                .to_vec();
+            let norm = embedding.iter().map(|v| v * v).sum::<f32>().sqrt();
+            if norm > 0.0 {
+                embedding.iter_mut().for_each(|v| *v /= norm);
+            }
+            Ok(embedding)
+        }
+    }
+
+    fn view3(value: &TValue) -> Result<tract_ndarray::ArrayView3<'_, f32>, String> {
+        value
+            .to_array_view::<f32>()
+            .map_err(|e| e.to_string())?
+            .into_dimensionality()
+            .map_err(|e| e.to_string())
+    }
+
+    /// Intersection over union of two corner boxes
+    fn overlap(a: &[f32; 4], b: &[f32; 4]) -> f32 {
+        let width = (a[2].min(b[2]) - a[0].max(b[0])).max(0.0);
+        let height = (a[3].min(b[3]) - a[1].max(b[1])).max(0.0);
+        let intersection = width * height;
+        let area = |r: &[f32; 4]| (r[2] - r[0]) * (r[3] - r[1]);
+        let union = area(a) + area(b) - intersection;
+        if union <= 0.0 {
+            0.0
+        } else {
+            intersection / union
+        }
+    }
+}
@@ -0,0 +1,814 @@
+use crate::companions::companion_paths;
+use crate::error::CommandError;
+use crate::hash_cache::{HashAlgorithm, HashCache};
+use crate::live_photos;
+use crate::scanner::{compute_full_hash, is_cloud_placeholder};
+use crate::xmp::sidecar_path;
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File, FileTimes};
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Bytes actually allocated on disk for a file
+/// Differs from the logical size for sparse, compressed (APFS/HFS+) and dataless
+/// cloud files; this is what deleting the file would really free
+#[cfg(unix)]
+pub fn allocated_size(metadata: &fs::Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    // st_blocks is always in 512-byte units regardless of the filesystem block size
+    metadata.blocks() * 512
+}
+
+#[cfg(not(unix))]
+pub fn allocated_size(metadata: &fs::Metadata) -> u64 {
+    metadata.len()
+}
+
+/// Adds up the bytes that deleting files would free on disk
+/// A hard-linked file's storage counts once, and only when every link to it was
+/// added. Blocks an APFS clone shares with another file can't be told apart from
+/// its own, so with clones the total is an upper bound.
+#[derive(Debug, Default)]
+pub struct DiskUsage {
+    bytes: u64,
+    /// Links added so far to each hard-linked file
+    links: HashMap<(u64, u64), u64>,
+}
+
+impl DiskUsage {
+    pub fn add(&mut self, metadata: &fs::Metadata) {
+        let Some(id) = hardlink_id(metadata) else {
+            self.bytes += allocated_size(metadata);
+            return;
+        };
+        let added = self.links.entry(id).or_default();
+        *added += 1;
+        if *added == link_count(metadata) {
+            self.bytes += allocated_size(metadata);
+        }
+    }
+
+    pub fn bytes(&self) -> u64 {
+        self.bytes
+    }
+}
+
+#[cfg(unix)]
+fn link_count(metadata: &fs::Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    metadata.nlink()
+}
+
+#[cfg(not(unix))]
+fn link_count(_metadata: &fs::Metadata) -> u64 {
+    1
+}
+
+/// Device and inode of a file with more than one hard link, so paths that
+/// share the same storage can be told apart from copies
+#[cfg(unix)]
+pub fn hardlink_id(metadata: &fs::Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    (metadata.nlink() > 1).then(|| (metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+pub fn hardlink_id(_metadata: &fs::Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+/// Replace `copy` with a hard link to `original`, so both paths stay but the
+/// data is stored once
+/// The link is made under a temporary name and renamed over the copy, so the
+/// copy's path never goes missing; fails across volumes
+pub fn replace_with_hardlink(original: &Path, copy: &Path) -> Result<(), String> {
+    let name = copy
+        .file_name()
+        .ok_or("Invalid file name")?
+        .to_string_lossy();
+    let temporary = copy.with_file_name(format!(".{}.pmlink", name));
+    fs::hard_link(original, &temporary).map_err(|e| match e.kind() {
+        ErrorKind::CrossesDevices => "On another volume than the copy kept".to_string(),
+        _ => e.to_string(),
+    })?;
+    fs::rename(&temporary, copy).map_err(|e| {
+        let _ = fs::remove_file(&temporary);
+        e.to_string()
+    })
+}
+
+/// Replace `copy` with an APFS clone of `original`: both stay separate files,
+/// but they share their data blocks until one of them is changed
+/// The clone keeps the copy's timestamps and replaces it through a rename like
+/// `replace_with_hardlink`; fails across volumes and on other file systems
+#[cfg(target_os = "macos")]
+pub fn replace_with_clone(original: &Path, copy: &Path) -> Result<(), String> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let name = copy
+        .file_name()
+        .ok_or("Invalid file name")?
+        .to_string_lossy();
+    let temporary = copy.with_file_name(format!(".{}.pmclone", name));
+    let from = CString::new(original.as_os_str().as_bytes()).map_err(|e| e.to_string())?;
+    let to = CString::new(temporary.as_os_str().as_bytes()).map_err(|e| e.to_string())?;
+    if unsafe { libc::clonefile(from.as_ptr(), to.as_ptr(), 0) } != 0 {
+        let error = io::Error::last_os_error();
+        return Err(match (error.kind(), error.raw_os_error()) {
+            (ErrorKind::CrossesDevices, _) => "On another volume than the copy kept".to_string(),
+            (_, Some(libc::ENOTSUP)) => "The volume doesn't support clones".to_string(),
+            _ => error.to_string(),
+        });
+    }
+    copy_file_times(copy, &temporary)
+        .and_then(|()| fs::rename(&temporary, copy))
+        .map_err(|e| {
+            let _ = fs::remove_file(&temporary);
+            e.to_string()
+        })
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn replace_with_clone(_original: &Path, _copy: &Path) -> Result<(), String> {
+    Err("Clones are only supported on macOS".to_string())
+}
+
+/// Whether a rename from `source` to `target` stays on one volume; `target`
+/// may not exist yet, so its nearest existing folder is checked
+#[cfg(unix)]
+pub fn same_volume(source: &Path, target: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    let device = |path: &Path| {
+        path.ancestors()
+            .find_map(|a| fs::metadata(a).ok())
+            .map(|m| m.dev())
+    };
+    device(source) == device(target)
+}
+
+/// Windows: paths on the same drive letter or network share
+#[cfg(not(unix))]
+pub fn same_volume(source: &Path, target: &Path) -> bool {
+    let prefix = |path: &Path| {
+        path.components()
+            .next()
+            .map(|c| c.as_os_str().to_ascii_lowercase())
+    };
+    prefix(source) == prefix(target)
+}
+
+/// Move a single file, falling back to copy + verify + delete across volumes
+/// Returns the verified full hash when the copy path was used
+/// Cached hashes follow the file to its new path
+pub fn move_path(
+    source: &Path,
+    target: &Path,
+    cache: Option<&HashCache>,
+) -> Result<Option<String>, CommandError> {
+    match fs::rename(source, target) {
+        Ok(()) => {
+            if let Some(c) = cache {
+                c.relocate(&source.to_string_lossy(), &target.to_string_lossy());
+            }
+            Ok(None)
+        }
+        Err(e) if e.kind() == ErrorKind::CrossesDevices => {
+            copy_verify_delete(source, target, cache).map(Some)
+        }
+        Err(e) => Err(CommandError::io(source, &e)),
+    }
+}
+
+/// Move several files as a unit: if any move fails, the ones already done are
+/// moved back so the group is never left split across two places
+/// Returns the verified hash (if any) of each move, in order
+pub fn move_all(
+    moves: &[(PathBuf, PathBuf)],
+    cache: Option<&HashCache>,
+) -> Result<Vec<Option<String>>, CommandError> {
+    let mut hashes = Vec::with_capacity(moves.len());
+    for (i, (source, target)) in moves.iter().enumerate() {
+        match move_path(source, target, cache) {
+            Ok(hash) => hashes.push(hash),
+            Err(e) => {
+                roll_back(&moves[..i], cache);
+                return Err(e);
+            }
+        }
+    }
+    Ok(hashes)
+}
+
+/// Move files that were moved from `source` to `target` back, last first
+/// Returns the files that could not be moved back, by their current path
+pub fn roll_back(
+    moves: &[(PathBuf, PathBuf)],
+    cache: Option<&HashCache>,
+) -> Vec<(PathBuf, String)> {
+    let mut stranded = Vec::new();
+    for (source, target) in moves.iter().rev() {
+        if let Err(undo) = move_path(target, source, cache) {
+            eprintln!(
+                "Failed to roll back {} -> {}: {}",
+                target.display(),
+                source.display(),
+                undo
+            );
+            stranded.push((target.clone(), undo.to_string()));
+        }
+    }
+    stranded
+}
+
+/// Files the OS drops into folders by itself; a folder holding nothing else
+/// counts as empty
+const FOLDER_JUNK: &[&str] = &[".DS_Store", "Thumbs.db", "desktop.ini"];
+
+/// Remove the empty folders under `dir`, deepest first; `dir` itself and its
+/// parents are kept
+/// Returns the folders removed
+pub fn remove_empty_dirs(dir: &Path, protected: &dyn Fn(&Path) -> bool) -> Vec<PathBuf> {
+    let mut removed = Vec::new();
+    if let Ok(entries) = fs::read_dir(dir) {
+        for entry in entries.filter_map(|e| e.ok()) {
+            // Symlinked folders are left alone, not followed
+            if entry.file_type().is_ok_and(|t| t.is_dir()) {
+                let folder = entry.path();
+                removed.extend(remove_empty_dirs(&folder, protected));
+                if remove_if_empty(&folder, protected) {
+                    removed.push(folder);
+                }
+            }
+        }
+    }
+    removed
+}
+
+/// Remove `dir` if it is empty, then each parent left empty, stopping at the
+/// first folder that isn't or that is `protected`; used after files were moved
+/// out of `dir`
+/// Returns the folders removed
+pub fn remove_emptied_dirs(dir: &Path, protected: &dyn Fn(&Path) -> bool) -> Vec<PathBuf> {
+    dir.ancestors()
+        .take_while(|folder| remove_if_empty(folder, protected))
+        .map(Path::to_path_buf)
+        .collect()
+}
+
+/// Remove `dir` if it holds nothing but OS junk files
+fn remove_if_empty(dir: &Path, protected: &dyn Fn(&Path) -> bool) -> bool {
+    if protected(dir) {
+        return false;
+    }
+    let Ok(entries) = fs::read_dir(dir) else {
+        return false;
+    };
+    let mut junk = Vec::new();
+    for entry in entries {
+        let Ok(entry) = entry else {
+            return false;
+        };
+        let is_junk = FOLDER_JUNK.iter().any(|name| entry.file_name() == *name)
+            && entry.file_type().is_ok_and(|t| t.is_file());
+        if !is_junk {
+            return false;
+        }
+        junk.push(entry.path());
+    }
+    junk.iter().all(|file| fs::remove_file(file).is_ok()) && fs::remove_dir(dir).is_ok()
+}
+
+/// Copy a file to another volume, check the copy's full hash against the source
+/// hash (from the cache when fresh), and only then remove the source
+/// Cloud placeholders are refused rather than downloaded by the copy
+fn copy_verify_delete(
+    source: &Path,
+    target: &Path,
+    cache: Option<&HashCache>,
+) -> Result<String, CommandError> {
+    let source_str = source.to_string_lossy().to_string();
+    if is_cloud_placeholder(&source_str) {
+        return Err(CommandError::CloudNotDownloaded { path: source_str });
+    }
+    let source_metadata = fs::metadata(source).map_err(|e| CommandError::io(source, &e))?;
+    let source_size = source_metadata.len();
+    let algorithm = cache
+        .map(|c| c.algorithm())
+        .unwrap_or_else(HashAlgorithm::configured);
+
+    // Only trust a cached hash if the source is unchanged since it was cached
+    let source_hash = cache
+        .and_then(|c| c.verify_cache_entry(&source_str, &source_metadata))
+        .and_then(|info| info.full_hash)
+        .or_else(|| compute_full_hash(&source_str, algorithm))
+        .ok_or_else(|| CommandError::Io {
+            path: source_str.clone(),
+            message: "Could not read the file to hash it".to_string(),
+        })?;
+
+    // Times as they were before hashing read the file and bumped its access time
+    let times = file_times(&source_metadata).map_err(|e| CommandError::io(source, &e))?;
+    copy_with_attributes(source, target).map_err(|e| {
+        // Don't leave a partial copy behind, e.g. when the drive filled up
+        let _ = fs::remove_file(target);
+        CommandError::io(target, &e)
+    })?;
+
+    let target_str = target.to_string_lossy().to_string();
+    let target_hash = compute_full_hash(&target_str, algorithm);
+    if target_hash.as_deref() != Some(source_hash.as_str()) {
+        let _ = fs::remove_file(target);
+        return Err(CommandError::Io {
+            path: target_str,
+            message: format!("The copy doesn't match {}", source_str),
+        });
+    }
+    // Set once the copy has been read back, which bumps its access time too
+    if let Err(e) = set_file_times(target, times) {
+        let _ = fs::remove_file(target);
+        return Err(CommandError::io(target, &e));
+    }
+
+    fs::remove_file(source).map_err(|e| CommandError::io(source, &e))?;
+
+    if let Some(c) = cache {
+        c.invalidate(&source_str);
+        c.set_full_hash(&target_str, source_size, &source_hash);
+    }
+
+    Ok(source_hash)
+}
+
+/// Copy a file keeping timestamps, extended attributes (Finder tags, quarantine,
+/// etc.) and permissions. Plain `fs::copy` drops most of these on macOS.
+pub fn copy_preserving_metadata(source: &Path, target: &Path) -> io::Result<()> {
+    // Read before copying, since reading the data updates the access time
+    let times = file_times(&fs::metadata(source)?)?;
+    copy_with_attributes(source, target)?;
+    set_file_times(target, times)
+}
+
+/// macOS: copyfile(3) with COPYFILE_ALL copies data, ACLs, POSIX stat info and xattrs
+#[cfg(target_os = "macos")]
+fn copy_with_attributes(source: &Path, target: &Path) -> io::Result<()> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    // COPYFILE_ACL | COPYFILE_STAT | COPYFILE_XATTR | COPYFILE_DATA
+    const COPYFILE_ALL: u32 = 0x0F;
+
+    let from = CString::new(source.as_os_str().as_bytes())?;
+    let to = CString::new(target.as_os_str().as_bytes())?;
+    let result = unsafe {
+        libc::copyfile(
+            from.as_ptr(),
+            to.as_ptr(),
+            std::ptr::null_mut(),
+            COPYFILE_ALL,
+        )
+    };
+    if result == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+/// Linux and other Unixes: fs::copy keeps data and permissions, extended
+/// attributes are copied one by one; times are restored afterwards
+#[cfg(all(unix, not(target_os = "macos")))]
+fn copy_with_attributes(source: &Path, target: &Path) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    fs::copy(source, target)?;
+    let names = match xattr::list(source) {
+        Ok(names) => names,
+        Err(e) if e.kind() == ErrorKind::Unsupported => return Ok(()),
+        Err(e) => return Err(e),
+    };
+    // Setting attributes needs write access, which a read-only original's
+    // permissions don't give the copy
+    let permissions = fs::metadata(target)?.permissions();
+    let mode = permissions.mode();
+    if mode & 0o200 == 0 {
+        fs::set_permissions(target, fs::Permissions::from_mode(mode | 0o200))?;
+    }
+    let copied = names.into_iter().try_for_each(|name| {
+        let Some(value) = xattr::get(source, &name)? else {
+            return Ok(());
+        };
+        match xattr::set(target, &name, &value) {
+            // The target volume can't store them (FAT, some network shares), or
+            // the namespace needs privileges (security.*, trusted.*)
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    ErrorKind::Unsupported | ErrorKind::PermissionDenied
+                ) =>
+            {
+                Ok(())
+            }
+            result => result,
+        }
+    });
+    if mode & 0o200 == 0 {
+        fs::set_permissions(target, permissions)?;
+    }
+    copied
+}
+
+/// Other platforms: fs::copy keeps data and permissions (on Windows, attributes
+/// and alternate data streams too); times are restored afterwards
+#[cfg(not(unix))]
+fn copy_with_attributes(source: &Path, target: &Path) -> io::Result<()> {
+    fs::copy(source, target).map(|_| ())
+}
+
+/// Restore modification/access (and on macOS, creation) times on the copy
+pub fn copy_file_times(source: &Path, target: &Path) -> io::Result<()> {
+    set_file_times(target, file_times(&fs::metadata(source)?)?)
+}
+
+/// A file's modification/access (and on macOS, creation) times, to set on another
+fn file_times(metadata: &fs::Metadata) -> io::Result<FileTimes> {
+    let times = FileTimes::new()
+        .set_accessed(metadata.accessed()?)
+        .set_modified(metadata.modified()?);
+
+    #[cfg(target_os = "macos")]
+    let times = {
+        use std::os::macos::fs::FileTimesExt;
+        match metadata.created() {
+            Ok(created) => times.set_created(created),
+            Err(_) => times,
+        }
+    };
+
+    Ok(times)
+}
+
+/// Set a file's modification time, e.g. to when the photo was taken
+pub fn set_modified_time(path: &Path, time: std::time::SystemTime) -> io::Result<()> {
+    set_file_times(path, FileTimes::new().set_modified(time))
+}
+
+fn set_file_times(target: &Path, times: FileTimes) -> io::Result<()> {
+    // Opened read-only: copied permissions may not allow writing, but the owner can set times
+    #[cfg(not(windows))]
+    let file = File::open(target)?;
+    // Windows needs an explicit FILE_WRITE_ATTRIBUTES handle to set times
+    #[cfg(windows)]
+    let file = {
+        use std::os::windows::fs::OpenOptionsExt;
+        const FILE_WRITE_ATTRIBUTES: u32 = 0x100;
+        fs::OpenOptions::new()
+            .access_mode(FILE_WRITE_ATTRIBUTES)
+            .open(target)?
+    };
+    file.set_times(times)
+}
+
+/// macOS: select the file in a Finder window
+#[cfg(target_os = "macos")]
+pub fn reveal(path: &Path) -> io::Result<()> {
+    Command::new("open").arg("-R").arg(path).spawn().map(|_| ())
+}
+
+/// The running Quick Look preview, closed when the next one opens
+#[cfg(target_os = "macos")]
+static QUICK_LOOK: std::sync::Mutex<Option<std::process::Child>> = std::sync::Mutex::new(None);
+
+/// macOS: show the file in a Quick Look panel, which decodes RAW formats the
+/// webview can't; replaces the preview that is already open
+#[cfg(target_os = "macos")]
+pub fn quick_look(path: &Path) -> io::Result<()> {
+    use std::process::Stdio;
+    let mut current = QUICK_LOOK.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(mut previous) = current.take() {
+        let _ = previous.kill();
+        let _ = previous.wait();
+    }
+    let child = Command::new("qlmanage")
+        .arg("-p")
+        .arg(path)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    *current = Some(child);
+    Ok(())
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn quick_look(_path: &Path) -> io::Result<()> {
+    Err(io::Error::new(
+        ErrorKind::Unsupported,
+        "Quick Look is only available on macOS",
+    ))
+}
+
+/// Windows: select the file in an Explorer window
+#[cfg(windows)]
+pub fn reveal(path: &Path) -> io::Result<()> {
+    use std::os::windows::process::CommandExt;
+    // Explorer parses its own command line; "/select," must be glued to the quoted path
+    Command::new("explorer")
+        .raw_arg(format!("/select,\"{}\"", path.display()))
+        .spawn()
+        .map(|_| ())
+}
+
+/// Linux and other Unixes: ask the file manager to select the file over D-Bus
+/// (FileManager1 is implemented by Nautilus, Dolphin, Nemo, Thunar, ...) and fall
+/// back to opening the containing folder
+#[cfg(all(unix, not(target_os = "macos")))]
+pub fn reveal(path: &Path) -> io::Result<()> {
+    let shown = Command::new("dbus-send")
+        .args([
+            "--session",
+            "--print-reply",
+            "--dest=org.freedesktop.FileManager1",
+            "--type=method_call",
+            "/org/freedesktop/FileManager1",
+            "org.freedesktop.FileManager1.ShowItems",
+        ])
+        .arg(format!("array:string:{}", file_uri(path)))
+        .arg("string:")
+        .output()
+        .is_ok_and(|output| output.status.success());
+    if shown {
+        return Ok(());
+    }
+    Command::new("xdg-open")
+        .arg(path.parent().unwrap_or(path))
+        .spawn()
+        .map(|_| ())
+}
+
+/// `file://` URI with everything but unreserved characters and `/` percent-encoded
+/// (commas too, which dbus-send would take as an array separator)
+#[cfg(all(unix, not(target_os = "macos")))]
+fn file_uri(path: &Path) -> String {
+    use std::os::unix::ffi::OsStrExt;
+    let mut uri = String::from("file://");
+    for &byte in path.as_os_str().as_bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                uri.push(byte as char)
+            }
+            _ => uri.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    uri
+}
+
+/// File name after changing a group's stem (photo, RAW/JPEG pair, sidecars);
+/// keeps everything after the old stem so `IMG_1.ARW.xmp` becomes `NEW.ARW.xmp`
+/// The stem is matched ignoring case, as cameras and editors disagree on it
+/// (`IMG_1.JPG` next to `img_1.xmp`)
+pub fn restem(name: &str, old_stem: &str, new_stem: &str) -> String {
+    let rest = name
+        .get(..old_stem.len())
+        .filter(|prefix| prefix.to_lowercase() == old_stem.to_lowercase())
+        .map(|_| &name[old_stem.len()..]);
+    match rest {
+        Some(rest) => format!("{}{}", new_stem, rest),
+        None => name.to_string(),
+    }
+}
+
+/// Files that go wherever their photo goes: its XMP sidecar, depth maps, edited
+/// renditions, .AAE files and the video of a Live Photo
+pub fn companions_of(photo: &Path) -> Vec<PathBuf> {
+    let mut companions = companion_paths(photo);
+    companions.extend(live_photos::video_of(photo));
+    let xmp = sidecar_path(photo);
+    if xmp != photo && xmp.is_file() && !companions.contains(&xmp) {
+        companions.push(xmp);
+    }
+    companions
+}
+
+/// Plans a batch of moves: names taken on disk or by an earlier move of the
+/// batch get a number, and each photo takes its companions along under its
+/// new stem, so pairs and sidecars are never left behind or split up
+pub struct MovePlanner {
+    /// Files moved in their own right, which no photo takes along
+    requested: HashSet<PathBuf>,
+    /// Sources and targets of the moves planned so far
+    moved: HashSet<PathBuf>,
+    planned: HashSet<PathBuf>,
+}
+
+impl MovePlanner {
+    /// `requested` are the files of the batch; a photo leaves those to be
+    /// moved by themselves rather than taking them along
+    pub fn new(requested: impl IntoIterator<Item = PathBuf>) -> Self {
+        Self {
+            requested: requested.into_iter().collect(),
+            moved: HashSet::new(),
+            planned: HashSet::new(),
+        }
+    }
+
+    /// `target`, or the first numbered name that is free on disk and in the plan
+    pub fn unique_target(&mut self, target: PathBuf) -> Result<PathBuf, String> {
+        let planned = &self.planned;
+        let target = if target.exists() || planned.contains(&target) {
+            find_unique_name_with(&target, |p| p.exists() || planned.contains(p))?
+        } else {
+            target
+        };
+        self.planned.insert(target.clone());
+        Ok(target)
+    }
+
+    /// Plan moving `photo` to `target` (numbered if taken), followed by its
+    /// companions and `related` files (e.g. the other half of a RAW+JPEG pair),
+    /// renamed to the photo's new stem next to it
+    /// Empty when the photo was already taken along by another one
+    pub fn plan_group(
+        &mut self,
+        photo: &Path,
+        target: PathBuf,
+        related: &[PathBuf],
+    ) -> Result<Vec<(PathBuf, PathBuf)>, String> {
+        if !self.moved.insert(photo.to_path_buf()) {
+            return Ok(Vec::new());
+        }
+        let target = self.unique_target(target)?;
+        let stem = |path: &Path| {
+            path.file_stem()
+                .and_then(|s| s.to_str())
+                .unwrap_or_default()
+                .to_string()
+        };
+        let (old_stem, new_stem) = (stem(photo), stem(&target));
+        let folder = target.parent().unwrap_or(Path::new("")).to_path_buf();
+
+        let mut moves = vec![(photo.to_path_buf(), target)];
+        for companion in companions_of(photo)
+            .into_iter()
+            .chain(related.iter().cloned())
+        {
+            if !companion.is_file()
+                || self.requested.contains(&companion)
+                || !self.moved.insert(companion.clone())
+            {
+                continue;
+            }
+            let name = companion
+                .file_name()
+                .and_then(|n| n.to_str())
+                .unwrap_or_default();
+            let companion_target =
+                self.unique_target(folder.join(restem(name, &old_stem, &new_stem)))?;
+            moves.push((companion, companion_target));
+        }
+        Ok(moves)
+    }
+}
+
+/// Find a unique name for a file by appending a number
+pub fn find_unique_name(path: &Path) -> Result<PathBuf, String> {
+    find_unique_name_with(path, |p| p.exists())
+}
+
+/// Find a unique name using a custom check for whether a path is taken
+pub fn find_unique_name_with(
+    path: &Path,
+    is_taken: impl Fn(&Path) -> bool,
+) -> Result<PathBuf, String> {
+    let stem = path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .ok_or("Invalid file name")?;
+    let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+    let parent = path.parent().ok_or("Invalid path")?;
+
+    let mut counter = 1;
+    loop {
+        let new_name = if ext.is_empty() {
+            format!("{} ({})", stem, counter)
+        } else {
+            format!("{} ({}).{}", stem, counter, ext)
+        };
+
+        let new_path = parent.join(&new_name);
+        if !is_taken(&new_path) {
+            return Ok(new_path);
+        }
+        counter += 1;
+
+        if counter > 1000 {
+            return Err("Could not find unique name".to_string());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, SystemTime};
+    use tempfile::TempDir;
+
+    const CONTENTS: &[u8] = b"photo bytes";
+    const MODIFIED: u64 = 1_600_000_000;
+    const ACCESSED: u64 = 1_650_000_000;
+    const XATTR: &str = "user.photo-manager.test";
+
+    fn time(secs: u64) -> SystemTime {
+        SystemTime::UNIX_EPOCH + Duration::from_secs(secs)
+    }
+
+    /// Write a file with set times, permissions and, where the volume takes
+    /// one, an extended attribute; returns whether the attribute was set
+    fn write_original(path: &Path) -> bool {
+        fs::write(path, CONTENTS).unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(path, fs::Permissions::from_mode(0o640)).unwrap();
+        }
+        #[cfg(unix)]
+        let has_xattr = xattr::set(path, XATTR, b"kept").is_ok();
+        #[cfg(not(unix))]
+        let has_xattr = false;
+        // Set last: setting attributes and permissions changes ctime, and an
+        // access time older than ctime is what reads bump under relatime
+        let times = FileTimes::new()
+            .set_modified(time(MODIFIED))
+            .set_accessed(time(ACCESSED));
+        set_file_times(path, times).unwrap();
+        has_xattr
+    }
+
+    fn assert_metadata_kept(path: &Path, has_xattr: bool) {
+        let metadata = fs::metadata(path).unwrap();
+        assert_eq!(metadata.modified().unwrap(), time(MODIFIED));
+        assert_eq!(metadata.accessed().unwrap(), time(ACCESSED));
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(metadata.permissions().mode() & 0o777, 0o640);
+            if has_xattr {
+                let value = xattr::get(path, XATTR).unwrap();
+                assert_eq!(value.as_deref(), Some(&b"kept"[..]));
+            }
+        }
+        #[cfg(not(unix))]
+        let _ = has_xattr;
+        assert_eq!(fs::read(path).unwrap(), CONTENTS);
+    }
+
+    /// A folder on another volume than `dir`, when there is one to write to
+    #[cfg(unix)]
+    fn other_volume(dir: &Path) -> Option<TempDir> {
+        use std::os::unix::fs::MetadataExt;
+        let device = fs::metadata(dir).ok()?.dev();
+        ["/dev/shm", "/run/shm"]
+            .iter()
+            .filter(|candidate| fs::metadata(candidate).is_ok_and(|m| m.dev() != device))
+            .find_map(|candidate| tempfile::tempdir_in(candidate).ok())
+    }
+
+    #[cfg(not(unix))]
+    fn other_volume(_dir: &Path) -> Option<TempDir> {
+        None
+    }
+
+    #[test]
+    fn copy_keeps_times_permissions_and_xattrs() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("IMG_0001.JPG");
+        let target = dir.path().join("copy.JPG");
+        let has_xattr = write_original(&source);
+
+        copy_preserving_metadata(&source, &target).unwrap();
+
+        assert_metadata_kept(&target, has_xattr);
+        assert!(source.exists());
+    }
+
+    #[test]
+    fn cross_device_move_keeps_times_permissions_and_xattrs() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("IMG_0001.JPG");
+        let other = other_volume(dir.path());
+        let target_dir = other.as_ref().unwrap_or(&dir).path();
+        let target = target_dir.join("moved.JPG");
+        // Only expect the attribute where both volumes take one
+        let has_xattr = write_original(&source) && write_original(&target_dir.join("probe.JPG"));
+
+        let hash = match &other {
+            Some(_) => move_path(&source, &target, None).unwrap(),
+            // No second volume here: run the copy path a cross-device move takes
+            None => Some(copy_verify_delete(&source, &target, None).unwrap()),
+        };
+
+        assert!(hash.is_some(), "the move should have copied and verified");
+        assert!(!source.exists());
+        assert_metadata_kept(&target, has_xattr);
+    }
+}
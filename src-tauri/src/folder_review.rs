@@ -0,0 +1,150 @@
+use crate::catalog::Catalog;
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+/// Tracks which folders the user has already gone through, so a large backlog
+/// can be worked down folder by folder
+pub struct FolderReviews {
+    conn: Connection,
+}
+
+/// A folder that has not been reviewed yet, with what is waiting in it
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UnreviewedFolder {
+    pub path: String,
+    pub photo_count: usize,
+    pub total_size: u64,
+    /// Oldest photo in the folder (capture date, falling back to file date), ms since epoch
+    pub oldest_photo: i64,
+    /// Ranking score: size in GB weighted up by how many years the folder has waited
+    pub score: f64,
+}
+
+/// Create the review table; called when the catalog is opened
+pub fn create_tables(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS folder_reviews (
+            path TEXT PRIMARY KEY,
+            reviewed_at INTEGER NOT NULL,
+            inferred INTEGER NOT NULL DEFAULT 0
+        );",
+    )
+}
+
+impl FolderReviews {
+    /// Open the review table in the catalog database
+    pub fn open() -> Result<Self, String> {
+        let conn = Catalog::connection()?;
+        Ok(Self { conn })
+    }
+
+    /// Explicitly mark a folder as reviewed, or clear the mark
+    pub fn mark(&self, folder: &str, reviewed: bool) -> Result<(), String> {
+        let folder = folder.trim_end_matches('/');
+        if reviewed {
+            self.conn.execute(
+                "INSERT OR REPLACE INTO folder_reviews (path, reviewed_at, inferred) VALUES (?1, ?2, 0)",
+                params![folder, chrono::Utc::now().timestamp_millis()],
+            )
+        } else {
+            self.conn
+                .execute("DELETE FROM folder_reviews WHERE path = ?1", params![folder])
+        }
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+    }
+
+    /// Infer that the folders of files the user just moved or trashed were reviewed
+    /// An explicit mark is kept as explicit; only its timestamp is refreshed
+    pub fn record_activity<'a>(&self, files: impl IntoIterator<Item = &'a str>) {
+        let folders: HashSet<String> = files
+            .into_iter()
+            .filter_map(|f| Path::new(f).parent())
+            .map(|p| p.to_string_lossy().to_string())
+            .collect();
+        let now = chrono::Utc::now().timestamp_millis();
+        for folder in folders {
+            let _ = self.conn.execute(
+                "INSERT INTO folder_reviews (path, reviewed_at, inferred) VALUES (?1, ?2, 1)
+                ON CONFLICT(path) DO UPDATE SET reviewed_at = excluded.reviewed_at",
+                params![folder, now],
+            );
+        }
+    }
+
+    /// Catalog folders never reviewed, largest and longest-waiting first
+    pub fn unreviewed(&self, limit: usize) -> Result<Vec<UnreviewedFolder>, String> {
+        // One read snapshot for both tables, so a scan landing in between can't skew it
+        let _snapshot = self
+            .conn
+            .unchecked_transaction()
+            .map_err(|e| e.to_string())?;
+        let reviewed: HashSet<String> = self
+            .conn
+            .prepare("SELECT path FROM folder_reviews")
+            .and_then(|mut stmt| {
+                stmt.query_map([], |row| row.get(0))?
+                    .collect::<Result<HashSet<_>, _>>()
+            })
+            .map_err(|e| e.to_string())?;
+
+        let mut stmt = self
+            .conn
+            .prepare("SELECT path, size, COALESCE(capture_date, modified_at) FROM photos")
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, i64>(1)? as u64,
+                    row.get::<_, i64>(2)?,
+                ))
+            })
+            .map_err(|e| e.to_string())?;
+
+        let mut folders: HashMap<String, UnreviewedFolder> = HashMap::new();
+        for row in rows {
+            let (path, size, timestamp) = row.map_err(|e| e.to_string())?;
+            let Some(folder) = Path::new(&path).parent() else {
+                continue;
+            };
+            let folder = folder.to_string_lossy().to_string();
+            if reviewed.contains(&folder) {
+                continue;
+            }
+            let entry = folders
+                .entry(folder.clone())
+                .or_insert_with(|| UnreviewedFolder {
+                    path: folder,
+                    photo_count: 0,
+                    total_size: 0,
+                    oldest_photo: i64::MAX,
+                    score: 0.0,
+                });
+            entry.photo_count += 1;
+            entry.total_size += size;
+            if timestamp > 0 {
+                entry.oldest_photo = entry.oldest_photo.min(timestamp);
+            }
+        }
+
+        let now = chrono::Utc::now().timestamp_millis();
+        let mut result: Vec<UnreviewedFolder> = folders
+            .into_values()
+            .map(|mut folder| {
+                if folder.oldest_photo == i64::MAX {
+                    folder.oldest_photo = now;
+                }
+                let age_years = (now - folder.oldest_photo).max(0) as f64 / 31_557_600_000.0;
+                folder.score = folder.total_size as f64 / 1e9 * (1.0 + age_years);
+                folder
+            })
+            .collect();
+        result.sort_by(|a, b| b.score.total_cmp(&a.score));
+        result.truncate(limit);
+        Ok(result)
+    }
+}
@@ -0,0 +1,190 @@
+use crate::catalog::Catalog;
+use crate::exclusions::Exclusions;
+use crate::hash_cache::HashCache;
+use crate::scanner::{compute_full_hash, is_media_extension};
+use rayon::prelude::*;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Why a file on the card can't be accounted for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum StragglerReason {
+    /// No file in the library has the same content
+    NotInLibrary,
+    /// The card file, or the folder holding it, could not be read
+    Unreadable,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Straggler {
+    pub path: String,
+    pub size: u64,
+    pub reason: StragglerReason,
+}
+
+/// Result of checking a memory card against the library before formatting it
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FormatCheck {
+    pub volume: String,
+    /// Photos and videos found on the card
+    pub file_count: usize,
+    /// Card files with a byte-identical copy in the library
+    pub confirmed_count: usize,
+    /// Other files on the card (camera databases, thumbnails, ...), not checked
+    pub skipped_count: usize,
+    /// True only when every photo and video on the card is confirmed
+    pub safe: bool,
+    pub stragglers: Vec<Straggler>,
+}
+
+/// Check every photo and video on a volume against the library by content hash
+/// Card files are always hashed from the card; library copies are matched by
+/// size first and hashed through the cache, which is re-checked against the file
+/// Library files stored on the volume itself never count as copies
+pub fn check_volume(volume: &Path, cache: &HashCache) -> Result<FormatCheck, String> {
+    if !volume.is_dir() {
+        return Err(format!("{} is not a mounted volume", volume.display()));
+    }
+
+    let CardFiles {
+        media: card_files,
+        skipped_count,
+        unreadable,
+    } = card_media_files(volume);
+    let sizes: HashSet<u64> = card_files.iter().map(|(_, size)| *size).collect();
+    let candidates = library_files_by_size(volume, &sizes)?;
+
+    let mut stragglers: Vec<Straggler> = card_files
+        .par_iter()
+        .filter_map(|(path, size)| {
+            let path_str = path.to_string_lossy().to_string();
+            let straggler = |reason| Straggler {
+                path: path_str.clone(),
+                size: *size,
+                reason,
+            };
+            let Some(hash) = compute_full_hash(&path_str, cache.algorithm()) else {
+                return Some(straggler(StragglerReason::Unreadable));
+            };
+            let imported = has_copy(&candidates, *size, &hash, cache);
+            (!imported).then(|| straggler(StragglerReason::NotInLibrary))
+        })
+        .collect();
+    let confirmed_count = card_files.len() - stragglers.len();
+    // Whatever sits in a folder that can't be listed may not be in the library
+    stragglers.extend(unreadable.into_iter().map(|path| Straggler {
+        path: path.to_string_lossy().to_string(),
+        size: 0,
+        reason: StragglerReason::Unreadable,
+    }));
+
+    Ok(FormatCheck {
+        volume: volume.to_string_lossy().to_string(),
+        file_count: card_files.len(),
+        confirmed_count,
+        skipped_count,
+        safe: stragglers.is_empty(),
+        stragglers,
+    })
+}
+
+/// What walking a card found
+pub struct CardFiles {
+    /// Photos and videos with their sizes, sorted by path
+    pub media: Vec<(PathBuf, u64)>,
+    /// Other files (camera databases, thumbnails, ...)
+    pub skipped_count: usize,
+    /// Folders and files the walk couldn't read, so what they hold is unknown
+    pub unreadable: Vec<PathBuf>,
+}
+
+/// Photos and videos on a card, the number of other files and whatever
+/// couldn't be read
+pub fn card_media_files(volume: &Path) -> CardFiles {
+    let mut files = CardFiles {
+        media: Vec::new(),
+        skipped_count: 0,
+        unreadable: Vec::new(),
+    };
+    let exclusions = Exclusions::default();
+    for entry in WalkDir::new(volume)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|e| !exclusions.excludes(volume, e))
+    {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                let path = e.path().unwrap_or(volume).to_path_buf();
+                files.unreadable.push(path);
+                continue;
+            }
+        };
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let ext = entry
+            .path()
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_lowercase())
+            .unwrap_or_default();
+        if is_media_extension(&ext) {
+            let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+            files.media.push((entry.into_path(), size));
+        } else {
+            files.skipped_count += 1;
+        }
+    }
+    files
+}
+
+/// Whether one of the size-matched library files has this content
+pub fn has_copy(
+    candidates: &HashMap<u64, Vec<String>>,
+    size: u64,
+    hash: &str,
+    cache: &HashCache,
+) -> bool {
+    candidates.get(&size).is_some_and(|copies| {
+        copies
+            .iter()
+            .any(|copy| cache.full_hash(copy, size).as_deref() == Some(hash))
+    })
+}
+
+/// Cataloged files (photos and their related files) off the volume whose size
+/// matches one of `sizes`
+pub fn library_files_by_size(
+    volume: &Path,
+    sizes: &HashSet<u64>,
+) -> Result<HashMap<u64, Vec<String>>, String> {
+    let mut by_size: HashMap<u64, Vec<String>> = HashMap::new();
+    for photo in Catalog::open()?.all_photos()? {
+        // Reading a placeholder's content would download it; it isn't a local copy yet
+        if photo.is_cloud_placeholder || Path::new(&photo.path).starts_with(volume) {
+            continue;
+        }
+        if sizes.contains(&photo.size) {
+            by_size.entry(photo.size).or_default().push(photo.path);
+        }
+        for related in photo.related_files {
+            let Ok(metadata) = fs::metadata(&related.path) else {
+                continue;
+            };
+            if sizes.contains(&metadata.len()) {
+                by_size
+                    .entry(metadata.len())
+                    .or_default()
+                    .push(related.path);
+            }
+        }
+    }
+    Ok(by_size)
+}
@@ -0,0 +1,48 @@
+use reverse_geocoder::ReverseGeocoder;
+use std::sync::OnceLock;
+
+/// Farthest a photo can be from the nearest town and still be named after it
+const MAX_DISTANCE_KM: f64 = 100.0;
+
+const EARTH_RADIUS_KM: f64 = 6371.0;
+
+/// Towns and cities from GeoNames, bundled with the app; loaded on first use
+static GEOCODER: OnceLock<ReverseGeocoder> = OnceLock::new();
+
+/// Nearest town for a position, as "Town, Region, CC"; `None` for invalid
+/// coordinates and for places far from any town (at sea, in the wilderness)
+pub fn place_name(latitude: f64, longitude: f64) -> Option<String> {
+    if !is_valid(latitude, longitude) {
+        return None;
+    }
+    let result = GEOCODER
+        .get_or_init(ReverseGeocoder::new)
+        .search((latitude, longitude));
+    // The search measures squared straight-line distance on a unit sphere
+    if result.distance.sqrt() * EARTH_RADIUS_KM > MAX_DISTANCE_KM {
+        return None;
+    }
+    let record = result.record;
+    let parts: Vec<&str> = [&record.name, &record.admin1, &record.cc]
+        .into_iter()
+        .map(|part| part.trim())
+        .filter(|part| !part.is_empty())
+        .collect();
+    Some(parts.join(", ")).filter(|name| !name.is_empty())
+}
+
+/// Great-circle distance between two (latitude, longitude) positions
+pub fn distance_km(a: (f64, f64), b: (f64, f64)) -> f64 {
+    let (lat1, lat2) = (a.0.to_radians(), b.0.to_radians());
+    let d_lat = lat2 - lat1;
+    let d_lon = (b.1 - a.1).to_radians();
+    let h = (d_lat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (d_lon / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS_KM * h.sqrt().min(1.0).asin()
+}
+
+/// In range and not 0,0, which cameras and services write for "no fix"
+pub fn is_valid(latitude: f64, longitude: f64) -> bool {
+    (-90.0..=90.0).contains(&latitude)
+        && (-180.0..=180.0).contains(&longitude)
+        && (latitude != 0.0 || longitude != 0.0)
+}
@@ -0,0 +1,93 @@
+use crate::geocode;
+use crate::metadata::{update_exif, writable_exif_type};
+use crate::xmp;
+use little_exif::exif_tag::ExifTag;
+use little_exif::metadata::Metadata;
+use little_exif::rational::uR64;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// A GPS position in decimal degrees, north and east positive
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Coordinates {
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+/// Where a position was written
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GeotagTarget {
+    /// The file's own EXIF block
+    Exif,
+    /// The XMP sidecar next to the file
+    Sidecar,
+}
+
+/// Write a position into the file's EXIF where that can be done safely
+/// (JPEG, PNG, WebP, TIFF), and into its XMP sidecar otherwise, so RAW,
+/// HEIC and video files are never rewritten; `None` removes the position
+pub fn write_position(path: &Path, position: Option<Coordinates>) -> Result<GeotagTarget, String> {
+    if let Some(p) = position {
+        if !geocode::is_valid(p.latitude, p.longitude) {
+            return Err(format!("Invalid position {}, {}", p.latitude, p.longitude));
+        }
+    }
+    if !path.is_file() {
+        return Err("The file is gone".to_string());
+    }
+    match writable_exif_type(path) {
+        Some(file_type) => {
+            update_exif(path, file_type, |metadata| set_gps_tags(metadata, position))?;
+            Ok(GeotagTarget::Exif)
+        }
+        None => {
+            xmp::write_gps(path, position.map(|p| (p.latitude, p.longitude)))?;
+            Ok(GeotagTarget::Sidecar)
+        }
+    }
+}
+
+/// Replace the GPS tags with `position`, or remove them
+fn set_gps_tags(metadata: &mut Metadata, position: Option<Coordinates>) {
+    for tag in [
+        ExifTag::GPSVersionID(Vec::new()),
+        ExifTag::GPSLatitudeRef(String::new()),
+        ExifTag::GPSLatitude(Vec::new()),
+        ExifTag::GPSLongitudeRef(String::new()),
+        ExifTag::GPSLongitude(Vec::new()),
+    ] {
+        metadata.remove_tag(tag);
+    }
+    if let Some(p) = position {
+        let latitude_ref = if p.latitude < 0.0 { "S" } else { "N" };
+        let longitude_ref = if p.longitude < 0.0 { "W" } else { "E" };
+        metadata.set_tag(ExifTag::GPSVersionID(vec![2, 3, 0, 0]));
+        metadata.set_tag(ExifTag::GPSLatitudeRef(latitude_ref.to_string()));
+        metadata.set_tag(ExifTag::GPSLatitude(degrees_minutes_seconds(p.latitude)));
+        metadata.set_tag(ExifTag::GPSLongitudeRef(longitude_ref.to_string()));
+        metadata.set_tag(ExifTag::GPSLongitude(degrees_minutes_seconds(p.longitude)));
+    }
+}
+
+/// Unsigned degrees, minutes and hundredths of seconds, as EXIF stores them
+fn degrees_minutes_seconds(value: f64) -> Vec<uR64> {
+    let value = value.abs();
+    let degrees = value.trunc();
+    let minutes = ((value - degrees) * 60.0).trunc();
+    let seconds = (value - degrees - minutes / 60.0) * 3600.0;
+    vec![
+        uR64 {
+            nominator: degrees as u32,
+            denominator: 1,
+        },
+        uR64 {
+            nominator: minutes as u32,
+            denominator: 1,
+        },
+        uR64 {
+            nominator: (seconds * 100.0).round() as u32,
+            denominator: 100,
+        },
+    ]
+}
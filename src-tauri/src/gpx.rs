@@ -0,0 +1,174 @@
+use crate::geocode;
+use crate::geotag::Coordinates;
+use chrono::DateTime;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+
+/// How far apart two track points may be, in seconds, for a photo taken
+/// between them to be placed on the line joining them; also how far a photo
+/// may be from the nearest point when the track has a hole or ends
+pub const DEFAULT_MAX_GAP_SECONDS: i64 = 600;
+
+/// How `correlate_gpx` lines photos up with the track
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct GpxOptions {
+    /// Seconds added to each capture time before it is looked up, for a
+    /// camera clock that was wrong or set to another time zone
+    pub time_offset: i64,
+    /// Seconds; `DEFAULT_MAX_GAP_SECONDS` when unset
+    pub max_gap: Option<i64>,
+    /// Replace positions photos already have
+    pub overwrite: bool,
+}
+
+/// A recorded position
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TrackPoint {
+    /// Seconds since the Unix epoch (GPX times are UTC)
+    pub time: i64,
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+/// Every timed point of a GPX file, in time order
+#[derive(Debug, Clone, Default)]
+pub struct Track {
+    pub points: Vec<TrackPoint>,
+}
+
+impl Track {
+    /// Read track and route points with a time; waypoints are skipped since
+    /// they mark places rather than where the recorder was
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let xml = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let track = Self::parse(&xml).map_err(|e| format!("Invalid GPX file: {}", e))?;
+        if track.points.is_empty() {
+            return Err("The GPX file has no timed track points".to_string());
+        }
+        Ok(track)
+    }
+
+    pub fn parse(xml: &str) -> Result<Self, quick_xml::Error> {
+        let mut reader = Reader::from_str(xml);
+        let mut points = Vec::new();
+        // Position of the point being read, and its <time> text so far
+        let mut current: Option<(f64, f64)> = None;
+        let mut time: Option<String> = None;
+        let mut in_time = false;
+
+        loop {
+            match reader.read_event()? {
+                Event::Start(ref e) => match e.local_name().as_ref() {
+                    b"trkpt" | b"rtept" => {
+                        current = position(e)?;
+                        time = None;
+                    }
+                    b"time" if current.is_some() => {
+                        time = Some(String::new());
+                        in_time = true;
+                    }
+                    _ => {}
+                },
+                Event::Text(e) if in_time => {
+                    if let Some(time) = time.as_mut() {
+                        time.push_str(&e.decode()?);
+                    }
+                }
+                Event::End(ref e) => match e.local_name().as_ref() {
+                    b"trkpt" | b"rtept" => {
+                        let parsed = time
+                            .take()
+                            .and_then(|t| DateTime::parse_from_rfc3339(t.trim()).ok());
+                        if let (Some((latitude, longitude)), Some(t)) = (current.take(), parsed) {
+                            points.push(TrackPoint {
+                                time: t.timestamp(),
+                                latitude,
+                                longitude,
+                            });
+                        }
+                    }
+                    b"time" => in_time = false,
+                    _ => {}
+                },
+                Event::Eof => break,
+                _ => {}
+            }
+        }
+
+        points.sort_by_key(|p| p.time);
+        Ok(Self { points })
+    }
+
+    /// Where the recorder was at `time`: interpolated between the points
+    /// either side when they are close enough together, otherwise the nearest
+    /// point if it is within `max_gap` seconds
+    pub fn position_at(&self, time: i64, max_gap: i64) -> Option<Coordinates> {
+        let after = self.points.partition_point(|p| p.time < time);
+        let next = self.points.get(after);
+        let previous = after.checked_sub(1).and_then(|i| self.points.get(i));
+
+        if let (Some(a), Some(b)) = (previous, next) {
+            if b.time - a.time <= max_gap {
+                let fraction = if b.time == a.time {
+                    0.0
+                } else {
+                    (time - a.time) as f64 / (b.time - a.time) as f64
+                };
+                return Some(Coordinates {
+                    latitude: a.latitude + (b.latitude - a.latitude) * fraction,
+                    longitude: interpolate_longitude(a.longitude, b.longitude, fraction),
+                });
+            }
+        }
+        [previous, next]
+            .into_iter()
+            .flatten()
+            .filter(|p| (p.time - time).abs() <= max_gap)
+            .min_by_key(|p| (p.time - time).abs())
+            .map(|p| Coordinates {
+                latitude: p.latitude,
+                longitude: p.longitude,
+            })
+    }
+}
+
+/// `lat` and `lon` attributes of a point, if both are valid
+fn position(element: &BytesStart) -> Result<Option<(f64, f64)>, quick_xml::Error> {
+    let mut latitude = None;
+    let mut longitude = None;
+    for attribute in element.attributes().with_checks(false) {
+        let attribute = attribute.map_err(quick_xml::Error::from)?;
+        let value = attribute.unescape_value()?;
+        match attribute.key.local_name().as_ref() {
+            b"lat" => latitude = value.trim().parse::<f64>().ok(),
+            b"lon" => longitude = value.trim().parse::<f64>().ok(),
+            _ => {}
+        }
+    }
+    Ok(latitude
+        .zip(longitude)
+        .filter(|&(lat, lon)| geocode::is_valid(lat, lon)))
+}
+
+/// Move along the shorter way round, so a track crossing the antimeridian
+/// doesn't swing across the whole globe
+fn interpolate_longitude(from: f64, to: f64, fraction: f64) -> f64 {
+    let mut delta = to - from;
+    if delta > 180.0 {
+        delta -= 360.0;
+    } else if delta < -180.0 {
+        delta += 360.0;
+    }
+    let longitude = from + delta * fraction;
+    if longitude > 180.0 {
+        longitude - 360.0
+    } else if longitude < -180.0 {
+        longitude + 360.0
+    } else {
+        longitude
+    }
+}
@@ -1,22 +1,113 @@
+use crate::config::AppConfig;
+use crate::pixel_hash::compute_pixel_hash;
+use crate::scanner::compute_full_hash;
 use rusqlite::{Connection, params};
-use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+
+/// Hash algorithm used for trailing and full content hashes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashAlgorithm {
+    Sha256,
+    /// Much faster on large RAW files; uses memory-mapped reads
+    Blake3,
+}
+
+impl HashAlgorithm {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            HashAlgorithm::Sha256 => "sha256",
+            HashAlgorithm::Blake3 => "blake3",
+        }
+    }
+
+    /// Parse a config value, defaulting to SHA-256 for unknown names
+    pub fn from_name(name: &str) -> Self {
+        match name.to_lowercase().as_str() {
+            "blake3" => HashAlgorithm::Blake3,
+            _ => HashAlgorithm::Sha256,
+        }
+    }
+
+    /// Algorithm selected in the app config
+    pub fn configured() -> Self {
+        Self::from_name(&AppConfig::load().hash_algorithm)
+    }
+}
 
 /// Cached file info - size and hashes
 pub struct CachedFileInfo {
     pub size: u64,
     pub trailing_hash: Option<String>,
     pub full_hash: Option<String>,
+    /// File modification time when the entry was stored (None for old entries)
+    pub modified_at: Option<i64>,
+}
+
+/// Which stored hashes `verify_fixity` checks
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct FixityOptions {
+    /// Check at most this many files, the ones verified longest ago first;
+    /// every file when unset
+    pub sample: Option<usize>,
+    /// Leave out files verified within this many days
+    pub skip_verified_within_days: Option<u32>,
+}
+
+/// A stored full hash due for a fixity check
+pub struct FixityEntry {
+    pub path: String,
+    pub size: u64,
+    pub full_hash: String,
+    pub modified_at: Option<i64>,
+}
+
+impl FixityEntry {
+    /// Whether the file still has the size and modification time it had when
+    /// it was hashed; content that changed anyway points at bit rot or a bad copy
+    pub fn matches_metadata(&self, metadata: &fs::Metadata) -> bool {
+        self.size == metadata.len()
+            && (self.modified_at.is_none() || self.modified_at == modified_millis(metadata))
+    }
+}
+
+/// Size and contents of the hash cache database
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheStats {
+    pub entry_count: usize,
+    pub trailing_hash_count: usize,
+    pub full_hash_count: usize,
+    /// Entries whose hash was computed with another algorithm (unused until switched back)
+    pub other_algorithm_count: usize,
+    /// Entries whose content was confirmed by `verify_fixity`
+    pub verified_count: usize,
+    /// Size of the database file on disk
+    pub file_size: u64,
 }
 
 /// Cache for file metadata and hashes stored in SQLite
-/// Uses path as the only key since files are immutable
+/// Keyed by path; each row also records the file's size and modification time
+/// so `verify_cache_entry` can drop entries for files edited or replaced since
+/// Hashes are only returned when they were computed with the cache's algorithm
+/// Safe to share between threads (rayon workers write results as they finish);
+/// statements are serialized on one connection
 pub struct HashCache {
-    conn: Connection,
+    conn: Mutex<Connection>,
+    algorithm: HashAlgorithm,
 }
 
 impl HashCache {
-    /// Open or create the hash cache database
+    /// Open or create the hash cache database using the configured hash algorithm
     pub fn open() -> Result<Self, String> {
+        Self::open_with_algorithm(HashAlgorithm::configured())
+    }
+
+    /// Open or create the hash cache database for a specific hash algorithm
+    pub fn open_with_algorithm(algorithm: HashAlgorithm) -> Result<Self, String> {
         let db_path = Self::db_path();
         
         // Ensure parent directory exists
@@ -26,14 +117,24 @@ impl HashCache {
 
         let conn = Connection::open(&db_path).map_err(|e| e.to_string())?;
         
+        // WAL lets other connections (commands running during a scan) read while
+        // the scanner writes
+        conn.execute_batch(
+            "PRAGMA journal_mode = WAL;
+            PRAGMA synchronous = NORMAL;
+            PRAGMA busy_timeout = 5000;",
+        )
+        .map_err(|e| e.to_string())?;
+
         // Create tables if they don't exist
-        // Note: We key by path only since files are immutable
         conn.execute_batch(
             "CREATE TABLE IF NOT EXISTS file_hashes (
                 path TEXT PRIMARY KEY,
                 size INTEGER NOT NULL,
                 trailing_hash TEXT,
-                full_hash TEXT
+                full_hash TEXT,
+                hash_algorithm TEXT NOT NULL DEFAULT 'sha256',
+                modified_at INTEGER
             );
             CREATE INDEX IF NOT EXISTS idx_size ON file_hashes(size);
             CREATE INDEX IF NOT EXISTS idx_trailing_hash ON file_hashes(trailing_hash);
@@ -41,7 +142,56 @@ impl HashCache {
             "
         ).map_err(|e| e.to_string())?;
 
-        Ok(Self { conn })
+        // Migrate databases created before the algorithm column existed
+        if conn.prepare("SELECT hash_algorithm FROM file_hashes LIMIT 0").is_err() {
+            conn.execute(
+                "ALTER TABLE file_hashes ADD COLUMN hash_algorithm TEXT NOT NULL DEFAULT 'sha256'",
+                [],
+            ).map_err(|e| e.to_string())?;
+        }
+
+        // Rows from before modification times were stored have NULL and are
+        // trusted on size alone until they are next verified
+        if conn
+            .prepare("SELECT modified_at FROM file_hashes LIMIT 0")
+            .is_err()
+        {
+            conn.execute("ALTER TABLE file_hashes ADD COLUMN modified_at INTEGER", [])
+                .map_err(|e| e.to_string())?;
+        }
+
+        // Hash of the decoded pixels, for the pixels hash tier
+        if conn
+            .prepare("SELECT pixel_hash FROM file_hashes LIMIT 0")
+            .is_err()
+        {
+            conn.execute("ALTER TABLE file_hashes ADD COLUMN pixel_hash TEXT", [])
+                .map_err(|e| e.to_string())?;
+        }
+
+        // When `verify_fixity` last found the content matching the full hash
+        if conn
+            .prepare("SELECT verified_at FROM file_hashes LIMIT 0")
+            .is_err()
+        {
+            conn.execute("ALTER TABLE file_hashes ADD COLUMN verified_at INTEGER", [])
+                .map_err(|e| e.to_string())?;
+        }
+
+        Ok(Self {
+            conn: Mutex::new(conn),
+            algorithm,
+        })
+    }
+
+    /// The connection, recovering it if a worker panicked while holding the lock
+    fn conn(&self) -> MutexGuard<'_, Connection> {
+        self.conn.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Algorithm used for hashes read from and written to this cache
+    pub fn algorithm(&self) -> HashAlgorithm {
+        self.algorithm
     }
 
     fn db_path() -> PathBuf {
@@ -51,60 +201,335 @@ impl HashCache {
             .join("hash_cache.db")
     }
 
-    /// Get cached info for a file by path only (files are immutable)
+    /// Get cached info for a file by path without checking it is still current
+    /// Prefer `verify_cache_entry` unless the entry was verified earlier
     pub fn get(&self, path: &str) -> Option<CachedFileInfo> {
-        self.conn.query_row(
-            "SELECT size, trailing_hash, full_hash FROM file_hashes WHERE path = ?1",
+        self.conn().query_row(
+            "SELECT size, trailing_hash, full_hash, hash_algorithm, modified_at FROM file_hashes WHERE path = ?1",
             params![path],
             |row| {
+                // Hashes from a different algorithm are useless for comparison
+                let same_algorithm = row.get::<_, String>(3)? == self.algorithm.as_str();
                 Ok(CachedFileInfo {
                     size: row.get::<_, i64>(0)? as u64,
-                    trailing_hash: row.get(1)?,
-                    full_hash: row.get(2)?,
+                    trailing_hash: if same_algorithm { row.get(1)? } else { None },
+                    full_hash: if same_algorithm { row.get(2)? } else { None },
+                    modified_at: row.get(4)?,
                 })
             }
         ).ok()
     }
 
+    /// Cached info for a file if the entry still matches the file's size and
+    /// modification time; a stale entry is deleted and None returned
+    pub fn verify_cache_entry(
+        &self,
+        path: &str,
+        metadata: &fs::Metadata,
+    ) -> Option<CachedFileInfo> {
+        let info = self.get(path)?;
+        let modified_at = modified_millis(metadata);
+
+        let size_matches = info.size == metadata.len();
+        let time_matches = match (info.modified_at, modified_at) {
+            (Some(stored), Some(current)) => stored == current,
+            // Legacy row: trust the size and record the time for next time
+            (None, _) => {
+                if size_matches {
+                    let _ = self.conn().execute(
+                        "UPDATE file_hashes SET modified_at = ?2 WHERE path = ?1",
+                        params![path, modified_at],
+                    );
+                }
+                true
+            }
+            (Some(_), None) => false,
+        };
+
+        if size_matches && time_matches {
+            Some(info)
+        } else {
+            self.invalidate(path);
+            None
+        }
+    }
+
+    /// Forget everything cached for a file
+    pub fn invalidate(&self, path: &str) {
+        let _ = self
+            .conn()
+            .execute("DELETE FROM file_hashes WHERE path = ?1", params![path]);
+    }
+
+    /// Follow a file moved within a volume: the content and modification time
+    /// are unchanged, so its hashes stay valid under the new path
+    pub fn relocate(&self, from: &str, to: &str) {
+        let conn = self.conn();
+        let _ = conn.execute("DELETE FROM file_hashes WHERE path = ?1", params![to]);
+        let _ = conn.execute(
+            "UPDATE file_hashes SET path = ?2 WHERE path = ?1",
+            params![from, to],
+        );
+    }
+
     /// Store size only (during analyze phase, no hashing yet)
     pub fn set_size(&self, path: &str, size: u64) {
-        let _ = self.conn.execute(
-            "INSERT OR IGNORE INTO file_hashes (path, size) VALUES (?1, ?2)",
-            params![path, size as i64],
+        let _ = self.conn().execute(
+            "INSERT OR IGNORE INTO file_hashes (path, size, hash_algorithm, modified_at) VALUES (?1, ?2, ?3, ?4)",
+            params![path, size as i64, self.algorithm.as_str(), file_modified_millis(path)],
         );
     }
 
     /// Store trailing hash, also stores/updates size
     pub fn set_trailing_hash(&self, path: &str, size: u64, trailing_hash: &str) {
-        // First try to get existing full_hash if any
-        let existing_full: Option<String> = self.conn.query_row(
-            "SELECT full_hash FROM file_hashes WHERE path = ?1",
-            params![path],
-            |row| row.get(0)
-        ).ok().flatten();
+        // First try to get existing full_hash if any (same algorithm only)
+        let conn = self.conn();
+        let existing_full: Option<String> = conn
+            .query_row(
+                "SELECT full_hash FROM file_hashes WHERE path = ?1 AND hash_algorithm = ?2",
+                params![path, self.algorithm.as_str()],
+                |row| row.get(0),
+            )
+            .ok()
+            .flatten();
 
         // Insert or replace with all current values
-        let _ = self.conn.execute(
-            "INSERT OR REPLACE INTO file_hashes (path, size, trailing_hash, full_hash) 
-             VALUES (?1, ?2, ?3, ?4)",
-            params![path, size as i64, trailing_hash, existing_full],
+        let _ = conn.execute(
+            "INSERT OR REPLACE INTO file_hashes (path, size, trailing_hash, full_hash, hash_algorithm, modified_at) 
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![path, size as i64, trailing_hash, existing_full, self.algorithm.as_str(), file_modified_millis(path)],
         );
     }
 
     /// Store full hash, also stores/updates size
     pub fn set_full_hash(&self, path: &str, size: u64, full_hash: &str) {
-        // First try to get existing trailing_hash if any
-        let existing_trailing: Option<String> = self.conn.query_row(
-            "SELECT trailing_hash FROM file_hashes WHERE path = ?1",
-            params![path],
-            |row| row.get(0)
-        ).ok().flatten();
+        // First try to get existing trailing_hash if any (same algorithm only)
+        let conn = self.conn();
+        let existing_trailing: Option<String> = conn
+            .query_row(
+                "SELECT trailing_hash FROM file_hashes WHERE path = ?1 AND hash_algorithm = ?2",
+                params![path, self.algorithm.as_str()],
+                |row| row.get(0),
+            )
+            .ok()
+            .flatten();
 
         // Insert or replace with all current values
-        let _ = self.conn.execute(
-            "INSERT OR REPLACE INTO file_hashes (path, size, trailing_hash, full_hash) 
-             VALUES (?1, ?2, ?3, ?4)",
-            params![path, size as i64, existing_trailing, full_hash],
+        let _ = conn.execute(
+            "INSERT OR REPLACE INTO file_hashes (path, size, trailing_hash, full_hash, hash_algorithm, modified_at) 
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![path, size as i64, existing_trailing, full_hash, self.algorithm.as_str(), file_modified_millis(path)],
+        );
+    }
+
+    /// Full hash of a file, from the cache when the entry is still current,
+    /// otherwise computed and stored
+    pub fn full_hash(&self, path: &str, size: u64) -> Option<String> {
+        if let Some(hash) = self.cached_full_hash(path, size) {
+            return Some(hash);
+        }
+        let hash = compute_full_hash(path, self.algorithm)?;
+        self.set_full_hash(path, size, &hash);
+        Some(hash)
+    }
+
+    /// Full hash of a file if the cache has a current one; never reads the file
+    pub fn cached_full_hash(&self, path: &str, size: u64) -> Option<String> {
+        fs::metadata(path)
+            .ok()
+            .and_then(|m| self.verify_cache_entry(path, &m))
+            .filter(|info| info.size == size)
+            .and_then(|info| info.full_hash)
+    }
+
+    /// Hash of a still image's decoded pixels, from the cache when the entry is
+    /// still current, otherwise computed and stored; None if it can't be decoded
+    pub fn pixel_hash(&self, path: &str) -> Option<String> {
+        let metadata = fs::metadata(path).ok()?;
+        if self.verify_cache_entry(path, &metadata).is_some() {
+            let cached: Option<String> = self
+                .conn()
+                .query_row(
+                    "SELECT pixel_hash FROM file_hashes WHERE path = ?1 AND hash_algorithm = ?2",
+                    params![path, self.algorithm.as_str()],
+                    |row| row.get(0),
+                )
+                .ok()
+                .flatten();
+            if cached.is_some() {
+                return cached;
+            }
+        }
+
+        let hash = compute_pixel_hash(Path::new(path), self.algorithm)?;
+        let conn = self.conn();
+        let _ = conn.execute(
+            "INSERT OR IGNORE INTO file_hashes (path, size, hash_algorithm, modified_at)
+            VALUES (?1, ?2, ?3, ?4)",
+            params![
+                path,
+                metadata.len() as i64,
+                self.algorithm.as_str(),
+                modified_millis(&metadata)
+            ],
+        );
+        let _ = conn.execute(
+            "UPDATE file_hashes SET pixel_hash = ?2 WHERE path = ?1 AND hash_algorithm = ?3",
+            params![path, hash, self.algorithm.as_str()],
+        );
+        Some(hash)
+    }
+
+    /// Files with a full hash from this cache's algorithm that are due for a
+    /// fixity check: never verified first, then the ones verified longest ago
+    pub fn fixity_due(&self, options: &FixityOptions) -> Result<Vec<FixityEntry>, String> {
+        let cutoff = match options.skip_verified_within_days {
+            Some(days) => chrono::Utc::now().timestamp_millis() - days as i64 * 86_400_000,
+            None => i64::MAX,
+        };
+        // A negative LIMIT means no limit
+        let limit = options.sample.map_or(-1, |n| n as i64);
+
+        let conn = self.conn();
+        let mut stmt = conn
+            .prepare(
+                "SELECT path, size, full_hash, modified_at FROM file_hashes
+                WHERE full_hash IS NOT NULL AND hash_algorithm = ?1
+                    AND (verified_at IS NULL OR verified_at < ?2)
+                ORDER BY verified_at IS NOT NULL, verified_at, path
+                LIMIT ?3",
+            )
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map(params![self.algorithm.as_str(), cutoff, limit], |row| {
+                Ok(FixityEntry {
+                    path: row.get(0)?,
+                    size: row.get::<_, i64>(1)? as u64,
+                    full_hash: row.get(2)?,
+                    modified_at: row.get(3)?,
+                })
+            })
+            .map_err(|e| e.to_string())?;
+        rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+    }
+
+    /// Record that a file's content still matched its full hash
+    pub fn set_verified(&self, path: &str, verified_at: i64) {
+        let _ = self.conn().execute(
+            "UPDATE file_hashes SET verified_at = ?2 WHERE path = ?1",
+            params![path, verified_at],
         );
     }
+
+    /// Entry counts and database size
+    pub fn stats(&self) -> Result<CacheStats, String> {
+        self.conn()
+            .query_row(
+                "SELECT COUNT(*),
+                    COUNT(trailing_hash) FILTER (WHERE hash_algorithm = ?1),
+                    COUNT(full_hash) FILTER (WHERE hash_algorithm = ?1),
+                    COUNT(*) FILTER (WHERE hash_algorithm != ?1),
+                    COUNT(verified_at) FILTER (WHERE hash_algorithm = ?1)
+                FROM file_hashes",
+                params![self.algorithm.as_str()],
+                |row| {
+                    Ok(CacheStats {
+                        entry_count: row.get::<_, i64>(0)? as usize,
+                        trailing_hash_count: row.get::<_, i64>(1)? as usize,
+                        full_hash_count: row.get::<_, i64>(2)? as usize,
+                        other_algorithm_count: row.get::<_, i64>(3)? as usize,
+                        verified_count: row.get::<_, i64>(4)? as usize,
+                        file_size: fs::metadata(Self::db_path()).map(|m| m.len()).unwrap_or(0),
+                    })
+                },
+            )
+            .map_err(|e| e.to_string())
+    }
+
+    /// Remove entries for files that no longer exist, then compact the database
+    /// Files on volumes that aren't mounted right now are kept
+    /// Returns the number of entries removed
+    pub fn prune(&self) -> Result<usize, String> {
+        let mut conn = self.conn();
+        let paths: Vec<String> = {
+            let mut stmt = conn
+                .prepare("SELECT path FROM file_hashes")
+                .map_err(|e| e.to_string())?;
+            let rows = stmt
+                .query_map([], |row| row.get(0))
+                .map_err(|e| e.to_string())?;
+            rows.collect::<Result<_, _>>().map_err(|e| e.to_string())?
+        };
+
+        let tx = conn.transaction().map_err(|e| e.to_string())?;
+        let mut removed = 0;
+        for path in paths {
+            let path_ref = Path::new(&path);
+            if !path_ref.exists() && volume_mounted(path_ref) {
+                tx.execute("DELETE FROM file_hashes WHERE path = ?1", params![path])
+                    .map_err(|e| e.to_string())?;
+                removed += 1;
+            }
+        }
+        tx.commit().map_err(|e| e.to_string())?;
+        drop(conn);
+
+        if removed > 0 {
+            self.vacuum()?;
+        }
+        Ok(removed)
+    }
+
+    /// Remove every entry and compact the database
+    pub fn clear(&self) -> Result<(), String> {
+        self.conn()
+            .execute("DELETE FROM file_hashes", [])
+            .map_err(|e| e.to_string())?;
+        self.vacuum()
+    }
+
+    /// Give the space of deleted rows back to the filesystem
+    fn vacuum(&self) -> Result<(), String> {
+        self.conn()
+            .execute_batch("VACUUM")
+            .map_err(|e| e.to_string())
+    }
+}
+
+/// Whether the volume holding a path is available, so a missing file means it
+/// was deleted or moved rather than that its drive is unplugged
+/// External volumes are mounted under `/Volumes/<name>` on macOS,
+/// `/media/<user>/<name>` or `/run/media/<user>/<name>` on Linux, and are
+/// drive letters on Windows
+pub fn volume_mounted(path: &Path) -> bool {
+    let components: Vec<Component> = path.components().collect();
+    // Number of leading components that name the mount point
+    let depth = match components.as_slice() {
+        [Component::Prefix(_), Component::RootDir, ..] => 2,
+        [Component::RootDir, Component::Normal(top), ..] if *top == "Volumes" => 3,
+        [Component::RootDir, Component::Normal(top), ..] if *top == "media" => 4,
+        [Component::RootDir, Component::Normal(top), Component::Normal(next), ..]
+            if *top == "run" && *next == "media" =>
+        {
+            5
+        }
+        _ => return true,
+    };
+    if components.len() < depth {
+        return true;
+    }
+    components[..depth].iter().collect::<PathBuf>().exists()
+}
+
+/// Modification time in milliseconds since epoch
+fn modified_millis(metadata: &fs::Metadata) -> Option<i64> {
+    metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_millis() as i64)
+}
+
+fn file_modified_millis(path: &str) -> Option<i64> {
+    fs::metadata(path).ok().as_ref().and_then(modified_millis)
 }
@@ -0,0 +1,130 @@
+use crate::catalog::Catalog;
+use crate::scanner::PhotoFile;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Stable numeric photo id that survives renames and moves
+/// `PhotoFile.id` stays the path for older callers; 0 means no id was assigned
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct PhotoId(pub i64);
+
+/// Registry mapping stable ids to the current path (plus name, size, modification
+/// time and content hash used to recognise a file after it moved)
+/// Kept in the catalog database but separate from `photos`, which each scan rewrites
+pub struct PhotoIds {
+    conn: Connection,
+}
+
+/// Create the id table; called when the catalog is opened
+pub fn create_tables(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS photo_ids (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            path TEXT NOT NULL UNIQUE,
+            name TEXT NOT NULL,
+            size INTEGER NOT NULL,
+            modified_at INTEGER NOT NULL,
+            hash TEXT
+        );
+        CREATE INDEX IF NOT EXISTS idx_photo_ids_fingerprint
+            ON photo_ids(name, size, modified_at);",
+    )
+}
+
+impl PhotoIds {
+    /// Open the id table in the catalog database
+    pub fn open() -> Result<Self, String> {
+        let conn = Catalog::connection()?;
+        Ok(Self { conn })
+    }
+
+    /// Assign ids to scanned photos: by path, else by taking over the id of a
+    /// vanished file with the same name, size and modification time (moved
+    /// outside the app), else a new id
+    pub fn resolve_all(&mut self, photos: &mut [PhotoFile]) -> Result<(), String> {
+        let tx = self.conn.transaction().map_err(|e| e.to_string())?;
+        for photo in photos.iter_mut() {
+            photo.photo_id = resolve(&tx, photo).map_err(|e| e.to_string())?;
+        }
+        tx.commit().map_err(|e| e.to_string())
+    }
+
+    /// Remember content hashes computed during a scan
+    pub fn record_hashes(&mut self, photos: &[PhotoFile]) -> Result<(), String> {
+        let tx = self.conn.transaction().map_err(|e| e.to_string())?;
+        for photo in photos {
+            if let (Some(hash), PhotoId(id)) = (&photo.hash, photo.photo_id) {
+                tx.execute(
+                    "UPDATE photo_ids SET hash = ?1 WHERE id = ?2",
+                    params![hash, id],
+                )
+                .map_err(|e| e.to_string())?;
+            }
+        }
+        tx.commit().map_err(|e| e.to_string())
+    }
+
+    /// Follow a file the app moved or renamed so its id keeps pointing at it
+    /// A row left at the new path by a file that used to be there is dropped, so
+    /// the moved file keeps its id rather than taking over the stale one
+    pub fn relocate(&mut self, from: &str, to: &str) -> Result<(), String> {
+        let name = Path::new(to)
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let tx = self.conn.transaction().map_err(|e| e.to_string())?;
+        tx.execute(
+            "DELETE FROM photo_ids WHERE path = ?2
+                AND EXISTS (SELECT 1 FROM photo_ids WHERE path = ?1)",
+            params![from, to],
+        )
+        .map_err(|e| e.to_string())?;
+        tx.execute(
+            "UPDATE photo_ids SET path = ?2, name = ?3 WHERE path = ?1",
+            params![from, to, name],
+        )
+        .map_err(|e| e.to_string())?;
+        tx.commit().map_err(|e| e.to_string())
+    }
+}
+
+fn resolve(conn: &Connection, photo: &PhotoFile) -> rusqlite::Result<PhotoId> {
+    let existing: Option<i64> = conn
+        .query_row(
+            "SELECT id FROM photo_ids WHERE path = ?1",
+            params![photo.path],
+            |row| row.get(0),
+        )
+        .optional()?;
+    if let Some(id) = existing {
+        return Ok(PhotoId(id));
+    }
+
+    let mut stmt = conn.prepare_cached(
+        "SELECT id, path FROM photo_ids WHERE name = ?1 AND size = ?2 AND modified_at = ?3",
+    )?;
+    let candidates = stmt
+        .query_map(
+            params![photo.name, photo.size as i64, photo.modified_at],
+            |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)),
+        )?
+        .collect::<Result<Vec<_>, _>>()?;
+    if let Some((id, _)) = candidates
+        .into_iter()
+        .find(|(_, old_path)| !Path::new(old_path).exists())
+    {
+        conn.execute(
+            "UPDATE photo_ids SET path = ?1 WHERE id = ?2",
+            params![photo.path, id],
+        )?;
+        return Ok(PhotoId(id));
+    }
+
+    conn.execute(
+        "INSERT INTO photo_ids (path, name, size, modified_at) VALUES (?1, ?2, ?3, ?4)",
+        params![photo.path, photo.name, photo.size as i64, photo.modified_at],
+    )?;
+    Ok(PhotoId(conn.last_insert_rowid()))
+}
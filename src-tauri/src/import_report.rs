@@ -0,0 +1,179 @@
+use crate::hash_cache::HashAlgorithm;
+use crate::scanner::compute_full_hash;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A file copied from a device into the library
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportedFile {
+    pub source: String,
+    pub destination: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ImportStatus {
+    /// Source and destination hashes match
+    Verified,
+    Mismatch,
+    MissingSource,
+    MissingDestination,
+}
+
+/// Outcome of checking one imported file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportReportEntry {
+    pub source: String,
+    pub destination: String,
+    pub size: u64,
+    pub source_hash: Option<String>,
+    pub destination_hash: Option<String>,
+    pub status: ImportStatus,
+}
+
+/// Chain-of-custody record of an import: every source file, its hash and
+/// where it ended up, sealed with a SHA-256 of the report contents so later
+/// edits to the file are detectable
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportReport {
+    pub id: String,
+    pub created_at: String,
+    /// Device or folder the files were imported from
+    pub source: String,
+    /// Library folder the files were imported into
+    pub destination: String,
+    pub hash_algorithm: String,
+    pub entries: Vec<ImportReportEntry>,
+    pub verified_count: usize,
+    pub failed_count: usize,
+    /// SHA-256 of the report with this field empty
+    pub signature: String,
+    /// Whether the stored signature still matches the contents (computed on load)
+    #[serde(skip_deserializing)]
+    pub signature_valid: bool,
+}
+
+impl ImportReport {
+    /// Hash every source file and its copy and build a signed report
+    /// Both sides are read from disk; cached hashes are not trusted here
+    pub fn verify(
+        source: &str,
+        destination: &str,
+        files: &[ImportedFile],
+        algorithm: HashAlgorithm,
+    ) -> Self {
+        let entries: Vec<ImportReportEntry> = files
+            .par_iter()
+            .map(|file| verify_file(file, algorithm))
+            .collect();
+        let verified_count = entries
+            .iter()
+            .filter(|e| e.status == ImportStatus::Verified)
+            .count();
+
+        let now = chrono::Local::now();
+        let mut report = Self {
+            id: format!("import-{}", now.format("%Y%m%d-%H%M%S")),
+            created_at: now.to_rfc3339(),
+            source: source.to_string(),
+            destination: destination.to_string(),
+            hash_algorithm: algorithm.as_str().to_string(),
+            failed_count: entries.len() - verified_count,
+            verified_count,
+            entries,
+            signature: String::new(),
+            signature_valid: true,
+        };
+        report.signature = report.digest();
+        report
+    }
+
+    /// SHA-256 over the report's JSON with the signature blanked
+    fn digest(&self) -> String {
+        let unsigned = Self {
+            signature: String::new(),
+            signature_valid: false,
+            ..self.clone()
+        };
+        let json = serde_json::to_vec(&unsigned).unwrap_or_default();
+        format!("{:x}", Sha256::digest(json))
+    }
+
+    /// Store the report with the app's import history and next to the imported files
+    pub fn save(&self) -> Result<PathBuf, String> {
+        let json = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        let file_name = format!("{}.json", self.id);
+
+        let dir = reports_dir();
+        fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+        let path = dir.join(&file_name);
+        fs::write(&path, &json).map_err(|e| e.to_string())?;
+
+        // A copy travels with the import; failing to write it is not fatal
+        let alongside = Path::new(&self.destination).join(format!(".photo-manager-{}", file_name));
+        if let Err(e) = fs::write(&alongside, &json) {
+            eprintln!("Failed to write {}: {}", alongside.display(), e);
+        }
+        Ok(path)
+    }
+
+    /// Every stored report, newest first, with its signature checked
+    pub fn load_all() -> Result<Vec<Self>, String> {
+        let entries = match fs::read_dir(reports_dir()) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.to_string()),
+        };
+
+        let mut reports: Vec<Self> = entries
+            .filter_map(|e| e.ok())
+            .filter(|e| e.path().extension().is_some_and(|ext| ext == "json"))
+            .filter_map(|e| fs::read_to_string(e.path()).ok())
+            .filter_map(|json| serde_json::from_str::<Self>(&json).ok())
+            .map(|mut report| {
+                report.signature_valid = report.digest() == report.signature;
+                report
+            })
+            .collect();
+        reports.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+        Ok(reports)
+    }
+}
+
+fn verify_file(file: &ImportedFile, algorithm: HashAlgorithm) -> ImportReportEntry {
+    let source_size = fs::metadata(&file.source).ok().map(|m| m.len());
+    let source_hash = source_size.and_then(|_| compute_full_hash(&file.source, algorithm));
+    let destination_hash = Path::new(&file.destination)
+        .exists()
+        .then(|| compute_full_hash(&file.destination, algorithm))
+        .flatten();
+
+    let status = match (&source_hash, &destination_hash) {
+        (None, _) => ImportStatus::MissingSource,
+        (_, None) => ImportStatus::MissingDestination,
+        (Some(s), Some(d)) if s == d => ImportStatus::Verified,
+        _ => ImportStatus::Mismatch,
+    };
+
+    ImportReportEntry {
+        source: file.source.clone(),
+        destination: file.destination.clone(),
+        size: source_size.unwrap_or(0),
+        source_hash,
+        destination_hash,
+        status,
+    }
+}
+
+fn reports_dir() -> PathBuf {
+    dirs::config_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("photo-manager")
+        .join("import-reports")
+}
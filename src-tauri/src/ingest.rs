@@ -0,0 +1,355 @@
+use crate::catalog::Catalog;
+use crate::config::{default_organize_template, AppConfig};
+use crate::file_ops::{copy_preserving_metadata, find_unique_name_with};
+use crate::format_check::{card_media_files, has_copy, library_files_by_size};
+use crate::hash_cache::HashCache;
+use crate::import_report::{ImportReport, ImportStatus, ImportedFile};
+use crate::jobs::JobMonitor;
+use crate::metadata::{capture_date_or_file_date, expand_date_template};
+use crate::scanner::compute_full_hash;
+use crate::shutdown;
+use crate::volumes;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tauri::Window;
+
+/// A mounted volume that looks like a camera card (it has a DCIM folder)
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Device {
+    pub path: String,
+    pub name: String,
+}
+
+/// How `import_from_device` names and places the copied files
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct IngestOptions {
+    /// Library folder to import into
+    pub destination: String,
+    /// Date folders under the destination (YYYY, MM, DD); the organize
+    /// template from the config when unset
+    pub folder_template: Option<String>,
+    /// New file name without extension (YYYY, MM, DD, `{time}` for HHMMSS and
+    /// `{name}` for the original name); the original name when unset
+    pub rename_template: Option<String>,
+    /// Eject the card when every file was copied and verified
+    pub eject: bool,
+}
+
+/// `ingest-progress` payload
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IngestProgress {
+    /// "scanning" | "hashing" | "copying" | "verifying" | "ejecting" | "complete"
+    pub stage: String,
+    pub current: usize,
+    pub total: usize,
+    pub current_file: String,
+}
+
+/// A card file that was not imported
+#[derive(Debug, Clone, Serialize)]
+pub struct IngestFailure {
+    pub path: String,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IngestResult {
+    pub volume: String,
+    pub imported_count: usize,
+    /// Card files whose content is already in the library (or earlier on the card)
+    pub already_imported_count: usize,
+    pub failed: Vec<IngestFailure>,
+    /// Copies whose hash matches the card file (see the import report)
+    pub verified_count: usize,
+    pub report_id: String,
+    pub ejected: bool,
+}
+
+/// Mounted volumes with a DCIM folder, the layout every camera writes
+pub fn camera_volumes() -> Vec<Device> {
+    volumes::list()
+        .into_iter()
+        .filter(|volume| volume.is_camera_card)
+        .map(|volume| Device {
+            path: volume.path,
+            name: volume.name,
+        })
+        .collect()
+}
+
+/// The card to import from when none was chosen: the only one inserted
+pub fn only_camera_volume() -> Result<PathBuf, String> {
+    let mut volumes = camera_volumes();
+    match volumes.len() {
+        0 => Err("No camera card found".to_string()),
+        1 => Ok(PathBuf::from(volumes.remove(0).path)),
+        _ => Err("Several camera cards are inserted; choose one".to_string()),
+    }
+}
+
+/// Copy the photos and videos on a card that aren't in the library yet into
+/// dated folders, verify every copy against the card by hash, record a signed
+/// import report and optionally eject the card
+/// Card files are hashed from the card; files already in the library are
+/// recognised by size and then by hash, like `safe_to_format` does
+pub fn import_from_device(
+    window: &Window,
+    volume: &Path,
+    options: &IngestOptions,
+) -> Result<IngestResult, String> {
+    if options.destination.trim().is_empty() {
+        return Err("Choose a library folder to import into".to_string());
+    }
+    if !volume.is_dir() {
+        return Err(format!("{} is not a mounted volume", volume.display()));
+    }
+    let destination = Path::new(&options.destination);
+    let progress = |stage: &str, current: usize, total: usize, path: Option<&Path>| {
+        let _ = window.emit(
+            "ingest-progress",
+            IngestProgress {
+                stage: stage.to_string(),
+                current,
+                total,
+                current_file: path
+                    .and_then(|p| p.file_name())
+                    .map(|n| n.to_string_lossy().to_string())
+                    .unwrap_or_default(),
+            },
+        );
+    };
+    let monitor = JobMonitor::start(window, "import");
+    // Taken once the job's turn in the queue has come, not while it waits
+    let _busy = shutdown::begin("import");
+    let cancelled = || "Import cancelled".to_string();
+    let cache = HashCache::open()?;
+
+    progress("scanning", 0, 0, None);
+    let card_files = card_media_files(volume).media;
+    let sizes: HashSet<u64> = card_files.iter().map(|(_, size)| *size).collect();
+    let candidates = library_files_by_size(volume, &sizes)?;
+
+    // Hash every card file; the order is kept so copies come out sorted by path
+    let hashed_count = AtomicUsize::new(0);
+    let hashed: Vec<(&PathBuf, u64, Option<String>)> = card_files
+        .par_iter()
+        .map(|(path, size)| {
+            let path_str = path.to_string_lossy();
+            if monitor.is_cancelled() {
+                return (path, *size, None);
+            }
+            let _work = monitor.working_on(&path_str);
+            let hash = compute_full_hash(&path_str, cache.algorithm());
+            let current = hashed_count.fetch_add(1, Ordering::Relaxed) + 1;
+            progress("hashing", current, card_files.len(), Some(path));
+            (path, *size, hash.filter(|_| !monitor.is_skipped(&path_str)))
+        })
+        .collect();
+    if monitor.is_cancelled() {
+        return Err(cancelled());
+    }
+
+    let mut failed = Vec::new();
+    let mut already_imported_count = 0;
+    let mut seen: HashSet<String> = HashSet::new();
+    let mut to_copy: Vec<&PathBuf> = Vec::new();
+    for (path, size, hash) in hashed {
+        let Some(hash) = hash else {
+            failed.push(IngestFailure {
+                path: path.to_string_lossy().to_string(),
+                error: "Could not read the file".to_string(),
+            });
+            continue;
+        };
+        if has_copy(&candidates, size, &hash, &cache) || !seen.insert(hash) {
+            already_imported_count += 1;
+        } else {
+            to_copy.push(path);
+        }
+    }
+
+    let folder_template = options
+        .folder_template
+        .clone()
+        .filter(|t| !t.trim().is_empty())
+        .or_else(|| Some(AppConfig::load().organize_template).filter(|t| !t.trim().is_empty()))
+        .unwrap_or_else(default_organize_template);
+    let rename_template = options
+        .rename_template
+        .as_deref()
+        .filter(|t| !t.trim().is_empty());
+
+    let mut planned: HashSet<PathBuf> = HashSet::new();
+    let mut imported: Vec<ImportedFile> = Vec::new();
+    for (i, source) in to_copy.iter().enumerate() {
+        if monitor.is_cancelled() {
+            break;
+        }
+        let source_str = source.to_string_lossy().to_string();
+        let _work = monitor.working_on(&source_str);
+        progress("copying", i + 1, to_copy.len(), Some(source));
+
+        let copied = copy_into_library(
+            source,
+            destination,
+            &folder_template,
+            rename_template,
+            &mut planned,
+        );
+        match copied {
+            _ if monitor.is_skipped(&source_str) => failed.push(IngestFailure {
+                path: source_str,
+                error: "Skipped after stalling".to_string(),
+            }),
+            Ok(target) => imported.push(ImportedFile {
+                source: source_str,
+                destination: target.to_string_lossy().to_string(),
+            }),
+            Err(error) => failed.push(IngestFailure {
+                path: source_str,
+                error,
+            }),
+        }
+    }
+    let interrupted = monitor.is_cancelled();
+    drop(monitor);
+
+    progress("verifying", 0, imported.len(), None);
+    let volume_str = volume.to_string_lossy().to_string();
+    let report = ImportReport::verify(
+        &volume_str,
+        &options.destination,
+        &imported,
+        cache.algorithm(),
+    );
+    report.save()?;
+
+    // Verified copies are in the library now, for the next import's check
+    let verified: Vec<(String, u64, String)> = report
+        .entries
+        .iter()
+        .filter(|entry| entry.status == ImportStatus::Verified)
+        .filter_map(|entry| {
+            let hash = entry.destination_hash.clone()?;
+            cache.set_full_hash(&entry.destination, entry.size, &hash);
+            Some((entry.destination.clone(), entry.size, hash))
+        })
+        .collect();
+    Catalog::open()?.add_imported(&verified)?;
+
+    // Only a card whose every file is safely in the library is ejected
+    let mut ejected = false;
+    if options.eject && !interrupted && failed.is_empty() && report.failed_count == 0 {
+        progress("ejecting", 0, 0, None);
+        match eject(volume) {
+            Ok(()) => ejected = true,
+            Err(error) => failed.push(IngestFailure {
+                path: volume_str.clone(),
+                error: format!("Eject failed: {}", error),
+            }),
+        }
+    }
+    progress("complete", imported.len(), imported.len(), None);
+
+    Ok(IngestResult {
+        volume: volume_str,
+        imported_count: imported.len(),
+        already_imported_count,
+        failed,
+        verified_count: report.verified_count,
+        report_id: report.id,
+        ejected,
+    })
+}
+
+/// Copy one card file to `destination/<date folders>/<name>`, never
+/// overwriting anything on disk or planned earlier in the same import
+fn copy_into_library(
+    source: &Path,
+    destination: &Path,
+    folder_template: &str,
+    rename_template: Option<&str>,
+    planned: &mut HashSet<PathBuf>,
+) -> Result<PathBuf, String> {
+    let date = capture_date_or_file_date(source).ok_or("No capture or file date")?;
+    let stem = source
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .ok_or("Invalid file name")?;
+    let name = match rename_template {
+        // Date tokens first, so they can't be picked out of the original name
+        Some(template) => expand_date_template(template, &date)
+            .replace("{time}", &date.format("%H%M%S").to_string())
+            .replace("{name}", stem),
+        None => stem.to_string(),
+    };
+    let file_name = match source.extension().and_then(|e| e.to_str()) {
+        Some(ext) => format!("{}.{}", name, ext),
+        None => name,
+    };
+
+    let target = destination
+        .join(expand_date_template(folder_template, &date))
+        .join(file_name);
+    let target = if target.exists() || planned.contains(&target) {
+        find_unique_name_with(&target, |p| p.exists() || planned.contains(p))?
+    } else {
+        target
+    };
+    planned.insert(target.clone());
+
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    copy_preserving_metadata(source, &target).map_err(|e| e.to_string())?;
+    Ok(target)
+}
+
+/// Unmount and eject a card
+fn eject(volume: &Path) -> Result<(), String> {
+    let output = eject_command(volume).output().map_err(|e| e.to_string())?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(String::from_utf8_lossy(&output.stderr).trim().to_string())
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn eject_command(volume: &Path) -> Command {
+    let mut command = Command::new("diskutil");
+    command.arg("eject").arg(volume);
+    command
+}
+
+#[cfg(windows)]
+fn eject_command(volume: &Path) -> Command {
+    // The shell's "Eject" verb on the drive, as in Explorer
+    let drive = volume.to_string_lossy().trim_end_matches('\\').to_string();
+    let mut command = Command::new("powershell");
+    command.args([
+        "-NoProfile",
+        "-Command",
+        &format!(
+            "(New-Object -ComObject Shell.Application).Namespace(17).ParseName('{}').InvokeVerb('Eject')",
+            drive
+        ),
+    ]);
+    command
+}
+
+#[cfg(not(any(target_os = "macos", windows)))]
+fn eject_command(volume: &Path) -> Command {
+    let mut command = Command::new("gio");
+    command.args(["mount", "--eject"]).arg(volume);
+    command
+}
@@ -0,0 +1,397 @@
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager, Window};
+
+/// How often a job reports its progress
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(500);
+
+/// A job that finishes nothing for this long is reported as stalled
+const STALL_AFTER: Duration = Duration::from_secs(30);
+
+/// Jobs that read or write a whole library; only one of them runs at a time
+/// and the others wait their turn, so two never compete for the same disk
+const QUEUED_KINDS: &[&str] = &[
+    "scan",
+    "fixity",
+    "compare",
+    "convert",
+    "export",
+    "transcode",
+    "import",
+    "consolidate",
+    "faces",
+    "machine-tags",
+    "quality",
+];
+
+static NEXT_JOB_ID: AtomicU64 = AtomicU64::new(1);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    /// Waiting for the job ahead of it to finish
+    Queued,
+    Running,
+    /// Paused by the user; it stops at the next file until resumed
+    Paused,
+}
+
+/// `job-progress` payload and `list_jobs` entry, the same for every kind of job
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JobProgress {
+    pub job_id: u64,
+    /// "scan", "export", "delete", ...
+    pub kind: String,
+    pub status: JobStatus,
+    /// Step the job is on (a scan's "hashing"), for jobs that report steps
+    pub phase: Option<String>,
+    /// Files (or other units of work) finished in this phase, out of `total`
+    /// when the job knows it
+    pub current: usize,
+    pub total: Option<usize>,
+    /// Files finished over the whole job
+    pub completed: usize,
+    pub current_file: Option<String>,
+    /// Seconds since the job last finished something
+    pub idle_secs: u64,
+    /// When the job was started, ms since epoch
+    pub started_at: i64,
+}
+
+/// `job-stalled` payload, sent once per stall
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JobStalled {
+    pub job_id: u64,
+    pub kind: String,
+    /// File the job has been stuck on longest (or the last one it touched)
+    pub path: Option<String>,
+    pub stalled_secs: u64,
+}
+
+struct JobState {
+    id: u64,
+    kind: String,
+    started_at: i64,
+    completed: AtomicUsize,
+    /// Current phase and its size, if the job reports phases
+    phase: Mutex<Option<(String, Option<usize>)>>,
+    /// Units finished in the current phase
+    current: AtomicUsize,
+    last_progress: Mutex<Instant>,
+    /// Files being worked on and when each was started
+    in_flight: Mutex<HashMap<String, Instant>>,
+    last_path: Mutex<Option<String>>,
+    skipped: Mutex<HashSet<String>>,
+    cancelled: AtomicBool,
+    queued: AtomicBool,
+    paused: Mutex<bool>,
+    resumed: Condvar,
+    finished: AtomicBool,
+}
+
+/// Lock a mutex, recovering it if a worker panicked while holding it
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+impl JobState {
+    fn touch(&self) {
+        *lock(&self.last_progress) = Instant::now();
+    }
+
+    /// The file the job is most likely stuck on
+    fn stuck_path(&self) -> Option<String> {
+        lock(&self.in_flight)
+            .iter()
+            .min_by_key(|(_, started)| **started)
+            .map(|(path, _)| path.clone())
+            .or_else(|| lock(&self.last_path).clone())
+    }
+
+    fn status(&self) -> JobStatus {
+        if self.queued.load(Ordering::Relaxed) {
+            JobStatus::Queued
+        } else if *lock(&self.paused) {
+            JobStatus::Paused
+        } else {
+            JobStatus::Running
+        }
+    }
+
+    fn progress(&self) -> JobProgress {
+        let phase = lock(&self.phase).clone();
+        JobProgress {
+            job_id: self.id,
+            kind: self.kind.clone(),
+            status: self.status(),
+            current: self.current.load(Ordering::Relaxed),
+            total: phase.as_ref().and_then(|(_, total)| *total),
+            phase: phase.map(|(name, _)| name),
+            completed: self.completed.load(Ordering::Relaxed),
+            current_file: lock(&self.last_path).clone(),
+            idle_secs: lock(&self.last_progress).elapsed().as_secs(),
+            started_at: self.started_at,
+        }
+    }
+
+    fn set_paused(&self, paused: bool) {
+        *lock(&self.paused) = paused;
+        self.resumed.notify_all();
+        // Time spent paused isn't a stall
+        self.touch();
+    }
+}
+
+/// Running and queued jobs, in the order they were started
+#[derive(Default)]
+struct Registry {
+    jobs: Mutex<Vec<Arc<JobState>>>,
+    /// Signalled whenever a job finishes or is cancelled, so queued jobs can
+    /// check whether it is their turn
+    changed: Condvar,
+}
+
+impl Registry {
+    /// Whether a queued job may start: no earlier job of a queued kind is left
+    fn is_turn_of(jobs: &[Arc<JobState>], id: u64) -> bool {
+        jobs.iter()
+            .find(|job| QUEUED_KINDS.contains(&job.kind.as_str()))
+            .is_none_or(|first| first.id == id)
+    }
+}
+
+/// The job queue, kept in Tauri state
+/// Every long-running command runs as a job through a `JobMonitor`; this is
+/// where the frontend lists, cancels, pauses and resumes them
+#[derive(Default)]
+pub struct Jobs {
+    registry: Arc<Registry>,
+}
+
+impl Jobs {
+    fn find(&self, job_id: u64) -> Result<Arc<JobState>, String> {
+        lock(&self.registry.jobs)
+            .iter()
+            .find(|job| job.id == job_id)
+            .cloned()
+            .ok_or_else(|| "Job is not running".to_string())
+    }
+
+    /// Every running, paused and queued job, oldest first
+    pub fn list(&self) -> Vec<JobProgress> {
+        lock(&self.registry.jobs)
+            .iter()
+            .map(|job| job.progress())
+            .collect()
+    }
+
+    /// Ask a job to stop at the next file; a queued job never starts
+    pub fn cancel(&self, job_id: u64) -> Result<(), String> {
+        let job = self.find(job_id)?;
+        job.cancelled.store(true, Ordering::Relaxed);
+        job.resumed.notify_all();
+        self.registry.changed.notify_all();
+        Ok(())
+    }
+
+    /// Hold a job at the next file until it is resumed or cancelled
+    pub fn pause(&self, job_id: u64) -> Result<(), String> {
+        self.find(job_id)?.set_paused(true);
+        Ok(())
+    }
+
+    pub fn resume(&self, job_id: u64) -> Result<(), String> {
+        self.find(job_id)?.set_paused(false);
+        Ok(())
+    }
+
+    /// Resume every paused job, e.g. when the app is asked to quit and waits
+    /// for file operations to finish
+    pub fn resume_all(&self) {
+        for job in lock(&self.registry.jobs).iter() {
+            job.set_paused(false);
+        }
+    }
+
+    /// Ask a job to give up on one file and carry on with the rest
+    pub fn skip(&self, job_id: u64, path: &str) -> Result<(), String> {
+        let job = self.find(job_id)?;
+        lock(&job.skipped).insert(path.to_string());
+        // The user has seen the stall; give the rest of the job a fresh timer
+        job.touch();
+        Ok(())
+    }
+}
+
+/// A long-running job's handle on the queue, its progress and its controls
+/// A watchdog thread emits `job-progress` to every window a couple of times a
+/// second and `job-stalled` when nothing finishes for a while (typically a read hanging on
+/// a network volume). The user can then cancel the job or skip the file; a read
+/// blocked in the kernel can't be interrupted, so a skipped file's result is
+/// dropped as soon as the read returns and the job moves on
+/// Jobs of a queued kind wait in `start` until the ones before them finish
+/// The job is unregistered when the monitor is dropped
+pub struct JobMonitor {
+    state: Arc<JobState>,
+    registry: Arc<Registry>,
+}
+
+impl JobMonitor {
+    pub fn start(window: &Window, kind: &str) -> Self {
+        let registry = Arc::clone(&window.state::<Jobs>().registry);
+        let queued = QUEUED_KINDS.contains(&kind);
+        let state = Arc::new(JobState {
+            id: NEXT_JOB_ID.fetch_add(1, Ordering::Relaxed),
+            kind: kind.to_string(),
+            started_at: chrono::Utc::now().timestamp_millis(),
+            completed: AtomicUsize::new(0),
+            phase: Mutex::new(None),
+            current: AtomicUsize::new(0),
+            last_progress: Mutex::new(Instant::now()),
+            in_flight: Mutex::new(HashMap::new()),
+            last_path: Mutex::new(None),
+            skipped: Mutex::new(HashSet::new()),
+            cancelled: AtomicBool::new(false),
+            queued: AtomicBool::new(queued),
+            paused: Mutex::new(false),
+            resumed: Condvar::new(),
+            finished: AtomicBool::new(false),
+        });
+        lock(&registry.jobs).push(Arc::clone(&state));
+
+        let watched = Arc::clone(&state);
+        let app = window.app_handle();
+        thread::spawn(move || watchdog(&watched, &app));
+
+        if queued {
+            let mut jobs = lock(&registry.jobs);
+            while !Registry::is_turn_of(&jobs[..], state.id) && !state.cancelled.load(Ordering::Relaxed)
+            {
+                jobs = registry
+                    .changed
+                    .wait(jobs)
+                    .unwrap_or_else(|e| e.into_inner());
+            }
+            state.queued.store(false, Ordering::Relaxed);
+            state.touch();
+        }
+
+        Self { state, registry }
+    }
+
+    /// Mark a file as being worked on until the returned guard is dropped
+    pub fn working_on(&self, path: &str) -> WorkGuard<'_> {
+        lock(&self.state.in_flight).insert(path.to_string(), Instant::now());
+        *lock(&self.state.last_path) = Some(path.to_string());
+        WorkGuard {
+            state: &self.state,
+            path: path.to_string(),
+        }
+    }
+
+    /// Record progress that isn't tied to one file (a directory entry, a
+    /// percentage reported by ffmpeg, ...)
+    pub fn progress(&self, path: Option<&str>) {
+        if let Some(path) = path {
+            *lock(&self.state.last_path) = Some(path.to_string());
+        }
+        self.state.touch();
+    }
+
+    /// Start a new phase of `total` units (when known); files finished from
+    /// now on count towards it in `job-progress`
+    pub fn set_phase(&self, phase: &str, total: Option<usize>) {
+        *lock(&self.state.phase) = Some((phase.to_string(), total));
+        self.state.current.store(0, Ordering::Relaxed);
+    }
+
+    /// Start a new phase of `total` units of which `done` need no work (hashes
+    /// found in the cache, say), so its progress runs against the full total
+    pub fn set_phase_from(&self, phase: &str, done: usize, total: usize) {
+        *lock(&self.state.phase) = Some((phase.to_string(), Some(total)));
+        self.state.current.store(done, Ordering::Relaxed);
+    }
+
+    /// Whether the user cancelled the job
+    /// Jobs call this before each file, so this is also where a paused job
+    /// waits until it is resumed or cancelled
+    pub fn is_cancelled(&self) -> bool {
+        let mut paused = lock(&self.state.paused);
+        while *paused && !self.state.cancelled.load(Ordering::Relaxed) {
+            paused = self
+                .state
+                .resumed
+                .wait(paused)
+                .unwrap_or_else(|e| e.into_inner());
+        }
+        self.state.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// Whether the user asked to skip this file
+    pub fn is_skipped(&self, path: &str) -> bool {
+        lock(&self.state.skipped).contains(path)
+    }
+}
+
+impl Drop for JobMonitor {
+    fn drop(&mut self) {
+        self.state.finished.store(true, Ordering::Relaxed);
+        lock(&self.registry.jobs).retain(|job| job.id != self.state.id);
+        self.registry.changed.notify_all();
+    }
+}
+
+/// A file in progress; finishing it counts as progress
+pub struct WorkGuard<'a> {
+    state: &'a JobState,
+    path: String,
+}
+
+impl Drop for WorkGuard<'_> {
+    fn drop(&mut self) {
+        lock(&self.state.in_flight).remove(&self.path);
+        self.state.completed.fetch_add(1, Ordering::Relaxed);
+        self.state.current.fetch_add(1, Ordering::Relaxed);
+        self.state.touch();
+    }
+}
+
+fn watchdog(state: &JobState, app: &AppHandle) {
+    let mut reported: Option<Option<String>> = None;
+    loop {
+        thread::sleep(PROGRESS_INTERVAL);
+        if state.finished.load(Ordering::Relaxed) {
+            break;
+        }
+
+        let progress = state.progress();
+        let _ = app.emit_all("job-progress", &progress);
+
+        // Waiting in the queue or paused isn't being stuck
+        let idle = lock(&state.last_progress).elapsed();
+        if idle < STALL_AFTER || progress.status != JobStatus::Running {
+            reported = None;
+            continue;
+        }
+        // Report each stall once, and again if the job gets stuck somewhere else
+        let path = state.stuck_path();
+        if reported.as_ref() != Some(&path) {
+            let _ = app.emit_all(
+                "job-stalled",
+                JobStalled {
+                    job_id: state.id,
+                    kind: state.kind.clone(),
+                    path: path.clone(),
+                    stalled_secs: idle.as_secs(),
+                },
+            );
+            reported = Some(path);
+        }
+    }
+}
@@ -0,0 +1,237 @@
+use crate::archive::ArchivedPaths;
+use crate::catalog::Catalog;
+use crate::ids::PhotoId;
+use crate::scanner::PhotoFile;
+use chrono::TimeZone;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+
+/// In-memory library holding the results of the most recent scan
+/// Managed as Tauri state so the frontend can page through it lazily
+/// Scans publish a whole new snapshot at once; readers take an `Arc` to the current
+/// one and drop the lock straight away, so a long query never sees a half-updated
+/// library and never holds up the next scan
+#[derive(Default)]
+pub struct Library {
+    photos: RwLock<Arc<Vec<PhotoFile>>>,
+}
+
+/// How photos are bucketed into virtual groups
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GroupBy {
+    Month,
+    Folder,
+    Camera,
+}
+
+impl GroupBy {
+    fn prefix(self) -> &'static str {
+        match self {
+            GroupBy::Month => "month",
+            GroupBy::Folder => "folder",
+            GroupBy::Camera => "camera",
+        }
+    }
+
+    fn from_prefix(prefix: &str) -> Option<Self> {
+        match prefix {
+            "month" => Some(GroupBy::Month),
+            "folder" => Some(GroupBy::Folder),
+            "camera" => Some(GroupBy::Camera),
+            _ => None,
+        }
+    }
+}
+
+/// Group header returned to the frontend (members are fetched separately)
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PhotoGroup {
+    /// Stable id of the form `<group_by>:<key>`, e.g. `month:2024-03`
+    pub id: String,
+    pub label: String,
+    pub count: usize,
+    pub total_size: u64,
+}
+
+/// A page of group members
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GroupMembers {
+    pub group_id: String,
+    pub total: usize,
+    pub offset: usize,
+    pub photos: Vec<PhotoFile>,
+}
+
+impl Library {
+    /// Start from the persisted catalog so groups are available before any scan
+    pub fn from_catalog() -> Self {
+        let photos = Catalog::open()
+            .and_then(|c| c.all_photos())
+            .unwrap_or_default();
+        Self {
+            photos: RwLock::new(Arc::new(photos)),
+        }
+    }
+
+    /// Replace the library contents with a fresh scan result
+    pub fn replace(&self, photos: Vec<PhotoFile>) {
+        if let Ok(mut guard) = self.photos.write() {
+            *guard = Arc::new(photos);
+        }
+    }
+
+    /// Follow files the app moved or renamed, as photos or as related files,
+    /// so they are found at their new paths before the next scan
+    pub fn relocate<'a>(&self, moves: impl IntoIterator<Item = (&'a str, &'a str)>) {
+        let moves: HashMap<&str, &str> = moves.into_iter().collect();
+        let Ok(mut guard) = self.photos.write() else {
+            return;
+        };
+        let name_of = |path: &str| {
+            Path::new(path)
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default()
+        };
+        for photo in Arc::make_mut(&mut guard).iter_mut() {
+            if let Some(to) = moves.get(photo.path.as_str()) {
+                photo.id = to.to_string();
+                photo.path = to.to_string();
+                photo.name = name_of(to);
+                photo.directory = Path::new(to)
+                    .parent()
+                    .map(|p| p.to_string_lossy().to_string())
+                    .unwrap_or_default();
+            }
+            for file in &mut photo.related_files {
+                if let Some(to) = moves.get(file.path.as_str()) {
+                    file.path = to.to_string();
+                    file.name = name_of(to);
+                }
+            }
+        }
+    }
+
+    /// The current library contents, consistent for as long as it is held
+    pub fn snapshot(&self) -> Arc<Vec<PhotoFile>> {
+        self.photos
+            .read()
+            .map(|guard| Arc::clone(&guard))
+            .unwrap_or_default()
+    }
+
+    /// A photo from the last scan by its stable id
+    pub fn get(&self, photo_id: PhotoId) -> Option<PhotoFile> {
+        self.snapshot()
+            .iter()
+            .find(|p| p.photo_id == photo_id)
+            .cloned()
+    }
+
+    /// Group headers with counts, ordered newest month first or alphabetically
+    /// Archived photos are left out
+    pub fn groups(&self, group_by: GroupBy, archived: &ArchivedPaths) -> Vec<PhotoGroup> {
+        let photos = self.snapshot();
+
+        let mut groups: BTreeMap<String, PhotoGroup> = BTreeMap::new();
+        for photo in photos.iter().filter(|p| !archived.contains(&p.path)) {
+            let (key, label) = group_key(photo, group_by);
+            let group = groups.entry(key.clone()).or_insert_with(|| PhotoGroup {
+                id: format!("{}:{}", group_by.prefix(), key),
+                label,
+                count: 0,
+                total_size: 0,
+            });
+            group.count += 1;
+            group.total_size += photo.size;
+        }
+
+        let mut result: Vec<PhotoGroup> = groups.into_values().collect();
+        if group_by == GroupBy::Month {
+            result.reverse();
+        }
+        result
+    }
+
+    /// A page of photos in a group, newest first
+    pub fn group_members(
+        &self,
+        group_id: &str,
+        offset: usize,
+        limit: usize,
+        archived: &ArchivedPaths,
+    ) -> Result<GroupMembers, String> {
+        let (prefix, key) = group_id.split_once(':').ok_or("Invalid group id")?;
+        let group_by = GroupBy::from_prefix(prefix).ok_or("Invalid group id")?;
+
+        let photos = self.snapshot();
+        let mut members: Vec<&PhotoFile> = photos
+            .iter()
+            .filter(|p| group_key(p, group_by).0 == key && !archived.contains(&p.path))
+            .collect();
+        members.sort_by_key(|p| std::cmp::Reverse(photo_timestamp(p)));
+
+        Ok(GroupMembers {
+            group_id: group_id.to_string(),
+            total: members.len(),
+            offset,
+            photos: members
+                .into_iter()
+                .skip(offset)
+                .take(limit)
+                .cloned()
+                .collect(),
+        })
+    }
+}
+
+/// Capture date if known, otherwise the file date
+fn photo_timestamp(photo: &PhotoFile) -> i64 {
+    photo.capture_date.unwrap_or(photo.modified_at)
+}
+
+/// Compute the (key, label) pair a photo belongs to
+fn group_key(photo: &PhotoFile, group_by: GroupBy) -> (String, String) {
+    match group_by {
+        GroupBy::Month => {
+            let month = chrono::Local
+                .timestamp_millis_opt(photo_timestamp(photo))
+                .single()
+                .filter(|_| photo_timestamp(photo) > 0)
+                .map(|d| (d.format("%Y-%m").to_string(), d.format("%B %Y").to_string()));
+            month.unwrap_or_else(|| ("unknown".to_string(), "Unknown date".to_string()))
+        }
+        GroupBy::Folder => {
+            let folder = Path::new(&photo.path)
+                .parent()
+                .map(|p| p.to_string_lossy().to_string())
+                .unwrap_or_default();
+            (folder.clone(), photo.directory.clone())
+        }
+        GroupBy::Camera => match camera_name(photo) {
+            Some(camera) => (camera.clone(), camera),
+            None => ("unknown".to_string(), "Unknown camera".to_string()),
+        },
+    }
+}
+
+/// Make and model for display, without repeating a make the model already starts with
+pub fn camera_name(photo: &PhotoFile) -> Option<String> {
+    camera_name_of(photo.camera_make.as_deref(), photo.camera_model.as_deref())
+}
+
+pub fn camera_name_of(make: Option<&str>, model: Option<&str>) -> Option<String> {
+    match (make, model) {
+        (Some(make), Some(model)) if !model.starts_with(make) => {
+            Some(format!("{} {}", make, model))
+        }
+        (_, Some(model)) => Some(model.to_string()),
+        (Some(make), None) => Some(make.to_string()),
+        (None, None) => None,
+    }
+}
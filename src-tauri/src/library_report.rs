@@ -0,0 +1,100 @@
+use crate::duplicates::duplicate_groups;
+use crate::library::camera_name;
+use crate::scanner::PhotoFile;
+use chrono::TimeZone;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// File format of `export_report`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportFormat {
+    #[default]
+    Csv,
+    Json,
+}
+
+/// One photo in a library report
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReportRow {
+    pub path: String,
+    pub size: u64,
+    pub hash: Option<String>,
+    /// Local time, RFC 3339
+    pub capture_date: Option<String>,
+    /// Number of the duplicate set the file belongs to, counted from 1
+    pub duplicate_group: Option<usize>,
+    pub camera: Option<String>,
+}
+
+const CSV_HEADER: &str = "path,size,hash,capture_date,duplicate_group,camera";
+
+/// One row per photo, in path order
+pub fn rows(photos: &[PhotoFile]) -> Vec<ReportRow> {
+    let mut group_of: HashMap<&str, usize> = HashMap::new();
+    let groups = duplicate_groups(photos);
+    for (i, group) in groups.iter().enumerate() {
+        for member in &group.members {
+            group_of.insert(member.path.as_str(), i + 1);
+        }
+    }
+
+    let mut rows: Vec<ReportRow> = photos
+        .iter()
+        .map(|photo| ReportRow {
+            path: photo.path.clone(),
+            size: photo.size,
+            hash: photo.hash.clone(),
+            capture_date: photo
+                .capture_date
+                .and_then(|ms| chrono::Local.timestamp_millis_opt(ms).single())
+                .map(|date| date.to_rfc3339()),
+            duplicate_group: group_of.get(photo.path.as_str()).copied(),
+            camera: camera_name(photo),
+        })
+        .collect();
+    rows.sort_by(|a, b| a.path.cmp(&b.path));
+    rows
+}
+
+/// Write the rows to `destination` as CSV or pretty-printed JSON
+pub fn write(destination: &Path, rows: &[ReportRow], format: ReportFormat) -> Result<(), String> {
+    let contents = match format {
+        ReportFormat::Json => serde_json::to_string_pretty(rows).map_err(|e| e.to_string())?,
+        ReportFormat::Csv => {
+            let mut csv = String::from(CSV_HEADER);
+            csv.push_str("\r\n");
+            for row in rows {
+                let fields = [
+                    csv_field(&row.path),
+                    row.size.to_string(),
+                    csv_field(row.hash.as_deref().unwrap_or_default()),
+                    row.capture_date.clone().unwrap_or_default(),
+                    row.duplicate_group
+                        .map(|g| g.to_string())
+                        .unwrap_or_default(),
+                    csv_field(row.camera.as_deref().unwrap_or_default()),
+                ];
+                csv.push_str(&fields.join(","));
+                csv.push_str("\r\n");
+            }
+            csv
+        }
+    };
+    if let Some(parent) = destination.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    fs::write(destination, contents).map_err(|e| e.to_string())
+}
+
+/// Quote a field when it holds a comma, quote or line break (RFC 4180)
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
@@ -0,0 +1,235 @@
+use crate::albums::Albums;
+use crate::hash_cache::HashCache;
+use crate::ratings::{Flag, Ratings};
+use crate::scanner::PhotoFile;
+use crate::tags::Tags;
+use rusqlite::{Connection, OpenFlags};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Collections made by hand; smart collections are saved searches with no members
+const REGULAR_COLLECTION: &str = "com.adobe.ag.library.collection";
+
+/// Outcome of `import_lightroom_catalog`
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LightroomImport {
+    /// Catalog photos found in the library
+    pub matched: usize,
+    /// Of those, photos found by content because they moved since Lightroom saw them
+    pub matched_by_hash: usize,
+    /// Catalog photos with no file in the library
+    pub unmatched: Vec<String>,
+    /// Photos given a rating, flag or label (photos already rated here are left alone)
+    pub rated: usize,
+    /// Keywords added, counted per photo
+    pub keywords: usize,
+    /// Albums the collections were added to
+    pub albums: Vec<String>,
+}
+
+/// A photo in a Lightroom catalog with what the app imports from it
+struct CatalogImage {
+    path: PathBuf,
+    /// 0 to 5 stars
+    rating: Option<i64>,
+    /// 1 picked, -1 rejected, 0 neither
+    pick: i64,
+    color_label: Option<String>,
+}
+
+/// Import ratings, flags, color labels, keywords and collections from a
+/// Lightroom Classic catalog for the photos it shares with the library
+/// Photos are matched by path, or, when the catalog's file is still on disk
+/// but somewhere else, by content against same-size library photos
+pub fn import_catalog(
+    catalog_path: &Path,
+    photos: &[PhotoFile],
+    cache: &HashCache,
+) -> Result<LightroomImport, String> {
+    let conn = Connection::open_with_flags(catalog_path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|e| format!("Could not open the Lightroom catalog: {}", e))?;
+    let images = read_images(&conn).map_err(|e| match e {
+        rusqlite::Error::SqliteFailure(f, _) if f.code == rusqlite::ErrorCode::DatabaseBusy => {
+            "The catalog is in use; close Lightroom and try again".to_string()
+        }
+        e => format!("Not a Lightroom catalog: {}", e),
+    })?;
+
+    let by_path: HashMap<&Path, &PhotoFile> =
+        photos.iter().map(|p| (Path::new(&p.path), p)).collect();
+    let mut by_size: HashMap<u64, Vec<&PhotoFile>> = HashMap::new();
+    for photo in photos {
+        by_size.entry(photo.size).or_default().push(photo);
+    }
+
+    let mut result = LightroomImport::default();
+    let mut matches: HashMap<i64, String> = HashMap::new();
+    for (id, image) in &images {
+        let found = match by_path.get(image.path.as_path()) {
+            Some(photo) => Some(photo.path.clone()),
+            None => {
+                let found = find_by_content(&image.path, &by_size, cache);
+                result.matched_by_hash += found.is_some() as usize;
+                found
+            }
+        };
+        match found {
+            Some(path) => {
+                matches.insert(*id, path);
+            }
+            None => result
+                .unmatched
+                .push(image.path.to_string_lossy().to_string()),
+        }
+    }
+    result.matched = matches.len();
+
+    let ratings = Ratings::open()?;
+    for (id, path) in &matches {
+        let image = &images[id];
+        if ratings.has_row(path)? {
+            continue;
+        }
+        let label = image.color_label.as_deref().filter(|l| !l.is_empty());
+        let rating = image.rating.filter(|&r| r > 0);
+        if rating.is_none() && image.pick == 0 && label.is_none() {
+            continue;
+        }
+        if let Some(stars) = rating {
+            ratings.set_rating(path, stars.clamp(0, 5) as u8)?;
+        }
+        match image.pick {
+            1 => ratings.set_flag(path, Some(Flag::Pick))?,
+            -1 => ratings.set_flag(path, Some(Flag::Reject))?,
+            _ => {}
+        }
+        if label.is_some() {
+            ratings.set_label(path, label)?;
+        }
+        result.rated += 1;
+    }
+
+    let tags = Tags::open()?;
+    let keywords = read_members(
+        &conn,
+        "SELECT ki.image, k.name FROM AgLibraryKeywordImage ki
+        JOIN AgLibraryKeyword k ON k.id_local = ki.tag
+        WHERE k.name IS NOT NULL AND k.name != ''",
+    )
+    .map_err(|e| e.to_string())?;
+    for (id, names) in keywords {
+        if let Some(path) = matches.get(&id) {
+            tags.add(std::slice::from_ref(path), &names)?;
+            result.keywords += names.len();
+        }
+    }
+
+    let collections = read_members(
+        &conn,
+        &format!(
+            "SELECT ci.image, c.name FROM AgLibraryCollectionImage ci
+            JOIN AgLibraryCollection c ON c.id_local = ci.collection
+            WHERE c.creationId = '{}' AND c.name IS NOT NULL",
+            REGULAR_COLLECTION
+        ),
+    )
+    .map_err(|e| e.to_string())?;
+    let mut members: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for (id, names) in collections {
+        if let Some(path) = matches.get(&id) {
+            for name in names {
+                members.entry(name).or_default().push(path.clone());
+            }
+        }
+    }
+    let albums = Albums::open()?;
+    let existing = albums.list()?;
+    for (name, paths) in members {
+        // A collection goes into the album of the same name, made if needed
+        let album_id = match existing.iter().find(|a| a.name.eq_ignore_ascii_case(&name)) {
+            Some(album) => album.id,
+            None => albums.create(&name)?.id,
+        };
+        albums.add(album_id, &paths, cache)?;
+        result.albums.push(name);
+    }
+
+    Ok(result)
+}
+
+fn read_images(conn: &Connection) -> rusqlite::Result<HashMap<i64, CatalogImage>> {
+    let mut stmt = conn.prepare(
+        "SELECT i.id_local, r.absolutePath, fo.pathFromRoot, f.baseName, f.extension,
+            i.rating, i.pick, i.colorLabels
+        FROM Adobe_images i
+        JOIN AgLibraryFile f ON f.id_local = i.rootFile
+        JOIN AgLibraryFolder fo ON fo.id_local = f.folder
+        JOIN AgLibraryRootFolder r ON r.id_local = fo.rootFolder",
+    )?;
+    let images = stmt
+        .query_map([], |row| {
+            let root: String = row.get(1)?;
+            let folder: String = row.get(2)?;
+            let base_name: String = row.get(3)?;
+            let extension: String = row.get(4)?;
+            let mut path = format!("{}{}{}", root, folder, base_name);
+            if !extension.is_empty() {
+                path = format!("{}.{}", path, extension);
+            }
+            Ok((
+                row.get(0)?,
+                CatalogImage {
+                    path: native_path(&path),
+                    rating: row.get::<_, Option<f64>>(5)?.map(|r| r.round() as i64),
+                    pick: row.get::<_, Option<f64>>(6)?.unwrap_or(0.0).round() as i64,
+                    color_label: row.get(7)?,
+                },
+            ))
+        })?
+        .collect::<rusqlite::Result<_>>()?;
+    Ok(images)
+}
+
+/// Names (keywords or collections) per image id
+fn read_members(conn: &Connection, sql: &str) -> rusqlite::Result<HashMap<i64, Vec<String>>> {
+    let mut stmt = conn.prepare(sql)?;
+    let mut members: HashMap<i64, Vec<String>> = HashMap::new();
+    for row in stmt.query_map([], |row| {
+        Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+    })? {
+        let (image, name) = row?;
+        members.entry(image).or_default().push(name);
+    }
+    Ok(members)
+}
+
+/// Lightroom stores paths with forward slashes on every platform
+fn native_path(path: &str) -> PathBuf {
+    if cfg!(windows) {
+        PathBuf::from(path.replace('/', "\\"))
+    } else {
+        PathBuf::from(path)
+    }
+}
+
+/// The library photo with the same content as a catalog file still on disk
+fn find_by_content(
+    path: &Path,
+    by_size: &HashMap<u64, Vec<&PhotoFile>>,
+    cache: &HashCache,
+) -> Option<String> {
+    let size = fs::metadata(path).ok()?.len();
+    let candidates = by_size.get(&size)?;
+    let hash = cache.full_hash(&path.to_string_lossy(), size)?;
+    candidates
+        .iter()
+        .find(|photo| {
+            photo.hash.as_deref() == Some(hash.as_str())
+                || (photo.hash.is_none()
+                    && cache.full_hash(&photo.path, size).as_deref() == Some(hash.as_str()))
+        })
+        .map(|photo| photo.path.clone())
+}
@@ -0,0 +1,62 @@
+use crate::media_info::probe_content_identifier;
+use crate::metadata::read_content_identifier;
+use crate::scanner::is_cloud_placeholder;
+use rayon::prelude::*;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+/// Still formats an iPhone saves the photo half of a Live Photo in
+pub fn is_live_still(extension: &str) -> bool {
+    matches!(extension, "jpg" | "jpeg" | "heic" | "heif")
+}
+
+/// Videos among the scanned groups that belong to a Live Photo: a MOV next to
+/// a still of the same name that carries the same content identifier
+pub fn find_videos(file_groups: &HashMap<String, Vec<PathBuf>>) -> HashSet<PathBuf> {
+    let mut candidates: Vec<(&PathBuf, &PathBuf)> = Vec::new();
+    for group in file_groups.values() {
+        for video in group.iter().filter(|p| extension(p) == "mov") {
+            for still in group.iter().filter(|p| is_live_still(&extension(p))) {
+                candidates.push((still, video));
+            }
+        }
+    }
+
+    candidates
+        .into_par_iter()
+        .filter(|(still, video)| is_pair(still, video))
+        .map(|(_, video)| video.clone())
+        .collect()
+}
+
+/// The Live Photo video stored next to a still, if there is one
+pub fn video_of(still: &Path) -> Option<PathBuf> {
+    if !is_live_still(&extension(still)) {
+        return None;
+    }
+    let stem = still.file_stem()?.to_str()?;
+    ["MOV", "mov"]
+        .iter()
+        .map(|ext| still.with_file_name(format!("{}.{}", stem, ext)))
+        .find(|video| video.is_file() && is_pair(still, video))
+}
+
+/// Whether both files carry the same content identifier; cloud placeholders
+/// are never opened, since reading them would download them
+fn is_pair(still: &Path, video: &Path) -> bool {
+    let placeholder = |p: &Path| is_cloud_placeholder(&p.to_string_lossy());
+    if placeholder(still) || placeholder(video) {
+        return false;
+    }
+    match read_content_identifier(still) {
+        Some(id) => probe_content_identifier(video).is_some_and(|v| v.eq_ignore_ascii_case(&id)),
+        None => false,
+    }
+}
+
+fn extension(path: &Path) -> String {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase())
+        .unwrap_or_default()
+}
@@ -0,0 +1,234 @@
+use crate::analysis::AnalysisStore;
+use crate::catalog::Catalog;
+use crate::tags::TagCount;
+use rusqlite::{params, Connection};
+
+/// Labels a model gave photos ("dog", "beach", "screenshot"), stored in the
+/// catalog apart from the user's own tags
+/// They are never written to sidecars, and are replaced whenever a photo is
+/// labeled again. The model is read from the model folder in builds with
+/// the `machine-tags` feature; everything else here works without it
+pub struct MachineTags {
+    conn: Connection,
+}
+
+/// A label and how sure the model is of it, 0 to 1
+pub struct Label {
+    pub tag: String,
+    pub confidence: f32,
+}
+
+/// Create the machine tag tables; called when the catalog is opened so
+/// searches can filter on them before anything has been labeled
+pub fn create_tables(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS machine_tags (
+            path TEXT NOT NULL,
+            tag TEXT NOT NULL COLLATE NOCASE,
+            confidence REAL NOT NULL,
+            PRIMARY KEY (path, tag)
+        );
+        CREATE INDEX IF NOT EXISTS idx_machine_tags_tag ON machine_tags(tag);
+        CREATE TABLE IF NOT EXISTS machine_tag_scans (
+            path TEXT PRIMARY KEY,
+            modified_at INTEGER NOT NULL
+        );
+        ",
+    )
+}
+
+impl AnalysisStore for MachineTags {
+    const SCANS: &'static str = "machine_tag_scans";
+    const TABLES: &'static [&'static str] = &["machine_tags", "machine_tag_scans"];
+
+    fn connection(&self) -> &Connection {
+        &self.conn
+    }
+}
+
+impl MachineTags {
+    /// Open the machine tag tables in the catalog database
+    pub fn open() -> Result<Self, String> {
+        let conn = Catalog::connection()?;
+        Ok(Self { conn })
+    }
+
+    /// Store a photo's labels in place of the ones it had
+    pub fn replace(
+        &mut self,
+        path: &str,
+        modified_at: i64,
+        labels: &[Label],
+    ) -> Result<(), String> {
+        let tx = self.conn.transaction().map_err(|e| e.to_string())?;
+        tx.execute("DELETE FROM machine_tags WHERE path = ?1", params![path])
+            .map_err(|e| e.to_string())?;
+        for label in labels {
+            tx.execute(
+                "INSERT OR REPLACE INTO machine_tags (path, tag, confidence) VALUES (?1, ?2, ?3)",
+                params![path, label.tag, label.confidence],
+            )
+            .map_err(|e| e.to_string())?;
+        }
+        tx.execute(
+            "INSERT OR REPLACE INTO machine_tag_scans (path, modified_at) VALUES (?1, ?2)",
+            params![path, modified_at],
+        )
+        .map_err(|e| e.to_string())?;
+        tx.commit().map_err(|e| e.to_string())
+    }
+
+    /// Labels of a single file, most confident first
+    pub fn for_path(&self, path: &str) -> Result<Vec<String>, String> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT tag FROM machine_tags WHERE path = ?1 ORDER BY confidence DESC")
+            .map_err(|e| e.to_string())?;
+        let tags = stmt
+            .query_map(params![path], |row| row.get(0))
+            .map_err(|e| e.to_string())?
+            .collect::<Result<Vec<String>, _>>()
+            .map_err(|e| e.to_string())?;
+        Ok(tags)
+    }
+
+    /// Every label in use with its photo count, most used first
+    pub fn list(&self) -> Result<Vec<TagCount>, String> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT tag, COUNT(*) FROM machine_tags
+                GROUP BY tag ORDER BY COUNT(*) DESC, tag",
+            )
+            .map_err(|e| e.to_string())?;
+        let tags = stmt
+            .query_map([], |row| {
+                Ok(TagCount {
+                    name: row.get(0)?,
+                    count: row.get::<_, i64>(1)? as usize,
+                })
+            })
+            .map_err(|e| e.to_string())?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+        Ok(tags)
+    }
+}
+
+/// Follow a labeled file the app moved or renamed, so it isn't labeled again
+pub fn relocate(conn: &Connection, from: &str, to: &str) -> rusqlite::Result<()> {
+    for table in ["machine_tags", "machine_tag_scans"] {
+        conn.execute(
+            &format!("DELETE FROM {} WHERE path = ?1", table),
+            params![to],
+        )?;
+        conn.execute(
+            &format!("UPDATE {} SET path = ?2 WHERE path = ?1", table),
+            params![from, to],
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(feature = "machine-tags")]
+pub use model::Classifier;
+
+/// Stand-in for builds without the `machine-tags` feature, which can't label
+/// photos
+#[cfg(not(feature = "machine-tags"))]
+pub struct Classifier;
+
+#[cfg(not(feature = "machine-tags"))]
+impl Classifier {
+    pub fn load() -> Result<Self, String> {
+        Err("This build can't label photos; it needs the `machine-tags` feature".to_string())
+    }
+
+    pub fn classify(&self, _path: &std::path::Path) -> Result<Vec<Label>, String> {
+        Err("This build can't label photos".to_string())
+    }
+}
+
+#[cfg(feature = "machine-tags")]
+mod model {
+    use super::Label;
+    use crate::onnx::{load_model, load_upright, read_model_file, tensor, Model};
+    use image::imageops::FilterType;
+    use image::DynamicImage;
+    use std::collections::HashMap;
+    use std::path::Path;
+    use tract_onnx::prelude::*;
+
+    /// An ImageNet-style classifier such as MobileNetV2: a 224×224 RGB image
+    /// in, a logit for each class out
+    const CLASSIFIER: &str = "classifier.onnx";
+    const INPUT_SIZE: u32 = 224;
+    /// ImageNet channel means and deviations, on the 0 to 255 scale
+    const MEAN: [f32; 3] = [123.675, 116.28, 103.53];
+    const DEVIATION: [f32; 3] = [58.395, 57.12, 57.375];
+
+    /// The tag for each class, one line per class in the model's order; a
+    /// blank line drops the class. Classes sharing a tag (every dog breed as
+    /// "dog") add up their probabilities
+    const LABELS: &str = "classifier-labels.txt";
+
+    /// Probability a tag needs to be kept
+    const MIN_CONFIDENCE: f32 = 0.2;
+    /// Most tags kept per photo
+    const MAX_LABELS: usize = 5;
+
+    /// The classifier and its tags, loaded once per labeling job
+    pub struct Classifier {
+        model: Model,
+        tags: Vec<Option<String>>,
+    }
+
+    impl Classifier {
+        pub fn load() -> Result<Self, String> {
+            let labels = String::from_utf8(read_model_file(LABELS)?)
+                .map_err(|_| format!("{} is not UTF-8 text", LABELS))?;
+            Ok(Self {
+                model: load_model(CLASSIFIER, [1, 3, INPUT_SIZE, INPUT_SIZE])?,
+                tags: labels
+                    .lines()
+                    .map(|line| Some(line.trim().to_lowercase()).filter(|t| !t.is_empty()))
+                    .collect(),
+            })
+        }
+
+        /// Tags for a photo, most likely first
+        pub fn classify(&self, path: &Path) -> Result<Vec<Label>, String> {
+            // The middle of the upright photo, as the model was trained on
+            let image = DynamicImage::ImageRgb8(load_upright(path)?)
+                .resize_to_fill(INPUT_SIZE, INPUT_SIZE, FilterType::Triangle)
+                .to_rgb8();
+            let outputs = self
+                .model
+                .run(tvec!(tensor(&image, MEAN, DEVIATION).into()))
+                .map_err(|e| e.to_string())?;
+            let logits = outputs[0].as_slice::<f32>().map_err(|e| e.to_string())?;
+
+            let max = logits.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+            let exponents: Vec<f32> = logits.iter().map(|l| (l - max).exp()).collect();
+            let total: f32 = exponents.iter().sum();
+            let mut by_tag: HashMap<&str, f32> = HashMap::new();
+            for (tag, exponent) in self.tags.iter().zip(&exponents) {
+                if let Some(tag) = tag {
+                    *by_tag.entry(tag).or_default() += exponent / total;
+                }
+            }
+
+            let mut labels: Vec<Label> = by_tag
+                .into_iter()
+                .filter(|(_, p)| *p >= MIN_CONFIDENCE)
+                .map(|(tag, confidence)| Label {
+                    tag: tag.to_string(),
+                    confidence,
+                })
+                .collect();
+            labels.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+            labels.truncate(MAX_LABELS);
+            Ok(labels)
+        }
+    }
+}
@@ -1,27 +1,263 @@
+mod albums;
+mod analysis;
+mod animation;
+mod archive;
+mod backup_compare;
+mod bursts;
+mod capture_time;
+mod catalog;
+mod cloud_files;
 mod commands;
+mod companions;
+mod compare;
 mod config;
+mod consolidation;
+mod convert;
+mod date_mismatch;
+mod delete_confirmation;
+mod directory_tree;
+mod duplicates;
+mod editors;
+mod error;
+mod events;
+mod exclusions;
+mod export;
+mod faces;
+mod file_ops;
+mod folder_review;
+mod format_check;
+mod geocode;
+mod geotag;
+mod gpx;
 mod hash_cache;
+mod ids;
+mod import_report;
+mod ingest;
+mod jobs;
+mod library;
+mod library_report;
+mod lightroom;
+mod live_photos;
+mod machine_tags;
+mod media_info;
+mod metadata;
+mod notifications;
+#[cfg(any(feature = "faces", feature = "machine-tags"))]
+mod onnx;
+mod operation_log;
+mod pairings;
+mod photo_protocol;
+mod pixel_hash;
+mod quality;
+mod ratings;
+mod raw_preview;
+mod report;
+mod rules;
+mod scan_checkpoint;
+mod scan_options;
 mod scanner;
+mod session;
+mod shutdown;
+mod smart_albums;
+mod snapshots;
+mod source_kind;
+mod stacks;
+mod stats;
+mod tags;
+mod takeout;
+mod transcode;
+mod transform;
+mod transliterate;
+mod trash_history;
+mod volumes;
+mod windows;
+mod xmp;
 
 use commands::{
-    create_folder, load_config, move_files, move_files_batch, rename_file, reveal_in_finder,
-    save_config, scan_directories, trash_files,
+    add_tags, add_to_album, apply_rules, browse_snapshot, cache_stats, cancel_job, clear_cache,
+    cluster_events, compare_photos, compare_trees, compared_photos, convert_images, correlate_gpx,
+    create_album, create_folder, create_smart_album, create_stack, deduplicate_by_clonefile,
+    deduplicate_by_hardlink, delete_permanently, delete_smart_album, detect_faces,
+    detect_machine_tags, dissolve_stack, download_cloud_files, evaluate_smart_album,
+    evict_cloud_files, execute_consolidation, export_files, export_report, export_zip,
+    find_blurry_photos, find_date_mismatches, flip_image, folder_stats, get_consolidation_plan,
+    get_duplicate_groups, get_events, get_import_reports, get_interrupted_scan,
+    get_operation_history, get_photo, get_ratings, get_stacks, get_unreviewed_folders,
+    hash_pending_files, heif_preview, import_from_device, import_lightroom_catalog, import_takeout,
+    library_stats, list_albums, list_archived, list_available_editors, list_directory_tree,
+    list_group_members, list_import_devices, list_jobs, list_machine_tags, list_people,
+    list_photo_groups, list_scan_presets, list_smart_albums, list_snapshots, list_stacks,
+    list_tags, list_volumes, load_config, load_session, mark_folder_reviewed, move_files,
+    move_files_batch, move_group, name_person, open_compare_window, open_with, organize_by_date,
+    pause_job, plan_consolidation, preview_rules, prune_cache, query_photos, quick_look,
+    rank_duplicates, refresh_catalog, remove_empty_directories, remove_from_album, remove_tags,
+    rename_file, rename_files_batch, rename_group, resolve_top_groups, restore_snapshot_metadata,
+    resume_job, resume_scan, reveal_in_finder, rotate_image, safe_to_format, save_config,
+    save_session, scan_directories, score_quality, search_photos, set_archived, set_flag,
+    set_gps_coordinates, set_label, set_rating, set_stack_cover, shift_capture_time,
+    skip_stalled_file, stack_files, suggest_best_in_stack, take_snapshot, transcode_videos,
+    trash_files, trash_group, unstack_files, unstack_photos, verify_fixity, verify_import,
 };
+use jobs::Jobs;
+use library::Library;
+use windows::ComparedPhotos;
 
 fn main() {
+    // The catalog schema is brought up to date once, before anything opens it
+    if let Err(e) = catalog::Catalog::connection() {
+        eprintln!("Failed to open catalog: {}", e);
+    }
+
+    // A daily snapshot of the catalog, taken off the startup path
+    std::thread::spawn(|| {
+        if let Err(e) = snapshots::take_if_due() {
+            eprintln!("Failed to snapshot catalog: {}", e);
+        }
+    });
+
+    // State is shared by every window (the library grid, the compare window),
+    // so they all see the same photos and the same job queue
     tauri::Builder::default()
+        .manage(Library::from_catalog())
+        .manage(Jobs::default())
+        .manage(ComparedPhotos::default())
+        .register_uri_scheme_protocol("photo", |_app, request| photo_protocol::handle(request))
+        .setup(|app| {
+            // One watcher for the whole app; it tells every window
+            volumes::watch(app.handle());
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             scan_directories,
             load_config,
             save_config,
+            save_session,
+            load_session,
             move_files,
             move_files_batch,
+            organize_by_date,
+            preview_rules,
+            apply_rules,
+            plan_consolidation,
+            get_consolidation_plan,
+            execute_consolidation,
+            mark_folder_reviewed,
+            get_unreviewed_folders,
+            list_directory_tree,
+            folder_stats,
+            library_stats,
+            find_date_mismatches,
             trash_files,
+            delete_permanently,
+            remove_empty_directories,
             rename_file,
+            rename_files_batch,
+            move_group,
+            rename_group,
+            trash_group,
+            get_operation_history,
             create_folder,
             reveal_in_finder,
+            quick_look,
+            heif_preview,
+            open_with,
+            list_available_editors,
+            open_compare_window,
+            compared_photos,
+            compare_photos,
+            list_photo_groups,
+            list_group_members,
+            get_duplicate_groups,
+            rank_duplicates,
+            export_report,
+            import_lightroom_catalog,
+            resolve_top_groups,
+            deduplicate_by_hardlink,
+            deduplicate_by_clonefile,
+            cache_stats,
+            prune_cache,
+            clear_cache,
+            verify_fixity,
+            verify_import,
+            get_import_reports,
+            safe_to_format,
+            compare_trees,
+            list_volumes,
+            list_import_devices,
+            import_from_device,
+            import_takeout,
+            cancel_job,
+            list_jobs,
+            pause_job,
+            resume_job,
+            skip_stalled_file,
+            download_cloud_files,
+            evict_cloud_files,
+            convert_images,
+            export_files,
+            export_zip,
+            query_photos,
+            search_photos,
+            get_photo,
+            list_snapshots,
+            take_snapshot,
+            browse_snapshot,
+            restore_snapshot_metadata,
+            create_album,
+            add_to_album,
+            remove_from_album,
+            list_albums,
+            create_smart_album,
+            evaluate_smart_album,
+            list_smart_albums,
+            delete_smart_album,
+            set_archived,
+            list_archived,
+            create_stack,
+            set_stack_cover,
+            unstack_photos,
+            dissolve_stack,
+            get_stacks,
+            stack_files,
+            unstack_files,
+            list_stacks,
+            add_tags,
+            remove_tags,
+            list_scan_presets,
+            list_tags,
+            set_rating,
+            set_flag,
+            set_label,
+            get_ratings,
+            set_gps_coordinates,
+            correlate_gpx,
+            shift_capture_time,
+            rotate_image,
+            flip_image,
+            cluster_events,
+            get_events,
+            detect_faces,
+            list_people,
+            name_person,
+            detect_machine_tags,
+            list_machine_tags,
+            score_quality,
+            find_blurry_photos,
+            suggest_best_in_stack,
+            refresh_catalog,
+            resume_scan,
+            get_interrupted_scan,
+            hash_pending_files,
+            transcode_videos,
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        // Focusing after a notification opens its view; closing the main window
+        // closes the others, and quitting waits for moves and deletes in progress
+        .on_window_event(|event| {
+            notifications::on_window_event(&event);
+            windows::on_window_event(&event);
+            shutdown::on_window_event(event);
+        })
+        .build(tauri::generate_context!())
+        .expect("error while running tauri application")
+        .run(shutdown::on_run_event);
 }
-
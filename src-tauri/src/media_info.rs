@@ -0,0 +1,102 @@
+use crate::transcode::find_tool;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::Path;
+use std::process::Command;
+
+/// Video extensions scanned alongside photos
+pub const VIDEO_EXTENSIONS: &[&str] = &["mov", "mp4", "m4v", "avi", "mkv", "mts", "m2ts", "3gp"];
+
+/// Stream and container details of a video file
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaInfo {
+    pub duration_secs: Option<f64>,
+    /// Video codec name as reported by ffprobe ("h264", "hevc", "prores", ...)
+    pub codec: Option<String>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub frame_rate: Option<f64>,
+    /// Overall bitrate in bit/s
+    pub bitrate: Option<u64>,
+    /// Recording time from the container's creation_time tag, in milliseconds since epoch
+    pub created_at: Option<i64>,
+}
+
+pub fn is_video_extension(extension: &str) -> bool {
+    VIDEO_EXTENSIONS.contains(&extension)
+}
+
+/// Probe a video with ffprobe; None when ffprobe is missing or can't read the file
+pub fn probe_media_info(path: &Path) -> Option<MediaInfo> {
+    let ffprobe = find_tool("ffprobe")?;
+    let output = Command::new(ffprobe)
+        .args(["-v", "error", "-select_streams", "v:0"])
+        .args([
+            "-show_entries",
+            "stream=codec_name,width,height,avg_frame_rate,bit_rate:format=duration,bit_rate:format_tags=creation_time",
+        ])
+        .args(["-of", "json"])
+        .arg(path)
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    parse_ffprobe_json(&serde_json::from_slice(&output.stdout).ok()?)
+}
+
+/// Apple's content identifier from a QuickTime movie's metadata; the still of
+/// the same Live Photo carries the same value
+pub fn probe_content_identifier(path: &Path) -> Option<String> {
+    let ffprobe = find_tool("ffprobe")?;
+    let output = Command::new(ffprobe)
+        .args(["-v", "error", "-show_entries", "format_tags", "-of", "json"])
+        .arg(path)
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let json: Value = serde_json::from_slice(&output.stdout).ok()?;
+    json["format"]["tags"]["com.apple.quicktime.content.identifier"]
+        .as_str()
+        .map(|id| id.trim().to_string())
+        .filter(|id| !id.is_empty())
+}
+
+fn parse_ffprobe_json(json: &Value) -> Option<MediaInfo> {
+    let stream = json["streams"].get(0)?;
+    let format = &json["format"];
+
+    Some(MediaInfo {
+        duration_secs: number(&format["duration"]),
+        codec: stream["codec_name"].as_str().map(str::to_string),
+        width: stream["width"].as_u64().map(|w| w as u32),
+        height: stream["height"].as_u64().map(|h| h as u32),
+        frame_rate: stream["avg_frame_rate"].as_str().and_then(parse_rate),
+        // The container bitrate covers audio too; fall back to the video stream's
+        bitrate: number(&format["bit_rate"])
+            .or_else(|| number(&stream["bit_rate"]))
+            .map(|b| b as u64),
+        created_at: format["tags"]["creation_time"]
+            .as_str()
+            .and_then(|t| chrono::DateTime::parse_from_rfc3339(t).ok())
+            .map(|t| t.timestamp_millis()),
+    })
+}
+
+/// ffprobe prints most numbers as strings
+fn number(value: &Value) -> Option<f64> {
+    value
+        .as_str()
+        .and_then(|s| s.parse().ok())
+        .or_else(|| value.as_f64())
+}
+
+/// Parse a rational frame rate such as "30000/1001"
+fn parse_rate(rate: &str) -> Option<f64> {
+    let (num, den) = rate.split_once('/')?;
+    let (num, den): (f64, f64) = (num.parse().ok()?, den.parse().ok()?);
+    (den > 0.0 && num > 0.0).then(|| num / den)
+}
@@ -0,0 +1,267 @@
+use crate::file_ops::copy_file_times;
+use chrono::NaiveDateTime;
+use exif::{Exif, In, Reader, Tag, Value};
+use little_exif::filetype::FileExtension;
+use little_exif::metadata::Metadata;
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::path::Path;
+
+/// Subset of EXIF metadata surfaced on `PhotoFile`
+#[derive(Debug, Default, Clone)]
+pub struct ExifSummary {
+    pub capture_date: Option<NaiveDateTime>,
+    pub camera_make: Option<String>,
+    pub camera_model: Option<String>,
+    /// GPS position in decimal degrees, north and east positive
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    /// EXIF orientation, 1 (upright) to 8
+    pub orientation: Option<u16>,
+    /// Pixel size recorded in EXIF, for formats whose header the image crate
+    /// can't read (HEIF, RAW)
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+}
+
+/// Read and parse the EXIF block of an image file
+/// Works for JPEG, HEIF, PNG, WebP and TIFF-based RAW formats (ARW, NEF, DNG, ...)
+fn read_exif(path: &Path) -> Option<Exif> {
+    let file = File::open(path).ok()?;
+    let mut reader = BufReader::new(file);
+    Reader::new().read_from_container(&mut reader).ok()
+}
+
+/// Whether the file has an EXIF block that can be read
+pub fn has_exif(path: &Path) -> bool {
+    read_exif(path).is_some()
+}
+
+/// Read the EXIF summary (capture date, camera) from an image file
+pub fn read_exif_summary(path: &Path) -> Option<ExifSummary> {
+    let exif = read_exif(path)?;
+    Some(ExifSummary {
+        capture_date: capture_date_from_exif(&exif),
+        camera_make: ascii_field(&exif, Tag::Make),
+        camera_model: ascii_field(&exif, Tag::Model),
+        latitude: gps_coordinate(&exif, Tag::GPSLatitude, Tag::GPSLatitudeRef, b'S'),
+        longitude: gps_coordinate(&exif, Tag::GPSLongitude, Tag::GPSLongitudeRef, b'W'),
+        orientation: uint_field(&exif, Tag::Orientation)
+            .filter(|o| (1..=8).contains(o))
+            .map(|o| o as u16),
+        width: uint_field(&exif, Tag::PixelXDimension),
+        height: uint_field(&exif, Tag::PixelYDimension),
+    })
+}
+
+/// Formats whose EXIF can be rewritten safely: JPEG, PNG, WebP and TIFF
+/// RAW, HEIC and video files are never rewritten
+pub fn writable_exif_type(path: &Path) -> Option<FileExtension> {
+    let extension = path.extension()?.to_str()?.to_lowercase();
+    match extension.as_str() {
+        "jpg" | "jpeg" => Some(FileExtension::JPEG),
+        "png" => Some(FileExtension::PNG {
+            as_zTXt_chunk: true,
+        }),
+        "webp" => Some(FileExtension::WEBP),
+        "tif" | "tiff" => Some(FileExtension::TIFF),
+        _ => None,
+    }
+}
+
+/// Change EXIF tags with `edit`, writing the whole file to a temporary copy
+/// that then replaces the original, so an interrupted write never corrupts
+/// the photo; the file's dates are kept
+pub fn update_exif(
+    path: &Path,
+    file_type: FileExtension,
+    edit: impl FnOnce(&mut Metadata),
+) -> Result<(), String> {
+    let mut data = fs::read(path).map_err(|e| e.to_string())?;
+    let mut metadata = match Metadata::new_from_vec(&data, file_type) {
+        Ok(metadata) => metadata,
+        // Only start from scratch when there really is nothing to keep
+        Err(_) if !has_exif(path) => Metadata::new(),
+        Err(e) => return Err(format!("Could not read EXIF: {}", e)),
+    };
+    edit(&mut metadata);
+    metadata
+        .write_to_vec(&mut data, file_type)
+        .map_err(|e| format!("Could not write EXIF: {}", e))?;
+
+    let file_name = path.file_name().ok_or("Invalid file name")?;
+    let temp = path.with_file_name(format!(".{}.exif", file_name.to_string_lossy()));
+    let written = fs::write(&temp, &data)
+        .and_then(|_| copy_file_times(path, &temp))
+        .and_then(|_| fs::rename(&temp, path));
+    if let Err(e) = written {
+        let _ = fs::remove_file(&temp);
+        return Err(e.to_string());
+    }
+    Ok(())
+}
+
+/// Stored pixel size from the image header, without decoding the pixels
+pub fn header_dimensions(path: &Path) -> Option<(u32, u32)> {
+    image::image_dimensions(path).ok()
+}
+
+/// Size as displayed: orientations 5 to 8 turn the image a quarter turn, so
+/// width and height swap
+pub fn oriented_dimensions(width: u32, height: u32, orientation: Option<u16>) -> (u32, u32) {
+    match orientation {
+        Some(5..=8) => (height, width),
+        _ => (width, height),
+    }
+}
+
+/// Read the EXIF capture date (DateTimeOriginal) from an image file
+pub fn read_capture_date(path: &Path) -> Option<NaiveDateTime> {
+    capture_date_from_exif(&read_exif(path)?)
+}
+
+/// Apple's content identifier, which an iPhone writes into both halves of a
+/// Live Photo: the still's maker note and the video's QuickTime metadata
+pub fn read_content_identifier(path: &Path) -> Option<String> {
+    let exif = read_exif(path)?;
+    match exif
+        .get_field(Tag::MakerNote, In::PRIMARY)
+        .map(|f| &f.value)
+    {
+        Some(Value::Undefined(note, _)) => apple_maker_note_string(note, 0x0011),
+        _ => None,
+    }
+}
+
+/// ASCII entry `tag` of an Apple maker note: "Apple iOS\0", a version, "MM",
+/// then a big-endian IFD whose offsets count from the start of the note
+fn apple_maker_note_string(note: &[u8], tag: u16) -> Option<String> {
+    if !note.starts_with(b"Apple iOS\0") || note.get(12..14)? != b"MM" {
+        return None;
+    }
+    let u16_at = |at: usize| Some(u16::from_be_bytes(note.get(at..at + 2)?.try_into().ok()?));
+    let u32_at = |at: usize| Some(u32::from_be_bytes(note.get(at..at + 4)?.try_into().ok()?));
+
+    let count = u16_at(14)? as usize;
+    for entry in (0..count).map(|i| 16 + i * 12) {
+        // Type 2 is ASCII
+        if u16_at(entry)? != tag || u16_at(entry + 2)? != 2 {
+            continue;
+        }
+        let length = u32_at(entry + 4)? as usize;
+        let start = if length <= 4 {
+            entry + 8
+        } else {
+            u32_at(entry + 8)? as usize
+        };
+        let text = String::from_utf8_lossy(note.get(start..start.checked_add(length)?)?);
+        let text = text.trim_matches(|c: char| c.is_whitespace() || c == '\0');
+        return (!text.is_empty()).then(|| text.to_string());
+    }
+    None
+}
+
+/// Capture date from EXIF, falling back to the file's creation/modification time
+pub fn capture_date_or_file_date(path: &Path) -> Option<NaiveDateTime> {
+    read_capture_date(path).or_else(|| {
+        let metadata = fs::metadata(path).ok()?;
+        let time = metadata.created().or_else(|_| metadata.modified()).ok()?;
+        let local: chrono::DateTime<chrono::Local> = time.into();
+        Some(local.naive_local())
+    })
+}
+
+/// Expand a folder template like `YYYY/YYYY-MM` for the given date
+pub fn expand_date_template(template: &str, date: &NaiveDateTime) -> String {
+    template
+        .replace("YYYY", &date.format("%Y").to_string())
+        .replace("MM", &date.format("%m").to_string())
+        .replace("DD", &date.format("%d").to_string())
+}
+
+fn capture_date_from_exif(exif: &Exif) -> Option<NaiveDateTime> {
+    // Prefer the original capture time, then digitized, then the generic DateTime tag
+    // Each has its own sub-second tag, which bursts need to be told apart
+    for (tag, subsec) in [
+        (Tag::DateTimeOriginal, Tag::SubSecTimeOriginal),
+        (Tag::DateTimeDigitized, Tag::SubSecTimeDigitized),
+        (Tag::DateTime, Tag::SubSecTime),
+    ] {
+        if let Some(field) = exif.get_field(tag, In::PRIMARY) {
+            if let Value::Ascii(ref values) = field.value {
+                let subsec = match exif.get_field(subsec, In::PRIMARY).map(|f| &f.value) {
+                    Some(Value::Ascii(values)) => values.first().map(Vec::as_slice),
+                    _ => None,
+                };
+                if let Some(date) = values.first().and_then(|v| parse_exif_datetime(v, subsec)) {
+                    return Some(date);
+                }
+            }
+        }
+    }
+    None
+}
+
+/// Decimal degrees from a degrees/minutes/seconds GPS tag and its N/S or E/W
+/// reference; `negative` is the reference for south or west
+fn gps_coordinate(exif: &Exif, tag: Tag, reference: Tag, negative: u8) -> Option<f64> {
+    let field = exif.get_field(tag, In::PRIMARY)?;
+    let Value::Rational(ref parts) = field.value else {
+        return None;
+    };
+    let degrees = parts
+        .iter()
+        .take(3)
+        .zip([1.0, 60.0, 3600.0])
+        .map(|(part, scale)| part.to_f64() / scale)
+        .sum::<f64>();
+    if !degrees.is_finite() {
+        return None;
+    }
+    let is_negative = match exif.get_field(reference, In::PRIMARY).map(|f| &f.value) {
+        Some(Value::Ascii(values)) => values
+            .first()
+            .and_then(|v| v.first())
+            .is_some_and(|c| c.to_ascii_uppercase() == negative),
+        _ => false,
+    };
+    Some(if is_negative { -degrees } else { degrees })
+}
+
+/// Read a SHORT or LONG field
+fn uint_field(exif: &Exif, tag: Tag) -> Option<u32> {
+    exif.get_field(tag, In::PRIMARY)?
+        .value
+        .get_uint(0)
+        .filter(|v| *v > 0)
+}
+
+/// Read a trimmed ASCII field, ignoring empty values
+fn ascii_field(exif: &Exif, tag: Tag) -> Option<String> {
+    let field = exif.get_field(tag, In::PRIMARY)?;
+    if let Value::Ascii(ref values) = field.value {
+        let text = String::from_utf8_lossy(values.first()?);
+        let text = text.trim_matches(|c: char| c.is_whitespace() || c == '\0');
+        if !text.is_empty() {
+            return Some(text.to_string());
+        }
+    }
+    None
+}
+
+/// Parse an EXIF ASCII datetime ("YYYY:MM:DD HH:MM:SS") and its optional
+/// sub-second digits
+fn parse_exif_datetime(raw: &[u8], subsec: Option<&[u8]>) -> Option<NaiveDateTime> {
+    let mut dt = exif::DateTime::from_ascii(raw).ok()?;
+    if let Some(subsec) = subsec {
+        // Malformed digits just leave the time at whole seconds
+        let _ = dt.parse_subsec(subsec);
+    }
+    chrono::NaiveDate::from_ymd_opt(dt.year as i32, dt.month as u32, dt.day as u32)?
+        .and_hms_nano_opt(
+            dt.hour as u32,
+            dt.minute as u32,
+            dt.second as u32,
+            dt.nanosecond.unwrap_or(0),
+        )
+}
@@ -0,0 +1,59 @@
+use crate::windows::{app_focused, MAIN_WINDOW};
+use serde::Serialize;
+use std::sync::Mutex;
+use tauri::api::notification::Notification;
+use tauri::{GlobalWindowEvent, Manager, Window, WindowEvent};
+
+/// View the last notification was about, opened when the app is next focused
+static PENDING_VIEW: Mutex<Option<String>> = Mutex::new(None);
+
+/// `open-view` payload
+#[derive(Debug, Clone, Serialize)]
+pub struct OpenView {
+    /// "library" | "import" | "import-reports"
+    pub view: String,
+}
+
+/// Post a native notification when a background job finishes or fails while
+/// the app is in the background; nothing is posted while one of its windows
+/// has focus
+/// `describe` summarises a successful result for the notification body
+pub fn job_finished<T>(
+    window: &Window,
+    job: &str,
+    view: &str,
+    result: &Result<T, String>,
+    describe: impl FnOnce(&T) -> String,
+) {
+    if app_focused(&window.app_handle()) {
+        return;
+    }
+    let (title, body) = match result {
+        Ok(value) => (format!("{} finished", job), describe(value)),
+        Err(error) => (format!("{} failed", job), error.clone()),
+    };
+    if let Ok(mut pending) = PENDING_VIEW.lock() {
+        *pending = Some(view.to_string());
+    }
+    let identifier = window.config().tauri.bundle.identifier.clone();
+    if let Err(e) = Notification::new(identifier).title(title).body(body).show() {
+        eprintln!("Failed to post notification: {}", e);
+    }
+}
+
+/// Clicking a notification brings the app forward; the main window is then sent
+/// `open-view` with the view the notification was about
+pub fn on_window_event(event: &GlobalWindowEvent) {
+    let WindowEvent::Focused(true) = event.event() else {
+        return;
+    };
+    let view = PENDING_VIEW
+        .lock()
+        .ok()
+        .and_then(|mut pending| pending.take());
+    if let Some(view) = view {
+        if let Some(main) = event.window().get_window(MAIN_WINDOW) {
+            let _ = main.emit("open-view", OpenView { view });
+        }
+    }
+}
@@ -0,0 +1,68 @@
+use crate::photo_protocol::decodable_source;
+use image::metadata::Orientation;
+use image::{DynamicImage, ImageDecoder, ImageReader, RgbImage};
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use tract_onnx::prelude::*;
+
+/// An ONNX model optimized for a fixed input shape, ready to run
+pub type Model = TypedRunnableModel<TypedModel>;
+
+/// Folder the ONNX models are read from, as they aren't shipped with the app
+pub fn model_dir() -> PathBuf {
+    dirs::config_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("photo-manager")
+        .join("models")
+}
+
+/// The contents of `name` in the model folder, or an error saying where it
+/// should be placed
+pub fn read_model_file(name: &str) -> Result<Vec<u8>, String> {
+    let path = model_dir().join(name);
+    std::fs::read(&path).map_err(|e| match e.kind() {
+        std::io::ErrorKind::NotFound => {
+            format!("Model {} is missing; place it at {}", name, path.display())
+        }
+        _ => format!("Failed to read {}: {}", path.display(), e),
+    })
+}
+
+/// Load the model `name` from the model folder for a single input of `shape`
+/// (N×C×H×W)
+pub fn load_model(name: &str, shape: [u32; 4]) -> Result<Model, String> {
+    let bytes = read_model_file(name)?;
+    let shape = shape.map(|d| d as usize);
+    tract_onnx::onnx()
+        .model_for_read(&mut Cursor::new(&bytes))
+        .and_then(|model| model.with_input_fact(0, f32::fact(shape).into()))
+        .and_then(|model| model.into_optimized())
+        .and_then(|model| model.into_runnable())
+        .map_err(|e| format!("Failed to load {}: {}", name, e))
+}
+
+/// Decode a photo and turn it upright by its EXIF orientation
+/// HEIC/HEIF and RAW files are read through their JPEG rendition
+pub fn load_upright(path: &Path) -> Result<RgbImage, String> {
+    let source = decodable_source(path)?;
+    let mut decoder = ImageReader::open(&source)
+        .map_err(|e| e.to_string())?
+        .with_guessed_format()
+        .map_err(|e| e.to_string())?
+        .into_decoder()
+        .map_err(|e| e.to_string())?;
+    let orientation = decoder.orientation().unwrap_or(Orientation::NoTransforms);
+    let mut image = DynamicImage::from_decoder(decoder).map_err(|e| e.to_string())?;
+    image.apply_orientation(orientation);
+    Ok(image.to_rgb8())
+}
+
+/// An image as a 1×3×H×W tensor of `(value - mean) / scale`, per RGB channel
+/// on the 0 to 255 scale
+pub fn tensor(image: &RgbImage, mean: [f32; 3], scale: [f32; 3]) -> Tensor {
+    let shape = (1, 3, image.height() as usize, image.width() as usize);
+    tract_ndarray::Array4::from_shape_fn(shape, |(_, channel, y, x)| {
+        (image.get_pixel(x as u32, y as u32)[channel] as f32 - mean[channel]) / scale[channel]
+    })
+    .into()
+}
@@ -0,0 +1,178 @@
+use crate::catalog::Catalog;
+use rusqlite::{params, Connection};
+use serde::Serialize;
+
+/// Append-only record of everything the app did to files on disk: moves,
+/// renames, trashing and duplicate resolution, with every file involved
+/// Entries are only ever added, so the log can answer "what happened to these
+/// files last Tuesday" long after the fact
+pub struct OperationLog {
+    conn: Connection,
+}
+
+/// A file touched by an operation
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LoggedFile {
+    pub path: String,
+    /// Where the file went; none when it was trashed or replaced in place
+    pub destination: Option<String>,
+    /// Full hash confirmed on the destination when a move crossed volumes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub verified_hash: Option<String>,
+}
+
+impl LoggedFile {
+    pub fn moved(from: &str, to: &str) -> Self {
+        Self {
+            path: from.to_string(),
+            destination: Some(to.to_string()),
+            verified_hash: None,
+        }
+    }
+
+    /// A move whose copy was verified against this hash
+    pub fn verified(from: &str, to: &str, hash: Option<&str>) -> Self {
+        Self {
+            verified_hash: hash.map(str::to_string),
+            ..Self::moved(from, to)
+        }
+    }
+
+    pub fn removed(path: &str) -> Self {
+        Self {
+            path: path.to_string(),
+            destination: None,
+            verified_hash: None,
+        }
+    }
+}
+
+/// One logged operation with its files
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LoggedOperation {
+    pub id: i64,
+    /// "move" | "organize" | "rename" | "trash" | "resolve" | "consolidate" |
+    /// "link" | "clone" | "undo" | "delete" |
+    /// "remove_folders"
+    pub kind: String,
+    /// When the operation finished, ms since epoch
+    pub performed_at: i64,
+    pub files: Vec<LoggedFile>,
+}
+
+/// Create the log tables; called when the catalog is opened
+pub fn create_tables(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS operations (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            kind TEXT NOT NULL,
+            performed_at INTEGER NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS idx_operations_performed_at ON operations(performed_at);
+        CREATE TABLE IF NOT EXISTS operation_files (
+            operation_id INTEGER NOT NULL,
+            path TEXT NOT NULL,
+            destination TEXT,
+            verified_hash TEXT
+        );
+        CREATE INDEX IF NOT EXISTS idx_operation_files_operation
+            ON operation_files(operation_id);",
+    )
+}
+
+impl OperationLog {
+    /// Open the log tables in the catalog database
+    pub fn open() -> Result<Self, String> {
+        let conn = Catalog::connection()?;
+        Ok(Self { conn })
+    }
+
+    /// Append an operation and its files in one transaction
+    pub fn record(&mut self, kind: &str, files: &[LoggedFile]) -> Result<(), String> {
+        let tx = self.conn.transaction().map_err(|e| e.to_string())?;
+        tx.execute(
+            "INSERT INTO operations (kind, performed_at) VALUES (?1, ?2)",
+            params![kind, chrono::Utc::now().timestamp_millis()],
+        )
+        .map_err(|e| e.to_string())?;
+        let id = tx.last_insert_rowid();
+        {
+            let mut stmt = tx
+                .prepare(
+                    "INSERT INTO operation_files (operation_id, path, destination, verified_hash)
+                    VALUES (?1, ?2, ?3, ?4)",
+                )
+                .map_err(|e| e.to_string())?;
+            for file in files {
+                stmt.execute(params![id, file.path, file.destination, file.verified_hash])
+                    .map_err(|e| e.to_string())?;
+            }
+        }
+        tx.commit().map_err(|e| e.to_string())
+    }
+
+    /// Operations performed between `since` and `until` (ms since epoch, both
+    /// optional), optionally of one kind, newest first
+    pub fn history(
+        &self,
+        since: Option<i64>,
+        until: Option<i64>,
+        kind: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<LoggedOperation>, String> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT id, kind, performed_at FROM operations
+                WHERE performed_at >= ?1 AND performed_at <= ?2
+                    AND (?3 IS NULL OR kind = ?3)
+                ORDER BY performed_at DESC, id DESC
+                LIMIT ?4",
+            )
+            .map_err(|e| e.to_string())?;
+        let mut operations = stmt
+            .query_map(
+                params![
+                    since.unwrap_or(i64::MIN),
+                    until.unwrap_or(i64::MAX),
+                    kind,
+                    limit.min(i64::MAX as usize) as i64
+                ],
+                |row| {
+                    Ok(LoggedOperation {
+                        id: row.get(0)?,
+                        kind: row.get(1)?,
+                        performed_at: row.get(2)?,
+                        files: Vec::new(),
+                    })
+                },
+            )
+            .map_err(|e| e.to_string())?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT path, destination, verified_hash FROM operation_files
+                WHERE operation_id = ?1 ORDER BY rowid",
+            )
+            .map_err(|e| e.to_string())?;
+        for operation in &mut operations {
+            operation.files = stmt
+                .query_map(params![operation.id], |row| {
+                    Ok(LoggedFile {
+                        path: row.get(0)?,
+                        destination: row.get(1)?,
+                        verified_hash: row.get(2)?,
+                    })
+                })
+                .map_err(|e| e.to_string())?
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| e.to_string())?;
+        }
+        Ok(operations)
+    }
+}
@@ -0,0 +1,131 @@
+use crate::catalog::Catalog;
+use rusqlite::{params, Connection};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Hand-made corrections to how scans group files under a primary photo,
+/// stored in the catalog database
+/// Scans pair a RAW with the JPEG of the same name and attach companions by
+/// name; an override either splits a file off to stand on its own or attaches
+/// it to a primary it doesn't share a name with
+pub struct Pairings {
+    conn: Connection,
+}
+
+/// What the override says about a file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Pairing {
+    /// Always its own photo, never claimed by a RAW or another primary
+    Standalone,
+    /// Always grouped under this primary file
+    AttachedTo(String),
+}
+
+/// Create the override table; called when the catalog is opened
+pub fn create_tables(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS pairing_overrides (
+            path TEXT PRIMARY KEY,
+            primary_path TEXT,
+            updated_at INTEGER NOT NULL
+        );",
+    )
+}
+
+impl Pairings {
+    /// Open the override table in the catalog database
+    pub fn open() -> Result<Self, String> {
+        let conn = Catalog::connection()?;
+        Ok(Self { conn })
+    }
+
+    /// Every override, by path
+    pub fn all(&self) -> Result<HashMap<String, Pairing>, String> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT path, primary_path FROM pairing_overrides")
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map([], |row| {
+                let primary: Option<String> = row.get(1)?;
+                Ok((
+                    row.get(0)?,
+                    primary.map_or(Pairing::Standalone, Pairing::AttachedTo),
+                ))
+            })
+            .map_err(|e| e.to_string())?;
+        rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+    }
+
+    /// Group files under `primary`; files grouped under one of them move to
+    /// `primary` too, so groups never nest
+    /// Returns the number of files attached
+    pub fn attach(&mut self, primary: &str, paths: &[String]) -> Result<usize, String> {
+        if !Path::new(primary).is_file() {
+            return Err(format!("{} is gone", primary));
+        }
+        let paths: Vec<&String> = paths.iter().filter(|p| p.as_str() != primary).collect();
+        if paths.is_empty() {
+            return Err("Choose files other than the primary to stack".to_string());
+        }
+        if let Some(path) = paths.iter().find(|p| !Path::new(p.as_str()).is_file()) {
+            return Err(format!("{} is gone", path));
+        }
+        let updated_at = chrono::Utc::now().timestamp_millis();
+
+        let tx = self.conn.transaction().map_err(|e| e.to_string())?;
+        // The primary leads its own group from now on
+        tx.execute(
+            "INSERT OR REPLACE INTO pairing_overrides (path, primary_path, updated_at)
+            VALUES (?1, NULL, ?2)",
+            params![primary, updated_at],
+        )
+        .map_err(|e| e.to_string())?;
+        for path in &paths {
+            tx.execute(
+                "UPDATE pairing_overrides SET primary_path = ?2, updated_at = ?3
+                WHERE primary_path = ?1",
+                params![path, primary, updated_at],
+            )
+            .map_err(|e| e.to_string())?;
+            tx.execute(
+                "INSERT OR REPLACE INTO pairing_overrides (path, primary_path, updated_at)
+                VALUES (?1, ?2, ?3)",
+                params![path, primary, updated_at],
+            )
+            .map_err(|e| e.to_string())?;
+        }
+        tx.commit().map_err(|e| e.to_string())?;
+        Ok(paths.len())
+    }
+
+    /// Make files stand on their own, e.g. a JPEG a RAW claimed because they
+    /// happen to share a name
+    pub fn detach(&mut self, paths: &[String]) -> Result<usize, String> {
+        let updated_at = chrono::Utc::now().timestamp_millis();
+        let tx = self.conn.transaction().map_err(|e| e.to_string())?;
+        for path in paths {
+            tx.execute(
+                "INSERT OR REPLACE INTO pairing_overrides (path, primary_path, updated_at)
+                VALUES (?1, NULL, ?2)",
+                params![path, updated_at],
+            )
+            .map_err(|e| e.to_string())?;
+        }
+        tx.commit().map_err(|e| e.to_string())?;
+        Ok(paths.len())
+    }
+}
+
+/// Follow a file the app moved or renamed
+pub fn relocate(conn: &Connection, from: &str, to: &str) -> rusqlite::Result<()> {
+    conn.execute(
+        "UPDATE OR IGNORE pairing_overrides SET path = ?2 WHERE path = ?1",
+        params![from, to],
+    )?;
+    conn.execute(
+        "UPDATE pairing_overrides SET primary_path = ?2 WHERE primary_path = ?1",
+        params![from, to],
+    )?;
+    Ok(())
+}
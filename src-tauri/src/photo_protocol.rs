@@ -0,0 +1,265 @@
+use crate::animation::thumbnail_dir;
+use crate::convert::{self, convert_image_to, ConvertFormat};
+use crate::media_info::is_video_extension;
+use crate::raw_preview::embedded_preview;
+use crate::scanner::RAW_EXTENSIONS;
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::UNIX_EPOCH;
+use tauri::http::{Request, Response, ResponseBuilder};
+
+/// Widths a downscaled image can be requested at
+const MIN_WIDTH: u32 = 16;
+const MAX_WIDTH: u32 = 4096;
+
+/// JPEG quality of downscaled images
+const DOWNSCALE_QUALITY: u8 = 85;
+
+/// Most bytes sent for one range request; players ask again for the rest
+const MAX_CHUNK: u64 = 2 * 1024 * 1024;
+
+/// Numbers the files downscaled images are written to before being renamed
+static NEXT_TEMPORARY: AtomicU64 = AtomicU64::new(1);
+
+/// Serve `photo://localhost/<path>` (`https://photo.localhost/<path>` on
+/// Windows), the path percent-encoded as `convertFileSrc(path, 'photo')` does
+/// `?w=512` serves a JPEG at most that many pixels on its longer side, made on
+/// first request and cached next to the thumbnails. HEIC/HEIF is served as a
+/// JPEG rendition and RAW files through their embedded preview, since the
+/// webview can't decode either
+/// Responses carry an ETag and must be revalidated, so an edited photo is never
+/// shown stale; byte ranges are honored, which video playback relies on, and
+/// answered at most MAX_CHUNK at a time. Videos are never sent whole: without
+/// a Range header they get their first chunk as a 206
+pub fn handle(request: &Request) -> Result<Response, Box<dyn Error>> {
+    let Some((path, width)) = parse_uri(request.uri()) else {
+        return status(400);
+    };
+    let file = match displayable(&path, width) {
+        Ok(file) => file,
+        Err(_) if !path.exists() => return status(404),
+        Err(e) => {
+            eprintln!("Failed to serve {}: {}", path.display(), e);
+            return status(500);
+        }
+    };
+    let Ok(metadata) = fs::metadata(&file) else {
+        return status(404);
+    };
+    let len = metadata.len();
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_millis())
+        .unwrap_or_default();
+    let etag = format!("\"{:x}-{:x}-{:x}\"", len, modified, changed(&metadata));
+
+    let header = |name: &str| {
+        request
+            .headers()
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string)
+    };
+    if header("If-None-Match").is_some_and(|tags| tags.split(',').any(|t| t.trim() == etag)) {
+        return ResponseBuilder::new()
+            .status(304)
+            .header("ETag", etag)
+            .body(Vec::new());
+    }
+
+    let response = ResponseBuilder::new()
+        .header("ETag", etag)
+        .header("Cache-Control", "no-cache")
+        .header("Accept-Ranges", "bytes")
+        .mimetype(mime_type(&file));
+    let range = match header("Range") {
+        Some(range) => Some(parse_range(&range, len)),
+        None if len > 0 && is_video_extension(&lowercase_extension(&file)) => {
+            Some(parse_range("bytes=0-", len))
+        }
+        None => None,
+    };
+    match range {
+        Some(Some((start, end))) => {
+            let end = end.min(start + MAX_CHUNK - 1);
+            let body = read_range(&file, start, end)?;
+            response
+                .status(206)
+                .header("Content-Range", format!("bytes {}-{}/{}", start, end, len))
+                .body(body)
+        }
+        Some(None) => ResponseBuilder::new()
+            .status(416)
+            .header("Content-Range", format!("bytes */{}", len))
+            .body(Vec::new()),
+        None => response.status(200).body(fs::read(&file)?),
+    }
+}
+
+/// When the file's inode last changed, so rewrites that keep the file's dates
+/// (geotagging, turning a photo) still get a new ETag; unknown off Unix
+#[cfg(unix)]
+fn changed(metadata: &fs::Metadata) -> i64 {
+    use std::os::unix::fs::MetadataExt;
+    metadata.ctime() * 1_000_000_000 + metadata.ctime_nsec()
+}
+
+#[cfg(not(unix))]
+fn changed(_metadata: &fs::Metadata) -> i64 {
+    0
+}
+
+fn status(code: u16) -> Result<Response, Box<dyn Error>> {
+    ResponseBuilder::new().status(code).body(Vec::new())
+}
+
+/// The file path and the requested width from a protocol URI
+fn parse_uri(uri: &str) -> Option<(PathBuf, Option<u32>)> {
+    let rest = &uri[uri.find("://")? + 3..];
+    let encoded = &rest[rest.find('/')? + 1..];
+    let (encoded, query) = encoded.split_once('?').unwrap_or((encoded, ""));
+    let path = PathBuf::from(percent_decode(encoded)?);
+    let width = query
+        .split('&')
+        .find_map(|pair| pair.strip_prefix("w="))
+        .and_then(|w| w.parse::<u32>().ok())
+        .map(|w| w.clamp(MIN_WIDTH, MAX_WIDTH));
+    Some((path, width))
+}
+
+fn percent_decode(encoded: &str) -> Option<String> {
+    let bytes = encoded.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = std::str::from_utf8(bytes.get(i + 1..i + 3)?).ok()?;
+            decoded.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+/// The file to send for `path`: the original, a JPEG the webview can show in
+/// its place, or a downscaled copy when `width` is given
+pub fn displayable(path: &Path, width: Option<u32>) -> Result<PathBuf, String> {
+    let source = decodable_source(path)?;
+    match width {
+        Some(width) if !is_video_extension(&lowercase_extension(path)) => {
+            downscaled(&source, width)
+        }
+        _ => Ok(source),
+    }
+}
+
+/// The original, or for HEIC/HEIF and RAW files a JPEG of the same picture
+/// that the webview and the image crate can decode
+pub fn decodable_source(path: &Path) -> Result<PathBuf, String> {
+    let extension = lowercase_extension(path);
+    if extension == "heic" || extension == "heif" {
+        convert::heif_preview(path)
+    } else if RAW_EXTENSIONS.contains(&extension.as_str()) {
+        embedded_preview(path).ok_or_else(|| "No embedded preview".to_string())
+    } else {
+        Ok(path.to_path_buf())
+    }
+}
+
+fn lowercase_extension(path: &Path) -> String {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase())
+        .unwrap_or_default()
+}
+
+/// A JPEG of `source` at most `width` pixels on its longer side, reused while
+/// it is newer than the source
+fn downscaled(source: &Path, width: u32) -> Result<PathBuf, String> {
+    let name = blake3::hash(source.to_string_lossy().as_bytes()).to_hex();
+    let target = thumbnail_dir().join(format!("{}.w{}.jpg", &name[..32], width));
+
+    let modified = |p: &Path| fs::metadata(p).and_then(|m| m.modified()).ok();
+    let source_modified =
+        modified(source).ok_or_else(|| format!("{} not found", source.display()))?;
+    if modified(&target).is_some_and(|scaled| scaled >= source_modified) {
+        return Ok(target);
+    }
+
+    fs::create_dir_all(thumbnail_dir()).map_err(|e| e.to_string())?;
+    // Written aside and renamed, so a concurrent request never reads half a file
+    let temporary = target.with_extension(format!(
+        "{}.tmp",
+        NEXT_TEMPORARY.fetch_add(1, Ordering::Relaxed)
+    ));
+    convert_image_to(
+        source,
+        &temporary,
+        ConvertFormat::Jpeg,
+        Some(DOWNSCALE_QUALITY),
+        Some(width),
+    )?;
+    fs::rename(&temporary, &target).map_err(|e| {
+        let _ = fs::remove_file(&temporary);
+        e.to_string()
+    })?;
+    Ok(target)
+}
+
+fn mime_type(path: &Path) -> &'static str {
+    match lowercase_extension(path).as_str() {
+        "jpg" | "jpeg" => "image/jpeg",
+        "png" => "image/png",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "bmp" => "image/bmp",
+        "tif" | "tiff" => "image/tiff",
+        "avif" => "image/avif",
+        "mp4" | "m4v" => "video/mp4",
+        "mov" => "video/quicktime",
+        "webm" => "video/webm",
+        _ => "application/octet-stream",
+    }
+}
+
+/// First and last byte of a single `bytes=` range, None when it can't be
+/// satisfied; multiple ranges are answered with the first. The caller caps
+/// the range at MAX_CHUNK
+fn parse_range(header: &str, len: u64) -> Option<(u64, u64)> {
+    let spec = header
+        .trim()
+        .strip_prefix("bytes=")?
+        .split(',')
+        .next()?
+        .trim();
+    let (start, end) = spec.split_once('-')?;
+    let (start, end) = match (start.trim(), end.trim()) {
+        // The last N bytes
+        ("", suffix) => {
+            let suffix: u64 = suffix.parse().ok()?;
+            (len.checked_sub(suffix.min(len))?, len.checked_sub(1)?)
+        }
+        (start, "") => (start.parse().ok()?, len.checked_sub(1)?),
+        (start, end) => (
+            start.parse().ok()?,
+            end.parse::<u64>().ok()?.min(len.checked_sub(1)?),
+        ),
+    };
+    (start <= end && start < len).then_some((start, end))
+}
+
+fn read_range(path: &Path, start: u64, end: u64) -> std::io::Result<Vec<u8>> {
+    let mut file = File::open(path)?;
+    file.seek(SeekFrom::Start(start))?;
+    let mut body = Vec::with_capacity((end - start + 1) as usize);
+    file.take(end - start + 1).read_to_end(&mut body)?;
+    Ok(body)
+}
@@ -0,0 +1,41 @@
+use crate::hash_cache::HashAlgorithm;
+use image::ImageReader;
+use sha2::{Digest, Sha256};
+use std::path::Path;
+
+/// Still formats the image crate decodes; HEIF and RAW are compared by bytes
+const DECODABLE_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png", "gif", "webp", "tiff", "tif", "bmp"];
+
+/// Whether files with this (lowercase) extension can be compared by pixels
+pub fn can_hash_pixels(extension: &str) -> bool {
+    DECODABLE_EXTENSIONS.contains(&extension)
+}
+
+/// Hash of an image's decoded pixels together with its size and pixel layout
+/// Two files with the same pixel hash show the same picture even when their
+/// bytes differ, e.g. after a tool rewrote the EXIF block
+/// Only the first frame of an animation is decoded, so animations should be
+/// compared by bytes instead
+pub fn compute_pixel_hash(path: &Path, algorithm: HashAlgorithm) -> Option<String> {
+    let image = ImageReader::open(path)
+        .ok()?
+        .with_guessed_format()
+        .ok()?
+        .decode()
+        .ok()?;
+    let layout = format!("{}x{} {:?}", image.width(), image.height(), image.color());
+    Some(match algorithm {
+        HashAlgorithm::Sha256 => {
+            let mut hasher = Sha256::new();
+            hasher.update(layout.as_bytes());
+            hasher.update(image.as_bytes());
+            format!("{:x}", hasher.finalize())
+        }
+        HashAlgorithm::Blake3 => {
+            let mut hasher = blake3::Hasher::new();
+            hasher.update(layout.as_bytes());
+            hasher.update(image.as_bytes());
+            hasher.finalize().to_hex().to_string()
+        }
+    })
+}
@@ -0,0 +1,229 @@
+use crate::analysis::AnalysisStore;
+use crate::catalog::Catalog;
+use crate::photo_protocol::displayable;
+use image::{GrayImage, ImageReader};
+use rusqlite::{params, Connection, Row};
+use serde::Serialize;
+use std::path::Path;
+
+/// Width of the preview photos are measured on; the same as the grid's
+/// thumbnails, so the preview is usually cached already
+const PREVIEW_WIDTH: u32 = 512;
+
+/// Sharpness below which a photo counts as blurry
+pub const BLURRY_SHARPNESS: f64 = 100.0;
+
+/// Sharpness at which the sharpness part of the score is half way
+const HALF_SHARP: f64 = 300.0;
+
+/// Luma at or above which a pixel is blown out, and at or below which it is
+/// crushed to black
+const HIGHLIGHT_LUMA: u8 = 250;
+const SHADOW_LUMA: u8 = 5;
+
+/// Sharpness and exposure of a photo, measured on a downscaled preview
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PhotoQuality {
+    pub path: String,
+    /// Variance of the Laplacian of the preview's luma: edges make it high,
+    /// blur and shake bring it down
+    pub sharpness: f64,
+    /// Mean luma, 0 (black) to 1 (white)
+    pub brightness: f64,
+    /// Fractions of pixels blown out and crushed to black
+    pub highlights_clipped: f64,
+    pub shadows_clipped: f64,
+    /// 0 to 1, mostly sharpness with some exposure; higher is better
+    pub score: f64,
+    pub blurry: bool,
+}
+
+/// Quality measured for photos, stored in the catalog database
+pub struct Quality {
+    conn: Connection,
+}
+
+/// Create the quality table; called when the catalog is opened
+pub fn create_tables(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS photo_quality (
+            path TEXT PRIMARY KEY,
+            modified_at INTEGER NOT NULL,
+            sharpness REAL NOT NULL,
+            brightness REAL NOT NULL,
+            highlights_clipped REAL NOT NULL,
+            shadows_clipped REAL NOT NULL,
+            score REAL NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS idx_photo_quality_sharpness ON photo_quality(sharpness);",
+    )
+}
+
+/// Measure a still photo on its downscaled preview (HEIC and RAW through
+/// their JPEG rendition)
+pub fn measure(path: &Path) -> Result<PhotoQuality, String> {
+    let preview = displayable(path, Some(PREVIEW_WIDTH))?;
+    let luma = ImageReader::open(&preview)
+        .map_err(|e| e.to_string())?
+        .with_guessed_format()
+        .map_err(|e| e.to_string())?
+        .decode()
+        .map_err(|e| e.to_string())?
+        .to_luma8();
+    if luma.width() < 3 || luma.height() < 3 {
+        return Err("Image is too small to measure".to_string());
+    }
+
+    let pixels = luma.as_raw();
+    let count = pixels.len() as f64;
+    let brightness = pixels.iter().map(|&p| p as f64).sum::<f64>() / count / 255.0;
+    let highlights = pixels.iter().filter(|&&p| p >= HIGHLIGHT_LUMA).count() as f64 / count;
+    let shadows = pixels.iter().filter(|&&p| p <= SHADOW_LUMA).count() as f64 / count;
+    Ok(scored(
+        path.to_string_lossy().to_string(),
+        laplacian_variance(&luma),
+        brightness,
+        highlights,
+        shadows,
+    ))
+}
+
+/// Variance of the 4-neighbour Laplacian over the inner pixels
+fn laplacian_variance(luma: &GrayImage) -> f64 {
+    let (width, height) = luma.dimensions();
+    let at = |x: u32, y: u32| luma.get_pixel(x, y)[0] as f64;
+    let mut sum = 0.0;
+    let mut sum_squares = 0.0;
+    for y in 1..height - 1 {
+        for x in 1..width - 1 {
+            let laplacian =
+                at(x - 1, y) + at(x + 1, y) + at(x, y - 1) + at(x, y + 1) - 4.0 * at(x, y);
+            sum += laplacian;
+            sum_squares += laplacian * laplacian;
+        }
+    }
+    let count = ((width - 2) * (height - 2)) as f64;
+    let mean = sum / count;
+    sum_squares / count - mean * mean
+}
+
+/// Combine the measurements into a score
+/// Sharpness counts most; exposure is best at middle brightness with little
+/// clipped, and loses everything once half the pixels are clipped
+fn scored(
+    path: String,
+    sharpness: f64,
+    brightness: f64,
+    highlights_clipped: f64,
+    shadows_clipped: f64,
+) -> PhotoQuality {
+    let sharp = sharpness / (sharpness + HALF_SHARP);
+    let exposure = (1.0 - ((brightness - 0.5) * 2.0).powi(2))
+        * (1.0 - 2.0 * (highlights_clipped + shadows_clipped)).max(0.0);
+    PhotoQuality {
+        path,
+        sharpness,
+        brightness,
+        highlights_clipped,
+        shadows_clipped,
+        score: 0.7 * sharp + 0.3 * exposure,
+        blurry: sharpness < BLURRY_SHARPNESS,
+    }
+}
+
+fn quality_from_row(row: &Row) -> rusqlite::Result<PhotoQuality> {
+    Ok(scored(
+        row.get(0)?,
+        row.get(1)?,
+        row.get(2)?,
+        row.get(3)?,
+        row.get(4)?,
+    ))
+}
+
+impl AnalysisStore for Quality {
+    const SCANS: &'static str = "photo_quality";
+    const TABLES: &'static [&'static str] = &["photo_quality"];
+
+    fn connection(&self) -> &Connection {
+        &self.conn
+    }
+}
+
+impl Quality {
+    /// Open the quality table in the catalog database
+    pub fn open() -> Result<Self, String> {
+        let conn = Catalog::connection()?;
+        Ok(Self { conn })
+    }
+
+    /// Store a photo's measurements in place of earlier ones
+    pub fn record(&self, quality: &PhotoQuality, modified_at: i64) -> Result<(), String> {
+        self.conn
+            .execute(
+                "INSERT OR REPLACE INTO photo_quality (path, modified_at, sharpness, brightness,
+                highlights_clipped, shadows_clipped, score) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    quality.path,
+                    modified_at,
+                    quality.sharpness,
+                    quality.brightness,
+                    quality.highlights_clipped,
+                    quality.shadows_clipped,
+                    quality.score
+                ],
+            )
+            .map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    /// Blurry photos, blurriest first; `max_sharpness` defaults to
+    /// `BLURRY_SHARPNESS`
+    pub fn blurry(&self, max_sharpness: Option<f64>) -> Result<Vec<PhotoQuality>, String> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT path, sharpness, brightness, highlights_clipped, shadows_clipped
+                FROM photo_quality WHERE sharpness < ?1 ORDER BY sharpness",
+            )
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map(
+                params![max_sharpness.unwrap_or(BLURRY_SHARPNESS)],
+                quality_from_row,
+            )
+            .map_err(|e| e.to_string())?;
+        rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+    }
+
+    /// The best measured member of a stack, None when none was measured
+    pub fn best_in_stack(&self, stack_id: i64) -> Result<Option<PhotoQuality>, String> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT q.path, q.sharpness, q.brightness, q.highlights_clipped, q.shadows_clipped
+                FROM photo_quality q JOIN stack_members m ON m.path = q.path
+                WHERE m.stack_id = ?1",
+            )
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map(params![stack_id], quality_from_row)
+            .map_err(|e| e.to_string())?;
+        let members: Vec<PhotoQuality> =
+            rows.collect::<Result<_, _>>().map_err(|e| e.to_string())?;
+        Ok(members
+            .into_iter()
+            .max_by(|a, b| a.score.total_cmp(&b.score)))
+    }
+}
+
+/// Follow a measured file the app moved or renamed, so it isn't measured again
+pub fn relocate(conn: &Connection, from: &str, to: &str) -> rusqlite::Result<()> {
+    conn.execute("DELETE FROM photo_quality WHERE path = ?1", params![to])?;
+    conn.execute(
+        "UPDATE photo_quality SET path = ?2 WHERE path = ?1",
+        params![from, to],
+    )?;
+    Ok(())
+}
@@ -0,0 +1,202 @@
+use crate::catalog::Catalog;
+use crate::scanner::PhotoFile;
+use crate::xmp;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Star ratings, pick/reject flags and color labels, stored in the catalog database
+pub struct Ratings {
+    conn: Connection,
+}
+
+/// Pick/reject flag
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Flag {
+    Pick,
+    Reject,
+}
+
+impl Flag {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Flag::Pick => "pick",
+            Flag::Reject => "reject",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "pick" => Some(Flag::Pick),
+            "reject" => Some(Flag::Reject),
+            _ => None,
+        }
+    }
+}
+
+/// Rating state of one photo
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PhotoRating {
+    pub path: String,
+    /// 0 (unrated) to 5 stars
+    pub rating: u8,
+    pub flag: Option<Flag>,
+    /// Color label name as used by Lightroom/Bridge ("Red", "Yellow", ...)
+    pub label: Option<String>,
+}
+
+/// Create the rating table; called when the catalog is opened
+pub fn create_tables(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS photo_ratings (
+            path TEXT PRIMARY KEY,
+            rating INTEGER NOT NULL DEFAULT 0,
+            flag TEXT,
+            label TEXT
+        );",
+    )
+}
+
+impl Ratings {
+    /// Open the rating table in the catalog database
+    pub fn open() -> Result<Self, String> {
+        let conn = Catalog::connection()?;
+        Ok(Self { conn })
+    }
+
+    /// Rating state of a file (defaults when it was never rated)
+    pub fn get(&self, path: &str) -> Result<PhotoRating, String> {
+        let row = self
+            .conn
+            .query_row(
+                "SELECT rating, flag, label FROM photo_ratings WHERE path = ?1",
+                params![path],
+                |row| {
+                    Ok(PhotoRating {
+                        path: path.to_string(),
+                        rating: row.get::<_, i64>(0)?.clamp(0, 5) as u8,
+                        flag: row
+                            .get::<_, Option<String>>(1)?
+                            .as_deref()
+                            .and_then(Flag::from_name),
+                        label: row.get(2)?,
+                    })
+                },
+            )
+            .optional()
+            .map_err(|e| e.to_string())?;
+        Ok(row.unwrap_or_else(|| PhotoRating {
+            path: path.to_string(),
+            ..Default::default()
+        }))
+    }
+
+    pub fn set_rating(&self, path: &str, rating: u8) -> Result<(), String> {
+        if rating > 5 {
+            return Err("Rating must be between 0 and 5".to_string());
+        }
+        self.upsert(
+            path,
+            "rating",
+            rusqlite::types::Value::Integer(rating as i64),
+        )
+    }
+
+    pub fn set_flag(&self, path: &str, flag: Option<Flag>) -> Result<(), String> {
+        let value = match flag {
+            Some(flag) => rusqlite::types::Value::Text(flag.as_str().to_string()),
+            None => rusqlite::types::Value::Null,
+        };
+        self.upsert(path, "flag", value)
+    }
+
+    pub fn set_label(&self, path: &str, label: Option<&str>) -> Result<(), String> {
+        let value = match label.map(str::trim).filter(|l| !l.is_empty()) {
+            Some(label) => rusqlite::types::Value::Text(label.to_string()),
+            None => rusqlite::types::Value::Null,
+        };
+        self.upsert(path, "label", value)
+    }
+
+    fn upsert(
+        &self,
+        path: &str,
+        column: &str,
+        value: rusqlite::types::Value,
+    ) -> Result<(), String> {
+        self.conn
+            .execute(
+                &format!(
+                    "INSERT INTO photo_ratings (path, {column}) VALUES (?1, ?2)
+                    ON CONFLICT(path) DO UPDATE SET {column} = excluded.{column}"
+                ),
+                params![path, value],
+            )
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+
+    /// Mirror a file's rating and label into its XMP sidecar
+    /// Rejected photos are written as rating -1, the convention Lightroom and Bridge use
+    pub fn write_sidecar(&self, path: &str) -> Result<(), String> {
+        let state = self.get(path)?;
+        let rating = match (state.flag, state.rating) {
+            (Some(Flag::Reject), _) => Some(-1),
+            (_, 0) => None,
+            (_, stars) => Some(stars as i32),
+        };
+        xmp::write_rating(Path::new(path), rating)?;
+        xmp::write_label(Path::new(path), state.label.as_deref())
+    }
+
+    /// Pick up ratings and labels set in other tools, for photos not rated here yet
+    /// Returns the number of photos imported
+    pub fn import_sidecars(&self, photos: &[PhotoFile]) -> Result<usize, String> {
+        let mut imported = 0;
+        for photo in photos {
+            let Some(sidecar) = &photo.sidecar_metadata else {
+                continue;
+            };
+            if (sidecar.rating.is_none() && sidecar.label.is_none()) || self.has_row(&photo.path)? {
+                continue;
+            }
+
+            match sidecar.rating {
+                Some(-1) => self.set_flag(&photo.path, Some(Flag::Reject))?,
+                Some(stars) => self.set_rating(&photo.path, stars.clamp(0, 5) as u8)?,
+                None => {}
+            }
+            if sidecar.label.is_some() {
+                self.set_label(&photo.path, sidecar.label.as_deref())?;
+            }
+            imported += 1;
+        }
+        Ok(imported)
+    }
+
+    /// Whether the file has been rated, flagged or labelled here
+    pub fn has_row(&self, path: &str) -> Result<bool, String> {
+        self.conn
+            .query_row(
+                "SELECT 1 FROM photo_ratings WHERE path = ?1",
+                params![path],
+                |_| Ok(()),
+            )
+            .optional()
+            .map(|row| row.is_some())
+            .map_err(|e| e.to_string())
+    }
+}
+
+/// Follow a rated file the app moved or renamed; a rating still at the new
+/// path belonged to a file that used to be there
+pub fn relocate(conn: &Connection, from: &str, to: &str) -> rusqlite::Result<()> {
+    conn.execute("DELETE FROM photo_ratings WHERE path = ?1", params![to])?;
+    conn.execute(
+        "UPDATE photo_ratings SET path = ?2 WHERE path = ?1",
+        params![from, to],
+    )?;
+    Ok(())
+}
@@ -0,0 +1,122 @@
+use crate::animation::thumbnail_dir;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A JPEG found inside a RAW file: where it is and its pixel size
+struct EmbeddedJpeg {
+    start: usize,
+    end: usize,
+    width: u32,
+    height: u32,
+}
+
+/// The largest JPEG embedded in a RAW file (ARW, CR2, CR3, NEF, DNG, ...),
+/// written to the thumbnail cache so RAW files without a sibling JPEG get a
+/// thumbnail and a preview; reused while it is newer than the RAW
+/// Cameras store a full-size or near full-size preview next to the sensor
+/// data; the sensor data itself, stored as lossless JPEG by some formats, is
+/// never picked
+pub fn embedded_preview(path: &Path) -> Option<PathBuf> {
+    let name = blake3::hash(path.to_string_lossy().as_bytes()).to_hex();
+    let target = thumbnail_dir().join(format!("{}.preview.jpg", &name[..32]));
+
+    let modified = |p: &Path| fs::metadata(p).and_then(|m| m.modified()).ok();
+    if let (Some(preview), Some(source)) = (modified(&target), modified(path)) {
+        if preview >= source {
+            return Some(target);
+        }
+    }
+
+    let data = fs::read(path).ok()?;
+    let largest = embedded_jpegs(&data).max_by_key(|j| j.width as u64 * j.height as u64)?;
+    fs::create_dir_all(thumbnail_dir()).ok()?;
+    fs::write(&target, &data[largest.start..largest.end]).ok()?;
+    Some(target)
+}
+
+/// Every complete baseline or progressive JPEG in `data`, found by its
+/// start-of-image marker and followed through its segments to its end
+/// TIFF-based formats point at their previews from IFDs and CR3 keeps them in
+/// boxes, but the JPEGs themselves are stored whole in every one of them, so
+/// this needs no per-format parsing
+fn embedded_jpegs(data: &[u8]) -> impl Iterator<Item = EmbeddedJpeg> + '_ {
+    let mut pos = 0;
+    std::iter::from_fn(move || {
+        while let Some(offset) = data[pos..].windows(3).position(|w| w == [0xFF, 0xD8, 0xFF]) {
+            let start = pos + offset;
+            match parse_jpeg(data, start) {
+                Some(jpeg) => {
+                    // Skip past it, so the EXIF thumbnail inside it isn't reported too
+                    pos = jpeg.end;
+                    return Some(jpeg);
+                }
+                None => pos = start + 1,
+            }
+        }
+        None
+    })
+}
+
+/// Follow the JPEG starting at `start` to its end-of-image marker; None when
+/// it is truncated or not a baseline or progressive image
+fn parse_jpeg(data: &[u8], start: usize) -> Option<EmbeddedJpeg> {
+    let byte = |i: usize| data.get(i).copied();
+    let mut pos = start + 2;
+    let mut size = None;
+    loop {
+        if byte(pos)? != 0xFF {
+            return None;
+        }
+        // Markers may be preceded by any number of fill bytes
+        while byte(pos + 1)? == 0xFF {
+            pos += 1;
+        }
+        let marker = byte(pos + 1)?;
+        pos += 2;
+        match marker {
+            0xD9 => {
+                let (width, height) = size?;
+                return Some(EmbeddedJpeg {
+                    start,
+                    end: pos,
+                    width,
+                    height,
+                });
+            }
+            // Restart markers and TEM carry no length
+            0x01 | 0xD0..=0xD7 => continue,
+            _ => {}
+        }
+        let length = u16::from_be_bytes([byte(pos)?, byte(pos + 1)?]) as usize;
+        if length < 2 {
+            return None;
+        }
+        match marker {
+            // Baseline, extended and progressive Huffman-coded frames
+            0xC0..=0xC2 => {
+                let height = u16::from_be_bytes([byte(pos + 3)?, byte(pos + 4)?]) as u32;
+                let width = u16::from_be_bytes([byte(pos + 5)?, byte(pos + 6)?]) as u32;
+                size = Some((width, height));
+            }
+            // Lossless and arithmetic-coded frames: sensor data, not a preview
+            0xC3 | 0xC5..=0xC7 | 0xC9..=0xCB | 0xCD..=0xCF => return None,
+            _ => {}
+        }
+        pos += length;
+        if marker == 0xDA {
+            // Entropy-coded data runs to the next marker that isn't a stuffed
+            // zero byte or a restart marker
+            loop {
+                if byte(pos)? == 0xFF {
+                    match byte(pos + 1)? {
+                        0x00 | 0xD0..=0xD7 => pos += 2,
+                        0xFF => pos += 1,
+                        _ => break,
+                    }
+                } else {
+                    pos += 1;
+                }
+            }
+        }
+    }
+}
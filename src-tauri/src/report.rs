@@ -0,0 +1,81 @@
+use crate::scanner::PhotoFile;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Post-scan summary written to the configured report folder
+/// Lets unattended scans leave a trail of what changed since the previous scan
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScanReport {
+    pub generated_at: String,
+    pub directories: Vec<String>,
+    pub photo_count: usize,
+    /// Paths not present in the previous scan
+    pub new_photos: Vec<String>,
+    /// Paths newly identified as duplicates since the previous scan
+    pub new_duplicates: Vec<String>,
+    /// On-disk bytes freed by removing every duplicate
+    pub reclaimable_bytes: u64,
+    /// Files that could not be fully checked
+    pub issues: Vec<String>,
+}
+
+impl ScanReport {
+    /// Build a report by comparing a scan with the previous duplicate state (id → is_duplicate)
+    pub fn build(
+        directories: &[String],
+        previous: &HashMap<String, bool>,
+        photos: &[PhotoFile],
+    ) -> Self {
+        let mut new_photos = Vec::new();
+        let mut new_duplicates = Vec::new();
+        let mut issues = Vec::new();
+
+        for photo in photos {
+            let was_duplicate = previous.get(&photo.id);
+            if was_duplicate.is_none() {
+                new_photos.push(photo.path.clone());
+            }
+            if photo.is_duplicate && was_duplicate != Some(&true) {
+                new_duplicates.push(photo.path.clone());
+            }
+            if photo.is_cloud_placeholder {
+                issues.push(format!(
+                    "{}: cloud placeholder, not checked for duplicates",
+                    photo.path
+                ));
+            } else if photo.size == 0 {
+                issues.push(format!("{}: empty file", photo.path));
+            }
+        }
+
+        Self {
+            generated_at: chrono::Local::now().to_rfc3339(),
+            directories: directories.to_vec(),
+            photo_count: photos.len(),
+            new_photos,
+            new_duplicates,
+            reclaimable_bytes: photos
+                .iter()
+                .filter(|p| p.is_duplicate)
+                .map(|p| p.disk_size)
+                .sum(),
+            issues,
+        }
+    }
+
+    /// Write the report as a timestamped JSON file, returning its path
+    pub fn write_to(&self, folder: &Path) -> Result<PathBuf, String> {
+        fs::create_dir_all(folder).map_err(|e| e.to_string())?;
+        let file_name = format!(
+            "scan-report-{}.json",
+            chrono::Local::now().format("%Y%m%d-%H%M%S")
+        );
+        let path = folder.join(file_name);
+        let json = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        fs::write(&path, json).map_err(|e| e.to_string())?;
+        Ok(path)
+    }
+}
@@ -0,0 +1,104 @@
+use crate::catalog::{Catalog, SearchQuery};
+use crate::file_ops::MovePlanner;
+use crate::metadata::expand_date_template;
+use chrono::{Local, TimeZone};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// A rule that files the photos a search matches into a folder, e.g. camera
+/// "iPhone" and year 2024 into `/Photos/iPhone/YYYY`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OrganizeRule {
+    pub name: String,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// The photos the rule applies to; the sort and page are ignored, and
+    /// every member of a stack is matched, not just its cover
+    #[serde(rename = "match")]
+    pub query: SearchQuery,
+    /// Absolute folder matching photos are moved into; YYYY, MM and DD are
+    /// replaced with the photo's capture date (file date without one)
+    pub destination: String,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+/// A move a rule calls for, of a photo or of a file that goes with it
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RuleMove {
+    /// Name of the rule that matched
+    pub rule: String,
+    pub from: String,
+    pub to: String,
+}
+
+/// Plan the moves the enabled rules call for, in rule order: a photo goes
+/// where the first rule matching it says, and later rules leave it alone
+/// Each photo takes its companions and related files (sidecars, RAW/JPEG pair)
+/// along under its new name. Photos already in place are left out, and names
+/// taken at the destination get a number; `paths` limits the plan to those photos
+pub fn plan(
+    rules: &[OrganizeRule],
+    paths: Option<&HashSet<String>>,
+) -> Result<Vec<RuleMove>, String> {
+    let catalog = Catalog::open()?;
+    let mut claimed: HashSet<String> = HashSet::new();
+    let mut planner = MovePlanner::new([]);
+    let mut moves = Vec::new();
+
+    for rule in rules.iter().filter(|r| r.enabled) {
+        let root = Path::new(&rule.destination);
+        if !root.is_absolute() {
+            return Err(format!(
+                "Rule \"{}\" needs an absolute destination folder",
+                rule.name
+            ));
+        }
+        let query = SearchQuery {
+            expand_stacks: true,
+            stack_id: None,
+            offset: 0,
+            // The page size is put straight into the SQL, so it has to fit an i64
+            limit: Some(i64::MAX as usize),
+            ..rule.query.clone()
+        };
+        for photo in catalog.search(&query)?.photos {
+            if paths.is_some_and(|p| !p.contains(&photo.path))
+                || !claimed.insert(photo.path.clone())
+            {
+                continue;
+            }
+            let source = Path::new(&photo.path);
+            let Some(date) = Local
+                .timestamp_millis_opt(photo.capture_date.unwrap_or(photo.modified_at))
+                .single()
+            else {
+                continue;
+            };
+            let folder = expand_date_template(&rule.destination, &date.naive_local());
+            let target = Path::new(&folder).join(&photo.name);
+            if target == source || !source.exists() {
+                continue;
+            }
+
+            let related: Vec<PathBuf> = photo
+                .related_files
+                .iter()
+                .map(|r| PathBuf::from(&r.path))
+                .collect();
+            for (from, to) in planner.plan_group(source, target, &related)? {
+                moves.push(RuleMove {
+                    rule: rule.name.clone(),
+                    from: from.to_string_lossy().to_string(),
+                    to: to.to_string_lossy().to_string(),
+                });
+            }
+        }
+    }
+    Ok(moves)
+}
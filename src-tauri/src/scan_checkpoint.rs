@@ -0,0 +1,122 @@
+use crate::catalog::Catalog;
+use crate::scan_options::ScanOptions;
+use crate::scanner::PhotoFile;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+/// The last phase an interrupted scan completed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScanStage {
+    /// The folders were walked; the file list is saved
+    Discovered,
+    /// Every photo was analyzed; the photos are saved and only duplicate
+    /// detection is left, whose hashes are already kept in the hash cache
+    Analyzed,
+}
+
+/// Where a scan got to, saved as it goes so `resume_scan` can pick it up after
+/// a crash or the app being closed; removed once the scan finishes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScanCheckpoint {
+    pub directories: Vec<String>,
+    pub options: ScanOptions,
+    pub stage: ScanStage,
+    /// Files the walk found, for the discovered stage
+    #[serde(default)]
+    pub files: Vec<PathBuf>,
+    /// When the checkpoint was saved, ms since epoch
+    pub saved_at: i64,
+}
+
+/// What the frontend is told about an interrupted scan
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InterruptedScan {
+    pub directories: Vec<String>,
+    pub stage: ScanStage,
+    pub saved_at: i64,
+}
+
+fn checkpoint_dir() -> PathBuf {
+    dirs::config_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("photo-manager")
+        .join("scan_checkpoint")
+}
+
+fn checkpoint_path() -> PathBuf {
+    checkpoint_dir().join("checkpoint.json")
+}
+
+/// Analyzed photos are kept in a catalog database of their own
+fn photos_path() -> PathBuf {
+    checkpoint_dir().join("photos.db")
+}
+
+/// Record that the walk finished and found `files`
+pub fn save_discovered(
+    directories: &[String],
+    options: &ScanOptions,
+    files: &[PathBuf],
+) -> Result<(), String> {
+    write(ScanCheckpoint {
+        directories: directories.to_vec(),
+        options: options.clone(),
+        stage: ScanStage::Discovered,
+        files: files.to_vec(),
+        saved_at: chrono::Utc::now().timestamp_millis(),
+    })
+}
+
+/// Record that analysis finished with `photos`
+pub fn save_analyzed(
+    directories: &[String],
+    options: &ScanOptions,
+    photos: &[PhotoFile],
+) -> Result<(), String> {
+    Catalog::open_path(&photos_path())?.replace_all(photos)?;
+    write(ScanCheckpoint {
+        directories: directories.to_vec(),
+        options: options.clone(),
+        stage: ScanStage::Analyzed,
+        files: Vec::new(),
+        saved_at: chrono::Utc::now().timestamp_millis(),
+    })
+}
+
+fn write(checkpoint: ScanCheckpoint) -> Result<(), String> {
+    fs::create_dir_all(checkpoint_dir()).map_err(|e| e.to_string())?;
+    let json = serde_json::to_string(&checkpoint).map_err(|e| e.to_string())?;
+    // Written aside and renamed, so a crash mid-write leaves the previous checkpoint
+    let temporary = checkpoint_path().with_extension("json.tmp");
+    fs::write(&temporary, json).map_err(|e| e.to_string())?;
+    fs::rename(&temporary, checkpoint_path()).map_err(|e| e.to_string())
+}
+
+/// The checkpoint of an interrupted scan, if there is one
+pub fn load() -> Option<ScanCheckpoint> {
+    let json = fs::read_to_string(checkpoint_path()).ok()?;
+    serde_json::from_str(&json).ok()
+}
+
+/// Summary of the interrupted scan, if there is one
+pub fn interrupted() -> Option<InterruptedScan> {
+    load().map(|checkpoint| InterruptedScan {
+        directories: checkpoint.directories,
+        stage: checkpoint.stage,
+        saved_at: checkpoint.saved_at,
+    })
+}
+
+/// Photos saved by `save_analyzed`
+pub fn saved_photos() -> Result<Vec<PhotoFile>, String> {
+    Catalog::open_path(&photos_path())?.all_photos()
+}
+
+/// Forget the checkpoint once its scan has finished
+pub fn clear() {
+    let _ = fs::remove_dir_all(checkpoint_dir());
+}
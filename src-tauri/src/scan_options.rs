@@ -0,0 +1,167 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Which files are compared with each other when looking for duplicates
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DedupeScope {
+    /// Across everything scanned
+    #[default]
+    Global,
+    /// Only within the same scanned root directory
+    Root,
+    /// Only within the same folder
+    Folder,
+    /// Skip duplicate detection entirely
+    Off,
+}
+
+/// How much hashing is done to confirm a duplicate
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HashTier {
+    /// Same size and same last 1 MB; fast but can mistake near-identical files
+    Quick,
+    /// Confirm with a hash of the whole file
+    #[default]
+    Full,
+    /// Compare still images by their decoded pixels, so copies whose metadata
+    /// was rewritten still match; other files are confirmed as in Full
+    Pixels,
+}
+
+/// What to do with cloud placeholders (files not downloaded locally)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CloudPolicy {
+    /// Hash them when needed, which downloads them
+    #[default]
+    Download,
+    /// Leave them out of duplicate detection
+    Skip,
+}
+
+/// Which file of a RAW+JPEG pair with the same name is shown as the photo
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RawJpegPrecedence {
+    /// The RAW, with the JPEG as its preview
+    #[default]
+    Raw,
+    /// The JPEG (for example the edited export), with the RAW attached
+    Jpeg,
+}
+
+/// How scans pick the primary file of a group of same-named files
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PrimaryPrecedence {
+    pub raw_jpeg: RawJpegPrecedence,
+    /// Pair a HEIC or JPEG still with any .MOV of the same name, not only the
+    /// Live Photo videos whose content identifier matches it
+    pub live_photos: bool,
+}
+
+/// Per-scan settings; the defaults match a plain full scan
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ScanOptions {
+    /// Reuse EXIF metadata from the catalog for files whose size and date are unchanged
+    pub incremental: bool,
+    /// Only these extensions (lowercase, no dot); empty means every supported type
+    pub extensions: Vec<String>,
+    pub min_size: Option<u64>,
+    pub max_size: Option<u64>,
+    pub dedupe_scope: DedupeScope,
+    pub hash_tier: HashTier,
+    pub cloud_policy: CloudPolicy,
+    /// Worker threads for metadata and hashing; one per core when unset
+    pub parallelism: Option<usize>,
+}
+
+/// A named, saved set of scan options
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScanPreset {
+    pub name: String,
+    pub options: ScanOptions,
+}
+
+impl ScanOptions {
+    /// Whether files with this (lowercase) extension are included
+    pub fn accepts_extension(&self, extension: &str) -> bool {
+        self.extensions.is_empty()
+            || self
+                .extensions
+                .iter()
+                .any(|e| e.trim_start_matches('.').eq_ignore_ascii_case(extension))
+    }
+
+    /// Whether files of this size are included
+    pub fn accepts_size(&self, size: u64) -> bool {
+        self.min_size.is_none_or(|min| size >= min) && self.max_size.is_none_or(|max| size <= max)
+    }
+
+    /// Whether the scan leaves out supported files by type or size, so it sees
+    /// only part of the folders it scans
+    pub fn is_filtered(&self) -> bool {
+        !self.extensions.is_empty() || self.min_size.is_some() || self.max_size.is_some()
+    }
+
+    /// Whether a file under one of `roots` is one the scan looks at
+    pub fn covers(&self, path: &str, extension: &str, size: u64, roots: &[String]) -> bool {
+        root_of(path, roots).is_some()
+            && self.accepts_extension(extension)
+            && self.accepts_size(size)
+    }
+
+    /// Key of the dedupe bucket a file belongs to; files are only compared
+    /// with others in the same bucket
+    pub fn dedupe_key(&self, path: &str, roots: &[String]) -> String {
+        match self.dedupe_scope {
+            DedupeScope::Global | DedupeScope::Off => String::new(),
+            DedupeScope::Root => root_of(path, roots).cloned().unwrap_or_default(),
+            DedupeScope::Folder => Path::new(path)
+                .parent()
+                .map(|p| p.to_string_lossy().to_string())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// The scanned root a path belongs to (the deepest one when roots are nested)
+pub fn root_of<'a>(path: &str, roots: &'a [String]) -> Option<&'a String> {
+    roots
+        .iter()
+        .filter(|root| Path::new(path).starts_with(root))
+        .max_by_key(|root| root.len())
+}
+
+/// Presets available out of the box, listed before the user's own
+pub fn builtin_presets() -> Vec<ScanPreset> {
+    vec![
+        ScanPreset {
+            name: "Full".to_string(),
+            options: ScanOptions::default(),
+        },
+        ScanPreset {
+            name: "Quick refresh".to_string(),
+            options: ScanOptions {
+                incremental: true,
+                hash_tier: HashTier::Quick,
+                cloud_policy: CloudPolicy::Skip,
+                ..Default::default()
+            },
+        },
+        ScanPreset {
+            name: "RAW only".to_string(),
+            options: ScanOptions {
+                extensions: crate::scanner::RAW_EXTENSIONS
+                    .iter()
+                    .map(|e| e.to_string())
+                    .collect(),
+                ..Default::default()
+            },
+        },
+    ]
+}
@@ -1,14 +1,39 @@
-use crate::hash_cache::HashCache;
+use crate::animation::{
+    may_be_animated, probe_animation, representative_thumbnail, thumbnail_dir, AnimationInfo,
+};
+use crate::bursts;
+use crate::catalog::Catalog;
+use crate::companions::{companion_of, group_stem};
+use crate::config::{AppConfig, DirectoryConfig};
+use crate::exclusions::{Exclusions, IgnoreFiles, WalkGuard};
+use crate::file_ops::{allocated_size, hardlink_id};
+use crate::geocode;
+use crate::hash_cache::{HashAlgorithm, HashCache};
+use crate::ids::{PhotoId, PhotoIds};
+use crate::jobs::JobMonitor;
+use crate::live_photos;
+use crate::media_info::{is_video_extension, probe_media_info, MediaInfo};
+use crate::metadata::{header_dimensions, oriented_dimensions, read_exif_summary};
+use crate::pairings::{Pairing, Pairings};
+use crate::pixel_hash::{can_hash_pixels, compute_pixel_hash};
+use crate::raw_preview::embedded_preview;
+use crate::scan_checkpoint::{self, ScanCheckpoint, ScanStage};
+use crate::scan_options::{
+    root_of, CloudPolicy, DedupeScope, HashTier, PrimaryPrecedence, RawJpegPrecedence, ScanOptions,
+};
+use crate::source_kind::{self, SourceKind};
+use crate::stacks::Stacks;
+use crate::trash_history::TrashHistory;
+use crate::xmp::{parse_date, parse_sidecar, SidecarMetadata};
+use chrono::TimeZone;
 use rayon::prelude::*;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs::{self, File};
 use std::io::{BufReader, Read, Seek, SeekFrom};
 use std::path::{Path, PathBuf};
-use std::process::Command;
 use std::sync::atomic::{AtomicUsize, Ordering};
-use std::sync::Arc;
 use tauri::Window;
 use walkdir::WalkDir;
 
@@ -18,7 +43,14 @@ const IMAGE_EXTENSIONS: &[&str] = &[
 ];
 
 /// RAW image extensions
-const RAW_EXTENSIONS: &[&str] = &["arw", "cr2", "cr3", "nef", "dng", "raf", "orf", "rw2", "pef"];
+pub const RAW_EXTENSIONS: &[&str] = &["arw", "cr2", "cr3", "nef", "dng", "raf", "orf", "rw2", "pef"];
+
+/// Whether a (lowercase) extension is a photo, RAW or video the scanner picks up as a primary
+pub fn is_media_extension(extension: &str) -> bool {
+    IMAGE_EXTENSIONS.contains(&extension)
+        || RAW_EXTENSIONS.contains(&extension)
+        || is_video_extension(extension)
+}
 
 /// Sidecar/metadata extensions
 const SIDECAR_EXTENSIONS: &[&str] = &["xmp", "xml"];
@@ -26,32 +58,110 @@ const SIDECAR_EXTENSIONS: &[&str] = &["xmp", "xml"];
 /// Size of trailing hash in bytes (1 MB)
 const TRAILING_HASH_SIZE: u64 = 1024 * 1024;
 
-#[derive(Debug, Serialize, Clone)]
+/// Number of analyzed photos whose metadata is read before they are streamed
+const SCAN_BATCH_SIZE: usize = 500;
+
+/// Target serialized size of one `scan-batch` / `scan-delta` event
+/// Events are split by measured JSON size rather than photo count: a few hundred
+/// photos with sidecar metadata can weigh several MB, and one huge IPC message
+/// stalls the webview while it is parsed
+const TARGET_EVENT_BYTES: usize = 256 * 1024;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct RelatedFile {
     pub path: String,
     pub name: String,
     #[serde(rename = "type")]
-    pub file_type: String, // "sidecar", "jpeg-preview", "raw"
+    pub file_type: String, // "sidecar", "jpeg-preview", "raw", "depth", "edited", "adjustments"
+}
+
+/// Whether duplicate detection reached a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DuplicateStatus {
+    /// Compared with every file it could duplicate; `is_duplicate` is final
+    #[default]
+    Checked,
+    /// A cloud placeholder the scan didn't hash (cloud policy "skip") although
+    /// other files have its size; `hash_pending_files` settles it
+    Unknown,
+}
+
+impl DuplicateStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DuplicateStatus::Checked => "checked",
+            DuplicateStatus::Unknown => "unknown",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "unknown" => DuplicateStatus::Unknown,
+            _ => DuplicateStatus::Checked,
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct PhotoFile {
+    /// Path of the file; kept for compatibility, prefer `photo_id`
     pub id: String,
+    /// Stable id that survives renames and moves
+    pub photo_id: PhotoId,
     pub path: String,
     pub name: String,
     pub directory: String,
     pub extension: String,
+    /// Logical file size in bytes
     pub size: u64,
+    /// Bytes allocated on disk (smaller for compressed, sparse or cloud placeholder files)
+    pub disk_size: u64,
     pub modified_at: i64,
     pub hash: Option<String>,
+    /// Hash of the decoded pixels, set instead of `hash` by the pixels hash tier
+    pub pixel_hash: Option<String>,
     pub thumbnail_path: Option<String>,
     pub related_files: Vec<RelatedFile>,
     pub is_duplicate: bool,
     pub duplicate_of: Option<String>,
+    /// Another scanned path that is a hard link to the same file; deleting this
+    /// path would free nothing, so it is never reported as a duplicate
+    pub hardlink_of: Option<String>,
+    pub duplicate_status: DuplicateStatus,
     /// True if file is a cloud placeholder (not fully downloaded)
     pub is_cloud_placeholder: bool,
+    /// EXIF capture time in milliseconds since epoch (None if unavailable)
+    pub capture_date: Option<i64>,
+    pub camera_make: Option<String>,
+    pub camera_model: Option<String>,
+    /// GPS position from EXIF or the XMP sidecar, in decimal degrees
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    /// Nearest town to the GPS position ("Town, Region, CC")
+    pub place_name: Option<String>,
+    /// Pixel size as displayed, i.e. with the EXIF orientation applied
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    /// EXIF orientation, 1 (upright) to 8
+    pub orientation: Option<u16>,
+    /// Priority of the configured directory the file was found under (0 if unranked)
+    pub root_priority: i32,
+    /// Duration, codec, resolution, frame rate and bitrate for video files
+    pub media_info: Option<MediaInfo>,
+    /// Frame count and loop duration for animated GIF/WebP files
+    pub animation: Option<AnimationInfo>,
+    /// When content identical to this file was last trashed from the app
+    /// (milliseconds since epoch), so repeated re-imports stand out
+    pub previously_trashed_at: Option<i64>,
+    /// Rating, label, keywords and edit state from the XMP sidecar, if there is one
+    pub sidecar_metadata: Option<SidecarMetadata>,
+    /// Stack the photo belongs to, either a detected burst or one made by hand
+    pub stack_id: Option<i64>,
+    /// Camera photo, screenshot or saved image, guessed once metadata is read
+    pub source_kind: SourceKind,
 }
 
 #[derive(Clone, Serialize)]
@@ -63,17 +173,115 @@ pub struct ScanProgress {
     pub message: String,
 }
 
+/// Chunk of analyzed photos streamed to the frontend during a scan
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScanBatch<'a> {
+    pub photos: &'a [PhotoFile],
+}
+
+/// Fields that change on already-streamed photos once hashing completes
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PhotoUpdate {
+    pub id: String,
+    pub size: u64,
+    pub hash: Option<String>,
+    pub pixel_hash: Option<String>,
+    pub is_duplicate: bool,
+    pub duplicate_of: Option<String>,
+    pub hardlink_of: Option<String>,
+    pub duplicate_status: DuplicateStatus,
+    pub is_cloud_placeholder: bool,
+    pub previously_trashed_at: Option<i64>,
+    pub stack_id: Option<i64>,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScanDelta<'a> {
+    pub updates: &'a [PhotoUpdate],
+}
+
+/// Returned by `scan_directories` instead of the full photo list
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScanSummary {
+    pub photo_count: usize,
+    pub duplicate_count: usize,
+    pub total_size: u64,
+    /// On-disk bytes freed by removing all duplicates
+    pub duplicate_disk_size: u64,
+}
+
+impl ScanSummary {
+    pub fn from_photos(photos: &[PhotoFile]) -> Self {
+        let duplicates = photos.iter().filter(|p| p.is_duplicate);
+        Self {
+            photo_count: photos.len(),
+            duplicate_count: duplicates.clone().count(),
+            total_size: photos.iter().map(|p| p.size).sum(),
+            duplicate_disk_size: duplicates.map(|p| p.disk_size).sum(),
+        }
+    }
+}
+
 /// Compute percentage string
 fn pct(current: usize, total: usize) -> String {
-    if total == 0 {
-        "0%".to_string()
-    } else {
-        format!("{}%", (current * 100) / total)
+    match (current * 100).checked_div(total) {
+        Some(percent) => format!("{}%", percent),
+        None => "0%".to_string(),
     }
 }
 
 /// Scan multiple directories for photos with progress reporting
-pub fn scan_directories_with_progress(directories: &[String], window: Window) -> Vec<PhotoFile> {
+/// Fails only when the user cancels the scan (see `jobs`)
+pub fn scan_directories_with_progress(
+    directories: &[String],
+    options: &ScanOptions,
+    window: Window,
+) -> Result<Vec<PhotoFile>, String> {
+    // Run on a dedicated thread pool when the parallelism is limited
+    let pool = options.parallelism.and_then(|threads| {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads.max(1))
+            .build()
+            .ok()
+    });
+    match pool {
+        Some(pool) => pool.install(|| scan(directories, options, window, None)),
+        None => scan(directories, options, window, None),
+    }
+}
+
+/// Continue an interrupted scan from its checkpoint: the walk is skipped, and
+/// so is analysis if it had finished; hashes computed before the interruption
+/// come from the hash cache
+pub fn resume_scan_with_progress(
+    checkpoint: ScanCheckpoint,
+    window: Window,
+) -> Result<Vec<PhotoFile>, String> {
+    let directories = checkpoint.directories.clone();
+    let options = checkpoint.options.clone();
+    let pool = options.parallelism.and_then(|threads| {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads.max(1))
+            .build()
+            .ok()
+    });
+    let resume = Some(checkpoint);
+    match pool {
+        Some(pool) => pool.install(|| scan(&directories, &options, window, resume)),
+        None => scan(&directories, &options, window, resume),
+    }
+}
+
+fn scan(
+    directories: &[String],
+    options: &ScanOptions,
+    window: Window,
+    resume: Option<ScanCheckpoint>,
+) -> Result<Vec<PhotoFile>, String> {
     let emit_progress = |phase: &str, current: usize, total: usize, message: &str| {
         let _ = window.emit(
             "scan-progress",
@@ -86,52 +294,75 @@ pub fn scan_directories_with_progress(directories: &[String], window: Window) ->
         );
     };
 
+    // Heartbeats let the frontend tell a slow scan from one stuck on a hung volume
+    let monitor = JobMonitor::start(&window, "scan");
+    let cancelled = || "Scan cancelled".to_string();
+
     // Open hash cache
     let cache = HashCache::open().ok();
 
-    // Phase 1: Discover files
-    emit_progress("discovery", 0, 0, "Discovering files...");
-    
-    let mut all_files: Vec<PathBuf> = Vec::new();
+    // A scan interrupted after analysis only has duplicate detection left
+    if resume
+        .as_ref()
+        .is_some_and(|c| c.stage == ScanStage::Analyzed)
+    {
+        let photos = scan_checkpoint::saved_photos()?;
+        emit_progress("analyzing", 0, 0, "Resuming from the saved photos...");
+        for batch in sized_chunks(&photos) {
+            let _ = window.emit("scan-batch", ScanBatch { photos: batch });
+        }
+        return find_duplicates(photos, directories, options, window, monitor, cache);
+    }
 
-    for (dir_idx, dir) in directories.iter().enumerate() {
-        emit_progress(
-            "discovery",
-            dir_idx,
-            directories.len(),
-            &format!("Scanning: {}", dir),
-        );
+    // Stable ids are assigned as photos are streamed, so the frontend gets them up front
+    let mut ids = PhotoIds::open().ok();
 
-        let path = Path::new(dir);
-        if !path.exists() {
-            continue;
-        }
+    let configured = AppConfig::load().directories;
 
-        for entry in WalkDir::new(path)
-            .follow_links(true)
+    // Root priorities decide which copy of a duplicate is the original
+    let priorities: HashMap<&str, i32> = configured
+        .iter()
+        .map(|d| (d.path.as_str(), d.priority))
+        .collect();
+    let root_priority = |path: &str| {
+        root_of(path, directories)
+            .and_then(|root| priorities.get(root.as_str()))
+            .copied()
+            .unwrap_or(0)
+    };
+
+    // Incremental scans reuse EXIF metadata of files unchanged since the last scan
+    let previous: HashMap<String, PhotoFile> = if options.incremental {
+        Catalog::open()
+            .and_then(|c| c.all_photos())
+            .unwrap_or_default()
             .into_iter()
-            .filter_map(|e| e.ok())
-        {
-            if entry.file_type().is_file() {
-                all_files.push(entry.path().to_path_buf());
+            .map(|p| (p.path.clone(), p))
+            .collect()
+    } else {
+        HashMap::new()
+    };
+
+    // Phase 1: Discover files; a resumed scan reuses the list it saved
+    let mut all_files = match resume {
+        Some(checkpoint) => checkpoint.files,
+        None => {
+            let files = discover_files(directories, &configured, &monitor, &emit_progress)?;
+            if let Err(e) = scan_checkpoint::save_discovered(directories, options, &files) {
+                eprintln!("Failed to save scan checkpoint: {}", e);
             }
+            files
         }
-    }
-
-    emit_progress(
-        "discovery",
-        directories.len(),
-        directories.len(),
-        &format!("Found {} files", all_files.len()),
-    );
+    };
 
     // Phase 2: Group files
     emit_progress("grouping", 0, all_files.len(), "Grouping related files...");
     
     let mut file_groups: HashMap<String, Vec<PathBuf>> = HashMap::new();
 
+    // Companions (depth maps, edited renditions, .AAE files) join their primary's group
     for file_path in &all_files {
-        if let Some(stem) = file_path.file_stem().and_then(|s| s.to_str()) {
+        if let Some(stem) = group_stem(file_path) {
             if let Some(parent) = file_path.parent() {
                 let key = format!("{}:{}", parent.display(), stem.to_lowercase());
                 file_groups.entry(key).or_default().push(file_path.clone());
@@ -141,6 +372,7 @@ pub fn scan_directories_with_progress(directories: &[String], window: Window) ->
 
     // Phase 3: Identify primary files (RAW files take precedence)
     emit_progress("analyzing", 0, all_files.len(), "Analyzing photos...");
+    monitor.set_phase("analyzing", Some(all_files.len()));
     
     let mut photos: Vec<PhotoFile> = Vec::new();
     let mut processed: std::collections::HashSet<PathBuf> = std::collections::HashSet::new();
@@ -148,20 +380,60 @@ pub fn scan_directories_with_progress(directories: &[String], window: Window) ->
     let mut _skipped: usize = 0;
     let mut cache_size_hits: usize = 0;
     let mut fs_reads: usize = 0;
+    // Number of photos already sent in `scan-batch` events
+    let mut streamed: usize = 0;
 
     // Sort files in place - no need to clone, we consume all_files here
-    // RAW files come first - they take precedence over JPEGs
-    all_files.sort_by(|a, b| {
-        let a_ext = a.extension().and_then(|e| e.to_str()).unwrap_or("");
-        let b_ext = b.extension().and_then(|e| e.to_str()).unwrap_or("");
-        let a_is_raw = RAW_EXTENSIONS.contains(&a_ext.to_lowercase().as_str());
-        let b_is_raw = RAW_EXTENSIONS.contains(&b_ext.to_lowercase().as_str());
-        // RAW files come first
-        b_is_raw.cmp(&a_is_raw)
+    // By default RAW files come first - they take precedence over JPEGs
+    let precedence = AppConfig::load().primary_precedence;
+    all_files.sort_by_key(|path| {
+        let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+        let is_raw = RAW_EXTENSIONS.contains(&ext.to_lowercase().as_str());
+        match precedence.raw_jpeg {
+            RawJpegPrecedence::Raw => !is_raw,
+            RawJpegPrecedence::Jpeg => is_raw,
+        }
     });
 
+    // Pairings corrected by hand (`stack_files`, `unstack_files`) beat the name rules
+    let pairings = Pairings::open().and_then(|p| p.all()).unwrap_or_default();
+    let standalone = |path: &Path| {
+        matches!(
+            pairings.get(path.to_string_lossy().as_ref()),
+            Some(Pairing::Standalone)
+        )
+    };
+    // Files attached by hand to a primary this scan found, by primary
+    let mut attached: HashMap<String, Vec<PathBuf>> = HashMap::new();
+    if !pairings.is_empty() {
+        let found: HashSet<String> = all_files
+            .iter()
+            .map(|p| p.to_string_lossy().to_string())
+            .collect();
+        for file in &all_files {
+            if let Some(Pairing::AttachedTo(primary)) =
+                pairings.get(file.to_string_lossy().as_ref())
+            {
+                if found.contains(primary) {
+                    attached
+                        .entry(primary.clone())
+                        .or_default()
+                        .push(file.clone());
+                }
+            }
+        }
+    }
+    let attached_files: HashSet<PathBuf> = attached.values().flatten().cloned().collect();
+
+    // MOVs whose content identifier matches a same-named still are Live Photo videos
+    let live_videos = live_photos::find_videos(&file_groups);
+
     let total_files = all_files.len();
     for (idx, file_path) in all_files.iter().enumerate() {
+        if monitor.is_cancelled() {
+            return Err(cancelled());
+        }
+        let _work = monitor.working_on(&file_path.to_string_lossy());
         // Update progress every 25 files for smoother updates
         if idx % 25 == 0 {
             emit_progress(
@@ -173,7 +445,7 @@ pub fn scan_directories_with_progress(directories: &[String], window: Window) ->
             );
         }
 
-        if processed.contains(file_path) {
+        if processed.contains(file_path) || attached_files.contains(file_path) {
             _skipped += 1;
             continue;
         }
@@ -192,34 +464,58 @@ pub fn scan_directories_with_progress(directories: &[String], window: Window) ->
 
         let is_raw = RAW_EXTENSIONS.contains(&ext.as_str());
         let is_image = IMAGE_EXTENSIONS.contains(&ext.as_str());
+        let is_video = is_video_extension(&ext);
 
         // Check if this is a primary file
-        if !is_raw && !is_image {
+        if (!is_raw && !is_image && !is_video) || !options.accepts_extension(&ext) {
             _skipped += 1;
             continue;
         }
 
         // Find related files in the same group
-        let stem = file_path
-            .file_stem()
-            .and_then(|s| s.to_str())
-            .unwrap_or("");
+        let stem = group_stem(file_path).unwrap_or_default();
         let parent = file_path.parent().unwrap_or(Path::new(""));
         let key = format!("{}:{}", parent.display(), stem.to_lowercase());
 
-        // If this is a JPEG/image and there's a RAW file with the same name, skip it
-        // The RAW will be processed first and claim this JPEG as a related file
-        if is_image && !is_raw {
+        // Companions are claimed by their primary photo; only orphans stand on their own
+        if companion_of(file_path).is_some() && !standalone(file_path) {
+            let has_primary = file_groups.get(&key).is_some_and(|group| {
+                group.iter().any(|p| {
+                    let p_ext = p
+                        .extension()
+                        .and_then(|e| e.to_str())
+                        .map(|e| e.to_lowercase())
+                        .unwrap_or_default();
+                    companion_of(p).is_none()
+                        && (RAW_EXTENSIONS.contains(&p_ext.as_str())
+                            || IMAGE_EXTENSIONS.contains(&p_ext.as_str())
+                            || is_video_extension(&p_ext))
+                        && options.accepts_extension(&p_ext)
+                })
+            });
+            if has_primary {
+                _skipped += 1;
+                continue;
+            }
+        }
+
+        // If a file with the same name takes precedence (by default a RAW over
+        // its JPEG), skip this one - that file claims it as a related file
+        if !standalone(file_path) {
             if let Some(group) = file_groups.get(&key) {
-                let has_raw_sibling = group.iter().any(|p| {
+                let outranked = group.iter().any(|p| {
                     let p_ext = p.extension()
                         .and_then(|e| e.to_str())
                         .map(|e| e.to_lowercase())
                         .unwrap_or_default();
-                    RAW_EXTENSIONS.contains(&p_ext.as_str())
+                    p != file_path
+                        && !attached_files.contains(p)
+                        && companion_of(p).is_none()
+                        && options.accepts_extension(&p_ext)
+                        && claims(precedence, &p_ext, &ext, live_videos.contains(file_path))
+                            .is_some()
                 });
-                if has_raw_sibling {
-                    // Skip this JPEG - it will be added as a related file to the RAW
+                if outranked {
                     _skipped += 1;
                     continue;
                 }
@@ -234,7 +530,10 @@ pub fn scan_directories_with_progress(directories: &[String], window: Window) ->
 
         if let Some(group) = file_groups.get(&key) {
             for related_path in group {
-                if related_path == file_path {
+                if related_path == file_path
+                    || standalone(related_path)
+                    || attached_files.contains(related_path)
+                {
                     continue;
                 }
 
@@ -246,32 +545,62 @@ pub fn scan_directories_with_progress(directories: &[String], window: Window) ->
 
                 let file_type = if SIDECAR_EXTENSIONS.contains(&related_ext.as_str()) {
                     "sidecar"
-                } else if is_raw && IMAGE_EXTENSIONS.contains(&related_ext.as_str()) {
-                    // RAW file with a JPEG companion = JPEG is a preview
-                    // Use this as the thumbnail source
-                    jpeg_preview_path = Some(related_path.to_string_lossy().to_string());
-                    "jpeg-preview"
+                } else if let Some((_, kind)) = companion_of(related_path) {
+                    kind.as_str()
+                } else if let Some(kind) = claims(
+                    precedence,
+                    &ext,
+                    &related_ext,
+                    live_videos.contains(related_path),
+                ) {
+                    // A RAW's JPEG is a preview - use it as the thumbnail source
+                    if kind == "jpeg-preview" {
+                        jpeg_preview_path = Some(related_path.to_string_lossy().to_string());
+                    }
+                    kind
                 } else {
                     continue;
                 };
 
                 processed.insert(related_path.clone());
-
-                related_files.push(RelatedFile {
-                    path: related_path.to_string_lossy().to_string(),
-                    name: related_path
-                        .file_name()
-                        .and_then(|n| n.to_str())
-                        .unwrap_or("")
-                        .to_string(),
-                    file_type: file_type.to_string(),
-                });
+                related_files.push(related_file(related_path, file_type));
             }
         }
 
+        // Files stacked under this one by hand
+        for related_path in attached
+            .get(file_path.to_string_lossy().as_ref())
+            .into_iter()
+            .flatten()
+        {
+            let related_ext = related_path
+                .extension()
+                .and_then(|e| e.to_str())
+                .map(|e| e.to_lowercase())
+                .unwrap_or_default();
+            let file_type = if SIDECAR_EXTENSIONS.contains(&related_ext.as_str()) {
+                "sidecar"
+            } else if let Some((_, kind)) = companion_of(related_path) {
+                kind.as_str()
+            } else if is_raw && IMAGE_EXTENSIONS.contains(&related_ext.as_str()) {
+                jpeg_preview_path
+                    .get_or_insert_with(|| related_path.to_string_lossy().to_string());
+                "jpeg-preview"
+            } else if RAW_EXTENSIONS.contains(&related_ext.as_str()) {
+                "raw"
+            } else {
+                "edited"
+            };
+            processed.insert(related_path.clone());
+            related_files.push(related_file(related_path, file_type));
+        }
+
         // For RAW files, use JPEG preview as thumbnail; for regular images, use the file itself
+        // Videos have no still to show
         let thumbnail_path = if is_raw {
             jpeg_preview_path
+        } else if is_video {
+            None
         } else {
             Some(file_path.to_string_lossy().to_string())
         };
@@ -283,14 +612,11 @@ pub fn scan_directories_with_progress(directories: &[String], window: Window) ->
             .unwrap_or("")
             .to_string();
 
-        // Try to get size from cache first (avoids hydrating cloud files)
-        let cached_info = cache.as_ref().and_then(|c| c.get(&path_str));
-        
         // Always read metadata for modified_at - this doesn't hydrate cloud files
         // (only reading file content does)
         let metadata = match fs::metadata(file_path) {
-            Ok(m) => m,
-            Err(_) => {
+            Ok(m) if !monitor.is_skipped(&path_str) => m,
+            _ => {
                 _skipped += 1;
                 continue;
             }
@@ -305,7 +631,13 @@ pub fn scan_directories_with_progress(directories: &[String], window: Window) ->
             .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
             .map(|d| d.as_millis() as i64)
             .unwrap_or(0);
-        
+
+        // Try to get size from cache first (avoids hydrating cloud files); entries
+        // for files edited or replaced since they were cached are dropped
+        let cached_info = cache
+            .as_ref()
+            .and_then(|c| c.verify_cache_entry(&path_str, &metadata));
+
         let (size, cloud_placeholder) = if let Some(info) = cached_info {
             // Use cached size - avoids reading file content for cloud files
             cache_size_hits += 1;
@@ -324,10 +656,18 @@ pub fn scan_directories_with_progress(directories: &[String], window: Window) ->
             (file_size, is_placeholder)
         };
         
+        if !options.accepts_size(size) {
+            _skipped += 1;
+            continue;
+        }
+
         let modified_at = file_time;
+        let disk_size = allocated_size(&metadata);
+        let root_priority = root_priority(&path_str);
 
         photos.push(PhotoFile {
             id: path_str.clone(),  // Note: id equals path, kept for frontend compatibility
+            photo_id: PhotoId::default(),
             path: path_str,
             name: file_path
                 .file_name()
@@ -337,14 +677,44 @@ pub fn scan_directories_with_progress(directories: &[String], window: Window) ->
             directory,
             extension: ext,
             size,
+            disk_size,
             modified_at,
             hash: None,
+            pixel_hash: None,
             thumbnail_path,
             related_files,
             is_duplicate: false,
             duplicate_of: None,
+            hardlink_of: None,
+            duplicate_status: DuplicateStatus::Checked,
             is_cloud_placeholder: cloud_placeholder,
+            capture_date: None,
+            camera_make: None,
+            camera_model: None,
+            latitude: None,
+            longitude: None,
+            place_name: None,
+            width: None,
+            height: None,
+            orientation: None,
+            root_priority,
+            media_info: None,
+            animation: None,
+            previously_trashed_at: None,
+            sidecar_metadata: None,
+            stack_id: None,
+            source_kind: SourceKind::Unknown,
         });
+
+        // Stream analyzed photos to the frontend in batches
+        if photos.len() - streamed >= SCAN_BATCH_SIZE {
+            stream_batch(&mut photos[streamed..], ids.as_mut(), &previous, &window);
+            streamed = photos.len();
+        }
+    }
+
+    if streamed < photos.len() {
+        stream_batch(&mut photos[streamed..], ids.as_mut(), &previous, &window);
     }
 
     // Final progress update for analyzing phase
@@ -362,14 +732,202 @@ pub fn scan_directories_with_progress(directories: &[String], window: Window) ->
     drop(all_files);
     drop(file_groups);
     drop(processed);
+    drop(previous);
+
+    // Analysis is the slow part to redo; save it for `resume_scan`
+    if let Err(e) = scan_checkpoint::save_analyzed(directories, options, &photos) {
+        eprintln!("Failed to save scan checkpoint: {}", e);
+    }
+
+    find_duplicates(photos, directories, options, window, monitor, cache)
+}
+
+/// Walk the scanned folders and list every file, leaving out excluded folders,
+/// ignored files and folders reached twice
+fn discover_files(
+    directories: &[String],
+    configured: &[DirectoryConfig],
+    monitor: &JobMonitor,
+    emit_progress: &impl Fn(&str, usize, usize, &str),
+) -> Result<Vec<PathBuf>, String> {
+    emit_progress("discovery", 0, 0, "Discovering files...");
+    monitor.set_phase("discovery", None);
+
+    let mut all_files: Vec<PathBuf> = Vec::new();
+    let mut walk_guard = WalkGuard::new(directories);
+
+    for (dir_idx, dir) in directories.iter().enumerate() {
+        emit_progress(
+            "discovery",
+            dir_idx,
+            directories.len(),
+            &format!("Scanning: {}", dir),
+        );
+
+        let path = Path::new(dir);
+        if !path.exists() {
+            continue;
+        }
+
+        // Excluded folders are pruned, not just filtered, so they are never crawled
+        let exclusions = configured
+            .iter()
+            .find(|d| d.path == *dir)
+            .map(Exclusions::for_directory)
+            .unwrap_or_default();
+        if !walk_guard.enter_root(path) {
+            // Listed twice, or the same folder under another path
+            continue;
+        }
+        let mut ignore_files = IgnoreFiles::default();
+        let mut walker = WalkDir::new(path).follow_links(true);
+        if let Some(depth) = exclusions.max_depth {
+            // Depth counts from the directory itself, whose files are at depth 1
+            walker = walker.max_depth(depth + 1);
+        }
+
+        for entry in walker
+            .into_iter()
+            .filter_entry(|e| {
+                !exclusions.excludes(path, e)
+                    && !ignore_files.excludes(path, e)
+                    && !walk_guard.excludes(e)
+            })
+            .filter_map(|e| e.ok())
+        {
+            if monitor.is_cancelled() {
+                return Err("Scan cancelled".to_string());
+            }
+            monitor.progress(entry.path().to_str());
+            if entry.file_type().is_file() {
+                all_files.push(entry.path().to_path_buf());
+            }
+        }
+    }
+
+    emit_progress(
+        "discovery",
+        directories.len(),
+        directories.len(),
+        &format!("Found {} files", all_files.len()),
+    );
+
+    Ok(all_files)
+}
+
+/// Everything after analysis: trash history, hard links, bursts, then the
+/// duplicate passes
+fn find_duplicates(
+    mut photos: Vec<PhotoFile>,
+    directories: &[String],
+    options: &ScanOptions,
+    window: Window,
+    monitor: JobMonitor,
+    cache: Option<HashCache>,
+) -> Result<Vec<PhotoFile>, String> {
+    let emit_progress = |phase: &str, current: usize, total: usize, message: &str| {
+        let _ = window.emit(
+            "scan-progress",
+            ScanProgress {
+                phase: phase.to_string(),
+                current,
+                total,
+                message: message.to_string(),
+            },
+        );
+    };
+    let cancelled = || "Scan cancelled".to_string();
+    let algorithm = cache
+        .as_ref()
+        .map(|c| c.algorithm())
+        .unwrap_or_else(HashAlgorithm::configured);
+    let photo_count = photos.len();
+
+    // Flag files whose content was trashed from the app before
+    if let Some(c) = cache.as_ref() {
+        let flagged = flag_previously_trashed(&mut photos, c);
+        emit_photo_updates(&photos, &flagged, &window);
+    }
+
+    // Hard links share their storage, so only one path of each takes part in
+    // duplicate detection
+    let linked = mark_hardlinks(&mut photos);
+    emit_photo_updates(&photos, &linked, &window);
+
+    // Bursts and brackets are stacked so they don't flood the grid
+    let stacked = stack_bursts(&mut photos, directories);
+    emit_photo_updates(&photos, &stacked, &window);
+
+    if options.dedupe_scope == DedupeScope::Off {
+        emit_progress(
+            "complete",
+            photo_count,
+            photo_count,
+            &format!("Done! {} photos, duplicate detection off", photo_count),
+        );
+        return Ok(photos);
+    }
+
+    // Files are only compared with others in the same dedupe bucket
+    let buckets: Vec<String> = photos
+        .iter()
+        .map(|p| options.dedupe_key(&p.path, directories))
+        .collect();
+
+    // Pixels tier: stills are matched by decoded content first, so copies whose
+    // metadata was rewritten, and so whose size differs, are still found
+    if options.hash_tier == HashTier::Pixels {
+        let changed = mark_pixel_duplicates(
+            &mut photos,
+            &buckets,
+            options,
+            cache.as_ref(),
+            algorithm,
+            &monitor,
+            &window,
+        );
+        if monitor.is_cancelled() {
+            return Err(cancelled());
+        }
+        emit_photo_updates(&photos, &changed, &window);
+    }
 
     // Phase 4: Find potential duplicates by file size (fast)
     emit_progress("duplicates", 0, photo_count, "Finding potential duplicates by file size...");
     
     // Group photos by file size
-    let mut size_groups: HashMap<u64, Vec<usize>> = HashMap::new();
+    let mut size_groups: HashMap<(&str, u64), Vec<usize>> = HashMap::new();
     for (idx, photo) in photos.iter().enumerate() {
-        size_groups.entry(photo.size).or_default().push(idx);
+        if photo.is_cloud_placeholder && options.cloud_policy == CloudPolicy::Skip {
+            continue;
+        }
+        // Already compared by pixels, or sharing storage with a listed path
+        if photo.pixel_hash.is_some() || photo.hardlink_of.is_some() {
+            continue;
+        }
+        size_groups
+            .entry((&buckets[idx], photo.size))
+            .or_default()
+            .push(idx);
+    }
+
+    // Skipped placeholders sharing a size with another file can't be ruled out
+    // without downloading them; one with a unique size is known not to be a copy
+    if options.cloud_policy == CloudPolicy::Skip {
+        let mut size_counts: HashMap<(&str, u64), usize> = HashMap::new();
+        for (idx, photo) in photos.iter().enumerate() {
+            *size_counts.entry((&buckets[idx], photo.size)).or_default() += 1;
+        }
+        let pending: Vec<usize> = (0..photos.len())
+            .filter(|&idx| {
+                photos[idx].is_cloud_placeholder
+                    && size_counts[&(buckets[idx].as_str(), photos[idx].size)] > 1
+            })
+            .collect();
+        for &idx in &pending {
+            photos[idx].duplicate_status = DuplicateStatus::Unknown;
+        }
+        emit_photo_updates(&photos, &pending, &window);
     }
 
     // Find size groups with more than one file (potential duplicates)
@@ -387,7 +945,7 @@ pub fn scan_directories_with_progress(directories: &[String], window: Window) ->
             photo_count,
             &format!("Done! {} photos, no duplicates found", photo_count),
         );
-        return photos;
+        return Ok(photos);
     }
 
     // Phase 5: Compute trailing hash for potential duplicates (fast - only last 1MB)
@@ -406,7 +964,7 @@ pub fn scan_directories_with_progress(directories: &[String], window: Window) ->
         .copied()
         .collect();
 
-    // Pre-fetch cached trailing hashes (sequential, to avoid thread-safety issues)
+    // Pre-fetch cached trailing hashes
     let mut cached_trailing_hashes: HashMap<usize, String> = HashMap::new();
     let mut needs_compute: Vec<usize> = Vec::new();
     
@@ -424,33 +982,10 @@ pub fn scan_directories_with_progress(directories: &[String], window: Window) ->
     
     let cache_hits = cached_trailing_hashes.len();
     let to_compute = needs_compute.len();
-    
-    // Atomic counter for progress reporting during parallel computation
-    let progress_counter = Arc::new(AtomicUsize::new(0));
-    let progress_counter_clone = Arc::clone(&progress_counter);
-    
-    // Spawn a thread to emit progress updates periodically
-    let window_clone = window.clone();
-    let progress_total = to_compute;
-    let progress_thread = std::thread::spawn(move || {
-        loop {
-            let current = progress_counter_clone.load(Ordering::Relaxed);
-            if current >= progress_total {
-                break;
-            }
-            let _ = window_clone.emit(
-                "scan-progress",
-                ScanProgress {
-                    phase: "trailing_hash".to_string(),
-                    current: cache_hits + current,
-                    total: potential_count,
-                    message: format!("[{}] Quick hash: {} cached, {} computed",
-                        pct(cache_hits + current, potential_count), cache_hits, current),
-                },
-            );
-            std::thread::sleep(std::time::Duration::from_millis(100));
-        }
-    });
+
+    // Hashes finished from here on are reported by the job's `job-progress`
+    // events, on top of the cached ones
+    monitor.set_phase_from("trailing_hash", cache_hits, potential_count);
 
     // Parallel computation of trailing hashes
     // We need to collect photo data first since we can't mutate during parallel iteration
@@ -465,6 +1000,11 @@ pub fn scan_directories_with_progress(directories: &[String], window: Window) ->
     let computed_hashes: Vec<(usize, Option<String>, Option<u64>)> = photo_data
         .par_iter()
         .map(|(idx, path, size, is_placeholder)| {
+            if monitor.is_cancelled() {
+                return (*idx, None, None);
+            }
+            let _work = monitor.working_on(path);
+
             // Handle cloud placeholder - need actual size
             let actual_size = if *is_placeholder {
                 fs::metadata(path).map(|m| m.len()).ok()
@@ -473,43 +1013,40 @@ pub fn scan_directories_with_progress(directories: &[String], window: Window) ->
             };
             
             let hash_size = actual_size.unwrap_or(*size);
-            let hash = compute_trailing_hash(path, hash_size);
-            
-            // Increment progress counter
-            progress_counter.fetch_add(1, Ordering::Relaxed);
-            
+            let hash = compute_trailing_hash(path, hash_size, algorithm)
+                .filter(|_| !monitor.is_skipped(path));
+
+            // Store right away instead of holding every result until the end
+            if let (Some(c), Some(h)) = (cache.as_ref(), &hash) {
+                c.set_trailing_hash(path, hash_size, h);
+            }
+
             (*idx, hash, actual_size)
         })
         .collect();
 
-    // Wait for progress thread to finish
-    let _ = progress_thread.join();
+    if monitor.is_cancelled() {
+        return Err(cancelled());
+    }
 
     // Merge results: cached + computed
     let mut trailing_hashes: HashMap<usize, String> = cached_trailing_hashes;
-    let mut cache_updates: Vec<(String, u64, String)> = Vec::new();
+    // Photos whose placeholder size was resolved (already streamed with the old size)
+    let mut resolved_placeholders: Vec<usize> = Vec::new();
     
     for (photo_idx, hash, actual_size) in computed_hashes {
         // Update photo if we resolved cloud placeholder size
         if let Some(size) = actual_size {
             photos[photo_idx].size = size;
             photos[photo_idx].is_cloud_placeholder = false;
+            resolved_placeholders.push(photo_idx);
         }
         
         if let Some(h) = hash {
-            let photo = &photos[photo_idx];
-            cache_updates.push((photo.path.clone(), photo.size, h.clone()));
             trailing_hashes.insert(photo_idx, h);
         }
     }
-    
-    // Update cache sequentially (not thread-safe)
-    if let Some(c) = cache.as_ref() {
-        for (path, size, hash) in cache_updates {
-            c.set_trailing_hash(&path, size, &hash);
-        }
-    }
-    
+
     // Final trailing hash progress
     emit_progress(
         "trailing_hash",
@@ -521,41 +1058,74 @@ pub fn scan_directories_with_progress(directories: &[String], window: Window) ->
     // Phase 6: Group by trailing hash to find likely duplicates
     emit_progress("duplicates", 0, photo_count, "Grouping by trailing hash...");
 
-    let mut trailing_hash_groups: HashMap<(&u64, &String), Vec<usize>> = HashMap::new();
+    let mut trailing_hash_groups: HashMap<(&str, u64, &String), Vec<usize>> = HashMap::new();
     for group in &size_collision_groups {
         for &photo_idx in group {
             if let Some(trailing_hash) = trailing_hashes.get(&photo_idx) {
-                let size = &photos[photo_idx].size;
+                let size = photos[photo_idx].size;
                 trailing_hash_groups
-                    .entry((size, trailing_hash))
+                    .entry((&buckets[photo_idx], size, trailing_hash))
                     .or_default()
                     .push(photo_idx);
             }
         }
     }
 
-    // Files that need full hash: those in trailing hash groups with 2+ files
-    let needs_full_hash: Vec<usize> = trailing_hash_groups
-        .values()
+    // Likely duplicates: trailing hash groups with 2+ files
+    let likely_groups: Vec<Vec<usize>> = trailing_hash_groups
+        .into_values()
         .filter(|group| group.len() > 1)
-        .flatten()
-        .copied()
         .collect();
 
+    // Files that need full hash
+    let needs_full_hash: Vec<usize> = likely_groups.iter().flatten().copied().collect();
+
     // Free intermediate data structures - they can be large
-    drop(trailing_hash_groups);
     drop(trailing_hashes);
     drop(size_collision_groups);
 
     if needs_full_hash.is_empty() {
+        emit_photo_updates(&photos, &resolved_placeholders, &window);
         emit_progress(
             "complete",
             photo_count,
             photo_count,
             &format!("Done! {} photos, no duplicates found (trailing hashes differ)", photo_count),
         );
-        return photos;
+        return Ok(photos);
+    }
+
+    // Quick tier: size and trailing hash are taken as proof, skipping the full hash
+    if options.hash_tier == HashTier::Quick {
+        let mut duplicate_count = 0;
+        for mut group in likely_groups {
+            prefer_priority_roots(&photos, &mut group);
+            let original = photos[group[0]].id.clone();
+            for &photo_idx in &group[1..] {
+                photos[photo_idx].is_duplicate = true;
+                photos[photo_idx].duplicate_of = Some(original.clone());
+                duplicate_count += 1;
+            }
+        }
+
+        let mut changed = needs_full_hash;
+        changed.extend(resolved_placeholders);
+        changed.sort_unstable();
+        changed.dedup();
+        emit_photo_updates(&photos, &changed, &window);
+
+        emit_progress(
+            "complete",
+            photo_count,
+            photo_count,
+            &format!(
+                "Done! {} photos, {} likely duplicates (quick hash)",
+                photo_count, duplicate_count
+            ),
+        );
+        return Ok(photos);
     }
+    drop(likely_groups);
 
     // Phase 7: Compute full hash only for files with matching trailing hashes
     // This phase uses parallel processing for significant speedup
@@ -586,33 +1156,7 @@ pub fn scan_directories_with_progress(directories: &[String], window: Window) ->
     
     let full_cache_hits = cached_full_hashes.len();
     let full_to_compute = needs_full_compute.len();
-    
-    // Atomic counter for progress reporting during parallel computation
-    let full_progress_counter = Arc::new(AtomicUsize::new(0));
-    let full_progress_counter_clone = Arc::clone(&full_progress_counter);
-    
-    // Spawn a thread to emit progress updates periodically
-    let window_clone2 = window.clone();
-    let full_progress_total = full_to_compute;
-    let full_progress_thread = std::thread::spawn(move || {
-        loop {
-            let current = full_progress_counter_clone.load(Ordering::Relaxed);
-            if current >= full_progress_total {
-                break;
-            }
-            let _ = window_clone2.emit(
-                "scan-progress",
-                ScanProgress {
-                    phase: "hashing".to_string(),
-                    current: full_cache_hits + current,
-                    total: full_hash_total,
-                    message: format!("[{}] Full hash: {} cached, {} computed",
-                        pct(full_cache_hits + current, full_hash_total), full_cache_hits, current),
-                },
-            );
-            std::thread::sleep(std::time::Duration::from_millis(100));
-        }
-    });
+    monitor.set_phase_from("hashing", full_cache_hits, full_hash_total);
 
     // Collect photo data for parallel computation
     let full_photo_data: Vec<(usize, String, u64, bool)> = needs_full_compute
@@ -627,6 +1171,11 @@ pub fn scan_directories_with_progress(directories: &[String], window: Window) ->
     let computed_full_hashes: Vec<(usize, Option<String>, Option<u64>)> = full_photo_data
         .par_iter()
         .map(|(idx, path, size, is_placeholder)| {
+            if monitor.is_cancelled() {
+                return (*idx, None, None);
+            }
+            let _work = monitor.working_on(path);
+
             // Handle cloud placeholder - need actual size
             let actual_size = if *is_placeholder {
                 fs::metadata(path).map(|m| m.len()).ok()
@@ -634,26 +1183,28 @@ pub fn scan_directories_with_progress(directories: &[String], window: Window) ->
                 None
             };
             
-            let hash = compute_full_hash(path);
-            
-            // Increment progress counter
-            full_progress_counter.fetch_add(1, Ordering::Relaxed);
-            
-            (*idx, hash, actual_size.or(Some(*size)))
+            let hash = compute_full_hash(path, algorithm).filter(|_| !monitor.is_skipped(path));
+            let size = actual_size.unwrap_or(*size);
+
+            // Store right away instead of holding every result until the end
+            if let (Some(c), Some(h)) = (cache.as_ref(), &hash) {
+                c.set_full_hash(path, size, h);
+            }
+
+            (*idx, hash, Some(size))
         })
         .collect();
 
-    // Wait for progress thread to finish
-    let _ = full_progress_thread.join();
+    if monitor.is_cancelled() {
+        return Err(cancelled());
+    }
 
     // Apply cached hashes to photos
     for (photo_idx, hash) in &cached_full_hashes {
         photos[*photo_idx].hash = Some(hash.clone());
     }
 
-    // Apply computed hashes to photos and collect cache updates
-    let mut full_cache_updates: Vec<(String, u64, String)> = Vec::new();
-    
+    // Apply computed hashes to photos
     for (photo_idx, hash, size) in computed_full_hashes {
         // Update photo size if resolved
         if let Some(s) = size {
@@ -662,19 +1213,10 @@ pub fn scan_directories_with_progress(directories: &[String], window: Window) ->
         }
         
         if let Some(h) = hash {
-            let photo = &photos[photo_idx];
-            full_cache_updates.push((photo.path.clone(), photo.size, h.clone()));
             photos[photo_idx].hash = Some(h);
         }
     }
-    
-    // Update cache sequentially (not thread-safe)
-    if let Some(c) = cache.as_ref() {
-        for (path, size, hash) in full_cache_updates {
-            c.set_full_hash(&path, size, &hash);
-        }
-    }
-    
+
     emit_progress(
         "hashing",
         full_hash_total,
@@ -685,21 +1227,31 @@ pub fn scan_directories_with_progress(directories: &[String], window: Window) ->
     // Phase 8: Use full hashes to identify confirmed duplicates
     emit_progress("duplicates", 0, photo_count, "Confirming duplicates by full content hash...");
     
-    let mut hash_map: HashMap<String, usize> = HashMap::new();
+    let mut hash_map: HashMap<(&str, String), usize> = HashMap::new();
     let mut duplicate_count = 0;
+    let mut needs_full_hash = needs_full_hash;
+    prefer_priority_roots(&photos, &mut needs_full_hash);
 
     for &photo_idx in &needs_full_hash {
-        if let Some(ref hash) = photos[photo_idx].hash {
-            if let Some(&original_idx) = hash_map.get(hash) {
+        if let Some(hash) = photos[photo_idx].hash.clone() {
+            let key = (buckets[photo_idx].as_str(), hash);
+            if let Some(&original_idx) = hash_map.get(&key) {
                 photos[photo_idx].is_duplicate = true;
                 photos[photo_idx].duplicate_of = Some(photos[original_idx].id.clone());
                 duplicate_count += 1;
             } else {
-                hash_map.insert(hash.clone(), photo_idx);
+                hash_map.insert(key, photo_idx);
             }
         }
     }
 
+    // Send hash/duplicate results for photos that were already streamed
+    let mut changed = needs_full_hash;
+    changed.extend(resolved_placeholders);
+    changed.sort_unstable();
+    changed.dedup();
+    emit_photo_updates(&photos, &changed, &window);
+
     emit_progress(
         "complete",
         photo_count,
@@ -707,40 +1259,525 @@ pub fn scan_directories_with_progress(directories: &[String], window: Window) ->
         &format!("Done! {} photos, {} confirmed duplicates", photo_count, duplicate_count),
     );
 
-    photos
+    Ok(photos)
+}
+
+/// Mark photos with the same content as a file trashed earlier
+/// Only files whose size matches a trashed file are hashed; returns their indices
+fn flag_previously_trashed(photos: &mut [PhotoFile], cache: &HashCache) -> Vec<usize> {
+    let Ok(history) = TrashHistory::open() else {
+        return Vec::new();
+    };
+    let algorithm = cache.algorithm();
+    let sizes = history.sizes(algorithm).unwrap_or_default();
+    if sizes.is_empty() {
+        return Vec::new();
+    }
+
+    let mut flagged = Vec::new();
+    for (idx, photo) in photos.iter_mut().enumerate() {
+        if photo.is_cloud_placeholder || !sizes.contains(&photo.size) {
+            continue;
+        }
+        let Some(hash) = cache.full_hash(&photo.path, photo.size) else {
+            continue;
+        };
+        if let Ok(Some(trashed_at)) = history.trashed_at(&hash, algorithm) {
+            photo.previously_trashed_at = Some(trashed_at);
+            flagged.push(idx);
+        }
+        photo.hash = Some(hash);
+    }
+    flagged
+}
+
+/// The related-file type a primary with extension `primary` gives a file of
+/// the same name with extension `other` (both lowercase), if it claims it;
+/// companions and sidecars are matched on their own
+/// `live_video` says the other file is a MOV confirmed to belong to a Live
+/// Photo, which a still claims even when `live_photos` is off
+fn claims(
+    precedence: PrimaryPrecedence,
+    primary: &str,
+    other: &str,
+    live_video: bool,
+) -> Option<&'static str> {
+    let is_raw = |e: &str| RAW_EXTENSIONS.contains(&e);
+    let is_image = |e: &str| IMAGE_EXTENSIONS.contains(&e);
+    match precedence.raw_jpeg {
+        RawJpegPrecedence::Raw if is_raw(primary) && is_image(other) => {
+            return Some("jpeg-preview")
+        }
+        RawJpegPrecedence::Jpeg if is_image(primary) && is_raw(other) => return Some("raw"),
+        _ => {}
+    }
+    let paired = live_video || precedence.live_photos;
+    (paired && live_photos::is_live_still(primary) && other == "mov").then_some("live-video")
+}
+
+fn related_file(path: &Path, file_type: &str) -> RelatedFile {
+    RelatedFile {
+        path: path.to_string_lossy().to_string(),
+        name: path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("")
+            .to_string(),
+        file_type: file_type.to_string(),
+    }
+}
+
+/// Stack the bursts and brackets among the scanned photos and record the
+/// stack of every photo; returns the indices of stacked photos
+fn stack_bursts(photos: &mut [PhotoFile], directories: &[String]) -> Vec<usize> {
+    let memberships = Stacks::open().and_then(|mut stacks| {
+        stacks.replace_bursts(directories, &bursts::detect(photos))?;
+        stacks.memberships()
+    });
+    let memberships = match memberships {
+        Ok(memberships) => memberships,
+        Err(e) => {
+            eprintln!("Failed to stack bursts: {}", e);
+            return Vec::new();
+        }
+    };
+    let mut stacked = Vec::new();
+    for (idx, photo) in photos.iter_mut().enumerate() {
+        photo.stack_id = memberships.get(&photo.path).copied();
+        if photo.stack_id.is_some() {
+            stacked.push(idx);
+        }
+    }
+    stacked
+}
+
+/// Point every path of a hard-linked file but one at that one, preferring the
+/// path in the highest-priority root; returns the indices of the linked paths
+fn mark_hardlinks(photos: &mut [PhotoFile]) -> Vec<usize> {
+    let ids: Vec<(usize, (u64, u64))> = photos
+        .par_iter()
+        .enumerate()
+        .filter_map(|(idx, photo)| {
+            let metadata = fs::metadata(&photo.path).ok()?;
+            Some((idx, hardlink_id(&metadata)?))
+        })
+        .collect();
+    let mut hardlinks: HashMap<(u64, u64), Vec<usize>> = HashMap::new();
+    for (idx, id) in ids {
+        hardlinks.entry(id).or_default().push(idx);
+    }
+
+    let mut linked = Vec::new();
+    for mut paths in hardlinks.into_values().filter(|paths| paths.len() > 1) {
+        prefer_priority_roots(photos, &mut paths);
+        let kept = photos[paths[0]].id.clone();
+        for &idx in &paths[1..] {
+            photos[idx].hardlink_of = Some(kept.clone());
+            linked.push(idx);
+        }
+    }
+    linked.sort_unstable();
+    linked
+}
+
+/// Pixels hash tier: hash the decoded pixels of still images that share a dedupe
+/// bucket, displayed size and orientation with another, and mark the copies
+/// Cloud placeholders are downloaded to read their size unless the policy skips
+/// them; photos given a pixel hash are left out of the byte comparison
+/// Returns the indices of photos that changed
+fn mark_pixel_duplicates(
+    photos: &mut [PhotoFile],
+    buckets: &[String],
+    options: &ScanOptions,
+    cache: Option<&HashCache>,
+    algorithm: HashAlgorithm,
+    monitor: &JobMonitor,
+    window: &Window,
+) -> Vec<usize> {
+    let candidates: Vec<usize> = (0..photos.len())
+        .filter(|&idx| {
+            let photo = &photos[idx];
+            // Animations are compared by bytes, since only a frame is decoded
+            let skipped = photo.is_cloud_placeholder
+                && (options.cloud_policy == CloudPolicy::Skip || may_be_animated(&photo.extension));
+            can_hash_pixels(&photo.extension)
+                && photo.animation.is_none()
+                && photo.hardlink_of.is_none()
+                && !skipped
+        })
+        .collect();
+
+    // Placeholders were not read while analyzing, so their size is unknown
+    type Resolved = (usize, Option<(u32, u32)>, Option<u16>, Option<u64>);
+    let placeholders: Vec<Resolved> = candidates
+        .par_iter()
+        .filter(|&&idx| photos[idx].is_cloud_placeholder)
+        .map(|&idx| {
+            let path = &photos[idx].path;
+            if monitor.is_cancelled() {
+                return (idx, None, None, None);
+            }
+            let _work = monitor.working_on(path);
+            let orientation = read_exif_summary(Path::new(path)).and_then(|e| e.orientation);
+            let dimensions = header_dimensions(Path::new(path))
+                .map(|(w, h)| oriented_dimensions(w, h, orientation));
+            let size = fs::metadata(path).map(|m| m.len()).ok();
+            (idx, dimensions, orientation, size)
+        })
+        .collect();
+    let mut changed = Vec::new();
+    for (idx, dimensions, orientation, size) in placeholders {
+        let photo = &mut photos[idx];
+        if let Some((width, height)) = dimensions {
+            photo.width = Some(width);
+            photo.height = Some(height);
+            photo.orientation = orientation;
+        }
+        if let Some(size) = size {
+            photo.size = size;
+            photo.is_cloud_placeholder = false;
+            changed.push(idx);
+        }
+    }
+    if monitor.is_cancelled() {
+        return changed;
+    }
+
+    let mut size_groups: HashMap<(&str, u32, u32, u16), Vec<usize>> = HashMap::new();
+    for &idx in &candidates {
+        let photo = &photos[idx];
+        if let (Some(width), Some(height)) = (photo.width, photo.height) {
+            size_groups
+                .entry((&buckets[idx], width, height, photo.orientation.unwrap_or(1)))
+                .or_default()
+                .push(idx);
+        }
+    }
+    let to_hash: Vec<usize> = size_groups
+        .into_values()
+        .filter(|group| group.len() > 1)
+        .flatten()
+        .collect();
+
+    let total = to_hash.len();
+    let hashed_count = AtomicUsize::new(0);
+    let hashes: Vec<(usize, String)> = to_hash
+        .par_iter()
+        .filter_map(|&idx| {
+            let path = &photos[idx].path;
+            if monitor.is_cancelled() {
+                return None;
+            }
+            let _work = monitor.working_on(path);
+            let hash = match cache {
+                Some(c) => c.pixel_hash(path),
+                None => compute_pixel_hash(Path::new(path), algorithm),
+            };
+            let current = hashed_count.fetch_add(1, Ordering::Relaxed) + 1;
+            let _ = window.emit(
+                "scan-progress",
+                ScanProgress {
+                    phase: "pixel_hash".to_string(),
+                    current,
+                    total,
+                    message: format!("[{}] Comparing pixels", pct(current, total)),
+                },
+            );
+            hash.filter(|_| !monitor.is_skipped(path))
+                .map(|hash| (idx, hash))
+        })
+        .collect();
+    if monitor.is_cancelled() {
+        return changed;
+    }
+
+    let mut hashed: Vec<usize> = Vec::with_capacity(hashes.len());
+    for (idx, hash) in hashes {
+        photos[idx].pixel_hash = Some(hash);
+        hashed.push(idx);
+    }
+    prefer_priority_roots(photos, &mut hashed);
+    let mut originals: HashMap<(&str, String), usize> = HashMap::new();
+    for &idx in &hashed {
+        let Some(hash) = photos[idx].pixel_hash.clone() else {
+            continue;
+        };
+        match originals.get(&(buckets[idx].as_str(), hash.clone())) {
+            Some(&original_idx) => {
+                photos[idx].is_duplicate = true;
+                photos[idx].duplicate_of = Some(photos[original_idx].id.clone());
+            }
+            None => {
+                originals.insert((buckets[idx].as_str(), hash), idx);
+            }
+        }
+    }
+
+    changed.extend(hashed);
+    changed.sort_unstable();
+    changed.dedup();
+    changed
+}
+
+/// Order candidate originals so files in higher-priority roots come first
+/// The sort is stable, so within a root scan order (RAW files first) still decides
+fn prefer_priority_roots(photos: &[PhotoFile], indices: &mut [usize]) {
+    indices.sort_by_key(|&idx| std::cmp::Reverse(photos[idx].root_priority));
 }
 
-/// Compute SHA-256 hash of the last 1MB of a file (or whole file if smaller)
-fn compute_trailing_hash(path: &str, file_size: u64) -> Option<String> {
+/// Fill in EXIF metadata and stable ids for a chunk of analyzed photos and emit it as a `scan-batch`
+fn stream_batch(
+    batch: &mut [PhotoFile],
+    ids: Option<&mut PhotoIds>,
+    previous: &HashMap<String, PhotoFile>,
+    window: &Window,
+) {
+    read_photo_metadata(batch, previous);
+    if let Some(ids) = ids {
+        if let Err(e) = ids.resolve_all(batch) {
+            eprintln!("Failed to assign photo ids: {}", e);
+        }
+    }
+    for photos in sized_chunks(batch) {
+        let _ = window.emit("scan-batch", ScanBatch { photos });
+    }
+}
+
+/// Emit `scan-delta` events for photos whose size, hash or duplicate status changed
+fn emit_photo_updates(photos: &[PhotoFile], indices: &[usize], window: &Window) {
+    let updates: Vec<PhotoUpdate> = indices
+        .iter()
+        .map(|&idx| {
+            let photo = &photos[idx];
+            PhotoUpdate {
+                id: photo.id.clone(),
+                size: photo.size,
+                hash: photo.hash.clone(),
+                pixel_hash: photo.pixel_hash.clone(),
+                is_duplicate: photo.is_duplicate,
+                duplicate_of: photo.duplicate_of.clone(),
+                hardlink_of: photo.hardlink_of.clone(),
+                duplicate_status: photo.duplicate_status,
+                is_cloud_placeholder: photo.is_cloud_placeholder,
+                previously_trashed_at: photo.previously_trashed_at,
+                stack_id: photo.stack_id,
+            }
+        })
+        .collect();
+    for updates in sized_chunks(&updates) {
+        let _ = window.emit("scan-delta", ScanDelta { updates });
+    }
+}
+
+/// Split items into consecutive runs of about `TARGET_EVENT_BYTES` of JSON each
+/// (at least one item per run, so an oversized item still goes out on its own)
+fn sized_chunks<T: Serialize>(items: &[T]) -> Vec<&[T]> {
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    let mut bytes = 0;
+    for (i, item) in items.iter().enumerate() {
+        bytes += json_size(item);
+        if bytes >= TARGET_EVENT_BYTES {
+            chunks.push(&items[start..=i]);
+            start = i + 1;
+            bytes = 0;
+        }
+    }
+    if start < items.len() {
+        chunks.push(&items[start..]);
+    }
+    chunks
+}
+
+/// Length of a value's JSON encoding, measured without building the string
+fn json_size<T: Serialize>(value: &T) -> usize {
+    struct Counter(usize);
+    impl std::io::Write for Counter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0 += buf.len();
+            Ok(buf.len())
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let mut counter = Counter(0);
+    let _ = serde_json::to_writer(&mut counter, value);
+    counter.0
+}
+
+/// Populate capture date, camera and pixel size from EXIF (or ffprobe for videos) in parallel
+/// Cloud placeholders are skipped - reading EXIF would hydrate them
+/// Files with the same size and date as in `previous` keep their earlier metadata
+fn read_photo_metadata(photos: &mut [PhotoFile], previous: &HashMap<String, PhotoFile>) {
+    photos.par_iter_mut().for_each(|photo| {
+        let unchanged = previous
+            .get(&photo.path)
+            .filter(|p| p.size == photo.size && p.modified_at == photo.modified_at);
+        if let Some(earlier) = unchanged {
+            photo.capture_date = earlier.capture_date;
+            photo.camera_make = earlier.camera_make.clone();
+            photo.camera_model = earlier.camera_model.clone();
+            photo.latitude = earlier.latitude;
+            photo.longitude = earlier.longitude;
+            photo.place_name = earlier.place_name.clone();
+            photo.width = earlier.width;
+            photo.height = earlier.height;
+            photo.orientation = earlier.orientation;
+            photo.media_info = earlier.media_info.clone();
+            photo.animation = earlier.animation;
+            if earlier.animation.is_some() {
+                photo.thumbnail_path = earlier.thumbnail_path.clone();
+            } else if photo.thumbnail_path.is_none() {
+                // A preview extracted from a RAW earlier
+                photo.thumbnail_path = earlier
+                    .thumbnail_path
+                    .clone()
+                    .filter(|t| Path::new(t).starts_with(thumbnail_dir()));
+            }
+        } else if is_video_extension(&photo.extension) {
+            if !photo.is_cloud_placeholder {
+                photo.media_info = probe_media_info(Path::new(&photo.path));
+                photo.capture_date = photo.media_info.as_ref().and_then(|m| m.created_at);
+                photo.width = photo.media_info.as_ref().and_then(|m| m.width);
+                photo.height = photo.media_info.as_ref().and_then(|m| m.height);
+            }
+        } else if !photo.is_cloud_placeholder {
+            if may_be_animated(&photo.extension) {
+                read_animation(photo);
+            }
+            if photo.thumbnail_path.is_none() && RAW_EXTENSIONS.contains(&photo.extension.as_str())
+            {
+                // No sibling JPEG to show, so use the preview the camera embedded
+                photo.thumbnail_path = embedded_preview(Path::new(&photo.path))
+                    .map(|p| p.to_string_lossy().to_string());
+            }
+            if let Some(exif) = read_exif_summary(Path::new(&photo.path)) {
+                photo.capture_date = exif.capture_date.and_then(|date| {
+                    chrono::Local
+                        .from_local_datetime(&date)
+                        .earliest()
+                        .map(|d| d.timestamp_millis())
+                });
+                photo.camera_make = exif.camera_make;
+                photo.camera_model = exif.camera_model;
+                photo.latitude = exif.latitude;
+                photo.longitude = exif.longitude;
+                photo.orientation = exif.orientation;
+                photo.width = exif.width;
+                photo.height = exif.height;
+            }
+            read_dimensions(photo);
+        }
+        photo.sidecar_metadata = photo
+            .related_files
+            .iter()
+            .find(|r| r.file_type == "sidecar")
+            .and_then(|r| parse_sidecar(Path::new(&r.path)));
+        let corrected = photo
+            .sidecar_metadata
+            .as_ref()
+            .and_then(|s| s.capture_date.as_deref())
+            .and_then(parse_date);
+        if corrected.is_some() {
+            // Written by `shift_capture_time` or another tool correcting the camera
+            photo.capture_date = corrected;
+        }
+        read_location(photo, unchanged);
+        photo.source_kind = source_kind::classify(photo);
+    });
+}
+
+/// Position from EXIF, else from the sidecar (where geotagging tools write
+/// for RAW files), named after the nearest town
+fn read_location(photo: &mut PhotoFile, earlier: Option<&PhotoFile>) {
+    let exif = photo.latitude.zip(photo.longitude);
+    let sidecar = photo
+        .sidecar_metadata
+        .as_ref()
+        .and_then(|s| s.latitude.zip(s.longitude));
+    let position = exif
+        .into_iter()
+        .chain(sidecar)
+        .find(|&(lat, lon)| geocode::is_valid(lat, lon));
+    photo.latitude = position.map(|(lat, _)| lat);
+    photo.longitude = position.map(|(_, lon)| lon);
+    photo.place_name = match (position, earlier) {
+        (Some(position), Some(earlier))
+            if earlier.latitude.zip(earlier.longitude) == Some(position) =>
+        {
+            earlier.place_name.clone()
+        }
+        (Some((lat, lon)), _) => geocode::place_name(lat, lon),
+        (None, _) => None,
+    };
+}
+
+/// Pixel size from the image header, else as recorded in EXIF, else from the
+/// JPEG preview of a RAW; turned to match the EXIF orientation
+fn read_dimensions(photo: &mut PhotoFile) {
+    let stored = header_dimensions(Path::new(&photo.path))
+        .or(photo.width.zip(photo.height))
+        .or_else(|| {
+            let preview = photo.thumbnail_path.as_deref()?;
+            header_dimensions(Path::new(preview))
+        });
+    let displayed = stored.map(|(w, h)| oriented_dimensions(w, h, photo.orientation));
+    photo.width = displayed.map(|(w, _)| w);
+    photo.height = displayed.map(|(_, h)| h);
+}
+
+/// Record animation details of a GIF/WebP and point its thumbnail at a
+/// generated still of a representative frame
+fn read_animation(photo: &mut PhotoFile) {
+    let path = Path::new(&photo.path);
+    photo.animation = probe_animation(path);
+    if let Some(info) = &photo.animation {
+        if let Some(thumbnail) = representative_thumbnail(path, info) {
+            photo.thumbnail_path = Some(thumbnail.to_string_lossy().to_string());
+        }
+    }
+}
+
+/// Compute the hash of the last 1MB of a file (or whole file if smaller)
+fn compute_trailing_hash(path: &str, file_size: u64, algorithm: HashAlgorithm) -> Option<String> {
     let mut file = File::open(path).ok()?;
-    let mut hasher = Sha256::new();
-    let mut buffer = [0u8; 65536]; // 64KB buffer
 
     // Seek to position for trailing hash
-    let start_pos = if file_size > TRAILING_HASH_SIZE {
-        file_size - TRAILING_HASH_SIZE
-    } else {
-        0
-    };
+    let start_pos = file_size.saturating_sub(TRAILING_HASH_SIZE);
     
     file.seek(SeekFrom::Start(start_pos)).ok()?;
-    let mut reader = BufReader::new(file);
-
-    loop {
-        match reader.read(&mut buffer) {
-            Ok(0) => break,
-            Ok(n) => hasher.update(&buffer[..n]),
-            Err(_) => return None,
+    let reader = BufReader::new(file);
+
+    match algorithm {
+        HashAlgorithm::Sha256 => sha256_reader(reader),
+        HashAlgorithm::Blake3 => {
+            let mut hasher = blake3::Hasher::new();
+            hasher.update_reader(reader).ok()?;
+            Some(hasher.finalize().to_hex().to_string())
         }
     }
+}
 
-    Some(format!("{:x}", hasher.finalize()))
+/// Compute the hash of an entire file
+pub fn compute_full_hash(path: &str, algorithm: HashAlgorithm) -> Option<String> {
+    match algorithm {
+        HashAlgorithm::Sha256 => {
+            let file = File::open(path).ok()?;
+            sha256_reader(BufReader::new(file))
+        }
+        HashAlgorithm::Blake3 => {
+            // Memory-mapped read; blake3 falls back to buffered reads for small files
+            let mut hasher = blake3::Hasher::new();
+            hasher.update_mmap(path).ok()?;
+            Some(hasher.finalize().to_hex().to_string())
+        }
+    }
 }
 
-/// Compute SHA-256 hash of entire file
-fn compute_full_hash(path: &str) -> Option<String> {
-    let file = File::open(path).ok()?;
-    let mut reader = BufReader::new(file);
+/// SHA-256 of everything readable from a reader
+fn sha256_reader(mut reader: impl Read) -> Option<String> {
     let mut hasher = Sha256::new();
     let mut buffer = [0u8; 65536]; // 64KB buffer
 
@@ -758,7 +1795,10 @@ fn compute_full_hash(path: &str) -> Option<String> {
 /// Check if a file is a cloud placeholder (dehydrated) on macOS
 /// Uses xattr to check for file provider attributes that indicate the file
 /// is not fully materialized locally (e.g., iCloud, Dropbox, OneDrive)
-fn is_cloud_placeholder(path: &str) -> bool {
+#[cfg(target_os = "macos")]
+pub fn is_cloud_placeholder(path: &str) -> bool {
+    use std::process::Command;
+
     // Check for common file provider extended attributes
     // com.apple.fileprovider.* attributes indicate file provider managed files
     // The presence of certain attributes or flags indicates dehydrated state
@@ -820,3 +1860,28 @@ fn is_cloud_placeholder(path: &str) -> bool {
     
     false
 }
+
+/// Check if a file is a cloud placeholder on Windows
+/// The Cloud Files API (OneDrive, iCloud for Windows, Dropbox) marks files whose
+/// content isn't on disk with the offline / recall-on-access attributes
+#[cfg(windows)]
+pub fn is_cloud_placeholder(path: &str) -> bool {
+    use std::os::windows::fs::MetadataExt;
+    const FILE_ATTRIBUTE_OFFLINE: u32 = 0x1000;
+    const FILE_ATTRIBUTE_RECALL_ON_OPEN: u32 = 0x40000;
+    const FILE_ATTRIBUTE_RECALL_ON_DATA_ACCESS: u32 = 0x400000;
+    fs::metadata(path).is_ok_and(|m| {
+        m.file_attributes()
+            & (FILE_ATTRIBUTE_OFFLINE
+                | FILE_ATTRIBUTE_RECALL_ON_OPEN
+                | FILE_ATTRIBUTE_RECALL_ON_DATA_ACCESS)
+            != 0
+    })
+}
+
+/// Linux sync clients (rclone, the Dropbox and OneDrive daemons) keep full local
+/// copies, so there are no placeholders to detect
+#[cfg(not(any(target_os = "macos", windows)))]
+pub fn is_cloud_placeholder(_path: &str) -> bool {
+    false
+}
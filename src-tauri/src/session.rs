@@ -0,0 +1,73 @@
+use crate::catalog::SearchQuery;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Where the user was in the app: saved by the frontend as they work, so a
+/// long triage session picks up where it left off after the app is reopened,
+/// or after the frontend crashes
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Session {
+    /// Ids of the selected photos
+    pub selected_ids: Vec<String>,
+    /// Photo the keyboard focus was on
+    pub focused_id: Option<String>,
+    /// Folder being browsed (the whole library when unset)
+    pub current_folder: Option<String>,
+    /// Vertical scroll offset of the grid or list, in pixels
+    pub scroll_top: f64,
+    /// "grid" | "list"
+    pub view_mode: Option<String>,
+    /// The last filter: the filter mode ("duplicates" | "all") and the search
+    /// shown, if any
+    pub filter_mode: Option<String>,
+    pub search: Option<SearchQuery>,
+    /// When the session was saved, ms since epoch; set by `save`
+    pub saved_at: i64,
+}
+
+fn session_path() -> PathBuf {
+    dirs::config_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("photo-manager")
+        .join("session.json")
+}
+
+/// Save the session in place of the previous one
+pub fn save(mut session: Session) -> Result<(), String> {
+    session.saved_at = chrono::Utc::now().timestamp_millis();
+    let path = session_path();
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    let json = serde_json::to_string(&session).map_err(|e| e.to_string())?;
+    // Written aside and renamed, so a crash mid-write leaves the previous session
+    let temporary = path.with_extension("json.tmp");
+    fs::write(&temporary, json).map_err(|e| e.to_string())?;
+    fs::rename(&temporary, path).map_err(|e| e.to_string())
+}
+
+/// The last saved session, None when there is none or it can't be read
+/// Selected photos that were moved or deleted since, and a folder that is
+/// gone, are left out
+pub fn load() -> Option<Session> {
+    let json = fs::read_to_string(session_path()).ok()?;
+    let mut session: Session = serde_json::from_str(&json).ok()?;
+    session.selected_ids.retain(|id| Path::new(id).exists());
+    if session
+        .focused_id
+        .as_deref()
+        .is_some_and(|id| !Path::new(id).exists())
+    {
+        session.focused_id = None;
+    }
+    if session
+        .current_folder
+        .as_deref()
+        .is_some_and(|dir| !Path::new(dir).is_dir())
+    {
+        session.current_folder = None;
+    }
+    Some(session)
+}
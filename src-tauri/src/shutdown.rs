@@ -0,0 +1,108 @@
+use crate::jobs::Jobs;
+use crate::windows::MAIN_WINDOW;
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Mutex, MutexGuard};
+use std::thread;
+use std::time::Duration;
+use tauri::{AppHandle, GlobalWindowEvent, Manager, RunEvent, WindowEvent};
+
+static NEXT_OPERATION_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Operations that would leave the library half-changed if the app quit now
+static IN_FLIGHT: Mutex<Vec<(u64, &'static str)>> = Mutex::new(Vec::new());
+
+/// Set while a deferred close or quit waits for operations to finish
+static WAITING: AtomicBool = AtomicBool::new(false);
+
+/// `quit-deferred` payload: why closing has to wait
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuitDeferred {
+    pub operations: Vec<&'static str>,
+}
+
+fn in_flight() -> MutexGuard<'static, Vec<(u64, &'static str)>> {
+    IN_FLIGHT.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Marks a move, rename or delete as in progress until dropped
+pub struct OperationGuard(u64);
+
+impl Drop for OperationGuard {
+    fn drop(&mut self) {
+        in_flight().retain(|(id, _)| *id != self.0);
+    }
+}
+
+/// Register a file operation that must finish before the app may exit
+pub fn begin(operation: &'static str) -> OperationGuard {
+    let id = NEXT_OPERATION_ID.fetch_add(1, Ordering::Relaxed);
+    in_flight().push((id, operation));
+    OperationGuard(id)
+}
+
+/// Names of the operations in progress (empty when it is safe to quit)
+fn pending() -> Vec<&'static str> {
+    in_flight()
+        .iter()
+        .map(|(_, operation)| *operation)
+        .collect()
+}
+
+/// Run `finish` once the running operations are done; move_all rolls a group
+/// back on failure, so each operation ends either complete or undone
+fn after_operations(finish: impl FnOnce() + Send + 'static) {
+    if WAITING.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    thread::spawn(move || {
+        while !pending().is_empty() {
+            thread::sleep(Duration::from_millis(200));
+        }
+        WAITING.store(false, Ordering::SeqCst);
+        finish();
+    });
+}
+
+/// Closing the main window while files are being moved or deleted waits for
+/// them and then closes; the frontend is told with a `quit-deferred` event
+pub fn on_window_event(event: GlobalWindowEvent) {
+    let WindowEvent::CloseRequested { api, .. } = event.event() else {
+        return;
+    };
+    // Other windows, like the compare window, close straight away
+    if event.window().label() != MAIN_WINDOW {
+        return;
+    }
+    let operations = pending();
+    if operations.is_empty() {
+        return;
+    }
+
+    api.prevent_close();
+    let window = event.window().clone();
+    // A paused job would never finish
+    window.state::<Jobs>().resume_all();
+    let _ = window.emit("quit-deferred", QuitDeferred { operations });
+    after_operations(move || {
+        let _ = window.close();
+    });
+}
+
+/// Same for quitting the app (⌘Q, the app menu)
+pub fn on_run_event(app: &AppHandle, event: RunEvent) {
+    let RunEvent::ExitRequested { api, .. } = event else {
+        return;
+    };
+    let operations = pending();
+    if operations.is_empty() {
+        return;
+    }
+
+    api.prevent_exit();
+    app.state::<Jobs>().resume_all();
+    let _ = app.emit_all("quit-deferred", QuitDeferred { operations });
+    let app = app.clone();
+    after_operations(move || app.exit(0));
+}
@@ -0,0 +1,127 @@
+use crate::catalog::{Catalog, PhotoPage, SearchQuery};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::Serialize;
+
+/// Named searches saved in the catalog database ("RAW, 2023, unrated, not
+/// duplicate"), whose photos are found anew each time they are opened
+pub struct SmartAlbums {
+    conn: Connection,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SmartAlbum {
+    pub id: i64,
+    pub name: String,
+    /// The saved filters and sort, without a page
+    pub query: SearchQuery,
+    pub created_at: i64,
+}
+
+/// Create the smart album table; called when the catalog is opened
+pub fn create_tables(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS smart_albums (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL,
+            query TEXT NOT NULL,
+            created_at INTEGER NOT NULL
+        );",
+    )
+}
+
+fn album_from_row(row: &Row) -> rusqlite::Result<SmartAlbum> {
+    let query: String = row.get(2)?;
+    Ok(SmartAlbum {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        // Filters added since the album was saved are left unset
+        query: serde_json::from_str(&query).unwrap_or_default(),
+        created_at: row.get(3)?,
+    })
+}
+
+impl SmartAlbums {
+    /// Open the smart album table in the catalog database
+    pub fn open() -> Result<Self, String> {
+        let conn = Catalog::connection()?;
+        Ok(Self { conn })
+    }
+
+    /// Save a search under a name; its offset and page size are dropped
+    pub fn create(&self, name: &str, query: &SearchQuery) -> Result<SmartAlbum, String> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err("Album name cannot be empty".to_string());
+        }
+        let query = SearchQuery {
+            offset: 0,
+            limit: None,
+            ..query.clone()
+        };
+        let json = serde_json::to_string(&query).map_err(|e| e.to_string())?;
+        let created_at = chrono::Utc::now().timestamp_millis();
+        self.conn
+            .execute(
+                "INSERT INTO smart_albums (name, query, created_at) VALUES (?1, ?2, ?3)",
+                params![name, json, created_at],
+            )
+            .map_err(|e| e.to_string())?;
+
+        Ok(SmartAlbum {
+            id: self.conn.last_insert_rowid(),
+            name: name.to_string(),
+            query,
+            created_at,
+        })
+    }
+
+    pub fn get(&self, id: i64) -> Result<SmartAlbum, String> {
+        self.conn
+            .query_row(
+                "SELECT id, name, query, created_at FROM smart_albums WHERE id = ?1",
+                params![id],
+                album_from_row,
+            )
+            .optional()
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| format!("No smart album with id {}", id))
+    }
+
+    /// Every smart album, by name
+    pub fn list(&self) -> Result<Vec<SmartAlbum>, String> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT id, name, query, created_at FROM smart_albums
+                ORDER BY name COLLATE NOCASE",
+            )
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map([], album_from_row)
+            .map_err(|e| e.to_string())?;
+        rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+    }
+
+    /// Delete a smart album; its photos are untouched
+    pub fn delete(&self, id: i64) -> Result<(), String> {
+        self.conn
+            .execute("DELETE FROM smart_albums WHERE id = ?1", params![id])
+            .map_err(|e| e.to_string())?;
+        Ok(())
+    }
+}
+
+/// Run a smart album's search against the catalog as it is now, one page at a
+/// time
+pub fn evaluate(
+    album: &SmartAlbum,
+    offset: usize,
+    limit: Option<usize>,
+) -> Result<PhotoPage, String> {
+    Catalog::open()?.search(&SearchQuery {
+        offset,
+        limit,
+        ..album.query.clone()
+    })
+}
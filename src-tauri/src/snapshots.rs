@@ -0,0 +1,152 @@
+use crate::catalog::Catalog;
+use rusqlite::{params, Connection, OpenFlags};
+use serde::Serialize;
+use std::fs;
+use std::path::PathBuf;
+
+/// A new snapshot is taken when the newest one is older than this
+const SNAPSHOT_INTERVAL_MS: i64 = 24 * 60 * 60 * 1000;
+
+/// Snapshots kept; the oldest are deleted beyond this
+const MAX_SNAPSHOTS: usize = 30;
+
+/// A stored copy of the catalog database
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotInfo {
+    /// When the snapshot was taken, ms since epoch; identifies it
+    pub timestamp: i64,
+    pub photo_count: usize,
+    /// Size of the snapshot file in bytes
+    pub size: u64,
+}
+
+fn snapshots_dir() -> PathBuf {
+    dirs::config_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("photo-manager")
+        .join("snapshots")
+}
+
+fn snapshot_path(timestamp: i64) -> PathBuf {
+    snapshots_dir().join(format!("catalog-{}.db", timestamp))
+}
+
+/// Timestamps of the stored snapshots, oldest first
+fn timestamps() -> Vec<i64> {
+    let Ok(entries) = fs::read_dir(snapshots_dir()) else {
+        return Vec::new();
+    };
+    let mut timestamps: Vec<i64> = entries
+        .filter_map(|e| e.ok())
+        .filter_map(|e| {
+            e.file_name()
+                .to_str()?
+                .strip_prefix("catalog-")?
+                .strip_suffix(".db")?
+                .parse()
+                .ok()
+        })
+        .collect();
+    timestamps.sort_unstable();
+    timestamps
+}
+
+fn info(timestamp: i64) -> Result<SnapshotInfo, String> {
+    let path = snapshot_path(timestamp);
+    let size = fs::metadata(&path).map_err(|e| e.to_string())?.len();
+    let conn = Connection::open_with_flags(&path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|e| e.to_string())?;
+    let photo_count: i64 = conn
+        .query_row("SELECT COUNT(*) FROM photos", [], |row| row.get(0))
+        .map_err(|e| e.to_string())?;
+    Ok(SnapshotInfo {
+        timestamp,
+        photo_count: photo_count as usize,
+        size,
+    })
+}
+
+/// Copy the catalog (photo list, metadata, tags, ratings, albums; never the
+/// files themselves) into a new snapshot and drop the oldest beyond the limit
+pub fn take() -> Result<SnapshotInfo, String> {
+    fs::create_dir_all(snapshots_dir()).map_err(|e| e.to_string())?;
+    let timestamp = chrono::Utc::now().timestamp_millis();
+    Catalog::open()?.backup_to(&snapshot_path(timestamp))?;
+
+    let stored = timestamps();
+    for old in &stored[..stored.len().saturating_sub(MAX_SNAPSHOTS)] {
+        if let Err(e) = fs::remove_file(snapshot_path(*old)) {
+            eprintln!("Failed to remove snapshot {}: {}", old, e);
+        }
+    }
+    info(timestamp)
+}
+
+/// Take a snapshot unless a recent one exists; called at startup and before
+/// a scan replaces the catalog, so there is roughly one per day of use
+pub fn take_if_due() -> Result<Option<SnapshotInfo>, String> {
+    let now = chrono::Utc::now().timestamp_millis();
+    match timestamps().last() {
+        Some(newest) if now - newest < SNAPSHOT_INTERVAL_MS => Ok(None),
+        _ => take().map(Some),
+    }
+}
+
+/// Every stored snapshot, newest first
+pub fn list() -> Vec<SnapshotInfo> {
+    timestamps()
+        .into_iter()
+        .rev()
+        .filter_map(|timestamp| info(timestamp).ok())
+        .collect()
+}
+
+/// Open a snapshot as a catalog, to search it like the live library
+pub fn open(timestamp: i64) -> Result<Catalog, String> {
+    let path = snapshot_path(timestamp);
+    if !path.exists() {
+        return Err("Snapshot not found".to_string());
+    }
+    Catalog::open_path(&path)
+}
+
+/// Copy the tags and rating/flag/label that `paths` had in a snapshot back
+/// into the live catalog; tags are added to the current ones, the rating
+/// replaces the current one. Returns the number of files that had anything
+pub fn restore_metadata(timestamp: i64, paths: &[String]) -> Result<usize, String> {
+    let path = snapshot_path(timestamp);
+    if !path.exists() {
+        return Err("Snapshot not found".to_string());
+    }
+    let mut conn = Catalog::connection()?;
+    conn.execute(
+        "ATTACH DATABASE ?1 AS snapshot",
+        params![path.to_string_lossy()],
+    )
+    .map_err(|e| e.to_string())?;
+
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let mut restored = 0;
+    for path in paths {
+        let tags = tx
+            .execute(
+                "INSERT OR IGNORE INTO photo_tags (path, tag)
+                SELECT path, tag FROM snapshot.photo_tags WHERE path = ?1",
+                params![path],
+            )
+            .map_err(|e| e.to_string())?;
+        let rating = tx
+            .execute(
+                "INSERT OR REPLACE INTO photo_ratings (path, rating, flag, label)
+                SELECT path, rating, flag, label FROM snapshot.photo_ratings WHERE path = ?1",
+                params![path],
+            )
+            .map_err(|e| e.to_string())?;
+        if tags + rating > 0 {
+            restored += 1;
+        }
+    }
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(restored)
+}
@@ -0,0 +1,145 @@
+use crate::media_info::is_video_extension;
+use crate::scanner::{PhotoFile, RAW_EXTENSIONS};
+use serde::{Deserialize, Serialize};
+
+/// Where a file most likely came from, guessed by `classify`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SourceKind {
+    /// Taken with a camera or phone camera
+    Camera,
+    /// A screenshot or screen recording
+    Screenshot,
+    /// An image saved from elsewhere (a download, meme, export or scan)
+    Download,
+    /// Not enough to go on: videos without a telling name, and cloud
+    /// placeholders whose EXIF hasn't been read
+    #[default]
+    Unknown,
+}
+
+impl SourceKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SourceKind::Camera => "camera",
+            SourceKind::Screenshot => "screenshot",
+            SourceKind::Download => "download",
+            SourceKind::Unknown => "unknown",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "camera" => SourceKind::Camera,
+            "screenshot" => SourceKind::Screenshot,
+            "download" => SourceKind::Download,
+            _ => SourceKind::Unknown,
+        }
+    }
+}
+
+/// Words screenshot tools put in file names, lowercase, in the languages
+/// macOS, Windows, Android and iOS name them in
+const SCREENSHOT_NAMES: &[&str] = &[
+    "screenshot",
+    "screen shot",
+    "screen_shot",
+    "screen recording",
+    "screenrecord",
+    "screen_recording",
+    "bildschirmfoto",
+    "capture d’écran",
+    "capture d'écran",
+    "schermafbeelding",
+    "captura de pantalla",
+    "istantanea schermo",
+    "skärmavbild",
+    "スクリーンショット",
+    "屏幕截图",
+];
+
+/// Screen sizes in pixels, narrow side first: phones, tablets and common
+/// monitors (1080×1920 and 1440×2560 monitors are listed with the phones)
+const SCREEN_SIZES: &[(u32, u32)] = &[
+    // iPhone
+    (640, 1136),
+    (750, 1334),
+    (828, 1792),
+    (1080, 1920),
+    (1125, 2436),
+    (1170, 2532),
+    (1179, 2556),
+    (1206, 2622),
+    (1242, 2208),
+    (1242, 2688),
+    (1284, 2778),
+    (1290, 2796),
+    (1320, 2868),
+    // iPad
+    (1488, 2266),
+    (1536, 2048),
+    (1620, 2160),
+    (1640, 2360),
+    (1668, 2224),
+    (1668, 2388),
+    (2048, 2732),
+    // Android
+    (720, 1280),
+    (720, 1600),
+    (1080, 2160),
+    (1080, 2280),
+    (1080, 2340),
+    (1080, 2400),
+    (1440, 2560),
+    (1440, 2960),
+    (1440, 3040),
+    (1440, 3120),
+    (1440, 3200),
+    // Monitors and laptops
+    (768, 1366),
+    (800, 1280),
+    (864, 1536),
+    (900, 1440),
+    (900, 1600),
+    (1050, 1680),
+    (1200, 1920),
+    (1504, 2256),
+    (1600, 2560),
+    (1800, 2880),
+    (1824, 2736),
+    (1864, 2880),
+    (1920, 3072),
+    (1964, 3024),
+    (2160, 3840),
+    (2234, 3456),
+    (2880, 5120),
+];
+
+/// Guess where a file came from after a scan has read its metadata
+/// Camera EXIF (a make or model) or a RAW format means a camera. Without it,
+/// a screenshot tool's file name, or a PNG the size of a device's screen,
+/// means a screenshot; any other still was saved from elsewhere
+pub fn classify(photo: &PhotoFile) -> SourceKind {
+    let name = photo.name.to_lowercase();
+    let has_camera = photo.camera_make.is_some() || photo.camera_model.is_some();
+    if has_camera || RAW_EXTENSIONS.contains(&photo.extension.as_str()) {
+        return SourceKind::Camera;
+    }
+    if SCREENSHOT_NAMES.iter().any(|n| name.contains(n)) {
+        return SourceKind::Screenshot;
+    }
+    if photo.is_cloud_placeholder || is_video_extension(&photo.extension) {
+        return SourceKind::Unknown;
+    }
+    if photo.extension == "png" && is_screen_size(photo.width, photo.height) {
+        return SourceKind::Screenshot;
+    }
+    SourceKind::Download
+}
+
+fn is_screen_size(width: Option<u32>, height: Option<u32>) -> bool {
+    let Some((width, height)) = width.zip(height) else {
+        return false;
+    };
+    SCREEN_SIZES.contains(&(width.min(height), width.max(height)))
+}
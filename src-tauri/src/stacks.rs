@@ -0,0 +1,367 @@
+use crate::catalog::Catalog;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+/// Photos stacked by hand behind a cover (edits, crops and other formats of
+/// the same shot), stored in the catalog database
+/// A photo is in at most one stack; searches show only the cover unless asked
+/// to expand stacks
+pub struct Stacks {
+    conn: Connection,
+}
+
+/// How a stack came about
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StackKind {
+    /// Stacked by hand
+    Manual,
+    /// A burst or exposure bracket found by a scan
+    Burst,
+}
+
+impl StackKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            StackKind::Manual => "manual",
+            StackKind::Burst => "burst",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "burst" => StackKind::Burst,
+            _ => StackKind::Manual,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Stack {
+    pub id: i64,
+    pub kind: StackKind,
+    pub cover_path: String,
+    /// Every member including the cover, in the order they were stacked
+    pub paths: Vec<String>,
+    pub created_at: i64,
+}
+
+/// Create the stack tables; called when the catalog is opened so searches can
+/// collapse stacks before one has been created
+pub fn create_tables(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS stacks (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            cover_path TEXT NOT NULL,
+            created_at INTEGER NOT NULL,
+            kind TEXT NOT NULL DEFAULT 'manual'
+        );
+        CREATE TABLE IF NOT EXISTS stack_members (
+            path TEXT PRIMARY KEY,
+            stack_id INTEGER NOT NULL REFERENCES stacks(id) ON DELETE CASCADE,
+            position INTEGER NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS idx_stack_members_stack ON stack_members(stack_id);
+        CREATE TABLE IF NOT EXISTS burst_exclusions (
+            path TEXT PRIMARY KEY
+        );
+        ",
+    )
+}
+
+impl Stacks {
+    /// Open the stack tables in the catalog database
+    pub fn open() -> Result<Self, String> {
+        let conn = Catalog::connection()?;
+        conn.execute_batch("PRAGMA foreign_keys = ON;")
+            .map_err(|e| e.to_string())?;
+        Ok(Self { conn })
+    }
+
+    /// Stack files behind `cover` (the first file when unset)
+    /// Files already in another stack are moved out of it; a stack left with a
+    /// single file is dissolved
+    pub fn create(&mut self, paths: &[String], cover: Option<&str>) -> Result<Stack, String> {
+        let mut seen = HashSet::new();
+        let paths: Vec<&String> = paths.iter().filter(|p| seen.insert(*p)).collect();
+        if paths.len() < 2 {
+            return Err("A stack needs at least two photos".to_string());
+        }
+        let cover = cover.unwrap_or(paths[0]);
+        if !paths.iter().any(|p| *p == cover) {
+            return Err("The cover must be one of the stacked photos".to_string());
+        }
+        let created_at = chrono::Utc::now().timestamp_millis();
+
+        let tx = self.conn.transaction().map_err(|e| e.to_string())?;
+        remove_members(&tx, &paths)?;
+        tx.execute(
+            "INSERT INTO stacks (cover_path, created_at) VALUES (?1, ?2)",
+            params![cover, created_at],
+        )
+        .map_err(|e| e.to_string())?;
+        let id = tx.last_insert_rowid();
+        for (position, path) in paths.iter().enumerate() {
+            tx.execute(
+                "INSERT INTO stack_members (path, stack_id, position) VALUES (?1, ?2, ?3)",
+                params![path, id, position as i64],
+            )
+            .map_err(|e| e.to_string())?;
+        }
+        tx.commit().map_err(|e| e.to_string())?;
+
+        Ok(Stack {
+            id,
+            kind: StackKind::Manual,
+            cover_path: cover.to_string(),
+            paths: paths.into_iter().cloned().collect(),
+            created_at,
+        })
+    }
+
+    /// Make another member the cover shown in place of the stack
+    pub fn set_cover(&self, stack_id: i64, cover: &str) -> Result<(), String> {
+        let updated = self
+            .conn
+            .execute(
+                "UPDATE stacks SET cover_path = ?2 WHERE id = ?1
+                AND EXISTS (SELECT 1 FROM stack_members WHERE stack_id = ?1 AND path = ?2)",
+                params![stack_id, cover],
+            )
+            .map_err(|e| e.to_string())?;
+        if updated == 0 {
+            return Err("Photo is not in this stack".to_string());
+        }
+        Ok(())
+    }
+
+    /// Take files out of their stacks; the files themselves are untouched
+    /// Files taken out of a burst are not stacked as one again by later scans
+    /// Returns the number of files unstacked
+    pub fn unstack(&mut self, paths: &[String]) -> Result<usize, String> {
+        let paths: Vec<&String> = paths.iter().collect();
+        let tx = self.conn.transaction().map_err(|e| e.to_string())?;
+        for path in &paths {
+            tx.execute(
+                "INSERT OR IGNORE INTO burst_exclusions (path)
+                SELECT m.path FROM stack_members m JOIN stacks s ON s.id = m.stack_id
+                WHERE m.path = ?1 AND s.kind = 'burst'",
+                params![path],
+            )
+            .map_err(|e| e.to_string())?;
+        }
+        let removed = remove_members(&tx, &paths)?;
+        tx.commit().map_err(|e| e.to_string())?;
+        Ok(removed)
+    }
+
+    /// Dissolve a whole stack; a dissolved burst stays dissolved
+    pub fn dissolve(&mut self, stack_id: i64) -> Result<(), String> {
+        let tx = self.conn.transaction().map_err(|e| e.to_string())?;
+        tx.execute(
+            "INSERT OR IGNORE INTO burst_exclusions (path)
+            SELECT m.path FROM stack_members m JOIN stacks s ON s.id = m.stack_id
+            WHERE s.id = ?1 AND s.kind = 'burst'",
+            params![stack_id],
+        )
+        .map_err(|e| e.to_string())?;
+        let deleted = tx
+            .execute("DELETE FROM stacks WHERE id = ?1", params![stack_id])
+            .map_err(|e| e.to_string())?;
+        if deleted == 0 {
+            return Err("Stack not found".to_string());
+        }
+        tx.commit().map_err(|e| e.to_string())
+    }
+
+    /// Replace the burst stacks under the scanned folders with fresh ones,
+    /// each a run of paths in shooting order with the first shot as cover
+    /// Files stacked by hand or taken out of a burst by hand are left out
+    /// Returns the number of burst stacks
+    pub fn replace_bursts(
+        &mut self,
+        directories: &[String],
+        bursts: &[Vec<String>],
+    ) -> Result<usize, String> {
+        let created_at = chrono::Utc::now().timestamp_millis();
+        let tx = self.conn.transaction().map_err(|e| e.to_string())?;
+        let stale: Vec<i64> = {
+            let mut stmt = tx
+                .prepare("SELECT id, cover_path FROM stacks WHERE kind = 'burst'")
+                .map_err(|e| e.to_string())?;
+            let rows = stmt
+                .query_map([], |row| {
+                    Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+                })
+                .map_err(|e| e.to_string())?;
+            let rows: Vec<(i64, String)> =
+                rows.collect::<Result<_, _>>().map_err(|e| e.to_string())?;
+            rows.into_iter()
+                .filter(|(_, cover)| directories.iter().any(|d| Path::new(cover).starts_with(d)))
+                .map(|(id, _)| id)
+                .collect()
+        };
+        for id in stale {
+            tx.execute("DELETE FROM stacks WHERE id = ?1", params![id])
+                .map_err(|e| e.to_string())?;
+        }
+        let taken: HashSet<String> = {
+            let mut stmt = tx
+                .prepare("SELECT path FROM stack_members UNION SELECT path FROM burst_exclusions")
+                .map_err(|e| e.to_string())?;
+            let rows = stmt
+                .query_map([], |row| row.get(0))
+                .map_err(|e| e.to_string())?;
+            rows.collect::<Result<_, _>>().map_err(|e| e.to_string())?
+        };
+
+        let mut count = 0;
+        for burst in bursts {
+            let paths: Vec<&String> = burst.iter().filter(|p| !taken.contains(*p)).collect();
+            if paths.len() < 2 {
+                continue;
+            }
+            tx.execute(
+                "INSERT INTO stacks (cover_path, created_at, kind) VALUES (?1, ?2, 'burst')",
+                params![paths[0], created_at],
+            )
+            .map_err(|e| e.to_string())?;
+            let id = tx.last_insert_rowid();
+            for (position, path) in paths.iter().enumerate() {
+                tx.execute(
+                    "INSERT INTO stack_members (path, stack_id, position) VALUES (?1, ?2, ?3)",
+                    params![path, id, position as i64],
+                )
+                .map_err(|e| e.to_string())?;
+            }
+            count += 1;
+        }
+        tx.commit().map_err(|e| e.to_string())?;
+        Ok(count)
+    }
+
+    /// Stack id of every stacked file
+    pub fn memberships(&self) -> Result<HashMap<String, i64>, String> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT path, stack_id FROM stack_members")
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .map_err(|e| e.to_string())?;
+        rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+    }
+
+    /// The stack a file belongs to, if any
+    pub fn for_path(&self, path: &str) -> Result<Option<Stack>, String> {
+        let stack_id: Option<i64> = self
+            .conn
+            .query_row(
+                "SELECT stack_id FROM stack_members WHERE path = ?1",
+                params![path],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| e.to_string())?;
+        stack_id.map(|id| self.get(id)).transpose()
+    }
+
+    /// All stacks, or those of one kind, newest first
+    pub fn list(&self, kind: Option<StackKind>) -> Result<Vec<Stack>, String> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT id FROM stacks WHERE ?1 IS NULL OR kind = ?1
+                ORDER BY created_at DESC, id DESC",
+            )
+            .map_err(|e| e.to_string())?;
+        let ids = stmt
+            .query_map(params![kind.map(StackKind::as_str)], |row| row.get(0))
+            .map_err(|e| e.to_string())?
+            .collect::<Result<Vec<i64>, _>>()
+            .map_err(|e| e.to_string())?;
+        ids.into_iter().map(|id| self.get(id)).collect()
+    }
+
+    fn get(&self, stack_id: i64) -> Result<Stack, String> {
+        let (cover_path, created_at, kind) = self
+            .conn
+            .query_row(
+                "SELECT cover_path, created_at, kind FROM stacks WHERE id = ?1",
+                params![stack_id],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get::<_, String>(2)?)),
+            )
+            .map_err(|_| "Stack not found".to_string())?;
+        let mut stmt = self
+            .conn
+            .prepare("SELECT path FROM stack_members WHERE stack_id = ?1 ORDER BY position")
+            .map_err(|e| e.to_string())?;
+        let paths = stmt
+            .query_map(params![stack_id], |row| row.get(0))
+            .map_err(|e| e.to_string())?
+            .collect::<Result<Vec<String>, _>>()
+            .map_err(|e| e.to_string())?;
+        Ok(Stack {
+            id: stack_id,
+            kind: StackKind::from_name(&kind),
+            cover_path,
+            paths,
+            created_at,
+        })
+    }
+}
+
+/// Follow a stacked file the app moved or renamed
+pub fn relocate(conn: &Connection, from: &str, to: &str) -> rusqlite::Result<()> {
+    conn.execute(
+        "UPDATE stack_members SET path = ?2 WHERE path = ?1",
+        params![from, to],
+    )?;
+    conn.execute(
+        "UPDATE stacks SET cover_path = ?2 WHERE cover_path = ?1",
+        params![from, to],
+    )?;
+    conn.execute(
+        "UPDATE OR IGNORE burst_exclusions SET path = ?2 WHERE path = ?1",
+        params![from, to],
+    )?;
+    Ok(())
+}
+
+/// Take files out of their stacks, handing the cover to the next member when
+/// the cover leaves and dissolving stacks left with fewer than two files
+fn remove_members(conn: &Connection, paths: &[&String]) -> Result<usize, String> {
+    let mut removed = 0;
+    for path in paths {
+        let stack_id: Option<i64> = conn
+            .query_row(
+                "DELETE FROM stack_members WHERE path = ?1 RETURNING stack_id",
+                params![path],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| e.to_string())?;
+        let Some(stack_id) = stack_id else {
+            continue;
+        };
+        removed += 1;
+
+        conn.execute(
+            "DELETE FROM stacks WHERE id = ?1
+            AND (SELECT COUNT(*) FROM stack_members WHERE stack_id = ?1) < 2",
+            params![stack_id],
+        )
+        .map_err(|e| e.to_string())?;
+        conn.execute(
+            "UPDATE stacks SET cover_path =
+                (SELECT path FROM stack_members WHERE stack_id = ?1 ORDER BY position LIMIT 1)
+            WHERE id = ?1 AND cover_path = ?2",
+            params![stack_id, path],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    Ok(removed)
+}
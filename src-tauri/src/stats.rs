@@ -0,0 +1,262 @@
+use crate::archive::ARCHIVED_CONDITION;
+use crate::catalog::Catalog;
+use crate::library::camera_name_of;
+use crate::scanner::RAW_EXTENSIONS;
+use chrono::{Datelike, TimeZone, Utc};
+use rusqlite::Connection;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::Hash;
+use std::path::Path;
+
+/// Overview numbers computed from the catalog, without touching the files
+/// Archived photos are left out, as they are from the library views
+pub struct Stats {
+    conn: Connection,
+}
+
+/// What one folder holds, not counting its subfolders
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FolderStats {
+    pub path: String,
+    pub photo_count: usize,
+    pub total_size: u64,
+    pub raw_count: usize,
+    pub jpeg_count: usize,
+    pub duplicate_count: usize,
+    /// Oldest and newest photo (capture date, falling back to file date), ms
+    /// since epoch; none when no photo has a date
+    pub oldest_photo: Option<i64>,
+    pub newest_photo: Option<i64>,
+}
+
+/// Photos sharing one value (an extension, a camera, a year) and their total size
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StatCount<K> {
+    /// None for photos without one, e.g. no camera in the EXIF
+    pub key: Option<K>,
+    pub count: usize,
+    pub total_size: u64,
+}
+
+/// Photos whose size falls in `[min_size, max_size)`
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SizeBucket {
+    pub min_size: u64,
+    /// None for the last, open-ended bucket
+    pub max_size: Option<u64>,
+    pub count: usize,
+    pub total_size: u64,
+}
+
+/// Numbers for the library overview
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LibraryStats {
+    pub photo_count: usize,
+    pub total_size: u64,
+    /// Most photos first
+    pub by_extension: Vec<StatCount<String>>,
+    /// "Make Model", most photos first
+    pub by_camera: Vec<StatCount<String>>,
+    /// Year of the capture date, falling back to the file date; oldest first
+    pub by_year: Vec<StatCount<i32>>,
+    pub size_histogram: Vec<SizeBucket>,
+    pub duplicate_count: usize,
+    /// Disk space trashing every duplicate would free
+    pub reclaimable_bytes: u64,
+}
+
+/// Lower bounds of the size histogram's buckets
+const SIZE_BUCKETS: &[u64] = &[
+    0,
+    1 << 20,
+    5 << 20,
+    10 << 20,
+    25 << 20,
+    50 << 20,
+    100 << 20,
+    1 << 30,
+];
+
+fn add<K: Hash + Eq>(counts: &mut HashMap<Option<K>, (usize, u64)>, key: Option<K>, size: u64) {
+    let entry = counts.entry(key).or_default();
+    entry.0 += 1;
+    entry.1 += size;
+}
+
+fn stat_counts<K>(counts: HashMap<Option<K>, (usize, u64)>) -> Vec<StatCount<K>> {
+    counts
+        .into_iter()
+        .map(|(key, (count, total_size))| StatCount {
+            key,
+            count,
+            total_size,
+        })
+        .collect()
+}
+
+impl Stats {
+    /// Read the catalog database
+    pub fn open() -> Result<Self, String> {
+        let conn = Catalog::connection()?;
+        Ok(Self { conn })
+    }
+
+    /// Stats of every folder holding cataloged photos, sorted by path;
+    /// `under` limits them to one folder and its subfolders
+    pub fn folders(&self, under: Option<&Path>) -> Result<Vec<FolderStats>, String> {
+        let mut stmt = self
+            .conn
+            .prepare(&format!(
+                "SELECT path, extension, size, is_duplicate, COALESCE(capture_date, modified_at)
+                FROM photos WHERE NOT {}",
+                ARCHIVED_CONDITION
+            ))
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, i64>(2)? as u64,
+                    row.get::<_, bool>(3)?,
+                    row.get::<_, i64>(4)?,
+                ))
+            })
+            .map_err(|e| e.to_string())?;
+
+        let mut folders: BTreeMap<String, FolderStats> = BTreeMap::new();
+        for row in rows {
+            let (path, extension, size, is_duplicate, timestamp) =
+                row.map_err(|e| e.to_string())?;
+            let Some(folder) = Path::new(&path).parent() else {
+                continue;
+            };
+            if under.is_some_and(|under| !folder.starts_with(under)) {
+                continue;
+            }
+            let folder = folder.to_string_lossy().to_string();
+            let stats = folders
+                .entry(folder.clone())
+                .or_insert_with(|| FolderStats {
+                    path: folder,
+                    ..Default::default()
+                });
+            stats.photo_count += 1;
+            stats.total_size += size;
+            let extension = extension.to_lowercase();
+            if RAW_EXTENSIONS.contains(&extension.as_str()) {
+                stats.raw_count += 1;
+            } else if extension == "jpg" || extension == "jpeg" {
+                stats.jpeg_count += 1;
+            }
+            if is_duplicate {
+                stats.duplicate_count += 1;
+            }
+            if timestamp > 0 {
+                stats.oldest_photo =
+                    Some(stats.oldest_photo.map_or(timestamp, |t| t.min(timestamp)));
+                stats.newest_photo =
+                    Some(stats.newest_photo.map_or(timestamp, |t| t.max(timestamp)));
+            }
+        }
+        Ok(folders.into_values().collect())
+    }
+
+    /// Counts by extension, camera and year, a size histogram and the space
+    /// duplicates take up, over the whole library
+    pub fn library(&self) -> Result<LibraryStats, String> {
+        let mut stmt = self
+            .conn
+            .prepare(&format!(
+                "SELECT extension, size, disk_size, is_duplicate, camera_make, camera_model,
+                    COALESCE(capture_date, modified_at)
+                FROM photos WHERE NOT {}",
+                ARCHIVED_CONDITION
+            ))
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, i64>(1)? as u64,
+                    row.get::<_, i64>(2)? as u64,
+                    row.get::<_, bool>(3)?,
+                    row.get::<_, Option<String>>(4)?,
+                    row.get::<_, Option<String>>(5)?,
+                    row.get::<_, i64>(6)?,
+                ))
+            })
+            .map_err(|e| e.to_string())?;
+
+        let mut stats = LibraryStats {
+            photo_count: 0,
+            total_size: 0,
+            by_extension: Vec::new(),
+            by_camera: Vec::new(),
+            by_year: Vec::new(),
+            size_histogram: SIZE_BUCKETS
+                .iter()
+                .zip(
+                    SIZE_BUCKETS
+                        .iter()
+                        .skip(1)
+                        .map(|&max| Some(max))
+                        .chain([None]),
+                )
+                .map(|(&min_size, max_size)| SizeBucket {
+                    min_size,
+                    max_size,
+                    count: 0,
+                    total_size: 0,
+                })
+                .collect(),
+            duplicate_count: 0,
+            reclaimable_bytes: 0,
+        };
+        let mut extensions = HashMap::new();
+        let mut cameras = HashMap::new();
+        let mut years = HashMap::new();
+        for row in rows {
+            let (extension, size, disk_size, is_duplicate, make, model, timestamp) =
+                row.map_err(|e| e.to_string())?;
+            stats.photo_count += 1;
+            stats.total_size += size;
+            add(&mut extensions, Some(extension.to_lowercase()), size);
+            add(
+                &mut cameras,
+                camera_name_of(make.as_deref(), model.as_deref()),
+                size,
+            );
+            let year = Utc
+                .timestamp_millis_opt(timestamp)
+                .single()
+                .filter(|_| timestamp > 0)
+                .map(|date| date.year());
+            add(&mut years, year, size);
+            let bucket = SIZE_BUCKETS.partition_point(|&min| min <= size) - 1;
+            stats.size_histogram[bucket].count += 1;
+            stats.size_histogram[bucket].total_size += size;
+            if is_duplicate {
+                stats.duplicate_count += 1;
+                stats.reclaimable_bytes += disk_size;
+            }
+        }
+
+        stats.by_extension = stat_counts(extensions);
+        stats
+            .by_extension
+            .sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.key.cmp(&b.key)));
+        stats.by_camera = stat_counts(cameras);
+        stats
+            .by_camera
+            .sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.key.cmp(&b.key)));
+        stats.by_year = stat_counts(years);
+        stats.by_year.sort_by_key(|c| c.key);
+        Ok(stats)
+    }
+}
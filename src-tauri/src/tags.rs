@@ -0,0 +1,128 @@
+use crate::catalog::Catalog;
+use crate::xmp;
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use std::path::Path;
+
+/// Keywords attached to photos, stored in the catalog database
+pub struct Tags {
+    conn: Connection,
+}
+
+/// A tag with the number of photos carrying it
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TagCount {
+    pub name: String,
+    pub count: usize,
+}
+
+/// Create the tag table; called when the catalog is opened
+pub fn create_tables(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS photo_tags (
+            path TEXT NOT NULL,
+            tag TEXT NOT NULL COLLATE NOCASE,
+            PRIMARY KEY (path, tag)
+        );
+        CREATE INDEX IF NOT EXISTS idx_photo_tags_tag ON photo_tags(tag);",
+    )
+}
+
+impl Tags {
+    /// Open the tag table in the catalog database
+    pub fn open() -> Result<Self, String> {
+        let conn = Catalog::connection()?;
+        Ok(Self { conn })
+    }
+
+    /// Attach tags to files; tags differing only in case are treated as the same
+    pub fn add(&self, paths: &[String], tags: &[String]) -> Result<(), String> {
+        for path in paths {
+            for tag in normalize(tags) {
+                self.conn
+                    .execute(
+                        "INSERT OR IGNORE INTO photo_tags (path, tag) VALUES (?1, ?2)",
+                        params![path, tag],
+                    )
+                    .map_err(|e| e.to_string())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Detach tags from files
+    pub fn remove(&self, paths: &[String], tags: &[String]) -> Result<(), String> {
+        for path in paths {
+            for tag in normalize(tags) {
+                self.conn
+                    .execute(
+                        "DELETE FROM photo_tags WHERE path = ?1 AND tag = ?2",
+                        params![path, tag],
+                    )
+                    .map_err(|e| e.to_string())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Tags of a single file, alphabetically
+    pub fn for_path(&self, path: &str) -> Result<Vec<String>, String> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT tag FROM photo_tags WHERE path = ?1 ORDER BY tag")
+            .map_err(|e| e.to_string())?;
+        let tags = stmt
+            .query_map(params![path], |row| row.get(0))
+            .map_err(|e| e.to_string())?
+            .collect::<Result<Vec<String>, _>>()
+            .map_err(|e| e.to_string())?;
+        Ok(tags)
+    }
+
+    /// Every tag in use with its photo count, most used first
+    pub fn list(&self) -> Result<Vec<TagCount>, String> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT tag, COUNT(*) FROM photo_tags
+                GROUP BY tag ORDER BY COUNT(*) DESC, tag",
+            )
+            .map_err(|e| e.to_string())?;
+        let tags = stmt
+            .query_map([], |row| {
+                Ok(TagCount {
+                    name: row.get(0)?,
+                    count: row.get::<_, i64>(1)? as usize,
+                })
+            })
+            .map_err(|e| e.to_string())?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+        Ok(tags)
+    }
+
+    /// Mirror the current tags of each file into its XMP sidecar as keywords
+    pub fn write_sidecars(&self, paths: &[String]) -> Result<(), String> {
+        for path in paths {
+            xmp::write_keywords(Path::new(path), &self.for_path(path)?)?;
+        }
+        Ok(())
+    }
+}
+
+/// Follow a tagged file the app moved or renamed; tags still at the new path
+/// belonged to a file that used to be there
+pub fn relocate(conn: &Connection, from: &str, to: &str) -> rusqlite::Result<()> {
+    conn.execute("DELETE FROM photo_tags WHERE path = ?1", params![to])?;
+    conn.execute(
+        "UPDATE photo_tags SET path = ?2 WHERE path = ?1",
+        params![from, to],
+    )?;
+    Ok(())
+}
+
+/// Trim tags and drop empty ones
+fn normalize(tags: &[String]) -> impl Iterator<Item = &str> {
+    tags.iter().map(|t| t.trim()).filter(|t| !t.is_empty())
+}
@@ -0,0 +1,418 @@
+use crate::config::{default_organize_template, AppConfig};
+use crate::file_ops::{copy_preserving_metadata, find_unique_name_with, set_modified_time};
+use crate::format_check::{card_media_files, has_copy, library_files_by_size};
+use crate::hash_cache::HashCache;
+use crate::ingest::{IngestFailure, IngestProgress};
+use crate::jobs::JobMonitor;
+use crate::metadata::{capture_date_or_file_date, expand_date_template, read_capture_date};
+use crate::scanner::compute_full_hash;
+use crate::shutdown;
+use crate::stacks::Stacks;
+use crate::xmp;
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tauri::Window;
+
+/// Stem suffixes Google Photos gives the edited copy of a photo, by export language
+const EDITED_SUFFIXES: &[&str] = &[
+    "-edited",
+    "-bearbeitet",
+    "-modifié",
+    "-editado",
+    "-modificato",
+    "-bewerkt",
+];
+
+/// How `import_takeout` places the imported files
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct TakeoutOptions {
+    /// Library folder to import into
+    pub destination: String,
+    /// Date folders under the destination (YYYY, MM, DD); the organize
+    /// template from the config when unset
+    pub folder_template: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TakeoutResult {
+    pub imported_count: usize,
+    /// Files whose content is already in the library, or earlier in the
+    /// Takeout (Google repeats a photo in every album it belongs to)
+    pub already_imported_count: usize,
+    /// Imported files given a capture time, caption or location from their JSON
+    pub with_metadata_count: usize,
+    /// Edited copies stacked on top of their original
+    pub edited_count: usize,
+    pub failed: Vec<IngestFailure>,
+}
+
+/// The parts of a Takeout `.json` sidecar the app uses
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct TakeoutJson {
+    /// Original file name
+    title: String,
+    description: String,
+    photo_taken_time: Option<TakeoutTimestamp>,
+    geo_data: Option<GeoData>,
+    geo_data_exif: Option<GeoData>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+struct TakeoutTimestamp {
+    /// Seconds since the epoch, as a string
+    timestamp: String,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(default)]
+struct GeoData {
+    latitude: f64,
+    longitude: f64,
+}
+
+/// Metadata Google kept outside the file
+#[derive(Debug, Clone, Default)]
+struct PhotoMetadata {
+    taken_at: Option<DateTime<Local>>,
+    description: Option<String>,
+    coordinates: Option<(f64, f64)>,
+}
+
+impl PhotoMetadata {
+    fn from_json(json: TakeoutJson) -> Self {
+        let taken_at = json
+            .photo_taken_time
+            .and_then(|t| t.timestamp.parse::<i64>().ok())
+            .and_then(|secs| Local.timestamp_opt(secs, 0).single());
+        // 0,0 means no location
+        let coordinates = [json.geo_data, json.geo_data_exif]
+            .into_iter()
+            .flatten()
+            .find(|g| g.latitude != 0.0 || g.longitude != 0.0)
+            .map(|g| (g.latitude, g.longitude));
+        Self {
+            taken_at,
+            description: Some(json.description.trim().to_string()).filter(|d| !d.is_empty()),
+            coordinates,
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.taken_at.is_none() && self.description.is_none() && self.coordinates.is_none()
+    }
+}
+
+/// The `.json` sidecars of one Takeout folder, by file name and by the
+/// original name recorded inside them (sidecar names get truncated)
+#[derive(Default)]
+struct FolderSidecars {
+    by_name: HashMap<String, PathBuf>,
+    by_title: HashMap<String, PathBuf>,
+}
+
+impl FolderSidecars {
+    fn read(folder: &Path) -> Self {
+        let mut sidecars = Self::default();
+        let Ok(entries) = fs::read_dir(folder) else {
+            return sidecars;
+        };
+        for path in entries.filter_map(|e| e.ok()).map(|e| e.path()) {
+            let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+                continue;
+            };
+            if !name.to_lowercase().ends_with(".json") {
+                continue;
+            }
+            if let Some(json) = read_json(&path) {
+                if !json.title.is_empty() {
+                    sidecars
+                        .by_title
+                        .entry(json.title)
+                        .or_insert_with(|| path.clone());
+                }
+            }
+            sidecars.by_name.insert(name.to_string(), path);
+        }
+        sidecars
+    }
+
+    /// Sidecar of the file named `name` in this folder
+    fn find(&self, name: &str) -> Option<&PathBuf> {
+        let mut candidates = vec![
+            format!("{}.json", name),
+            format!("{}.supplemental-metadata.json", name),
+        ];
+        // `IMG_1234(1).jpg` goes with `IMG_1234.jpg(1).json`
+        let path = Path::new(name);
+        if let (Some(stem), Some(ext)) = (
+            path.file_stem().and_then(|s| s.to_str()),
+            path.extension().and_then(|e| e.to_str()),
+        ) {
+            if let Some((base, counter)) = stem
+                .strip_suffix(')')
+                .and_then(|s| s.rsplit_once('('))
+                .filter(|(_, n)| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()))
+            {
+                candidates.push(format!("{}.{}({}).json", base, ext, counter));
+                candidates.push(format!(
+                    "{}.{}.supplemental-metadata({}).json",
+                    base, ext, counter
+                ));
+            }
+        }
+        candidates
+            .iter()
+            .find_map(|c| self.by_name.get(c))
+            .or_else(|| self.by_title.get(name))
+    }
+}
+
+fn read_json(path: &Path) -> Option<TakeoutJson> {
+    serde_json::from_str(&fs::read_to_string(path).ok()?).ok()
+}
+
+/// Name of the original when `name` is Google's edited copy of it
+fn original_of_edited(name: &str) -> Option<String> {
+    let path = Path::new(name);
+    let stem = path.file_stem()?.to_str()?;
+    let lower = stem.to_lowercase();
+    let suffix = EDITED_SUFFIXES.iter().find(|s| lower.ends_with(*s))?;
+    let base = &stem[..stem.len() - suffix.len()];
+    Some(match path.extension().and_then(|e| e.to_str()) {
+        Some(ext) => format!("{}.{}", base, ext),
+        None => base.to_string(),
+    })
+}
+
+/// Import a Google Photos Takeout folder into dated folders of the library
+/// Content already in the library is skipped by hash. The capture time,
+/// caption and location Google keeps in `.json` sidecars are written to an XMP
+/// sidecar next to each copy, and the copy's modification time is set to the
+/// capture time. Edited copies (`IMG_1234-edited.jpg`) take their original's
+/// metadata and are stacked on top of it
+pub fn import_takeout(
+    window: &Window,
+    takeout: &Path,
+    options: &TakeoutOptions,
+) -> Result<TakeoutResult, String> {
+    if options.destination.trim().is_empty() {
+        return Err("Choose a library folder to import into".to_string());
+    }
+    if !takeout.is_dir() {
+        return Err(format!("{} is not a folder", takeout.display()));
+    }
+    let destination = Path::new(&options.destination);
+    let progress = |stage: &str, current: usize, total: usize, path: Option<&Path>| {
+        let _ = window.emit(
+            "takeout-progress",
+            IngestProgress {
+                stage: stage.to_string(),
+                current,
+                total,
+                current_file: path
+                    .and_then(|p| p.file_name())
+                    .map(|n| n.to_string_lossy().to_string())
+                    .unwrap_or_default(),
+            },
+        );
+    };
+    let monitor = JobMonitor::start(window, "import");
+    // Taken once the job's turn in the queue has come, not while it waits
+    let _busy = shutdown::begin("import");
+    let cancelled = || "Takeout import cancelled".to_string();
+    let cache = HashCache::open()?;
+
+    progress("scanning", 0, 0, None);
+    let files = card_media_files(takeout).media;
+    let sizes: HashSet<u64> = files.iter().map(|(_, size)| *size).collect();
+    let candidates = library_files_by_size(takeout, &sizes)?;
+
+    let hashed_count = AtomicUsize::new(0);
+    let hashed: Vec<(&PathBuf, u64, Option<String>)> = files
+        .par_iter()
+        .map(|(path, size)| {
+            let path_str = path.to_string_lossy();
+            if monitor.is_cancelled() {
+                return (path, *size, None);
+            }
+            let _work = monitor.working_on(&path_str);
+            let hash = compute_full_hash(&path_str, cache.algorithm());
+            let current = hashed_count.fetch_add(1, Ordering::Relaxed) + 1;
+            progress("hashing", current, files.len(), Some(path));
+            (path, *size, hash.filter(|_| !monitor.is_skipped(&path_str)))
+        })
+        .collect();
+    if monitor.is_cancelled() {
+        return Err(cancelled());
+    }
+
+    let mut failed = Vec::new();
+    let mut already_imported_count = 0;
+    let mut seen: HashSet<String> = HashSet::new();
+    let mut to_copy: Vec<&PathBuf> = Vec::new();
+    for (path, size, hash) in hashed {
+        let Some(hash) = hash else {
+            failed.push(IngestFailure {
+                path: path.to_string_lossy().to_string(),
+                error: "Could not read the file".to_string(),
+            });
+            continue;
+        };
+        if has_copy(&candidates, size, &hash, &cache) || !seen.insert(hash) {
+            already_imported_count += 1;
+        } else {
+            to_copy.push(path);
+        }
+    }
+
+    let folder_template = options
+        .folder_template
+        .clone()
+        .filter(|t| !t.trim().is_empty())
+        .or_else(|| Some(AppConfig::load().organize_template).filter(|t| !t.trim().is_empty()))
+        .unwrap_or_else(default_organize_template);
+
+    let mut sidecars: HashMap<PathBuf, FolderSidecars> = HashMap::new();
+    let mut planned: HashSet<PathBuf> = HashSet::new();
+    // Where each Takeout file went, to stack edited copies on their originals
+    let mut copied: HashMap<&Path, PathBuf> = HashMap::new();
+    // Copies of edited versions, with the Takeout path of their original
+    let mut edited: Vec<(PathBuf, PathBuf)> = Vec::new();
+    let mut with_metadata_count = 0;
+    for (i, source) in to_copy.iter().enumerate() {
+        if monitor.is_cancelled() {
+            break;
+        }
+        let source_str = source.to_string_lossy().to_string();
+        let _work = monitor.working_on(&source_str);
+        progress("copying", i + 1, to_copy.len(), Some(source));
+
+        let folder = source.parent().unwrap_or(takeout);
+        let name = source
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or_default();
+        let original = original_of_edited(name);
+        let folder_sidecars = sidecars
+            .entry(folder.to_path_buf())
+            .or_insert_with(|| FolderSidecars::read(folder));
+        let metadata = folder_sidecars
+            .find(name)
+            .or_else(|| original.as_deref().and_then(|o| folder_sidecars.find(o)))
+            .and_then(|json| read_json(json))
+            .map(PhotoMetadata::from_json)
+            .unwrap_or_default();
+
+        match copy_with_metadata(
+            source,
+            destination,
+            &folder_template,
+            &metadata,
+            &mut planned,
+        ) {
+            _ if monitor.is_skipped(&source_str) => failed.push(IngestFailure {
+                path: source_str,
+                error: "Skipped after stalling".to_string(),
+            }),
+            Ok(target) => {
+                if !metadata.is_empty() {
+                    with_metadata_count += 1;
+                }
+                if let Some(original) = original {
+                    edited.push((target.clone(), folder.join(original)));
+                }
+                copied.insert(source.as_path(), target);
+            }
+            Err(error) => failed.push(IngestFailure {
+                path: source_str,
+                error,
+            }),
+        }
+    }
+    let imported_count = copied.len();
+
+    let mut edited_count = 0;
+    if !edited.is_empty() {
+        let mut stacks = Stacks::open()?;
+        for (edited_copy, original) in &edited {
+            let Some(original_copy) = copied.get(original.as_path()) else {
+                continue;
+            };
+            let paths = [
+                edited_copy.to_string_lossy().to_string(),
+                original_copy.to_string_lossy().to_string(),
+            ];
+            match stacks.create(&paths, Some(&paths[0])) {
+                Ok(_) => edited_count += 1,
+                Err(error) => failed.push(IngestFailure {
+                    path: paths[0].clone(),
+                    error,
+                }),
+            }
+        }
+    }
+    progress("complete", imported_count, imported_count, None);
+
+    Ok(TakeoutResult {
+        imported_count,
+        already_imported_count,
+        with_metadata_count,
+        edited_count,
+        failed,
+    })
+}
+
+/// Copy one Takeout file to `destination/<date folders>/<name>`, then record
+/// its Google metadata in an XMP sidecar and as its modification time
+fn copy_with_metadata(
+    source: &Path,
+    destination: &Path,
+    folder_template: &str,
+    metadata: &PhotoMetadata,
+    planned: &mut HashSet<PathBuf>,
+) -> Result<PathBuf, String> {
+    let exif_date = read_capture_date(source);
+    // Takeout files carry the download time, so the JSON beats the file date
+    let date: NaiveDateTime = exif_date
+        .or_else(|| metadata.taken_at.map(|t| t.naive_local()))
+        .or_else(|| capture_date_or_file_date(source))
+        .ok_or("No capture or file date")?;
+    let file_name = source.file_name().ok_or("Invalid file name")?;
+    let target = destination
+        .join(expand_date_template(folder_template, &date))
+        .join(file_name);
+    let target = if target.exists() || planned.contains(&target) {
+        find_unique_name_with(&target, |p| p.exists() || planned.contains(p))?
+    } else {
+        target
+    };
+    planned.insert(target.clone());
+
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    copy_preserving_metadata(source, &target).map_err(|e| e.to_string())?;
+
+    if let Some(taken_at) = metadata.taken_at {
+        if exif_date.is_none() {
+            xmp::write_capture_date(&target, &taken_at.fixed_offset())?;
+        }
+        set_modified_time(&target, taken_at.into()).map_err(|e| e.to_string())?;
+    }
+    if let Some(description) = &metadata.description {
+        xmp::write_description(&target, Some(description))?;
+    }
+    if metadata.coordinates.is_some() {
+        xmp::write_gps(&target, metadata.coordinates)?;
+    }
+    Ok(target)
+}
@@ -0,0 +1,144 @@
+use crate::file_ops::{copy_file_times, find_unique_name};
+use serde::Deserialize;
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// Output video codec
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VideoCodec {
+    /// Most compatible choice for sharing (H.265/HEVC is often rejected)
+    H264,
+    H265,
+}
+
+/// Options for a transcode batch
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TranscodeOptions {
+    #[serde(default = "default_codec")]
+    pub codec: VideoCodec,
+    /// Maximum output height in pixels; larger videos are scaled down
+    pub max_height: Option<u32>,
+    /// Target video bitrate in kbit/s (quality-based encoding when unset)
+    pub bitrate_kbps: Option<u32>,
+    /// Output folder; defaults to next to each source
+    pub destination: Option<String>,
+}
+
+fn default_codec() -> VideoCodec {
+    VideoCodec::H264
+}
+
+/// Locate a command-line tool, including Homebrew locations that GUI apps
+/// on macOS don't get on their PATH
+pub fn find_tool(name: &str) -> Option<PathBuf> {
+    let from_path = std::env::var_os("PATH")
+        .map(|paths| std::env::split_paths(&paths).collect::<Vec<_>>())
+        .unwrap_or_default();
+
+    from_path
+        .into_iter()
+        .chain(["/opt/homebrew/bin", "/usr/local/bin", "/usr/bin"].map(PathBuf::from))
+        .map(|dir| dir.join(name))
+        .find(|candidate| candidate.is_file())
+}
+
+/// Duration of a media file in seconds, via ffprobe
+fn probe_duration(ffprobe: &Path, source: &Path) -> Option<f64> {
+    let output = Command::new(ffprobe)
+        .args(["-v", "error", "-show_entries", "format=duration"])
+        .args(["-of", "default=noprint_wrappers=1:nokey=1"])
+        .arg(source)
+        .output()
+        .ok()?;
+    String::from_utf8_lossy(&output.stdout).trim().parse().ok()
+}
+
+/// Transcode a single video with ffmpeg, calling `on_progress` with a 0.0–1.0 fraction
+/// Returns the path of the written `.mp4`
+pub fn transcode_video(
+    source: &Path,
+    options: &TranscodeOptions,
+    mut on_progress: impl FnMut(f64),
+) -> Result<PathBuf, String> {
+    let ffmpeg =
+        find_tool("ffmpeg").ok_or("ffmpeg not found (install with `brew install ffmpeg`)")?;
+    let duration = find_tool("ffprobe").and_then(|ffprobe| probe_duration(&ffprobe, source));
+
+    let stem = source
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .ok_or("Invalid file name")?;
+    let dest_dir = match &options.destination {
+        Some(dir) => PathBuf::from(dir),
+        None => source.parent().ok_or("Invalid path")?.to_path_buf(),
+    };
+    fs::create_dir_all(&dest_dir).map_err(|e| e.to_string())?;
+
+    let target = dest_dir.join(format!("{}.mp4", stem));
+    let target = if target.exists() {
+        find_unique_name(&target)?
+    } else {
+        target
+    };
+
+    let mut command = Command::new(ffmpeg);
+    command
+        .args(["-hide_banner", "-nostdin", "-nostats", "-y", "-i"])
+        .arg(source)
+        // Keep capture date, location and other container metadata
+        .args(["-map_metadata", "0"]);
+
+    match options.codec {
+        VideoCodec::H264 => command.args(["-c:v", "libx264", "-pix_fmt", "yuv420p"]),
+        VideoCodec::H265 => command.args(["-c:v", "libx265", "-tag:v", "hvc1"]),
+    };
+
+    match options.bitrate_kbps {
+        Some(kbps) => command.args(["-b:v", &format!("{}k", kbps)]),
+        None => command.args(["-crf", "23"]),
+    };
+
+    if let Some(height) = options.max_height {
+        // Never upscale; -2 keeps the width even as encoders require
+        command.args(["-vf", &format!("scale=-2:'min({},ih)'", height)]);
+    }
+
+    command
+        .args(["-c:a", "aac", "-movflags", "+faststart"])
+        .args(["-progress", "pipe:1"])
+        .arg(&target)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null());
+
+    let mut child = command.spawn().map_err(|e| e.to_string())?;
+
+    // ffmpeg writes key=value progress lines; out_time_us is the encoded position
+    if let Some(stdout) = child.stdout.take() {
+        for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+            let micros = line
+                .strip_prefix("out_time_us=")
+                .or_else(|| line.strip_prefix("out_time_ms="))
+                .and_then(|v| v.trim().parse::<f64>().ok());
+            if let (Some(micros), Some(total)) = (micros, duration) {
+                if total > 0.0 {
+                    on_progress((micros / 1_000_000.0 / total).clamp(0.0, 1.0));
+                }
+            }
+        }
+    }
+
+    let status = child.wait().map_err(|e| e.to_string())?;
+    if !status.success() {
+        let _ = fs::remove_file(&target);
+        return Err(format!("ffmpeg failed to transcode {}", source.display()));
+    }
+
+    let _ = copy_file_times(source, &target);
+    on_progress(1.0);
+
+    Ok(target)
+}
@@ -0,0 +1,195 @@
+use crate::file_ops::copy_file_times;
+use crate::metadata::{
+    header_dimensions, oriented_dimensions, read_exif_summary, update_exif, writable_exif_type,
+};
+use little_exif::exif_tag::ExifTag;
+use little_exif::filetype::FileExtension;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+/// A turn or mirror of a photo as it is displayed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transform {
+    /// Quarter turns clockwise, 1 to 3
+    Rotate(u8),
+    FlipHorizontal,
+    FlipVertical,
+}
+
+/// Mirror direction for `flip_image`: horizontal swaps left and right,
+/// vertical swaps top and bottom
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FlipDirection {
+    Horizontal,
+    Vertical,
+}
+
+impl From<FlipDirection> for Transform {
+    fn from(direction: FlipDirection) -> Self {
+        match direction {
+            FlipDirection::Horizontal => Transform::FlipHorizontal,
+            FlipDirection::Vertical => Transform::FlipVertical,
+        }
+    }
+}
+
+/// How a photo was turned
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TransformMethod {
+    /// The JPEG's pixels were turned without re-encoding them
+    Pixels,
+    /// Only the EXIF orientation was changed
+    Orientation,
+}
+
+/// A photo after a transform
+pub struct Transformed {
+    pub method: TransformMethod,
+    /// EXIF orientation now in the file
+    pub orientation: u16,
+    /// Pixel size as displayed
+    pub dimensions: Option<(u32, u32)>,
+}
+
+/// An EXIF orientation as a horizontal mirror followed by clockwise quarter turns
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Orientation {
+    mirrored: bool,
+    turns: u8,
+}
+
+impl Orientation {
+    fn from_exif(value: u16) -> Self {
+        let (mirrored, turns) = match value {
+            2 => (true, 0),
+            3 => (false, 2),
+            4 => (true, 2),
+            5 => (true, 3),
+            6 => (false, 1),
+            7 => (true, 1),
+            8 => (false, 3),
+            _ => (false, 0),
+        };
+        Self { mirrored, turns }
+    }
+
+    fn to_exif(self) -> u16 {
+        match (self.mirrored, self.turns % 4) {
+            (false, 0) => 1,
+            (true, 0) => 2,
+            (false, 2) => 3,
+            (true, 2) => 4,
+            (true, 3) => 5,
+            (false, 1) => 6,
+            (true, 1) => 7,
+            _ => 8,
+        }
+    }
+
+    /// This orientation with `transform` applied after it
+    /// Mirroring a turned image is mirroring it first and turning it the other
+    /// way; a vertical mirror is a horizontal one and a half turn
+    fn then(self, transform: Transform) -> Self {
+        match transform {
+            Transform::Rotate(turns) => Self {
+                mirrored: self.mirrored,
+                turns: (self.turns + turns) % 4,
+            },
+            Transform::FlipHorizontal => Self {
+                mirrored: !self.mirrored,
+                turns: (4 - self.turns) % 4,
+            },
+            Transform::FlipVertical => Self {
+                mirrored: !self.mirrored,
+                turns: (6 - self.turns) % 4,
+            },
+        }
+    }
+}
+
+/// Turn or mirror a photo as it is displayed, keeping its dates
+/// JPEGs have their pixels turned losslessly with `jpegtran` when it is
+/// installed and the image is made of whole blocks; otherwise, and for PNG,
+/// WebP and TIFF, only the EXIF orientation is changed. RAW, HEIC and video
+/// files are never rewritten
+pub fn apply(path: &Path, transform: Transform) -> Result<Transformed, String> {
+    if !path.is_file() {
+        return Err("The file is gone".to_string());
+    }
+    let file_type =
+        writable_exif_type(path).ok_or("RAW, HEIC and video files can't be turned in place")?;
+    let current = read_exif_summary(path).and_then(|e| e.orientation);
+    let target = Orientation::from_exif(current.unwrap_or(1))
+        .then(transform)
+        .to_exif();
+
+    let (method, orientation) = if matches!(file_type, FileExtension::JPEG)
+        && turn_jpeg(path, target, current.is_some())?
+    {
+        (TransformMethod::Pixels, 1)
+    } else {
+        update_exif(path, file_type, |metadata| {
+            metadata.set_tag(ExifTag::Orientation(vec![target]))
+        })?;
+        (TransformMethod::Orientation, target)
+    };
+    Ok(Transformed {
+        method,
+        orientation,
+        dimensions: header_dimensions(path)
+            .map(|(w, h)| oriented_dimensions(w, h, Some(orientation))),
+    })
+}
+
+/// Turn a JPEG's pixels by what EXIF `orientation` describes, then mark it
+/// upright; false when `jpegtran` isn't installed or can't turn it perfectly
+/// (a size that isn't whole blocks would lose an edge)
+fn turn_jpeg(path: &Path, orientation: u16, has_orientation: bool) -> Result<bool, String> {
+    let operation: &[&str] = match orientation {
+        2 => &["-flip", "horizontal"],
+        3 => &["-rotate", "180"],
+        4 => &["-flip", "vertical"],
+        5 => &["-transpose"],
+        6 => &["-rotate", "90"],
+        7 => &["-transverse"],
+        8 => &["-rotate", "270"],
+        _ => return Ok(false),
+    };
+    let file_name = path.file_name().ok_or("Invalid file name")?;
+    let temp = path.with_file_name(format!(".{}.turn", file_name.to_string_lossy()));
+    let turned = Command::new("jpegtran")
+        .args(["-copy", "all", "-perfect"])
+        .args(operation)
+        .arg("-outfile")
+        .arg(&temp)
+        .arg(path)
+        .output()
+        .is_ok_and(|output| output.status.success());
+    if !turned {
+        let _ = fs::remove_file(&temp);
+        return Ok(false);
+    }
+
+    // The copied EXIF still has the old orientation, which would turn it twice
+    let upright = if has_orientation {
+        update_exif(&temp, FileExtension::JPEG, |metadata| {
+            metadata.set_tag(ExifTag::Orientation(vec![1]))
+        })
+    } else {
+        Ok(())
+    };
+    let replaced = upright.and_then(|_| {
+        copy_file_times(path, &temp)
+            .and_then(|_| fs::rename(&temp, path))
+            .map_err(|e| e.to_string())
+    });
+    if let Err(e) = replaced {
+        let _ = fs::remove_file(&temp);
+        return Err(e);
+    }
+    Ok(true)
+}
@@ -0,0 +1,106 @@
+use deunicode::deunicode;
+
+/// Hepburn romaji for the hiragana block starting at U+3041 (katakana are
+/// folded onto hiragana first); `っ` is empty because it doubles the next consonant
+const HIRAGANA_ROMAJI: [&str; 86] = [
+    "a", "a", "i", "i", "u", "u", "e", "e", "o", "o", // ぁ-お
+    "ka", "ga", "ki", "gi", "ku", "gu", "ke", "ge", "ko", "go", // か-ご
+    "sa", "za", "shi", "ji", "su", "zu", "se", "ze", "so", "zo", // さ-ぞ
+    "ta", "da", "chi", "ji", "", "tsu", "zu", "te", "de", "to", "do", // た-ど
+    "na", "ni", "nu", "ne", "no", // な-の
+    "ha", "ba", "pa", "hi", "bi", "pi", "fu", "bu", "pu", // は-ぷ
+    "he", "be", "pe", "ho", "bo", "po", // へ-ぽ
+    "ma", "mi", "mu", "me", "mo", // ま-も
+    "ya", "ya", "yu", "yu", "yo", "yo", // ゃ-よ
+    "ra", "ri", "ru", "re", "ro", // ら-ろ
+    "wa", "wa", "i", "e", "o", "n", "vu", "ka", "ke", // ゎ-ゖ
+];
+
+const SMALL_TSU: char = 'っ';
+
+/// Fold a katakana character onto its hiragana counterpart
+fn to_hiragana(c: char) -> char {
+    match c {
+        'ァ'..='ヶ' => char::from_u32(c as u32 - 0x60).unwrap_or(c),
+        _ => c,
+    }
+}
+
+fn kana_romaji(c: char) -> Option<&'static str> {
+    match c {
+        'ぁ'..='ゖ' => Some(HIRAGANA_ROMAJI[(c as u32 - 'ぁ' as u32) as usize]),
+        _ => None,
+    }
+}
+
+/// Small kana that merge with the syllable before them (きょ → kyo, ファ → fa)
+fn is_small_glide(c: char) -> bool {
+    matches!(c, 'ゃ' | 'ゅ' | 'ょ' | 'ぁ' | 'ぃ' | 'ぅ' | 'ぇ' | 'ぉ')
+}
+
+/// Replace hiragana and katakana with Hepburn romaji, leaving everything else
+/// Handles contracted syllables (しゃ → sha), the small っ (きって → kitte)
+/// and drops the long vowel mark, which users don't type (ラーメン → ramen)
+fn romanize_kana(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut double_next = false;
+    let mut previous_kana = false;
+
+    for c in text.chars() {
+        let c = to_hiragana(c);
+        if c == 'ー' {
+            continue;
+        }
+        if c == SMALL_TSU {
+            double_next = true;
+            continue;
+        }
+        let Some(romaji) = kana_romaji(c) else {
+            double_next = false;
+            previous_kana = false;
+            out.push(c);
+            continue;
+        };
+
+        if previous_kana && is_small_glide(c) {
+            // Drop the previous syllable's vowel: き+ょ → ky+o, し+ゃ → sh+a, ふ+ぁ → f+a
+            let vowel = out.pop();
+            let glide = if matches!(c, 'ゃ' | 'ゅ' | 'ょ') && vowel == Some('i') {
+                if out.ends_with("sh") || out.ends_with("ch") || out.ends_with('j') {
+                    &romaji[1..]
+                } else {
+                    romaji
+                }
+            } else {
+                &romaji[romaji.len() - 1..]
+            };
+            out.push_str(glide);
+            continue;
+        }
+
+        if double_next {
+            let first = romaji.chars().next().unwrap_or('a');
+            if romaji.starts_with("ch") {
+                out.push('t');
+            } else if !"aiueon".contains(first) {
+                out.push(first);
+            }
+            double_next = false;
+        }
+        out.push_str(romaji);
+        previous_kana = true;
+    }
+    out
+}
+
+/// Script-independent form of a file name for search: kana become romaji, Han
+/// characters pinyin, Cyrillic/Greek/Hangul and accented letters their Latin
+/// transliteration; the result is lowercase with only letters and digits, so
+/// "北京 2019.jpg", "beijing2019" and "Bei Jing" all meet in the middle
+pub fn search_key(text: &str) -> String {
+    deunicode(&romanize_kana(text))
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
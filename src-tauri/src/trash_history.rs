@@ -0,0 +1,87 @@
+use crate::catalog::Catalog;
+use crate::hash_cache::HashAlgorithm;
+use rusqlite::{params, Connection, OptionalExtension};
+use std::collections::HashSet;
+
+/// Content hashes of files the app moved to the Trash, so copies showing up
+/// again in a later scan can be flagged as previously deleted
+/// Only recorded when `rememberTrashed` is enabled in the config
+pub struct TrashHistory {
+    conn: Connection,
+}
+
+/// Create the trash history table; called when the catalog is opened
+pub fn create_tables(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS trashed_files (
+            hash TEXT NOT NULL,
+            hash_algorithm TEXT NOT NULL,
+            size INTEGER NOT NULL,
+            path TEXT NOT NULL,
+            trashed_at INTEGER NOT NULL,
+            PRIMARY KEY (hash, hash_algorithm)
+        );",
+    )
+}
+
+impl TrashHistory {
+    /// Open the trash history table in the catalog database
+    pub fn open() -> Result<Self, String> {
+        let conn = Catalog::connection()?;
+        Ok(Self { conn })
+    }
+
+    /// Remember a trashed file by content hash; trashing the same content
+    /// again refreshes the path and date
+    pub fn record(
+        &self,
+        hash: &str,
+        algorithm: HashAlgorithm,
+        size: u64,
+        path: &str,
+    ) -> Result<(), String> {
+        self.conn
+            .execute(
+                "INSERT OR REPLACE INTO trashed_files
+                    (hash, hash_algorithm, size, path, trashed_at)
+                VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    hash,
+                    algorithm.as_str(),
+                    size as i64,
+                    path,
+                    chrono::Utc::now().timestamp_millis()
+                ],
+            )
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+
+    /// Sizes of trashed files, used to avoid hashing files that cannot match
+    pub fn sizes(&self, algorithm: HashAlgorithm) -> Result<HashSet<u64>, String> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT DISTINCT size FROM trashed_files WHERE hash_algorithm = ?1")
+            .map_err(|e| e.to_string())?;
+        let sizes = stmt
+            .query_map(params![algorithm.as_str()], |row| {
+                Ok(row.get::<_, i64>(0)? as u64)
+            })
+            .map_err(|e| e.to_string())?
+            .collect::<Result<HashSet<_>, _>>()
+            .map_err(|e| e.to_string())?;
+        Ok(sizes)
+    }
+
+    /// When content with this hash was last trashed (milliseconds since epoch)
+    pub fn trashed_at(&self, hash: &str, algorithm: HashAlgorithm) -> Result<Option<i64>, String> {
+        self.conn
+            .query_row(
+                "SELECT trashed_at FROM trashed_files WHERE hash = ?1 AND hash_algorithm = ?2",
+                params![hash, algorithm.as_str()],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| e.to_string())
+    }
+}
@@ -0,0 +1,184 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+/// How often mounted volumes are compared for `volume-mounted`/`volume-unmounted`
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// A mounted removable or external volume
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Volume {
+    pub path: String,
+    pub name: String,
+    pub total_bytes: u64,
+    pub free_bytes: u64,
+    /// Has a DCIM folder, the layout every camera writes
+    pub is_camera_card: bool,
+}
+
+/// Folders removable volumes are mounted in
+#[cfg(target_os = "macos")]
+pub fn mount_points() -> Vec<PathBuf> {
+    // The boot volume is listed too, as a symlink to / that `read_dirs` skips
+    read_dirs(&[PathBuf::from("/Volumes")])
+}
+
+#[cfg(windows)]
+pub fn mount_points() -> Vec<PathBuf> {
+    // A: and B: are floppies, C: the system drive
+    ('D'..='Z')
+        .map(|letter| PathBuf::from(format!("{}:\\", letter)))
+        .filter(|root| root.exists())
+        .collect()
+}
+
+#[cfg(not(any(target_os = "macos", windows)))]
+pub fn mount_points() -> Vec<PathBuf> {
+    // udisks mounts under /media/<user> or /run/media/<user>
+    let user = std::env::var("USER").unwrap_or_default();
+    read_dirs(&[
+        Path::new("/media").join(&user),
+        Path::new("/run/media").join(&user),
+    ])
+}
+
+#[cfg(not(windows))]
+fn read_dirs(parents: &[PathBuf]) -> Vec<PathBuf> {
+    parents
+        .iter()
+        .filter_map(|parent| std::fs::read_dir(parent).ok())
+        .flatten()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().map(|t| t.is_dir()).unwrap_or(false))
+        .map(|e| e.path())
+        .collect()
+}
+
+/// Every mounted volume with its capacity
+pub fn list() -> Vec<Volume> {
+    mount_points()
+        .into_iter()
+        .map(|path| {
+            let (total_bytes, free_bytes) = space(&path).unwrap_or((0, 0));
+            Volume {
+                name: volume_name(&path),
+                is_camera_card: path.join("DCIM").is_dir(),
+                path: path.to_string_lossy().to_string(),
+                total_bytes,
+                free_bytes,
+            }
+        })
+        .collect()
+}
+
+/// Poll the mounted volumes in the background and emit `volume-mounted` and
+/// `volume-unmounted` (with the volume as last seen) as cards come and go
+pub fn watch(app: AppHandle) {
+    thread::spawn(move || {
+        let mut known: HashMap<String, Volume> =
+            list().into_iter().map(|v| (v.path.clone(), v)).collect();
+        loop {
+            thread::sleep(POLL_INTERVAL);
+            let current: HashMap<String, Volume> =
+                list().into_iter().map(|v| (v.path.clone(), v)).collect();
+            for (path, volume) in &current {
+                if !known.contains_key(path) {
+                    let _ = app.emit_all("volume-mounted", volume.clone());
+                }
+            }
+            for (path, volume) in known {
+                if !current.contains_key(&path) {
+                    let _ = app.emit_all("volume-unmounted", volume);
+                }
+            }
+            known = current;
+        }
+    });
+}
+
+#[cfg(not(windows))]
+fn volume_name(path: &Path) -> String {
+    path.file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.to_string_lossy().to_string())
+}
+
+/// Total and available bytes on the volume
+#[cfg(unix)]
+#[allow(clippy::unnecessary_cast)] // the field types differ between platforms
+fn space(path: &Path) -> Option<(u64, u64)> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_path = CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(c_path.as_ptr(), &mut stat) } != 0 {
+        return None;
+    }
+    let block = stat.f_frsize as u64;
+    Some((stat.f_blocks as u64 * block, stat.f_bavail as u64 * block))
+}
+
+#[cfg(windows)]
+#[link(name = "kernel32")]
+extern "system" {
+    fn GetDiskFreeSpaceExW(
+        directory: *const u16,
+        free_to_caller: *mut u64,
+        total: *mut u64,
+        total_free: *mut u64,
+    ) -> i32;
+    fn GetVolumeInformationW(
+        root: *const u16,
+        name: *mut u16,
+        name_len: u32,
+        serial: *mut u32,
+        max_component_len: *mut u32,
+        flags: *mut u32,
+        file_system: *mut u16,
+        file_system_len: u32,
+    ) -> i32;
+}
+
+#[cfg(windows)]
+fn wide(path: &Path) -> Vec<u16> {
+    use std::os::windows::ffi::OsStrExt;
+    path.as_os_str().encode_wide().chain(Some(0)).collect()
+}
+
+#[cfg(windows)]
+fn space(path: &Path) -> Option<(u64, u64)> {
+    let root = wide(path);
+    let (mut free, mut total, mut total_free) = (0u64, 0u64, 0u64);
+    let ok = unsafe { GetDiskFreeSpaceExW(root.as_ptr(), &mut free, &mut total, &mut total_free) };
+    (ok != 0).then_some((total, free))
+}
+
+/// The volume label, or the drive letter when it has none
+#[cfg(windows)]
+fn volume_name(path: &Path) -> String {
+    let root = wide(path);
+    let mut name = [0u16; 261];
+    let ok = unsafe {
+        GetVolumeInformationW(
+            root.as_ptr(),
+            name.as_mut_ptr(),
+            name.len() as u32,
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+            0,
+        )
+    };
+    let len = name.iter().position(|&c| c == 0).unwrap_or(name.len());
+    if ok != 0 && len > 0 {
+        String::from_utf16_lossy(&name[..len])
+    } else {
+        path.to_string_lossy().trim_end_matches('\\').to_string()
+    }
+}
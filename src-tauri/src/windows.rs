@@ -0,0 +1,77 @@
+use serde::Serialize;
+use std::sync::Mutex;
+use tauri::{AppHandle, GlobalWindowEvent, Manager, WindowBuilder, WindowEvent, WindowUrl};
+
+/// Label of the window tauri.conf.json opens, which shows the library
+pub const MAIN_WINDOW: &str = "main";
+
+/// Label of the window that shows photos side by side
+pub const COMPARE_WINDOW: &str = "compare";
+
+/// Photos the compare window shows, kept in Tauri state so the window can ask
+/// for them with `compared_photos` once its page has loaded
+#[derive(Default)]
+pub struct ComparedPhotos {
+    paths: Mutex<Vec<String>>,
+}
+
+impl ComparedPhotos {
+    pub fn paths(&self) -> Vec<String> {
+        self.paths.lock().map(|p| p.clone()).unwrap_or_default()
+    }
+
+    fn set(&self, paths: Vec<String>) {
+        if let Ok(mut current) = self.paths.lock() {
+            *current = paths;
+        }
+    }
+}
+
+/// `compare-photos` payload, sent to a compare window that is already open
+#[derive(Debug, Clone, Serialize)]
+pub struct ComparePhotos {
+    pub paths: Vec<String>,
+}
+
+/// Show photos side by side in the compare window: a new window is opened, or
+/// the open one is sent the photos and brought forward
+pub fn open_compare(app: &AppHandle, paths: Vec<String>) -> Result<(), String> {
+    app.state::<ComparedPhotos>().set(paths.clone());
+    if let Some(window) = app.get_window(COMPARE_WINDOW) {
+        window
+            .emit("compare-photos", ComparePhotos { paths })
+            .map_err(|e| e.to_string())?;
+        return window.set_focus().map_err(|e| e.to_string());
+    }
+    // The same page as the main window; it tells the two apart by label
+    WindowBuilder::new(app, COMPARE_WINDOW, WindowUrl::App("index.html".into()))
+        .title("Compare")
+        .inner_size(1200.0, 800.0)
+        .min_inner_size(800.0, 600.0)
+        .build()
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Whether any of the app's windows has focus
+pub fn app_focused(app: &AppHandle) -> bool {
+    app.windows()
+        .values()
+        .any(|window| window.is_focused().unwrap_or(false))
+}
+
+/// Closing the main window closes the windows opened from it, so the app quits
+/// instead of living on in a compare window
+pub fn on_window_event(event: &GlobalWindowEvent) {
+    let WindowEvent::Destroyed = event.event() else {
+        return;
+    };
+    if event.window().label() != MAIN_WINDOW {
+        return;
+    }
+    for (label, window) in event.window().windows() {
+        if label != MAIN_WINDOW {
+            let _ = window.close();
+        }
+    }
+}
@@ -0,0 +1,507 @@
+use crate::scanner::RAW_EXTENSIONS;
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone};
+use quick_xml::escape::resolve_predefined_entity;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::name::{Namespace, ResolveResult};
+use quick_xml::NsReader;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const DC_NS: &str = "http://purl.org/dc/elements/1.1/";
+const XMP_NS: &str = "http://ns.adobe.com/xap/1.0/";
+const RDF_NS: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#";
+const CRS_NS: &str = "http://ns.adobe.com/camera-raw-settings/1.0/";
+const EXIF_NS: &str = "http://ns.adobe.com/exif/1.0/";
+
+/// Camera Raw properties that are present even on unedited photos
+const CRS_BOOKKEEPING: &[&str] = &["RawFileName", "Version", "ProcessVersion", "HasSettings"];
+
+/// Metadata parsed from an XMP sidecar
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SidecarMetadata {
+    /// -1 = rejected, 0–5 stars
+    pub rating: Option<i32>,
+    /// Color label ("Red", "Yellow", ...)
+    pub label: Option<String>,
+    /// `dc:subject` keywords
+    pub keywords: Vec<String>,
+    /// When the metadata or develop settings were last changed
+    /// (`xmp:MetadataDate`, else `xmp:ModifyDate`), as written in the sidecar
+    pub modified_at: Option<String>,
+    /// Sidecar holds Camera Raw / Lightroom develop settings
+    pub has_edits: bool,
+    /// `exif:GPSLatitude`/`exif:GPSLongitude` in decimal degrees
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    /// `exif:DateTimeOriginal` as written in the sidecar, a corrected capture
+    /// time that takes precedence over the file's own
+    pub capture_date: Option<String>,
+}
+
+/// Parse an `.xmp` sidecar file
+pub fn parse_sidecar(sidecar: &Path) -> Option<SidecarMetadata> {
+    let xml = fs::read_to_string(sidecar).ok()?;
+    parse_xmp(&xml).ok()
+}
+
+/// Parse an XMP packet, resolving namespaces so any prefix works
+/// Properties may be written as attributes of `rdf:Description` (as Lightroom
+/// does) or as child elements
+pub fn parse_xmp(xml: &str) -> Result<SidecarMetadata, quick_xml::Error> {
+    let mut reader = NsReader::from_str(xml);
+    let mut meta = SidecarMetadata::default();
+    let mut metadata_date = None;
+    let mut modify_date = None;
+
+    // Element-form property being read, and its text so far
+    let mut property: Option<String> = None;
+    let mut text = String::new();
+    let mut in_subject = false;
+
+    loop {
+        let (ns, event) = reader.read_resolved_event()?;
+        let ns = namespace(&ns).map(str::to_string);
+        let ns = ns.as_deref();
+        match event {
+            Event::Start(ref e) | Event::Empty(ref e) => {
+                let local = String::from_utf8_lossy(e.local_name().as_ref()).to_string();
+                read_attributes(&reader, e, &mut meta, &mut metadata_date, &mut modify_date)?;
+
+                if ns == Some(CRS_NS) && !CRS_BOOKKEEPING.contains(&local.as_str()) {
+                    meta.has_edits = true;
+                }
+                if matches!(event, Event::Start(_)) {
+                    match (ns, local.as_str()) {
+                        (Some(DC_NS), "subject") => in_subject = true,
+                        (Some(RDF_NS), "li") if in_subject => {
+                            property = Some("keyword".to_string());
+                            text.clear();
+                        }
+                        (Some(XMP_NS), "Rating" | "Label" | "MetadataDate" | "ModifyDate")
+                        | (Some(EXIF_NS), "GPSLatitude" | "GPSLongitude" | "DateTimeOriginal") => {
+                            property = Some(local);
+                            text.clear();
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            Event::Text(e) if property.is_some() => text.push_str(&e.decode()?),
+            Event::CData(e) if property.is_some() => text.push_str(&e.decode()?),
+            Event::GeneralRef(e) if property.is_some() => {
+                if let Some(c) = e.resolve_char_ref()? {
+                    text.push(c);
+                } else if let Some(entity) = resolve_predefined_entity(&e.decode()?) {
+                    text.push_str(entity);
+                }
+            }
+            Event::End(ref e) => {
+                let local = e.local_name();
+                match (ns, local.as_ref()) {
+                    (Some(DC_NS), b"subject") => in_subject = false,
+                    (Some(RDF_NS), b"li") | (Some(XMP_NS), _) | (Some(EXIF_NS), _) => {
+                        let value = text.trim().to_string();
+                        match property.take().as_deref() {
+                            Some("keyword") if !value.is_empty() => meta.keywords.push(value),
+                            Some("Rating") => meta.rating = parse_rating(&value),
+                            Some("Label") => meta.label = Some(value).filter(|v| !v.is_empty()),
+                            Some("MetadataDate") => metadata_date = Some(value),
+                            Some("ModifyDate") => modify_date = Some(value),
+                            Some("GPSLatitude") => meta.latitude = parse_gps(&value),
+                            Some("GPSLongitude") => meta.longitude = parse_gps(&value),
+                            Some("DateTimeOriginal") => {
+                                meta.capture_date = Some(value).filter(|v| !v.is_empty())
+                            }
+                            _ => {}
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    meta.modified_at = metadata_date.or(modify_date).filter(|d| !d.is_empty());
+    Ok(meta)
+}
+
+/// Pick up properties written as attributes of a description element
+fn read_attributes(
+    reader: &NsReader<&[u8]>,
+    element: &BytesStart,
+    meta: &mut SidecarMetadata,
+    metadata_date: &mut Option<String>,
+    modify_date: &mut Option<String>,
+) -> Result<(), quick_xml::Error> {
+    for attribute in element.attributes().with_checks(false) {
+        let attribute = attribute.map_err(quick_xml::Error::from)?;
+        let (ns, local) = reader.resolve_attribute(attribute.key);
+        let value = attribute.unescape_value()?.trim().to_string();
+        match (namespace(&ns), local.as_ref()) {
+            (Some(XMP_NS), b"Rating") => meta.rating = parse_rating(&value),
+            (Some(XMP_NS), b"Label") => meta.label = Some(value).filter(|v| !v.is_empty()),
+            (Some(XMP_NS), b"MetadataDate") => *metadata_date = Some(value),
+            (Some(XMP_NS), b"ModifyDate") => *modify_date = Some(value),
+            (Some(EXIF_NS), b"GPSLatitude") => meta.latitude = parse_gps(&value),
+            (Some(EXIF_NS), b"GPSLongitude") => meta.longitude = parse_gps(&value),
+            (Some(EXIF_NS), b"DateTimeOriginal") => {
+                meta.capture_date = Some(value).filter(|v| !v.is_empty())
+            }
+            (Some(CRS_NS), b"HasSettings") => meta.has_edits |= value.eq_ignore_ascii_case("true"),
+            (Some(CRS_NS), name) => {
+                let name = String::from_utf8_lossy(name);
+                if !CRS_BOOKKEEPING.contains(&name.as_ref()) {
+                    meta.has_edits = true;
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn namespace<'a>(resolved: &ResolveResult<'a>) -> Option<&'a str> {
+    match resolved {
+        ResolveResult::Bound(Namespace(ns)) => std::str::from_utf8(ns).ok(),
+        _ => None,
+    }
+}
+
+/// XMP GPS coordinates are `DDD,MM.mmmmK` or `DDD,MM,SSK`, K being N, S, E or W
+fn parse_gps(value: &str) -> Option<f64> {
+    let reference = value.chars().last()?.to_ascii_uppercase();
+    if !matches!(reference, 'N' | 'S' | 'E' | 'W') {
+        return None;
+    }
+    let numbers = &value[..value.len() - 1];
+    let degrees = numbers
+        .split(',')
+        .zip([1.0, 60.0, 3600.0])
+        .map(|(part, scale)| part.trim().parse::<f64>().map(|n| n / scale))
+        .sum::<Result<f64, _>>()
+        .ok()?;
+    Some(if matches!(reference, 'S' | 'W') {
+        -degrees
+    } else {
+        degrees
+    })
+}
+
+/// An XMP date (ISO 8601, seconds optional) as ms since epoch; dates without
+/// a time zone are local time, as EXIF dates are read
+pub fn parse_date(value: &str) -> Option<i64> {
+    let value = value.trim();
+    let zoned = DateTime::parse_from_rfc3339(value)
+        .or_else(|_| DateTime::parse_from_str(value, "%Y-%m-%dT%H:%M%:z"))
+        .ok();
+    if let Some(date) = zoned {
+        return Some(date.timestamp_millis());
+    }
+    let local = ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%dT%H:%M"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())?;
+    Local
+        .from_local_datetime(&local)
+        .earliest()
+        .map(|date| date.timestamp_millis())
+}
+
+/// Ratings are integers, but some tools write them as decimals ("3.0")
+fn parse_rating(value: &str) -> Option<i32> {
+    value
+        .parse::<f64>()
+        .ok()
+        .map(|r| r.round().clamp(-1.0, 5.0) as i32)
+}
+
+/// Minimal packet used when a photo has no sidecar yet
+const EMPTY_PACKET: &str = "<?xpacket begin=\"\u{feff}\" id=\"W5M0MpCehiHzreSzNTczkc9d\"?>
+<x:xmpmeta xmlns:x=\"adobe:ns:meta/\">
+ <rdf:RDF xmlns:rdf=\"http://www.w3.org/1999/02/22-rdf-syntax-ns#\">
+  <rdf:Description rdf:about=\"\">
+  </rdf:Description>
+ </rdf:RDF>
+</x:xmpmeta>
+<?xpacket end=\"w\"?>
+";
+
+/// Sidecar path for a photo (`IMG_0001.ARW` → `IMG_0001.xmp`, as Lightroom names them)
+/// An existing sidecar with an upper-case extension is reused
+pub fn sidecar_path(photo: &Path) -> PathBuf {
+    let upper = photo.with_extension("XMP");
+    if upper.exists() {
+        upper
+    } else {
+        photo.with_extension("xmp")
+    }
+}
+
+/// Whether a RAW of the same name sits next to a non-RAW photo, so that the
+/// two would share one sidecar; the pair's sidecar then belongs to the RAW
+fn shares_raw_sidecar(photo: &Path) -> bool {
+    let extension = photo
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default()
+        .to_lowercase();
+    !RAW_EXTENSIONS.contains(&extension.as_str())
+        && RAW_EXTENSIONS.iter().any(|raw| {
+            photo.with_extension(raw).is_file()
+                || photo.with_extension(raw.to_uppercase()).is_file()
+        })
+}
+
+/// Write keywords as `dc:subject` into the photo's XMP sidecar, creating it if needed
+/// Other properties in an existing sidecar are left as they are
+pub fn write_keywords(photo: &Path, keywords: &[String]) -> Result<(), String> {
+    if keywords.is_empty() {
+        return update_sidecar(photo, "dc", DC_NS, "dc:subject", None);
+    }
+    let items: String = keywords
+        .iter()
+        .map(|k| format!("      <rdf:li>{}</rdf:li>\n", escape_xml(k)))
+        .collect();
+    let element = format!(
+        "   <dc:subject>\n    <rdf:Bag>\n{}    </rdf:Bag>\n   </dc:subject>\n",
+        items
+    );
+    update_sidecar(photo, "dc", DC_NS, "dc:subject", Some(&element))
+}
+
+/// Write `xmp:Rating` (-1 = rejected, 0–5 stars); `None` removes it
+pub fn write_rating(photo: &Path, rating: Option<i32>) -> Result<(), String> {
+    write_simple(
+        photo,
+        "xmp:Rating",
+        rating.map(|r| r.to_string()).as_deref(),
+    )
+}
+
+/// Write the `xmp:Label` color label (e.g. "Red"); `None` removes it
+pub fn write_label(photo: &Path, label: Option<&str>) -> Result<(), String> {
+    write_simple(photo, "xmp:Label", label)
+}
+
+/// Write `dc:description` as the default-language caption; `None` removes it
+pub fn write_description(photo: &Path, description: Option<&str>) -> Result<(), String> {
+    let element = description.map(|d| {
+        format!(
+            "   <dc:description>\n    <rdf:Alt>\n     <rdf:li xml:lang=\"x-default\">{}</rdf:li>\n    </rdf:Alt>\n   </dc:description>\n",
+            escape_xml(d)
+        )
+    });
+    update_sidecar(photo, "dc", DC_NS, "dc:description", element.as_deref())
+}
+
+/// Write `exif:DateTimeOriginal`, the capture time, as ISO 8601
+pub fn write_capture_date(
+    photo: &Path,
+    date: &DateTime<chrono::FixedOffset>,
+) -> Result<(), String> {
+    let element = format!(
+        "   <exif:DateTimeOriginal>{}</exif:DateTimeOriginal>\n",
+        date.format("%Y-%m-%dT%H:%M:%S%:z")
+    );
+    update_sidecar(
+        photo,
+        "exif",
+        EXIF_NS,
+        "exif:DateTimeOriginal",
+        Some(&element),
+    )
+}
+
+/// Write `exif:GPSLatitude` and `exif:GPSLongitude` (decimal degrees, north and
+/// east positive) in the XMP `DDD,MM.mmmmmmK` form; `None` removes them
+pub fn write_gps(photo: &Path, coordinates: Option<(f64, f64)>) -> Result<(), String> {
+    let (latitude, longitude) = match coordinates {
+        Some((lat, lon)) => (
+            Some(gps_coordinate(lat, 'N', 'S')),
+            Some(gps_coordinate(lon, 'E', 'W')),
+        ),
+        None => (None, None),
+    };
+    for (name, value) in [
+        ("exif:GPSLatitude", latitude),
+        ("exif:GPSLongitude", longitude),
+    ] {
+        let element = value.map(|v| format!("   <{}>{}</{}>\n", name, v, name));
+        update_sidecar(photo, "exif", EXIF_NS, name, element.as_deref())?;
+    }
+    Ok(())
+}
+
+fn gps_coordinate(degrees: f64, positive: char, negative: char) -> String {
+    let reference = if degrees < 0.0 { negative } else { positive };
+    let degrees = degrees.abs();
+    let whole = degrees.trunc();
+    format!(
+        "{},{:.6}{}",
+        whole as u32,
+        (degrees - whole) * 60.0,
+        reference
+    )
+}
+
+fn write_simple(photo: &Path, name: &str, value: Option<&str>) -> Result<(), String> {
+    let element = value.map(|v| format!("   <{}>{}</{}>\n", name, escape_xml(v), name));
+    update_sidecar(photo, "xmp", XMP_NS, name, element.as_deref())
+}
+
+/// Replace (or with `None`, remove) one property element in the sidecar
+fn update_sidecar(
+    photo: &Path,
+    prefix: &str,
+    namespace: &str,
+    name: &str,
+    element: Option<&str>,
+) -> Result<(), String> {
+    // A JPEG's keywords or rating must not overwrite the RAW's
+    if shares_raw_sidecar(photo) {
+        return Ok(());
+    }
+    let path = sidecar_path(photo);
+    let mut xml = if path.exists() {
+        fs::read_to_string(&path).map_err(|e| e.to_string())?
+    } else if element.is_none() {
+        return Ok(());
+    } else {
+        EMPTY_PACKET.to_string()
+    };
+
+    remove_element(&mut xml, name);
+    remove_attribute(&mut xml, name);
+
+    if let Some(element) = element {
+        let description = open_description(&mut xml)
+            .ok_or_else(|| format!("{}: no rdf:Description", path.display()))?;
+        declare_namespace(&mut xml, description, prefix, namespace);
+        let close = xml[description..]
+            .find("</rdf:Description>")
+            .map(|i| description + i)
+            .ok_or_else(|| format!("{}: unterminated rdf:Description", path.display()))?;
+        // Insert on its own line, before the closing tag's indentation
+        let line_start = xml[..close].trim_end_matches([' ', '\t']).len();
+        if xml[..line_start].ends_with('\n') {
+            xml.insert_str(line_start, element);
+        } else {
+            xml.insert_str(close, &format!("\n{}", element));
+        }
+    }
+
+    // Written beside the sidecar and renamed over it, so a crash can't leave it truncated
+    let name = path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .ok_or("Invalid sidecar name")?;
+    let temp = path.with_file_name(format!(".{}.tmp", name));
+    fs::write(&temp, xml)
+        .and_then(|_| fs::rename(&temp, &path))
+        .map_err(|e| {
+            let _ = fs::remove_file(&temp);
+            e.to_string()
+        })
+}
+
+/// Remove `<name>…</name>` or `<name/>` if present
+fn remove_element(xml: &mut String, name: &str) {
+    let open = format!("<{}", name);
+    let Some(start) = find_tag(xml, &open) else {
+        return;
+    };
+    let close = format!("</{}>", name);
+    let end = match xml[start..].find('>') {
+        Some(i) if xml[start..start + i].ends_with('/') => start + i + 1,
+        _ => match xml[start..].find(&close) {
+            Some(i) => start + i + close.len(),
+            None => return,
+        },
+    };
+    // Take the line's indentation and newline with it
+    let line_start = xml[..start].trim_end_matches([' ', '\t']).len();
+    let end = if xml[end..].starts_with('\n') {
+        end + 1
+    } else {
+        end
+    };
+    xml.replace_range(line_start..end, "");
+}
+
+/// Remove a `name="value"` attribute if present
+fn remove_attribute(xml: &mut String, name: &str) {
+    let Some(start) = find_attribute(xml, name) else {
+        return;
+    };
+    let value_start = start + name.len() + 1;
+    let Some(quote) = xml[value_start..].chars().next() else {
+        return;
+    };
+    let Some(len) = xml[value_start + 1..].find(quote) else {
+        return;
+    };
+    let end = value_start + 1 + len + 1;
+    // Take the whitespace before the attribute with it
+    let start = xml[..start].trim_end().len();
+    xml.replace_range(start..end, "");
+}
+
+/// Find `name=` preceded by whitespace, i.e. used as an attribute
+fn find_attribute(xml: &str, name: &str) -> Option<usize> {
+    let pattern = format!("{}=", name);
+    let mut from = 0;
+    while let Some(i) = xml[from..].find(&pattern) {
+        let start = from + i;
+        if xml[..start].ends_with(char::is_whitespace) {
+            return Some(start);
+        }
+        from = start + pattern.len();
+    }
+    None
+}
+
+/// Find `<name` followed by whitespace, `>` or `/`, so `dc:subject` doesn't match `dc:subjects`
+fn find_tag(xml: &str, open: &str) -> Option<usize> {
+    let mut from = 0;
+    while let Some(i) = xml[from..].find(open) {
+        let start = from + i;
+        match xml[start + open.len()..].chars().next() {
+            Some(c) if c.is_whitespace() || c == '>' || c == '/' => return Some(start),
+            _ => from = start + open.len(),
+        }
+    }
+    None
+}
+
+/// Byte offset of the first `<rdf:Description` tag, expanding a self-closing one
+fn open_description(xml: &mut String) -> Option<usize> {
+    let start = find_tag(xml, "<rdf:Description")?;
+    let end = start + xml[start..].find('>')?;
+    if xml[..end].ends_with('/') {
+        xml.replace_range(end - 1..end + 1, ">\n  </rdf:Description>");
+    }
+    Some(start)
+}
+
+/// Add `xmlns:prefix` to the description tag unless it's declared already
+fn declare_namespace(xml: &mut String, description: usize, prefix: &str, namespace: &str) {
+    let declaration = format!("xmlns:{}=", prefix);
+    if xml.contains(&declaration) {
+        return;
+    }
+    let insert_at = description + "<rdf:Description".len();
+    xml.insert_str(
+        insert_at,
+        &format!("\n    xmlns:{}=\"{}\"", prefix, namespace),
+    );
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -1,4 +1,4 @@
-import { useEffect } from 'react';
+import { useEffect, useRef } from 'react';
 import { Sidebar } from './components/Sidebar';
 import { Toolbar } from './components/Toolbar';
 import { PhotoGrid } from './components/PhotoGrid';
@@ -7,11 +7,49 @@ import { StatusBar } from './components/StatusBar';
 import { usePhotoStore } from './store/photoStore';
 
 function App() {
-  const { viewMode, loadConfig } = usePhotoStore();
+  const { viewMode, loadConfig, loadCatalog, loadSession, saveSession } = usePhotoStore();
+  const mainRef = useRef<HTMLElement>(null);
 
   useEffect(() => {
-    loadConfig();
-  }, [loadConfig]);
+    (async () => {
+      await loadConfig();
+      await loadCatalog();
+      // Resume where the last session left off
+      const session = await loadSession();
+      if (session && mainRef.current) {
+        mainRef.current.scrollTop = session.scrollTop;
+      }
+    })();
+  }, [loadConfig, loadCatalog, loadSession]);
+
+  // Save the session shortly after the selection, view, filter or scroll
+  // position changes, so a crash loses at most the last second
+  useEffect(() => {
+    let timer: ReturnType<typeof setTimeout> | undefined;
+    const save = () => saveSession({ scrollTop: mainRef.current?.scrollTop ?? 0 });
+    const schedule = () => {
+      clearTimeout(timer);
+      timer = setTimeout(save, 1000);
+    };
+    const unsubscribe = usePhotoStore.subscribe((state, previous) => {
+      if (
+        state.selectedIds !== previous.selectedIds ||
+        state.viewMode !== previous.viewMode ||
+        state.filterMode !== previous.filterMode
+      ) {
+        schedule();
+      }
+    });
+    const main = mainRef.current;
+    main?.addEventListener('scroll', schedule, { passive: true });
+    window.addEventListener('beforeunload', save);
+    return () => {
+      clearTimeout(timer);
+      unsubscribe();
+      main?.removeEventListener('scroll', schedule);
+      window.removeEventListener('beforeunload', save);
+    };
+  }, [saveSession]);
 
   return (
     <div className="flex h-full bg-surface-950">
@@ -24,7 +62,7 @@ function App() {
         <Toolbar />
 
         {/* Photo display area */}
-        <main className="flex-1 overflow-auto p-4">
+        <main ref={mainRef} className="flex-1 overflow-auto p-4">
           {viewMode === 'grid' ? <PhotoGrid /> : <PhotoList />}
         </main>
 
@@ -0,0 +1,51 @@
+import { useEffect, useState } from 'react';
+import { listen } from '@tauri-apps/api/event';
+import { usePhotoStore, PhotoFile } from '../store/photoStore';
+import { formatBytes, formatDate } from '../utils/format';
+import { photoSrc } from '../utils/photoSrc';
+
+// Longer side of the photos in the compare window, in pixels
+const COMPARE_WIDTH = 2048;
+
+/** Contents of the compare window: the photos sent by open_compare_window, side by side */
+export function CompareView() {
+  const { comparedPhotos } = usePhotoStore();
+  const [photos, setPhotos] = useState<PhotoFile[]>([]);
+
+  useEffect(() => {
+    comparedPhotos().then(setPhotos);
+    // The window stays open when other photos are compared
+    const unlisten = listen('compare-photos', () => {
+      comparedPhotos().then(setPhotos);
+    });
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, [comparedPhotos]);
+
+  return (
+    <div className="flex h-full gap-4 bg-surface-950 p-4">
+      {photos.map((photo) => (
+        <div key={photo.id} className="flex flex-1 flex-col overflow-hidden rounded-lg bg-surface-800">
+          <div className="flex flex-1 items-center justify-center overflow-hidden">
+            <img
+              src={photoSrc(photo.thumbnailPath || photo.path, COMPARE_WIDTH)}
+              alt={photo.name}
+              className="max-h-full max-w-full object-contain"
+            />
+          </div>
+          <div className="space-y-1 p-3 text-sm text-surface-300">
+            <div className="truncate font-medium text-white" title={photo.path}>
+              {photo.name}
+            </div>
+            <div>
+              {photo.width && photo.height ? `${photo.width} × ${photo.height} · ` : ''}
+              {formatBytes(photo.size)}
+            </div>
+            <div>{formatDate(photo.captureDate ?? photo.modifiedAt)}</div>
+          </div>
+        </div>
+      ))}
+    </div>
+  );
+}
@@ -1,5 +1,5 @@
 import { usePhotoStore, PhotoFile } from '../store/photoStore';
-import { convertFileSrc } from '@tauri-apps/api/tauri';
+import { useThumbnailSrc } from '../hooks/useThumbnailSrc';
 import clsx from 'clsx';
 
 interface PhotoCardProps {
@@ -30,18 +30,8 @@ export function PhotoCard({ photo, duplicateMode = false }: PhotoCardProps) {
     }
   };
 
-  // RAW extensions that browsers can't display
-  const RAW_EXTENSIONS = ['arw', 'cr2', 'cr3', 'nef', 'dng', 'raf', 'orf', 'rw2', 'pef'];
-  
-  // Check if the thumbnail itself is a RAW file (browsers can't display these)
-  const thumbnailExt = photo.thumbnailPath?.split('.').pop()?.toLowerCase() || '';
-  const isThumbnailRaw = RAW_EXTENSIONS.includes(thumbnailExt);
-  
-  // Convert file path to asset URL for Tauri
-  // Don't try to load RAW files directly - browsers can't display them
-  const thumbnailSrc = photo.thumbnailPath && !isThumbnailRaw
-    ? convertFileSrc(photo.thumbnailPath)
-    : null;
+  // Downscaled photo:// URL; RAW files have none, HEIC goes through a JPEG rendition
+  const thumbnailSrc = useThumbnailSrc(photo.thumbnailPath);
 
   return (
     <div
@@ -74,7 +64,11 @@ export function PhotoCard({ photo, duplicateMode = false }: PhotoCardProps) {
         {photo.isCloudPlaceholder && (
           <span
             className="rounded bg-sky-500/90 px-1.5 py-0.5 text-xs font-medium text-white shadow"
-            title="Cloud placeholder - not downloaded yet"
+            title={
+              photo.duplicateStatus === 'unknown'
+                ? 'Cloud placeholder - not downloaded yet, duplicate status unknown'
+                : 'Cloud placeholder - not downloaded yet'
+            }
           >
             ☁️
           </span>
@@ -106,7 +106,7 @@ export function PhotoGrid() {
     }
 
     const totalDuplicateSize = duplicateGroups.reduce(
-      (acc, g) => acc + g.duplicates.reduce((a, d) => a + d.size, 0),
+      (acc, g) => acc + g.duplicates.reduce((a, d) => a + d.diskSize, 0),
       0
     );
 
@@ -146,7 +146,7 @@ export function PhotoGrid() {
               </p>
               <p className="text-sm text-yellow-300/80">
                 {duplicateGroups.reduce((acc, g) => acc + g.duplicates.length, 0)} duplicate files
-                using {formatBytes(totalDuplicateSize)} of space
+                using up to {formatBytes(totalDuplicateSize)} of space
               </p>
             </div>
             {smartSelectCount > 0 && (
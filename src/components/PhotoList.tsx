@@ -3,7 +3,7 @@ import { usePhotoStore } from '../store/photoStore';
 import { useSortedPhotos, useDuplicateGroups } from '../hooks/useSortedPhotos';
 import { formatBytes, formatDate, shortenPath } from '../utils/format';
 import { getSmartSelections, getFullySelectedGroups } from '../utils/smartSelect';
-import { convertFileSrc } from '@tauri-apps/api/tauri';
+import { LIST_THUMBNAIL_WIDTH, photoSrc } from '../utils/photoSrc';
 import clsx from 'clsx';
 
 // Limit how many items we render at once to prevent memory issues
@@ -93,7 +93,7 @@ export function PhotoList() {
     const hasMore = duplicateGroups.length > visibleCount;
 
     const totalDuplicateSize = duplicateGroups.reduce(
-      (acc, g) => acc + g.duplicates.reduce((a, d) => a + d.size, 0),
+      (acc, g) => acc + g.duplicates.reduce((a, d) => a + d.diskSize, 0),
       0
     );
 
@@ -129,7 +129,7 @@ export function PhotoList() {
               </p>
               <p className="text-sm text-yellow-300/80">
                 {duplicateGroups.reduce((acc, g) => acc + g.duplicates.length, 0)} duplicate files
-                using {formatBytes(totalDuplicateSize)} of space
+                using up to {formatBytes(totalDuplicateSize)} of space
               </p>
             </div>
             {smartSelectCount > 0 && (
@@ -181,7 +181,7 @@ export function PhotoList() {
         {visibleGroups.map((group) => {
           const isOriginalSelected = selectedIds.has(group.original.id);
           const originalThumbnailSrc = group.original.thumbnailPath && !isRawThumbnail(group.original.thumbnailPath)
-            ? convertFileSrc(group.original.thumbnailPath)
+            ? photoSrc(group.original.thumbnailPath, LIST_THUMBNAIL_WIDTH)
             : null;
 
           return (
@@ -260,7 +260,7 @@ export function PhotoList() {
                 {group.duplicates.map((dup) => {
                   const isSelected = selectedIds.has(dup.id);
                   const thumbnailSrc = dup.thumbnailPath && !isRawThumbnail(dup.thumbnailPath)
-                    ? convertFileSrc(dup.thumbnailPath)
+                    ? photoSrc(dup.thumbnailPath, LIST_THUMBNAIL_WIDTH)
                     : null;
 
                   return (
@@ -384,7 +384,7 @@ export function PhotoList() {
           {visiblePhotos.map((photo) => {
             const isSelected = selectedIds.has(photo.id);
             const thumbnailSrc = photo.thumbnailPath && !isRawThumbnail(photo.thumbnailPath)
-              ? convertFileSrc(photo.thumbnailPath)
+              ? photoSrc(photo.thumbnailPath, LIST_THUMBNAIL_WIDTH)
               : null;
 
             return (
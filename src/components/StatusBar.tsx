@@ -45,7 +45,7 @@ export function StatusBar() {
       totalSize += p.size;
       if (p.isDuplicate) {
         duplicateCount++;
-        duplicateSize += p.size;
+        duplicateSize += p.diskSize;
       }
     }
     
@@ -60,6 +60,7 @@ export function StatusBar() {
     discovery: 'Discovering files',
     grouping: 'Grouping files',
     analyzing: 'Analyzing photos',
+    pixel_hash: 'Comparing pixels',
     trailing_hash: 'Quick hash (last 1MB)',
     hashing: 'Full hash',
     duplicates: 'Finding duplicates',
@@ -132,7 +133,7 @@ export function StatusBar() {
           <span className="text-surface-700">•</span>
           
           <span className="text-green-400">
-            {formatBytes(deleteResult.total_bytes)} reclaimed
+            {formatBytes(deleteResult.total_disk_bytes)} reclaimed
           </span>
 
           {deleteResult.failed_count > 0 && (
@@ -215,7 +216,7 @@ export function StatusBar() {
                 {duplicateCount.toLocaleString()} duplicate{duplicateCount !== 1 ? 's' : ''}
               </span>
               <span className="text-surface-700">•</span>
-              <span className="text-yellow-500">{formatBytes(duplicateSize)} reclaimable</span>
+              <span className="text-yellow-500">up to {formatBytes(duplicateSize)} reclaimable</span>
               <span className="text-surface-700">•</span>
               <span className="text-surface-500">
                 ({photos.length.toLocaleString()} total photos, {formatBytes(totalSize)})
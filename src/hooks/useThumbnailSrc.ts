@@ -0,0 +1,14 @@
+import { GRID_THUMBNAIL_WIDTH, photoSrc } from '../utils/photoSrc';
+
+// RAW extensions that browsers can't display
+const RAW_EXTENSIONS = ['arw', 'cr2', 'cr3', 'nef', 'dng', 'raf', 'orf', 'rw2', 'pef'];
+
+/** Downscaled URL for a thumbnail path, or null when the webview can't show it
+ * HEIC is converted to JPEG by the photo:// protocol */
+export function useThumbnailSrc(thumbnailPath?: string | null): string | null {
+  const ext = thumbnailPath?.split('.').pop()?.toLowerCase() || '';
+  if (!thumbnailPath || RAW_EXTENSIONS.includes(ext)) {
+    return null;
+  }
+  return photoSrc(thumbnailPath, GRID_THUMBNAIL_WIDTH);
+}
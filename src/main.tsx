@@ -1,11 +1,13 @@
 import React from 'react';
 import ReactDOM from 'react-dom/client';
+import { appWindow } from '@tauri-apps/api/window';
 import App from './App';
+import { CompareView } from './components/CompareView';
 import './index.css';
 
 ReactDOM.createRoot(document.getElementById('root')!).render(
   <React.StrictMode>
-    <App />
+    {appWindow.label === 'compare' ? <CompareView /> : <App />}
   </React.StrictMode>
 );
 
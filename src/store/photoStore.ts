@@ -3,34 +3,179 @@ import { invoke } from '@tauri-apps/api/tauri';
 import { listen, UnlistenFn } from '@tauri-apps/api/event';
 
 export interface PhotoFile {
+  // Path of the file (kept for compatibility; prefer photoId)
   id: string;
+  // Stable id that survives renames and moves
+  photoId: number;
   path: string;
   name: string;
   directory: string;
   extension: string;
   size: number;
+  // Bytes allocated on disk (what deleting actually frees)
+  diskSize: number;
   modifiedAt: number;
   hash?: string;
+  // Hash of the decoded pixels (pixels hash tier only)
+  pixelHash?: string;
   thumbnailPath?: string;
   // Related files (collapsed metadata)
   relatedFiles: RelatedFile[];
   // Duplicate info
   isDuplicate: boolean;
   duplicateOf?: string;
+  // Another scanned path that is a hard link to the same file
+  hardlinkOf?: string;
+  // 'unknown' = cloud placeholder not hashed yet (see hash_pending_files)
+  duplicateStatus: 'checked' | 'unknown';
   // Cloud storage status
   isCloudPlaceholder: boolean;
+  // EXIF metadata
+  captureDate?: number;
+  cameraMake?: string;
+  cameraModel?: string;
+  // GPS position (EXIF or sidecar) and the nearest town
+  latitude?: number;
+  longitude?: number;
+  placeName?: string;
+  // Pixel size as displayed (EXIF orientation applied) and the orientation, 1-8
+  width?: number;
+  height?: number;
+  orientation?: number;
+  // Priority of the configured directory the file is in
+  rootPriority: number;
+  // Duration, codec, resolution etc. for videos
+  mediaInfo?: MediaInfo;
+  // Frame count and loop length for animated GIF/WebP
+  animation?: AnimationInfo;
+  // When identical content was last trashed from the app
+  previouslyTrashedAt?: number;
+  // Parsed XMP sidecar
+  sidecarMetadata?: SidecarMetadata;
+  // Detected burst or stack made by hand
+  stackId?: number;
+  // Guessed from camera EXIF, file name, format and size
+  sourceKind: SourceKind;
+}
+
+export type SourceKind = 'camera' | 'screenshot' | 'download' | 'unknown';
+
+export interface MediaInfo {
+  durationSecs?: number;
+  codec?: string;
+  width?: number;
+  height?: number;
+  frameRate?: number;
+  bitrate?: number;
+  createdAt?: number;
+}
+
+export interface AnimationInfo {
+  frameCount: number;
+  durationMs: number;
+}
+
+export interface SidecarMetadata {
+  rating?: number;
+  label?: string;
+  keywords: string[];
+  modifiedAt?: string;
+  hasEdits: boolean;
+  latitude?: number;
+  longitude?: number;
+  // exif:DateTimeOriginal as written; overrides the file's capture date
+  captureDate?: string;
+}
+
+export interface GeoBounds {
+  south: number;
+  west: number;
+  north: number;
+  east: number;
+}
+
+export interface Coordinates {
+  latitude: number;
+  longitude: number;
+}
+
+export interface GeotagResult {
+  // Files whose EXIF was rewritten
+  exifCount: number;
+  // Files tagged through their XMP sidecar (RAW, HEIC, video)
+  sidecarCount: number;
+  failed: { path: string; error: string }[];
+}
+
+export interface DateMismatch {
+  path: string;
+  // ms since epoch; start of the day when the name has no time
+  nameDate: number;
+  nameHasTime: boolean;
+  captureDate?: number;
+  createdAt?: number;
+  captureDisagrees: boolean;
+  createdDisagrees: boolean;
+}
+
+export interface CaptureTimeShift {
+  // Files whose EXIF dates were shifted
+  exifCount: number;
+  // Files shifted through their XMP sidecar (RAW, video)
+  sidecarCount: number;
+  noCaptureTime: string[];
+  failed: { path: string; error: string }[];
+}
+
+export interface TransformResult {
+  // JPEGs whose pixels were turned losslessly
+  pixelsCount: number;
+  // Files whose EXIF orientation was changed instead
+  orientationCount: number;
+  failed: { path: string; error: string }[];
+}
+
+export interface GpxOptions {
+  // Seconds added to capture times (camera clock wrong or in another zone)
+  timeOffset?: number;
+  // Seconds; defaults to 600
+  maxGap?: number;
+  // Replace positions photos already have
+  overwrite?: boolean;
+}
+
+export interface GpxCorrelation {
+  trackPoints: number;
+  exifCount: number;
+  sidecarCount: number;
+  // Taken when the track has no position for them
+  outsideTrack: string[];
+  alreadyTagged: string[];
+  failed: { path: string; error: string }[];
 }
 
 export interface RelatedFile {
   path: string;
   name: string;
-  type: 'sidecar' | 'jpeg-preview' | 'raw';
+  type:
+    | 'sidecar'
+    | 'jpeg-preview'
+    | 'raw'
+    | 'live-video'
+    | 'depth'
+    | 'edited'
+    | 'adjustments';
 }
 
 export interface DirectoryConfig {
   path: string;
   enabled: boolean;
   name: string;
+  priority?: number;
+  exclude_patterns?: string[];
+  exclude_hidden?: boolean;
+  max_depth?: number | null;
+  read_only?: boolean;
 }
 
 export type ViewMode = 'grid' | 'list';
@@ -38,6 +183,48 @@ export type SortField = 'name' | 'date' | 'size' | 'path';
 export type SortOrder = 'asc' | 'desc';
 export type FilterMode = 'duplicates' | 'all';
 
+// One file of compare_photos, read from the file itself
+export interface ComparedFile {
+  path: string;
+  name: string;
+  extension: string;
+  size: number;
+  diskSize: number;
+  modifiedAt: number;
+  width?: number;
+  height?: number;
+  orientation?: number;
+  captureDate?: number;
+  cameraMake?: string;
+  cameraModel?: string;
+  latitude?: number;
+  longitude?: number;
+  hash?: string;
+  // Not set for formats that aren't decoded (HEIF, RAW, video)
+  pixelHash?: string;
+}
+
+export interface PhotoComparison {
+  files: ComparedFile[];
+  // Fields of ComparedFile that aren't the same for every file
+  differing: (keyof ComparedFile)[];
+  identicalBytes: boolean;
+  // null when a file can't be decoded and the bytes differ
+  identicalPixels: boolean | null;
+}
+
+// Where the user was, saved so the app resumes there after a restart or crash
+export interface Session {
+  selectedIds: string[];
+  focusedId?: string | null;
+  currentFolder?: string | null;
+  scrollTop: number;
+  viewMode?: ViewMode | null;
+  filterMode?: FilterMode | null;
+  search?: SearchQuery | null;
+  savedAt?: number;
+}
+
 interface UndoOperation {
   type: 'move';
   timestamp: number;
@@ -51,6 +238,733 @@ export interface ScanProgress {
   message: string;
 }
 
+export interface ScanBatch {
+  photos: PhotoFile[];
+}
+
+export type PhotoUpdate = Pick<
+  PhotoFile,
+  | 'id'
+  | 'size'
+  | 'hash'
+  | 'pixelHash'
+  | 'isDuplicate'
+  | 'duplicateOf'
+  | 'hardlinkOf'
+  | 'duplicateStatus'
+  | 'isCloudPlaceholder'
+  | 'previouslyTrashedAt'
+  | 'stackId'
+>;
+
+export interface ScanDelta {
+  updates: PhotoUpdate[];
+}
+
+export interface ScanSummary {
+  photoCount: number;
+  duplicateCount: number;
+  totalSize: number;
+  duplicateDiskSize: number;
+}
+
+// A scan cut short by a crash or by closing the app (see resume_scan)
+export interface InterruptedScan {
+  directories: string[];
+  stage: 'discovered' | 'analyzed';
+  savedAt: number;
+}
+
+export interface PhotoPage {
+  total: number;
+  offset: number;
+  photos: PhotoFile[];
+  stackSizes: Record<string, number>;
+}
+
+export interface Album {
+  id: number;
+  name: string;
+  createdAt: number;
+  photoCount: number;
+  coverPath: string | null;
+}
+
+// A saved search whose photos are found anew each time it is opened
+export interface SmartAlbum {
+  id: number;
+  name: string;
+  query: SearchQuery;
+  createdAt: number;
+}
+
+export interface EventOptions {
+  // Defaults to 180
+  timeGapMinutes?: number;
+  // Defaults to 30
+  maxDistanceKm?: number;
+}
+
+export interface PhotoEvent {
+  id: number;
+  // Where and when, e.g. "Kyoto, Mar 12 – 14, 2024"
+  name: string;
+  startAt: number;
+  endAt: number;
+  placeName: string | null;
+  latitude: number | null;
+  longitude: number | null;
+  coverPath: string;
+  // Members in capture order
+  paths: string[];
+}
+
+export interface EventClustering {
+  events: PhotoEvent[];
+  // Photos without a capture date
+  undated: number;
+}
+
+// A face's photo and its box as fractions of the upright photo
+export interface FaceRegion {
+  path: string;
+  x: number;
+  y: number;
+  width: number;
+  height: number;
+}
+
+export interface Person {
+  id: number;
+  // null until named
+  name: string | null;
+  faceCount: number;
+  photoCount: number;
+  cover: FaceRegion;
+}
+
+export interface PhotoQuality {
+  path: string;
+  // Variance of the Laplacian on a 512px preview; below 100 is blurry
+  sharpness: number;
+  // Mean luma, 0-1
+  brightness: number;
+  // Fractions of pixels blown out / crushed to black
+  highlightsClipped: number;
+  shadowsClipped: number;
+  // 0-1, higher is better
+  score: number;
+  blurry: boolean;
+}
+
+export interface QualityScoring {
+  scored: number;
+  blurry: number;
+  failed: { path: string; error: string }[];
+}
+
+export interface MachineTagging {
+  // Photos that were labeled
+  scanned: number;
+  // Those given at least one tag
+  tagged: number;
+  failed: { path: string; error: string }[];
+}
+
+export interface FaceDetection {
+  // Photos faces were looked for in
+  scanned: number;
+  faces: number;
+  people: Person[];
+  failed: { path: string; error: string }[];
+}
+
+export interface LightroomImport {
+  matched: number;
+  matchedByHash: number;
+  unmatched: string[];
+  rated: number;
+  keywords: number;
+  albums: string[];
+}
+
+export interface ArchivedEntry {
+  path: string;
+  archivedAt: number;
+  isFolder: boolean;
+}
+
+// 'burst' = burst or exposure bracket found by a scan
+export type StackKind = 'manual' | 'burst';
+
+export interface Stack {
+  id: number;
+  kind: StackKind;
+  coverPath: string;
+  paths: string[];
+  createdAt: number;
+}
+
+export interface UnreviewedFolder {
+  path: string;
+  photoCount: number;
+  totalSize: number;
+  oldestPhoto: number;
+  score: number;
+}
+
+/// A folder under a configured directory, from list_directory_tree
+export interface FolderNode {
+  path: string;
+  /// The configured name for a configured directory
+  name: string;
+  /// Photos directly in the folder and their total size
+  photoCount: number;
+  size: number;
+  /// Including all subfolders
+  totalPhotoCount: number;
+  totalSize: number;
+  /// False for folders only known from the catalog (drive not connected)
+  exists: boolean;
+  readOnly: boolean;
+  children: FolderNode[];
+}
+
+/// What one folder holds, not counting its subfolders, from folder_stats
+export interface FolderStats {
+  path: string;
+  photoCount: number;
+  totalSize: number;
+  rawCount: number;
+  jpegCount: number;
+  duplicateCount: number;
+  /// Capture date, falling back to file date, ms since epoch
+  oldestPhoto?: number;
+  newestPhoto?: number;
+}
+
+/// Photos sharing one value; key is absent for photos without one
+export interface StatCount<K> {
+  key?: K;
+  count: number;
+  totalSize: number;
+}
+
+/// From library_stats
+export interface LibraryStats {
+  photoCount: number;
+  totalSize: number;
+  byExtension: StatCount<string>[];
+  byCamera: StatCount<string>[];
+  byYear: StatCount<number>[];
+  /// Photos of at least minSize and under maxSize bytes
+  sizeHistogram: { minSize: number; maxSize?: number; count: number; totalSize: number }[];
+  duplicateCount: number;
+  /// Disk space trashing every duplicate would free
+  reclaimableBytes: number;
+}
+
+export interface TagCount {
+  name: string;
+  count: number;
+}
+
+export type PhotoFlag = 'pick' | 'reject';
+
+export interface PhotoRating {
+  path: string;
+  rating: number;
+  flag: PhotoFlag | null;
+  label: string | null;
+}
+
+export interface DuplicateGroup {
+  hash: string;
+  confidence: 'exact' | 'pixels' | 'likely';
+  members: PhotoFile[];
+  keeperId: number;
+  keeperReason: string;
+  reclaimableBytes: number;
+}
+
+export interface RankedGroup extends DuplicateGroup {
+  score: number;
+  cumulativePercent: number;
+}
+
+export interface DuplicateRanking {
+  totalGroups: number;
+  totalReclaimableBytes: number;
+  groups: RankedGroup[];
+}
+
+export interface ResolvePolicy {
+  includeLikely?: boolean;
+  moveTo?: string;
+}
+
+export interface ResolveResult {
+  groupsResolved: number;
+  removed: string[];
+  reclaimedBytes: number;
+  failed: { path: string; error: string }[];
+}
+
+export interface LoggedOperation {
+  id: number;
+  kind: 'move' | 'organize' | 'rename' | 'trash' | 'resolve' | 'consolidate' | 'link' | 'clone' | 'undo';
+  performedAt: number;
+  // verifiedHash: full hash confirmed on the destination of a cross-volume move
+  files: { path: string; destination?: string; verifiedHash?: string }[];
+}
+
+export interface LinkResult {
+  groupsLinked: number;
+  linked: string[];
+  reclaimedBytes: number;
+  failed: { path: string; error: string }[];
+}
+
+export interface ScanOptions {
+  incremental?: boolean;
+  extensions?: string[];
+  minSize?: number;
+  maxSize?: number;
+  dedupeScope?: 'global' | 'root' | 'folder' | 'off';
+  hashTier?: 'quick' | 'full' | 'pixels';
+  cloudPolicy?: 'download' | 'skip';
+  parallelism?: number;
+}
+
+// config.json `primaryPrecedence`
+export interface PrimaryPrecedence {
+  rawJpeg?: 'raw' | 'jpeg';
+  // Pair HEIC/JPEG stills with any same-named .MOV, not just matching Live Photos
+  livePhotos?: boolean;
+}
+
+// config.json `externalEditors`; app is a macOS bundle id or the app's path
+export interface ExternalEditor {
+  name: string;
+  app: string;
+}
+
+// config.json `organizeRules`; destination may use YYYY, MM and DD
+export interface OrganizeRule {
+  name: string;
+  enabled?: boolean;
+  match: SearchQuery;
+  destination: string;
+}
+
+// A move an organize rule calls for
+export interface RuleMove {
+  rule: string;
+  from: string;
+  to: string;
+}
+
+export interface AvailableEditor extends ExternalEditor {
+  /// Configured by the user rather than found on the system
+  configured: boolean;
+}
+
+export interface ScanPreset {
+  name: string;
+  options: ScanOptions;
+}
+
+export interface CacheStats {
+  entryCount: number;
+  trailingHashCount: number;
+  fullHashCount: number;
+  otherAlgorithmCount: number;
+  // Entries confirmed by verify_fixity
+  verifiedCount: number;
+  fileSize: number;
+}
+
+export interface FixityOptions {
+  // Check at most this many files, least recently verified first
+  sample?: number;
+  skipVerifiedWithinDays?: number;
+}
+
+export interface FixityProgress {
+  current: number;
+  total: number;
+  currentFile: string;
+  phase: 'verifying' | 'complete';
+}
+
+export interface FixityMismatch {
+  path: string;
+  expectedHash: string;
+  actualHash: string;
+  // Size or modification time changed too (likely an edit rather than corruption)
+  modified: boolean;
+}
+
+export interface FixityReport {
+  checkedCount: number;
+  verifiedCount: number;
+  changed: FixityMismatch[];
+  missing: string[];
+  // Unmounted volumes and cloud placeholders
+  unavailableCount: number;
+  failed: { path: string; error: string }[];
+}
+
+export type ImportStatus = 'verified' | 'mismatch' | 'missingSource' | 'missingDestination';
+
+export interface ImportReportEntry {
+  source: string;
+  destination: string;
+  size: number;
+  sourceHash?: string;
+  destinationHash?: string;
+  status: ImportStatus;
+}
+
+export interface ImportReport {
+  id: string;
+  createdAt: string;
+  source: string;
+  destination: string;
+  hashAlgorithm: string;
+  entries: ImportReportEntry[];
+  verifiedCount: number;
+  failedCount: number;
+  signature: string;
+  signatureValid: boolean;
+}
+
+export interface Straggler {
+  path: string;
+  size: number;
+  reason: 'notInLibrary' | 'unreadable';
+}
+
+export interface FormatCheck {
+  volume: string;
+  fileCount: number;
+  confirmedCount: number;
+  skippedCount: number;
+  safe: boolean;
+  stragglers: Straggler[];
+}
+
+export interface CompareOptions {
+  // Hash same-path files even when size and modification time match
+  checksum?: boolean;
+}
+
+export interface CompareProgress {
+  stage: 'listing' | 'hashing' | 'complete';
+  current: number;
+  total: number;
+  currentFile: string;
+}
+
+// Paths are relative to the two roots
+export interface TreeFile {
+  path: string;
+  size: number;
+}
+
+export interface TreeComparison {
+  libraryRoot: string;
+  backupRoot: string;
+  identicalCount: number;
+  missing: TreeFile[];
+  extra: TreeFile[];
+  differing: { path: string; librarySize: number; backupSize: number }[];
+  moved: { libraryPath: string; backupPath: string; size: number }[];
+  bytesToCopy: number;
+  // Absolute paths that could not be hashed
+  unreadable: string[];
+}
+
+export interface ExportOptions {
+  destination: string;
+  folderStructure?: 'preserve' | 'flatten';
+  convertRaw?: boolean;
+  maxDimension?: number;
+  quality?: number;
+}
+
+export interface ExportProgress {
+  current: number;
+  total: number;
+  currentFile: string;
+}
+
+export interface ExportedFile {
+  source: string;
+  destination: string;
+  size: number;
+  converted: boolean;
+}
+
+export interface ExportResult {
+  exported: ExportedFile[];
+  failed: { path: string; error: string }[];
+  manifestPath: string | null;
+}
+
+export type ReportFormat = 'csv' | 'json';
+
+export interface ZipExportResult {
+  zipPath: string | null;
+  added: number;
+  size: number;
+  failed: { path: string; error: string }[];
+}
+
+export interface ConsolidationRules {
+  folderTemplate?: string;
+  duplicates?: 'trash' | 'leave';
+  conflicts?: 'rename' | 'skip';
+}
+
+export interface PlanStep {
+  action: 'move' | 'trash';
+  from: string;
+  to: string | null;
+  companions: [string, string][];
+  size: number;
+  hash: string | null;
+  duplicateOf: string | null;
+  status: 'pending' | 'done' | 'failed';
+  error: string | null;
+}
+
+export interface PlanConflict {
+  path: string;
+  wanted: string;
+  resolvedTo: string | null;
+}
+
+export interface ProjectedFolder {
+  path: string;
+  fileCount: number;
+  size: number;
+}
+
+export interface ConsolidationPlan {
+  id: string;
+  createdAt: string;
+  sources: string[];
+  targetRoot: string;
+  rules: ConsolidationRules;
+  steps: PlanStep[];
+  conflicts: PlanConflict[];
+  duplicatesLeft: number;
+  unreadable: string[];
+  projection: {
+    folders: ProjectedFolder[];
+    fileCount: number;
+    totalSize: number;
+    reclaimedBytes: number;
+  };
+}
+
+export interface ConsolidationProgress {
+  stage: 'scanning' | 'hashing' | 'moving' | 'complete';
+  current: number;
+  total: number;
+  currentFile: string;
+}
+
+export interface ConsolidationRun {
+  completed: number;
+  failed: { path: string; error: string }[];
+  remaining: number;
+  operations: Array<{ from: string; to: string }>;
+  trashed: string[];
+}
+
+export interface Volume {
+  path: string;
+  name: string;
+  totalBytes: number;
+  freeBytes: number;
+  isCameraCard: boolean;
+}
+
+export interface Device {
+  path: string;
+  name: string;
+}
+
+export interface IngestOptions {
+  destination: string;
+  folderTemplate?: string;
+  renameTemplate?: string;
+  eject?: boolean;
+}
+
+export interface IngestProgress {
+  stage: 'scanning' | 'hashing' | 'copying' | 'verifying' | 'ejecting' | 'complete';
+  current: number;
+  total: number;
+  currentFile: string;
+}
+
+export interface IngestResult {
+  volume: string;
+  importedCount: number;
+  alreadyImportedCount: number;
+  failed: { path: string; error: string }[];
+  verifiedCount: number;
+  reportId: string;
+  ejected: boolean;
+}
+
+export interface TakeoutOptions {
+  destination: string;
+  folderTemplate?: string;
+}
+
+export interface TakeoutResult {
+  importedCount: number;
+  alreadyImportedCount: number;
+  withMetadataCount: number;
+  editedCount: number;
+  failed: { path: string; error: string }[];
+}
+
+export type JobKind =
+  | 'scan' | 'delete' | 'convert' | 'transcode' | 'import' | 'export' | 'consolidate'
+  | 'compare' | 'fixity' | 'resolve' | 'link' | 'move' | 'downloading' | 'evicting';
+
+/// Payload of `job-progress` and entries of `list_jobs`
+export interface JobProgress {
+  jobId: number;
+  kind: JobKind;
+  status: 'queued' | 'running' | 'paused';
+  phase?: string;
+  current: number;
+  total?: number;
+  completed: number;
+  currentFile?: string;
+  idleSecs: number;
+  startedAt: number;
+}
+
+export interface MoveProgress {
+  phase: 'moving' | 'copying' | 'rolling_back' | 'complete';
+  current: number;
+  total: number;
+  bytesMoved: number;
+  totalBytes: number;
+  currentFile: string;
+}
+
+/// Error of a `move_files` call; the files already moved were moved back
+/// Error returned by every command
+export interface CommandError {
+  kind:
+    | 'notFound'
+    | 'permissionDenied'
+    | 'conflict'
+    | 'crossDevice'
+    | 'cloudNotDownloaded'
+    | 'readOnly'
+    | 'unconfirmed'
+    | 'cancelled'
+    | 'io'
+    | 'other';
+  /// The file the error is about, if any
+  path?: string;
+  message: string;
+}
+
+export interface MoveFailure {
+  path?: string;
+  destination?: string;
+  error: CommandError;
+  rolledBack: number;
+  /// Files that could not be moved back, at the path they were left at
+  stranded: { path: string; error: string }[];
+}
+
+export interface JobStalled {
+  jobId: number;
+  kind: JobKind;
+  path?: string;
+  stalledSecs: number;
+}
+
+export interface OpenView {
+  view: 'library' | 'import' | 'import-reports';
+}
+
+export interface QuitDeferred {
+  operations: string[];
+}
+
+export interface CloudFilesProgress {
+  current: number;
+  total: number;
+  currentFile: string;
+  phase: 'downloading' | 'evicting' | 'complete';
+}
+
+export interface CloudFilesResult {
+  completed: string[];
+  failed: { path: string; error: string }[];
+}
+
+export interface SnapshotInfo {
+  timestamp: number;
+  photoCount: number;
+  size: number;
+}
+
+export interface SearchQuery {
+  extensions?: string[];
+  minSize?: number;
+  maxSize?: number;
+  dateFrom?: number;
+  dateTo?: number;
+  year?: number;
+  directory?: string;
+  isDuplicate?: boolean;
+  nameContains?: string;
+  isVideo?: boolean;
+  sourceKind?: SourceKind;
+  minDuration?: number;
+  maxDuration?: number;
+  albumId?: number;
+  personId?: number;
+  tags?: string[];
+  // Labels from detect_machine_tags, apart from the user's tags
+  machineTags?: string[];
+  hasLocation?: boolean;
+  place?: string;
+  // Substring of the camera make or model
+  camera?: string;
+  bounds?: GeoBounds;
+  minMegapixels?: number;
+  maxMegapixels?: number;
+  // width / height: below 1 is portrait, above 1 landscape
+  minAspectRatio?: number;
+  maxAspectRatio?: number;
+  minRating?: number;
+  // 0 finds unrated photos
+  maxRating?: number;
+  flag?: PhotoFlag;
+  label?: string;
+  includeArchived?: boolean;
+  expandStacks?: boolean;
+  stackId?: number;
+  sortField?: 'name' | 'date' | 'size' | 'path';
+  sortOrder?: SortOrder;
+  offset?: number;
+  limit?: number;
+}
+
 export interface DeleteProgress {
   phase: string;
   current: number;
@@ -63,6 +977,10 @@ export interface DeleteResult {
   deleted_count: number;
   failed_count: number;
   total_bytes: number;
+  total_disk_bytes: number;
+  files: string[];
+  /// From a delete_permanently dry run; pass it back to delete the same files
+  confirmation_token?: string;
   show_until?: number; // Timestamp when to hide the result
 }
 
@@ -104,8 +1022,11 @@ interface PhotoState {
   toggleDirectory: (path: string) => void;
 
   scanDirectories: () => Promise<void>;
+  loadCatalog: () => Promise<void>;
   loadConfig: () => Promise<void>;
   saveConfig: () => Promise<void>;
+  saveSession: (view: Pick<Session, 'scrollTop' | 'focusedId' | 'currentFolder' | 'search'>) => Promise<void>;
+  loadSession: () => Promise<Session | null>;
 
   setScanProgress: (progress: ScanProgress | null) => void;
   setDeleteProgress: (progress: DeleteProgress | null) => void;
@@ -123,6 +1044,27 @@ interface PhotoState {
   renamePhoto: (id: string, newName: string) => Promise<void>;
   createFolder: (path: string) => Promise<void>;
   revealInFinder: (path: string) => Promise<void>;
+  openWith: (path: string, app: string) => Promise<void>;
+  quickLook: (path: string) => Promise<void>;
+  rotateImage: (paths: string[], degrees: number) => Promise<TransformResult | null>;
+  flipImage: (paths: string[], direction: 'horizontal' | 'vertical') => Promise<TransformResult | null>;
+  detectFaces: (paths?: string[]) => Promise<FaceDetection | null>;
+  listPeople: () => Promise<Person[]>;
+  namePerson: (personId: number, name: string | null) => Promise<number | null>;
+  detectMachineTags: (paths?: string[]) => Promise<MachineTagging | null>;
+  listMachineTags: (path?: string) => Promise<{ name: string; count: number }[]>;
+  scoreQuality: (paths?: string[]) => Promise<QualityScoring | null>;
+  findBlurryPhotos: (maxSharpness?: number) => Promise<PhotoQuality[]>;
+  suggestBestInStack: (stackId: number) => Promise<PhotoQuality | null>;
+  createSmartAlbum: (name: string, query: SearchQuery) => Promise<SmartAlbum | null>;
+  evaluateSmartAlbum: (albumId: number, offset?: number, limit?: number) => Promise<PhotoPage | null>;
+  listSmartAlbums: () => Promise<SmartAlbum[]>;
+  deleteSmartAlbum: (albumId: number) => Promise<void>;
+  previewRules: (paths?: string[]) => Promise<RuleMove[]>;
+  openCompareWindow: (left: string, right: string) => Promise<void>;
+  comparedPhotos: () => Promise<PhotoFile[]>;
+  comparePhotos: (paths: string[]) => Promise<PhotoComparison | null>;
+  applyRules: (paths?: string[]) => Promise<void>;
 
   undo: () => Promise<void>;
 }
@@ -136,9 +1078,31 @@ async function setupProgressListener(
   if (unlistenProgress) {
     unlistenProgress();
   }
-  unlistenProgress = await listen<ScanProgress>('scan-progress', (event) => {
-    setScanProgress(event.payload);
+  let current: ScanProgress | null = null;
+  const unlistenScan = await listen<ScanProgress>('scan-progress', (event) => {
+    current = event.payload;
+    setScanProgress(current);
   });
+  // Hashing phases report through the scan job's `job-progress` events
+  const unlistenJob = await listen<JobProgress>('job-progress', (event) => {
+    const job = event.payload;
+    const hashing = job.phase === 'trailing_hash' || job.phase === 'hashing';
+    if (job.kind !== 'scan' || !hashing || !current || job.phase !== current.phase || !job.total) {
+      return;
+    }
+    const label = job.phase === 'hashing' ? 'Full hash' : 'Quick hash';
+    current = {
+      ...current,
+      current: job.current,
+      total: job.total,
+      message: `[${Math.round((job.current / job.total) * 100)}%] ${label}: ${job.current} of ${job.total} computed`,
+    };
+    setScanProgress(current);
+  });
+  unlistenProgress = () => {
+    unlistenScan();
+    unlistenJob();
+  };
 }
 
 export const usePhotoStore = create<PhotoState>((set, get) => ({
@@ -233,16 +1197,29 @@ export const usePhotoStore = create<PhotoState>((set, get) => ({
       scanProgress: { phase: 'starting', current: 0, total: 0, message: 'Starting scan...' },
     });
 
+    // Photos arrive in batches while the backend analyzes them; hash and
+    // duplicate results for already-received photos arrive as deltas
+    const received: PhotoFile[] = [];
+    const updates = new Map<string, PhotoUpdate>();
+    const unlistenBatch = await listen<ScanBatch>('scan-batch', (event) => {
+      for (const photo of event.payload.photos) received.push(photo);
+    });
+    const unlistenDelta = await listen<ScanDelta>('scan-delta', (event) => {
+      for (const update of event.payload.updates) updates.set(update.id, update);
+    });
+
     try {
-      const photos = await invoke<PhotoFile[]>('scan_directories', {
+      const summary = await invoke<ScanSummary>('scan_directories', {
         directories: enabledDirs,
       });
-      
-      // Count duplicates with a single pass (no intermediate array)
-      let duplicateCount = 0;
-      for (let i = 0; i < photos.length; i++) {
-        if (photos[i].isDuplicate) duplicateCount++;
-      }
+
+      const photos = updates.size === 0
+        ? received
+        : received.map((p) => {
+            const update = updates.get(p.id);
+            return update ? { ...p, ...update } : p;
+          });
+      const duplicateCount = summary.duplicateCount;
       
       // Show preparing phase while processing data for UI
       set({
@@ -273,6 +1250,32 @@ export const usePhotoStore = create<PhotoState>((set, get) => ({
     } catch (error) {
       console.error('Failed to scan directories:', error);
       set({ loading: false, scanProgress: null });
+    } finally {
+      unlistenBatch();
+      unlistenDelta();
+    }
+  },
+
+  // Load the persisted catalog from the last scan (instant startup, no rescan)
+  loadCatalog: async () => {
+    const pageSize = 5000;
+    const photos: PhotoFile[] = [];
+    try {
+      let page: PhotoPage;
+      do {
+        page = await invoke<PhotoPage>('query_photos', {
+          offset: photos.length,
+          limit: pageSize,
+        });
+        for (const photo of page.photos) photos.push(photo);
+      } while (page.photos.length > 0 && photos.length < page.total);
+
+      // A scan may have finished while we were loading
+      if (!get().loading) {
+        set({ photos });
+      }
+    } catch (error) {
+      console.error('Failed to load catalog:', error);
     }
   },
 
@@ -301,6 +1304,39 @@ export const usePhotoStore = create<PhotoState>((set, get) => ({
     }
   },
 
+  // Session persistence
+  saveSession: async (view) => {
+    const { selectedIds, viewMode, filterMode } = get();
+    const session: Session = {
+      ...view,
+      selectedIds: Array.from(selectedIds),
+      viewMode,
+      filterMode,
+    };
+    try {
+      await invoke('save_session', { session });
+    } catch (error) {
+      console.error('Failed to save session:', error);
+    }
+  },
+
+  // Restores the selection, view and filter; the caller restores the scroll position
+  loadSession: async () => {
+    try {
+      const session = await invoke<Session | null>('load_session');
+      if (!session) return null;
+      set((state) => ({
+        selectedIds: new Set(session.selectedIds),
+        viewMode: session.viewMode || state.viewMode,
+        filterMode: session.filterMode || state.filterMode,
+      }));
+      return session;
+    } catch (error) {
+      console.error('Failed to load session:', error);
+      return null;
+    }
+  },
+
   saveConfig: async () => {
     const { directories, viewMode, sortField, sortOrder, filterMode } = get();
     try {
@@ -358,6 +1394,8 @@ export const usePhotoStore = create<PhotoState>((set, get) => ({
         {
           files: toMove.map((p) => p.path),
           destination: destFolder,
+          dryRun: false,
+          removeEmptyFolders: false,
         }
       );
 
@@ -394,8 +1432,9 @@ export const usePhotoStore = create<PhotoState>((set, get) => ({
     set({ isDeleting: true, deleteProgress: null, deleteResult: null });
 
     try {
-      const result = await invoke<{ deleted_count: number; failed_count: number; total_bytes: number }>('trash_files', {
+      const result = await invoke<{ deleted_count: number; failed_count: number; total_bytes: number; total_disk_bytes: number }>('trash_files', {
         files: toDelete.map((p) => p.path),
+        dryRun: false,
       });
       
       set({ selectedIds: new Set(), isDeleting: false, deleteProgress: null });
@@ -405,6 +1444,7 @@ export const usePhotoStore = create<PhotoState>((set, get) => ({
         deleted_count: result.deleted_count,
         failed_count: result.failed_count,
         total_bytes: result.total_bytes,
+        total_disk_bytes: result.total_disk_bytes,
         show_until: Date.now() + 5000,
       });
       
@@ -451,6 +1491,211 @@ export const usePhotoStore = create<PhotoState>((set, get) => ({
     }
   },
 
+  openWith: async (path, app) => {
+    try {
+      await invoke('open_with', { path, app });
+    } catch (error) {
+      console.error(`Failed to open in ${app}:`, error);
+    }
+  },
+
+  quickLook: async (path) => {
+    try {
+      await invoke('quick_look', { path });
+    } catch (error) {
+      console.error('Failed to open Quick Look:', error);
+    }
+  },
+
+  rotateImage: async (paths, degrees) => {
+    try {
+      return await invoke<TransformResult>('rotate_image', { paths, degrees });
+    } catch (error) {
+      console.error('Failed to rotate:', error);
+      return null;
+    }
+  },
+
+  flipImage: async (paths, direction) => {
+    try {
+      return await invoke<TransformResult>('flip_image', { paths, direction });
+    } catch (error) {
+      console.error('Failed to flip:', error);
+      return null;
+    }
+  },
+
+  detectFaces: async (paths) => {
+    try {
+      return await invoke<FaceDetection>('detect_faces', { paths: paths ?? null });
+    } catch (error) {
+      console.error('Failed to detect faces:', error);
+      return null;
+    }
+  },
+
+  listPeople: async () => {
+    try {
+      return await invoke<Person[]>('list_people');
+    } catch (error) {
+      console.error('Failed to list people:', error);
+      return [];
+    }
+  },
+
+  namePerson: async (personId, name) => {
+    try {
+      return await invoke<number>('name_person', { personId, name });
+    } catch (error) {
+      console.error('Failed to name person:', error);
+      return null;
+    }
+  },
+
+  detectMachineTags: async (paths) => {
+    try {
+      return await invoke<MachineTagging>('detect_machine_tags', { paths: paths ?? null });
+    } catch (error) {
+      console.error('Failed to label photos:', error);
+      return null;
+    }
+  },
+
+  listMachineTags: async (path) => {
+    try {
+      return await invoke<{ name: string; count: number }[]>('list_machine_tags', {
+        path: path ?? null,
+      });
+    } catch (error) {
+      console.error('Failed to list machine tags:', error);
+      return [];
+    }
+  },
+
+  scoreQuality: async (paths) => {
+    try {
+      return await invoke<QualityScoring>('score_quality', { paths: paths ?? null });
+    } catch (error) {
+      console.error('Failed to score quality:', error);
+      return null;
+    }
+  },
+
+  findBlurryPhotos: async (maxSharpness) => {
+    try {
+      return await invoke<PhotoQuality[]>('find_blurry_photos', {
+        maxSharpness: maxSharpness ?? null,
+      });
+    } catch (error) {
+      console.error('Failed to find blurry photos:', error);
+      return [];
+    }
+  },
+
+  suggestBestInStack: async (stackId) => {
+    try {
+      return await invoke<PhotoQuality | null>('suggest_best_in_stack', { stackId });
+    } catch (error) {
+      console.error('Failed to suggest a cover:', error);
+      return null;
+    }
+  },
+
+  createSmartAlbum: async (name, query) => {
+    try {
+      return await invoke<SmartAlbum>('create_smart_album', { name, query });
+    } catch (error) {
+      console.error('Failed to create smart album:', error);
+      return null;
+    }
+  },
+
+  evaluateSmartAlbum: async (albumId, offset, limit) => {
+    try {
+      return await invoke<PhotoPage>('evaluate_smart_album', {
+        albumId,
+        offset: offset ?? null,
+        limit: limit ?? null,
+      });
+    } catch (error) {
+      console.error('Failed to open smart album:', error);
+      return null;
+    }
+  },
+
+  listSmartAlbums: async () => {
+    try {
+      return await invoke<SmartAlbum[]>('list_smart_albums');
+    } catch (error) {
+      console.error('Failed to list smart albums:', error);
+      return [];
+    }
+  },
+
+  deleteSmartAlbum: async (albumId) => {
+    try {
+      await invoke('delete_smart_album', { albumId });
+    } catch (error) {
+      console.error('Failed to delete smart album:', error);
+    }
+  },
+
+  previewRules: async (paths) => {
+    try {
+      return await invoke<RuleMove[]>('preview_rules', { paths: paths ?? null });
+    } catch (error) {
+      console.error('Failed to preview rules:', error);
+      return [];
+    }
+  },
+
+  openCompareWindow: async (left, right) => {
+    try {
+      await invoke('open_compare_window', { left, right });
+    } catch (error) {
+      console.error('Failed to open compare window:', error);
+    }
+  },
+
+  comparedPhotos: async () => {
+    try {
+      return await invoke<PhotoFile[]>('compared_photos');
+    } catch (error) {
+      console.error('Failed to load compared photos:', error);
+      return [];
+    }
+  },
+
+  comparePhotos: async (paths) => {
+    try {
+      return await invoke<PhotoComparison>('compare_photos', { paths });
+    } catch (error) {
+      console.error('Failed to compare photos:', error);
+      return null;
+    }
+  },
+
+  applyRules: async (paths) => {
+    try {
+      const operations = await invoke<Array<{ from: string; to: string }>>(
+        'apply_rules',
+        { paths: paths ?? null, removeEmptyFolders: false }
+      );
+      if (operations.length === 0) return;
+
+      set((state) => ({
+        undoStack: [
+          ...state.undoStack,
+          { type: 'move', timestamp: Date.now(), operations },
+        ],
+      }));
+
+      await get().scanDirectories();
+    } catch (error) {
+      console.error('Failed to apply rules:', error);
+    }
+  },
+
   // Undo
   undo: async () => {
     const { undoStack } = get();
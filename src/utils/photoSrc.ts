@@ -0,0 +1,12 @@
+import { convertFileSrc } from '@tauri-apps/api/tauri';
+
+// Longer side of thumbnails in the grid and in list rows, in pixels
+export const GRID_THUMBNAIL_WIDTH = 512;
+export const LIST_THUMBNAIL_WIDTH = 96;
+
+// URL serving a photo through the photo:// protocol; with a width it is a
+// cached JPEG downscaled to fit, so <img> tags never load the full original
+export function photoSrc(path: string, width?: number): string {
+  const src = convertFileSrc(path, 'photo');
+  return width ? `${src}?w=${width}` : src;
+}